@@ -0,0 +1,3 @@
+fn main() -> std::process::ExitCode {
+    faulx_compat::run("killall")
+}
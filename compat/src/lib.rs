@@ -0,0 +1,68 @@
+//! procps-compatible `pgrep`, `pkill` and `killall`, as shims starting
+//! `faulx` under those names.
+//!
+//! faulx picks its personality from `argv[0]`, so the shims only pass their
+//! arguments on; what they accept is up to the `faulx` they find.
+
+use std::{
+    env,
+    ffi::OsString,
+    io,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use faulx_core::exit;
+
+const FAULX: &str = "faulx";
+
+/// The `faulx` next to `current_exe`, as when both are installed together,
+/// or the one on `PATH`.
+#[must_use]
+pub fn faulx_path(current_exe: Option<&Path>) -> PathBuf {
+    current_exe
+        .map(|exe| exe.with_file_name(FAULX))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from(FAULX))
+}
+
+/// Replaces this process with `faulx` started as `tool`, with `args`.
+/// Returns only if that failed.
+pub fn exec(tool: &str, args: impl IntoIterator<Item = OsString>) -> io::Error {
+    let current_exe = env::current_exe().ok();
+    Command::new(faulx_path(current_exe.as_deref()))
+        .arg0(tool)
+        .args(args)
+        .exec()
+}
+
+/// The `main` of the shim for `tool`.
+#[must_use]
+pub fn run(tool: &str) -> ExitCode {
+    let e = exec(tool, env::args_os().skip(1));
+    eprintln!("{tool}: cannot run {FAULX}: {e}");
+    exit::ExitCode::Internal.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, process};
+
+    use super::*;
+
+    #[test]
+    fn test_faulx_path() {
+        let dir = env::temp_dir().join(format!("faulx_compat_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let pgrep = dir.join("pgrep");
+        assert_eq!(faulx_path(Some(&pgrep)), Path::new(FAULX));
+        assert_eq!(faulx_path(None), Path::new(FAULX));
+
+        let faulx = dir.join(FAULX);
+        fs::write(&faulx, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&faulx, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(faulx_path(Some(&pgrep)), faulx);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,581 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+use crate::{
+    killer::{Killer, SystemKiller},
+    processes::ProcessInfo,
+    signals::Signal,
+    sys,
+    time::{BootClock, TimeFormat},
+};
+
+/// Something done to every selected process.
+pub trait Action {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()>;
+
+    /// Short description used in verbose output, e.g. `renice 10`.
+    fn describe(&self) -> String;
+}
+
+/// Sends a signal to each process, through `killer`.
+#[derive(Debug, Clone, Copy)]
+pub struct Kill<K = SystemKiller> {
+    pub signal: Signal,
+    killer: K,
+}
+
+impl Kill {
+    #[must_use]
+    pub const fn new(signal: Signal) -> Self {
+        Self::with_killer(signal, SystemKiller)
+    }
+}
+
+impl<K: Killer> Kill<K> {
+    #[must_use]
+    pub const fn with_killer(signal: Signal, killer: K) -> Self {
+        Self { signal, killer }
+    }
+}
+
+impl<K: Killer> Action for Kill<K> {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        match process.tgid {
+            Some(tgid) => self.killer.kill_thread(tgid, process.pid, self.signal),
+            None => self.killer.kill(process.pid, self.signal),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("signal {}", self.signal.as_raw())
+    }
+}
+
+/// Sets the scheduling priority (nice value) of each process.
+#[derive(Debug, Clone, Copy)]
+pub struct Renice {
+    pub niceness: i32,
+}
+
+impl Renice {
+    #[must_use]
+    pub const fn new(niceness: i32) -> Self {
+        Self { niceness }
+    }
+}
+
+impl Action for Renice {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        let who = libc::id_t::try_from(process.pid.as_raw())
+            .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+        // SAFETY: setpriority only reads its integer arguments.
+        let ret = unsafe { libc::setpriority(libc::PRIO_PROCESS, who, self.niceness) };
+        sys::check(ret)?;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("renice {}", self.niceness)
+    }
+}
+
+/// Writes `/proc/<pid>/oom_score_adj` for each process.
+#[derive(Debug, Clone, Copy)]
+pub struct OomScoreAdj {
+    pub value: i16,
+}
+
+impl OomScoreAdj {
+    pub const MIN: i16 = -1000;
+    pub const MAX: i16 = 1000;
+
+    #[must_use]
+    pub const fn new(value: i16) -> Self {
+        Self { value }
+    }
+}
+
+impl Action for OomScoreAdj {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        fs::write(process.path("oom_score_adj"), self.value.to_string())
+    }
+
+    fn describe(&self) -> String {
+        format!("oom_score_adj {}", self.value)
+    }
+}
+
+/// An I/O scheduling class and priority level, as accepted by `ionice`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoPriority {
+    None,
+    Realtime(u8),
+    BestEffort(u8),
+    Idle,
+}
+
+impl IoPriority {
+    const CLASS_SHIFT: u32 = 13;
+    const MAX_LEVEL: u8 = 7;
+
+    const fn to_raw(self) -> libc::c_int {
+        let (class, level) = match self {
+            Self::None => (0, 0),
+            Self::Realtime(level) => (1, level),
+            Self::BestEffort(level) => (2, level),
+            Self::Idle => (3, 0),
+        };
+        (class << Self::CLASS_SHIFT) | level as libc::c_int
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => f.write_str("none"),
+            Self::Realtime(level) => write!(f, "realtime:{level}"),
+            Self::BestEffort(level) => write!(f, "best-effort:{level}"),
+            Self::Idle => f.write_str("idle"),
+        }
+    }
+}
+
+impl FromStr for IoPriority {
+    type Err = String;
+
+    /// Parses `CLASS[:LEVEL]`, where CLASS is `none`, `realtime`,
+    /// `best-effort` or `idle` (or their ionice numbers 0-3) and LEVEL is
+    /// 0-7, defaulting to 4.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (class, level) = s.split_once(':').map_or((s, None), |(c, l)| (c, Some(l)));
+        let level = level.map_or(Ok(4), |l| {
+            l.parse::<u8>()
+                .ok()
+                .filter(|l| *l <= Self::MAX_LEVEL)
+                .ok_or_else(|| format!("invalid I/O priority level '{l}' (expected 0-7)"))
+        })?;
+
+        match class.to_lowercase().as_str() {
+            "none" | "0" => Ok(Self::None),
+            "realtime" | "1" => Ok(Self::Realtime(level)),
+            "best-effort" | "2" => Ok(Self::BestEffort(level)),
+            "idle" | "3" => Ok(Self::Idle),
+            _ => Err(format!("invalid I/O scheduling class '{class}'")),
+        }
+    }
+}
+
+/// Sets the I/O scheduling class and priority of each process.
+#[derive(Debug, Clone, Copy)]
+pub struct Ionice {
+    pub priority: IoPriority,
+}
+
+impl Ionice {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+    #[must_use]
+    pub const fn new(priority: IoPriority) -> Self {
+        Self { priority }
+    }
+}
+
+impl Action for Ionice {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        // SAFETY: ioprio_set takes three integer arguments and no pointers.
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                Self::IOPRIO_WHO_PROCESS,
+                process.pid.as_raw(),
+                self.priority.to_raw(),
+            )
+        };
+        sys::check(ret)?;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("ionice {}", self.priority)
+    }
+}
+
+/// A set of CPUs, parsed either as a hexadecimal mask (`0x3`, `f0`) like
+/// `taskset`, or as a list of CPUs and ranges (`0-3,8`) when the value
+/// contains `,` or `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuMask {
+    cpus: Vec<usize>,
+}
+
+impl CpuMask {
+    #[must_use]
+    pub fn cpus(&self) -> &[usize] {
+        &self.cpus
+    }
+
+    fn parse_list(s: &str) -> Result<Vec<usize>, String> {
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid CPU number '{n}'"))
+        };
+        let mut cpus = Vec::new();
+        for part in s.split(',') {
+            if let Some((start, end)) = part.split_once('-') {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("invalid CPU range '{part}'"));
+                }
+                // Before the range is spelled out, which for huge bounds
+                // would not fit in memory.
+                if end >= sys::max_cpus() {
+                    return Err(format!("CPU {end} is out of range"));
+                }
+                cpus.extend(start..=end);
+            } else {
+                cpus.push(parse(part)?);
+            }
+        }
+        Ok(cpus)
+    }
+
+    fn parse_mask(s: &str) -> Result<Vec<usize>, String> {
+        let digits = s.strip_prefix("0x").unwrap_or(s);
+        if digits.is_empty() {
+            return Err("empty CPU mask".to_owned());
+        }
+        let mut cpus = Vec::new();
+        for (i, c) in digits.bytes().rev().enumerate() {
+            let nibble = char::from(c)
+                .to_digit(16)
+                .ok_or_else(|| format!("invalid CPU mask '{s}'"))?;
+            cpus.extend(
+                (0..4)
+                    .filter(|bit| nibble & (1 << bit) != 0)
+                    .map(|bit| i * 4 + bit),
+            );
+        }
+        Ok(cpus)
+    }
+}
+
+impl FromStr for CpuMask {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cpus = if s.contains([',', '-']) {
+            Self::parse_list(s)?
+        } else {
+            Self::parse_mask(&s.to_lowercase())?
+        };
+        cpus.sort_unstable();
+        cpus.dedup();
+
+        if cpus.is_empty() {
+            return Err("CPU mask selects no CPU".to_owned());
+        }
+        if let Some(cpu) = cpus.iter().find(|&&cpu| cpu >= sys::max_cpus()) {
+            return Err(format!("CPU {cpu} is out of range"));
+        }
+        Ok(Self { cpus })
+    }
+}
+
+impl fmt::Display for CpuMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cpus: Vec<String> = self.cpus.iter().map(ToString::to_string).collect();
+        f.write_str(&cpus.join(","))
+    }
+}
+
+/// Pins each process, and optionally all of its threads, to a set of CPUs.
+#[derive(Debug, Clone)]
+pub struct Affinity {
+    pub mask: CpuMask,
+    pub threads: bool,
+}
+
+impl Affinity {
+    #[must_use]
+    pub const fn new(mask: CpuMask, threads: bool) -> Self {
+        Self { mask, threads }
+    }
+}
+
+impl Action for Affinity {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        let cpus = self.mask.cpus();
+        if !self.threads {
+            return sys::set_affinity(process.pid, cpus);
+        }
+        for tid in process.threads()? {
+            match sys::set_affinity(tid, cpus) {
+                // The thread exited since the task directory was read.
+                Err(err) if err.raw_os_error() != Some(libc::ESRCH) => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("cpu affinity {}", self.mask)
+    }
+}
+
+/// Moves each process into a cgroup by writing its PID to `cgroup.procs`.
+#[derive(Debug, Clone)]
+pub struct CgroupMove {
+    procs: PathBuf,
+}
+
+impl CgroupMove {
+    const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+    /// `cgroup` is either an absolute cgroup directory or a path relative to
+    /// `/sys/fs/cgroup`. Fails if it has no `cgroup.procs` file.
+    pub fn new(cgroup: impl AsRef<Path>) -> io::Result<Self> {
+        let procs = Path::new(Self::CGROUP_ROOT)
+            .join(cgroup.as_ref())
+            .join("cgroup.procs");
+        if !procs.is_file() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a cgroup", cgroup.as_ref().display()),
+            ));
+        }
+        Ok(Self { procs })
+    }
+}
+
+impl Action for CgroupMove {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        // The kernel expects a single PID per write(2).
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&self.procs)?
+            .write_all(process.pid.to_string().as_bytes())
+    }
+
+    fn describe(&self) -> String {
+        let cgroup = self.procs.parent().unwrap_or(&self.procs);
+        format!("cgroup {}", cgroup.display())
+    }
+}
+
+/// Applies several actions in order, stopping at the first failure.
+#[derive(Default)]
+pub struct Chain {
+    actions: Vec<Box<dyn Action>>,
+}
+
+impl Chain {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, action: impl Action + 'static) {
+        self.actions.push(Box::new(action));
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+}
+
+impl Action for Chain {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        self.actions.iter_mut().try_for_each(|a| a.apply(process))
+    }
+
+    fn describe(&self) -> String {
+        self.actions
+            .iter()
+            .map(|a| a.describe())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Writes the PID of each process, optionally followed by its name, one
+/// record per line unless other delimiters are set. Call `finish` once done.
+#[derive(Debug)]
+pub struct Print<W: Write> {
+    out: W,
+    with_name: bool,
+    times: Option<(BootClock, TimeFormat, SystemTime)>,
+    separator: Vec<u8>,
+    terminator: Vec<u8>,
+    written: bool,
+}
+
+impl<W: Write> Print<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            with_name: false,
+            times: None,
+            separator: b"\n".to_vec(),
+            terminator: b"\n".to_vec(),
+            written: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn with_name(mut self, with_name: bool) -> Self {
+        self.with_name = with_name;
+        self
+    }
+
+    /// Follows each record with the start time of the process, shown as
+    /// `format` as of `now`.
+    #[must_use]
+    pub const fn with_times(
+        mut self,
+        clock: BootClock,
+        format: TimeFormat,
+        now: SystemTime,
+    ) -> Self {
+        self.times = Some((clock, format, now));
+        self
+    }
+
+    /// Writes `separator` between records and `terminator` after the last
+    /// one, e.g. `,` and a newline like `pgrep -d,`, or a NUL for both to
+    /// feed `xargs -0`.
+    #[must_use]
+    pub fn with_delimiters(mut self, separator: &[u8], terminator: &[u8]) -> Self {
+        self.separator = separator.to_vec();
+        self.terminator = terminator.to_vec();
+        self
+    }
+
+    /// Terminates the last record, if any.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.written {
+            self.out.write_all(&self.terminator)?;
+        }
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Action for Print<W> {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        if self.written {
+            self.out.write_all(&self.separator)?;
+        }
+        self.written = true;
+        write!(self.out, "{}", process.pid)?;
+        if self.with_name {
+            write!(self.out, " {}", process.comm.escaped())?;
+        }
+        if let Some((clock, format, now)) = &self.times {
+            match process.stat() {
+                Some(stat) => write!(self.out, " {}", clock.format(stat.starttime, *format, *now))?,
+                None => self.out.write_all(b" ?")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "print".to_owned()
+    }
+}
+
+/// Counts the processes it is applied to.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Count {
+    pub count: usize,
+}
+
+impl Action for Count {
+    fn apply(&mut self, _process: &ProcessInfo) -> io::Result<()> {
+        self.count += 1;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        "count".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pid;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_print_delimiters() {
+        let process =
+            |pid| ProcessInfo::new(Arc::from(Path::new("/nonexistent")), pid, b"sh".to_vec());
+        let printed = |print: Print<Vec<u8>>| {
+            let mut print = print;
+            for raw in 1..=3 {
+                print.apply(&process(pid(raw))).unwrap();
+            }
+            print.finish().unwrap();
+            print.out
+        };
+        assert_eq!(printed(Print::new(Vec::new())), b"1\n2\n3\n");
+        assert_eq!(
+            printed(Print::new(Vec::new()).with_name(true)),
+            b"1 sh\n2 sh\n3 sh\n"
+        );
+        assert_eq!(
+            printed(Print::new(Vec::new()).with_delimiters(b",", b"\n")),
+            b"1,2,3\n"
+        );
+        assert_eq!(
+            printed(Print::new(Vec::new()).with_delimiters(b"\0", b"\0")),
+            b"1\x002\x003\x00"
+        );
+
+        let mut empty = Print::new(Vec::new());
+        empty.finish().unwrap();
+        assert!(empty.out.is_empty());
+    }
+
+    #[test]
+    fn test_parse_io_priority() {
+        assert_eq!("idle".parse(), Ok(IoPriority::Idle));
+        assert_eq!("best-effort".parse(), Ok(IoPriority::BestEffort(4)));
+        assert_eq!("realtime:0".parse(), Ok(IoPriority::Realtime(0)));
+        assert_eq!("2:7".parse(), Ok(IoPriority::BestEffort(7)));
+        assert!("best-effort:8".parse::<IoPriority>().is_err());
+        assert!("fast".parse::<IoPriority>().is_err());
+    }
+
+    #[test]
+    fn test_parse_cpu_mask() {
+        let cpus = |s: &str| s.parse::<CpuMask>().map(|m| m.cpus().to_vec());
+        assert_eq!(cpus("0x3"), Ok(vec![0, 1]));
+        assert_eq!(cpus("F0"), Ok(vec![4, 5, 6, 7]));
+        assert_eq!(cpus("0-2,8"), Ok(vec![0, 1, 2, 8]));
+        assert_eq!(cpus("4-4"), Ok(vec![4]));
+        assert!(cpus("0").is_err());
+        assert!(cpus("3-1").is_err());
+        assert!(cpus("0xg").is_err());
+        assert!(cpus("0-100000").is_err());
+        assert_eq!(
+            cpus("0-18446744073709551615"),
+            Err("CPU 18446744073709551615 is out of range".to_owned())
+        );
+        assert!(cpus("0-4000000000").is_err());
+    }
+
+    #[test]
+    fn test_io_priority_raw() {
+        assert_eq!(IoPriority::BestEffort(4).to_raw(), (2 << 13) | 4);
+        assert_eq!(IoPriority::Idle.to_raw(), 3 << 13);
+    }
+}
@@ -0,0 +1,160 @@
+//! Where time comes from: the system, or a mock that only moves when told
+//! to, so that waits and timeouts can be tested without sleeping.
+
+use std::{
+    os::fd::BorrowedFd,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::sys;
+
+/// Tells the time and waits.
+pub trait Clock {
+    /// Monotonic time, for measuring waits and timeouts.
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, for comparing with when processes started.
+    fn system_time(&self) -> SystemTime;
+
+    fn sleep(&self, duration: Duration);
+
+    /// Sleeps for `duration`, or less if one of `fds` becomes readable
+    /// first, returning which of them are. Only sleeps unless it tells time
+    /// by the system's clock.
+    fn sleep_until_readable(&self, duration: Duration, fds: &[BorrowedFd<'_>]) -> Vec<bool> {
+        self.sleep(duration);
+        vec![false; fds.len()]
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        (**self).system_time()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration);
+    }
+
+    fn sleep_until_readable(&self, duration: Duration, fds: &[BorrowedFd<'_>]) -> Vec<bool> {
+        (**self).sleep_until_readable(duration, fds)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        (**self).system_time()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration);
+    }
+
+    fn sleep_until_readable(&self, duration: Duration, fds: &[BorrowedFd<'_>]) -> Vec<bool> {
+        (**self).sleep_until_readable(duration, fds)
+    }
+}
+
+/// The system's clocks, and `thread::sleep`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn sleep_until_readable(&self, duration: Duration, fds: &[BorrowedFd<'_>]) -> Vec<bool> {
+        if !fds.is_empty()
+            && let Ok(ready) = sys::poll_readable(fds, duration)
+        {
+            return ready;
+        }
+        thread::sleep(duration);
+        vec![false; fds.len()]
+    }
+}
+
+/// A clock standing still until it is advanced, which sleeping does at
+/// once.
+#[derive(Debug)]
+pub struct MockClock {
+    start: Instant,
+    wall: SystemTime,
+    elapsed: Mutex<Duration>,
+}
+
+impl MockClock {
+    /// A clock whose wall-clock time is `wall` until it is advanced.
+    #[must_use]
+    pub fn new(wall: SystemTime) -> Self {
+        Self {
+            start: Instant::now(),
+            wall,
+            elapsed: Mutex::default(),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+
+    /// How far the clock was moved forward, by sleeping or advancing.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn system_time(&self) -> SystemTime {
+        self.wall + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::new(UNIX_EPOCH);
+        let start = clock.now();
+        clock.sleep(Duration::from_secs(5));
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(clock.now() - start, Duration::from_millis(5500));
+        assert_eq!(
+            clock.system_time(),
+            UNIX_EPOCH + Duration::from_millis(5500)
+        );
+        assert_eq!(clock.elapsed(), Duration::from_millis(5500));
+    }
+}
@@ -0,0 +1,226 @@
+//! Process names, stored inline: the kernel's are at most 15 bytes, so a
+//! scan of many processes allocates nothing for them.
+//!
+//! A name is whatever bytes its process was given, so names are matched
+//! as bytes and escaped for output, where a hostile one could otherwise
+//! move the cursor, clear the screen or reverse the text after it.
+
+use std::{
+    borrow::Borrow,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// Longest name kept inline, the kernel's `TASK_COMM_LEN` of 16 with room
+/// for the trailing newline `/proc/<pid>/comm` adds.
+const INLINE: usize = 16;
+
+/// A process name, as raw bytes, inline up to 16 bytes and on the heap
+/// beyond that, for kernels with longer names.
+#[derive(Clone)]
+pub struct Comm(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, bytes: [u8; INLINE] },
+    Heap(Box<[u8]>),
+}
+
+impl Comm {
+    #[must_use]
+    pub fn new(name: &[u8]) -> Self {
+        match u8::try_from(name.len()) {
+            Ok(len) if name.len() <= INLINE => {
+                let mut bytes = [0; INLINE];
+                bytes[..name.len()].copy_from_slice(name);
+                Self(Repr::Inline { len, bytes })
+            }
+            _ => Self(Repr::Heap(name.into())),
+        }
+    }
+
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Inline { len, bytes } => &bytes[..usize::from(*len)],
+            Repr::Heap(bytes) => bytes,
+        }
+    }
+
+    /// The name as text that is safe to print, see `escape`.
+    #[must_use]
+    pub fn escaped(&self) -> Escaped<'_> {
+        escape(self.as_bytes())
+    }
+}
+
+/// `name` as it is safe to print, with no two names printed alike.
+///
+/// Printable UTF-8 is kept as it is, control characters and bytes that
+/// aren't UTF-8 become `\xNN`, characters that reorder text `\u{NNNN}`,
+/// and backslashes are doubled.
+#[must_use]
+pub const fn escape(name: &[u8]) -> Escaped<'_> {
+    Escaped(name)
+}
+
+/// A name escaped as it is written, see `escape`.
+#[derive(Debug, Clone, Copy)]
+pub struct Escaped<'a>(&'a [u8]);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use fmt::Write as _;
+        for chunk in self.0.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                match c {
+                    '\\' => f.write_str("\\\\")?,
+                    c if c.is_control() => {
+                        for byte in c.encode_utf8(&mut [0; 4]).bytes() {
+                            write!(f, "\\x{byte:02x}")?;
+                        }
+                    }
+                    c if reorders(c) => write!(f, "\\u{{{:04x}}}", u32::from(c))?,
+                    c => f.write_char(c)?,
+                }
+            }
+            for byte in chunk.invalid() {
+                write!(f, "\\x{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `c` is one of the marks, embeddings, overrides and isolates
+/// that change the direction of the text around it.
+const fn reorders(c: char) -> bool {
+    matches!(c, '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+impl Default for Comm {
+    fn default() -> Self {
+        Self::new(b"")
+    }
+}
+
+impl Deref for Comm {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for Comm {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<[u8]> for Comm {
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for Comm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&String::from_utf8_lossy(self.as_bytes()), f)
+    }
+}
+
+impl PartialEq for Comm {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for Comm {}
+
+impl Hash for Comm {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+impl PartialEq<[u8]> for Comm {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_bytes() == other
+    }
+}
+
+impl PartialEq<Vec<u8>> for Comm {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_bytes() == other.as_slice()
+    }
+}
+
+impl<const N: usize> PartialEq<&[u8; N]> for Comm {
+    fn eq(&self, other: &&[u8; N]) -> bool {
+        self.as_bytes() == other.as_slice()
+    }
+}
+
+impl From<&[u8]> for Comm {
+    fn from(name: &[u8]) -> Self {
+        Self::new(name)
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for Comm {
+    fn from(name: &[u8; N]) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<Vec<u8>> for Comm {
+    fn from(name: Vec<u8>) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl From<&str> for Comm {
+    fn from(name: &str) -> Self {
+        Self::new(name.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comm() {
+        let short = Comm::from("kworker/0:1H");
+        assert!(matches!(short.0, Repr::Inline { len: 12, .. }));
+        assert_eq!(short, b"kworker/0:1H");
+        assert_eq!(format!("{short:?}"), "\"kworker/0:1H\"");
+
+        let long = Comm::new(&[b'x'; 40]);
+        assert!(matches!(long.0, Repr::Heap(_)));
+        assert_eq!(long.len(), 40);
+        assert_eq!(long, vec![b'x'; 40]);
+
+        assert!(Comm::default().is_empty());
+        assert_eq!(Comm::new(b"sh"), Comm::from(b"sh".to_vec()));
+        assert_eq!(size_of::<Comm>(), 24);
+    }
+
+    #[test]
+    fn test_escape() {
+        let escaped = |name: &[u8]| escape(name).to_string();
+        assert_eq!(escaped(b"kworker/0:1H"), "kworker/0:1H");
+        assert_eq!(escaped("bäckerei".as_bytes()), "bäckerei");
+        assert_eq!(escaped(b"evil\x1b[2J"), "evil\\x1b[2J");
+        assert_eq!(escaped(b"two\nlines\t"), "two\\x0alines\\x09");
+        assert_eq!(escaped(b"bad\xff\xfeutf8"), "bad\\xff\\xfeutf8");
+        // A C1 control character, as its UTF-8 bytes.
+        assert_eq!(escaped("csi\u{9b}".as_bytes()), "csi\\xc2\\x9b");
+        assert_eq!(escaped("gpj.\u{202e}exe".as_bytes()), "gpj.\\u{202e}exe");
+        // Escapes in a name don't pass for the bytes they stand for.
+        assert_eq!(escaped(b"back\\x1b"), "back\\\\x1b");
+        assert_eq!(Comm::from("\x7f").escaped().to_string(), "\\x7f");
+    }
+}
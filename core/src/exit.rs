@@ -0,0 +1,62 @@
+//! Exit statuses of the binary, with the meanings pgrep and pkill document
+//! in procps, and one of faulx's own for a missing `/proc`.
+
+use std::process;
+
+use crate::sys::WaitStatus;
+
+/// How a run of killall, pgrep or pkill ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitCode {
+    /// One or more processes matched, and the action succeeded on them.
+    Matched,
+    /// No process matched, or the action failed on all of them. killall
+    /// also exits with this when one of its names matched nothing.
+    NoneMatched,
+    /// The command line was invalid, e.g. an unknown signal or a pattern
+    /// that does not compile.
+    Usage,
+    /// Something else went wrong, such as failing to read `/proc` or to
+    /// write the output.
+    Internal,
+    /// `/proc` is not mounted, and what was asked needs it.
+    NoProc,
+    /// A SIGINT or SIGTERM stopped the run before it was done, as with
+    /// Ctrl-C halfway through a mass kill.
+    Interrupted,
+}
+
+impl ExitCode {
+    /// The status the process exits with.
+    #[must_use]
+    pub const fn code(self) -> u8 {
+        match self {
+            Self::Matched => 0,
+            Self::NoneMatched => 1,
+            Self::Usage => 2,
+            Self::Internal => 3,
+            Self::NoProc => 4,
+            // 128 + SIGINT, like shells report a command Ctrl-C ended.
+            Self::Interrupted => 130,
+        }
+    }
+}
+
+/// The status to exit with for a child that ended with `status`: its own,
+/// or 128 plus the signal that killed it, like shells report it. `None` if
+/// it has not ended.
+#[must_use]
+pub fn wait_status_code(status: WaitStatus) -> Option<u8> {
+    let code = match status {
+        WaitStatus::Exited(_, code) => code,
+        WaitStatus::Signaled(_, signal) => 128 + signal,
+        _ => return None,
+    };
+    Some(u8::try_from(code).unwrap_or(u8::MAX))
+}
+
+impl From<ExitCode> for process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        Self::from(code.code())
+    }
+}
@@ -0,0 +1,661 @@
+//! `--where`: a small expression language over process attributes, for the
+//! one-off selections that would otherwise take a dozen flags, as in
+//! `user=alice && rss>1G && comm~^work`.
+//!
+//! A comparison is `FIELD OP VALUE`. Comparisons combine with `&&`, `||`,
+//! `!` and parentheses, `&&` binding tighter than `||`. A value runs up to
+//! the next space, `)`, `&&` or `||`, unless quoted with `'` or `"`. At
+//! most `MAX_DEPTH` `!` and parentheses may nest.
+//!
+//! | field     | value                                  | operators      |
+//! |-----------|----------------------------------------|----------------|
+//! | `comm`    | command name; a pattern for `~`        | `= != ~ !~`    |
+//! | `user`    | user name or UID                       | `= !=`         |
+//! | `state`   | state letter from `ps`, as in `Z`      | `= !=`         |
+//! | `uid`, `pid`, `ppid`, `pgrp`, `session` | number   | `= != < <= > >=` |
+//! | `rss`     | bytes, with `K`, `M` or `G`            | `= != < <= > >=` |
+//! | `cpu`     | CPU time, as in `90s`, `5m` or `2h`    | `= != < <= > >=` |
+//! | `age`     | time since start, as in `30s` or `1d`  | `= != < <= > >=` |
+//!
+//! `!=` and `!~` match whatever `=` and `~` don't, processes whose field
+//! can't be read included.
+
+use std::{cmp::Ordering, fmt, path::Path, str::FromStr};
+
+use crate::{
+    filters::{AllOf, AnyOf, Filter, NameFilter, Not, PatternFilter, UserFilter},
+    processes::{ProcessInfo, page_size},
+    time::{clock_ticks, uptime},
+    units::{parse_duration, parse_size},
+};
+
+/// A process attribute `--where` can compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Comm,
+    User,
+    State,
+    Uid,
+    Pid,
+    Ppid,
+    Pgrp,
+    Session,
+    Rss,
+    Cpu,
+    Age,
+}
+
+/// What a field's values are, which decides the operators it takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Name,
+    User,
+    State,
+    Number,
+    Size,
+    Duration,
+}
+
+impl Field {
+    const ALL: [Self; 11] = [
+        Self::Comm,
+        Self::User,
+        Self::State,
+        Self::Uid,
+        Self::Pid,
+        Self::Ppid,
+        Self::Pgrp,
+        Self::Session,
+        Self::Rss,
+        Self::Cpu,
+        Self::Age,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Comm => "comm",
+            Self::User => "user",
+            Self::State => "state",
+            Self::Uid => "uid",
+            Self::Pid => "pid",
+            Self::Ppid => "ppid",
+            Self::Pgrp => "pgrp",
+            Self::Session => "session",
+            Self::Rss => "rss",
+            Self::Cpu => "cpu",
+            Self::Age => "age",
+        }
+    }
+
+    const fn kind(self) -> Kind {
+        match self {
+            Self::Comm => Kind::Name,
+            Self::User => Kind::User,
+            Self::State => Kind::State,
+            Self::Uid | Self::Pid | Self::Ppid | Self::Pgrp | Self::Session => Kind::Number,
+            Self::Rss => Kind::Size,
+            Self::Cpu | Self::Age => Kind::Duration,
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `~`, the command name matching a pgrep-style pattern.
+    Matches,
+    NotMatches,
+}
+
+impl Op {
+    /// Longest first, so that `<=` isn't read as `<` followed by `=`.
+    const ALL: [(&'static str, Self); 9] = [
+        ("!~", Self::NotMatches),
+        ("!=", Self::Ne),
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("==", Self::Eq),
+        ("=", Self::Eq),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+        ("~", Self::Matches),
+    ];
+
+    const fn allowed(self, kind: Kind) -> bool {
+        match self {
+            Self::Eq | Self::Ne => true,
+            Self::Matches | Self::NotMatches => matches!(kind, Kind::Name),
+            Self::Lt | Self::Le | Self::Gt | Self::Ge => {
+                matches!(kind, Kind::Number | Kind::Size | Kind::Duration)
+            }
+        }
+    }
+
+    /// The operator this one negates, for `!=` and `!~`.
+    const fn negates(self) -> Option<Self> {
+        match self {
+            Self::Ne => Some(Self::Eq),
+            Self::NotMatches => Some(Self::Matches),
+            _ => None,
+        }
+    }
+
+    /// Whether a field comparing as `ordering` to the value passes.
+    const fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering.is_eq(),
+            Self::Lt => ordering.is_lt(),
+            Self::Le => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::Ge => ordering.is_ge(),
+            Self::Ne | Self::Matches | Self::NotMatches => false,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Matches => "~",
+            Self::NotMatches => "!~",
+        })
+    }
+}
+
+/// A parsed `--where` expression, checked but not yet resolved against the
+/// system: user names are looked up by `compile`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Compare { field: Field, op: Op, value: String },
+    Not(Box<Self>),
+    And(Vec<Self>),
+    Or(Vec<Self>),
+}
+
+impl Expr {
+    /// Turns the expression into a filter for processes under `root`.
+    pub fn compile(&self, root: &Path) -> Result<Box<dyn Filter>, String> {
+        let all = |exprs: &[Self]| {
+            exprs
+                .iter()
+                .map(|expr| expr.compile(root))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(match self {
+            Self::Compare { field, op, value } => compare(root, *field, *op, value)?,
+            Self::Not(expr) => Box::new(Not(expr.compile(root)?)),
+            Self::And(exprs) => Box::new(AllOf(all(exprs)?)),
+            Self::Or(exprs) => Box::new(AnyOf(all(exprs)?)),
+        })
+    }
+
+    /// How tightly the expression binds, to know when it needs parentheses.
+    const fn precedence(&self) -> u8 {
+        match self {
+            Self::Or(_) => 0,
+            Self::And(_) => 1,
+            Self::Compare { .. } | Self::Not(_) => 2,
+        }
+    }
+
+    fn fmt_within(&self, f: &mut fmt::Formatter<'_>, precedence: u8) -> fmt::Result {
+        if self.precedence() < precedence {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+/// Reads the value of a numeric field from a process, if it can.
+type Getter = Box<dyn Fn(&ProcessInfo) -> Option<u64> + Send + Sync>;
+
+fn compare(root: &Path, field: Field, op: Op, value: &str) -> Result<Box<dyn Filter>, String> {
+    if let Some(op) = op.negates() {
+        return Ok(Box::new(Not(compare(root, field, op, value)?)));
+    }
+    let ticks = clock_ticks();
+    let (get, threshold): (Getter, u64) = match field {
+        Field::Comm if op == Op::Matches => {
+            return Ok(Box::new(PatternFilter::new(value, false)?));
+        }
+        Field::Comm => return Ok(Box::new(NameFilter::new(value))),
+        Field::User => return Ok(Box::new(UserFilter::from_user(value)?)),
+        Field::State => {
+            let state = value.as_bytes()[0];
+            return Ok(Box::new(move |process: &ProcessInfo| {
+                process.stat().is_some_and(|stat| stat.state == state)
+            }));
+        }
+        Field::Uid => (
+            Box::new(|process: &ProcessInfo| process.uid().map(u64::from)),
+            parse_value(field, value)?,
+        ),
+        Field::Pid => (
+            Box::new(|process: &ProcessInfo| u64::try_from(process.pid.as_raw()).ok()),
+            parse_value(field, value)?,
+        ),
+        Field::Ppid | Field::Pgrp | Field::Session => (
+            Box::new(move |process: &ProcessInfo| {
+                let stat = process.stat()?;
+                let id = match field {
+                    Field::Ppid => stat.ppid,
+                    Field::Pgrp => stat.pgrp,
+                    _ => stat.session,
+                };
+                u64::try_from(id).ok()
+            }),
+            parse_value(field, value)?,
+        ),
+        Field::Rss => {
+            let page_size = page_size();
+            (
+                Box::new(move |process: &ProcessInfo| {
+                    process.stat().map(|stat| stat.rss * page_size)
+                }),
+                parse_value(field, value)?,
+            )
+        }
+        // Compared in clock ticks rather than seconds, so that `cpu>10`
+        // doesn't leave out 10.5 seconds.
+        Field::Cpu => (
+            Box::new(|process: &ProcessInfo| process.stat().map(|stat| stat.utime + stat.stime)),
+            parse_value(field, value)?.saturating_mul(ticks),
+        ),
+        Field::Age => {
+            let now = uptime(root).ok_or("age: can't read the uptime")?;
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_precision_loss,
+                clippy::cast_sign_loss
+            )] // uptime is positive and far from the limits
+            let now = (now * ticks as f64) as u64;
+            (
+                Box::new(move |process: &ProcessInfo| {
+                    process
+                        .stat()
+                        .map(|stat| now.saturating_sub(stat.starttime))
+                }),
+                parse_value(field, value)?.saturating_mul(ticks),
+            )
+        }
+    };
+    Ok(Box::new(move |process: &ProcessInfo| {
+        get(process).is_some_and(|actual| op.holds(actual.cmp(&threshold)))
+    }))
+}
+
+/// Parses the value of a numeric field: in bytes for `rss`, in seconds for
+/// `cpu` and `age`.
+fn parse_value(field: Field, value: &str) -> Result<u64, String> {
+    let parsed = match field.kind() {
+        // `rss=0` is meaningful, for kernel threads, but not a valid size.
+        Kind::Size => value.parse().or_else(|_| parse_size(value)),
+        Kind::Duration => parse_duration(value).map(|duration| duration.as_secs()),
+        _ => value
+            .parse()
+            .map_err(|_| format!("invalid number: {value}")),
+    };
+    parsed.map_err(|e| format!("{field}: {e}"))
+}
+
+/// Checks what can be checked of a comparison without the system.
+fn validate(field: Field, op: Op, value: &str) -> Result<(), String> {
+    match field.kind() {
+        Kind::Name if matches!(op, Op::Matches | Op::NotMatches) => {
+            PatternFilter::new(value, false).map(|_| ())
+        }
+        Kind::Name | Kind::User => Ok(()),
+        Kind::State if value.len() == 1 => Ok(()),
+        Kind::State => Err(format!("state: expected one letter, got {value}")),
+        Kind::Number | Kind::Size | Kind::Duration => parse_value(field, value).map(|_| ()),
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Shows the expression in a form `parse` reads back.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let joined = |f: &mut fmt::Formatter<'_>, exprs: &[Self], sep| {
+            for (n, expr) in exprs.iter().enumerate() {
+                if n > 0 {
+                    f.write_str(sep)?;
+                }
+                expr.fmt_within(f, self.precedence() + 1)?;
+            }
+            Ok(())
+        };
+        match self {
+            Self::Compare { field, op, value } if is_bare(value) => write!(f, "{field}{op}{value}"),
+            Self::Compare { field, op, value } if value.contains('\'') => {
+                write!(f, "{field}{op}\"{value}\"")
+            }
+            Self::Compare { field, op, value } => write!(f, "{field}{op}'{value}'"),
+            Self::Not(expr) => {
+                f.write_str("!")?;
+                expr.fmt_within(f, 2)
+            }
+            Self::And(exprs) => joined(f, exprs, " && "),
+            Self::Or(exprs) => joined(f, exprs, " || "),
+        }
+    }
+}
+
+/// Whether `value` reads back the same without quotes.
+fn is_bare(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with(['\'', '"'])
+        && !value.contains(|c: char| c.is_whitespace() || c == ')')
+        && !value.contains("&&")
+        && !value.contains("||")
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            input: s,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.or()?;
+        parser.skip_spaces();
+        if parser.pos < s.len() {
+            return Err(parser.error("expected && or ||"));
+        }
+        Ok(expr)
+    }
+}
+
+/// How deeply `!` and parentheses may nest, so that neither parsing nor
+/// dropping the expression can overflow the stack.
+pub const MAX_DEPTH: usize = 64;
+
+/// A recursive descent parser, one method per precedence level.
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips spaces, then `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_spaces();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn error(&self, what: &str) -> String {
+        format!(
+            "{what} at column {}",
+            self.input[..self.pos].chars().count() + 1
+        )
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut exprs = vec![self.and()?];
+        while self.eat("||") {
+            exprs.push(self.and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::Or(exprs)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut exprs = vec![self.unary()?];
+        while self.eat("&&") {
+            exprs.push(self.unary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            Expr::And(exprs)
+        })
+    }
+
+    /// Parses with `parse` one level deeper, up to `MAX_DEPTH`, after the
+    /// one-byte `!` or `(` just eaten.
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        if self.depth == MAX_DEPTH {
+            self.pos -= 1;
+            return Err(self.error("expression nested too deeply"));
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.nested(Self::unary)?)));
+        }
+        if self.eat("(") {
+            let expr = self.nested(Self::or)?;
+            if !self.eat(")") {
+                return Err(self.error("expected )"));
+            }
+            return Ok(expr);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.skip_spaces();
+        let start = self.pos;
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let name = &rest[..len];
+        let Some(field) = Field::ALL.into_iter().find(|field| field.name() == name) else {
+            let fields: Vec<_> = Field::ALL.iter().map(|field| field.name()).collect();
+            return Err(self.error(&format!("expected one of {}", fields.join(", "))));
+        };
+        self.pos += len;
+
+        self.skip_spaces();
+        let Some(&(token, op)) = Op::ALL
+            .iter()
+            .find(|(token, _)| self.rest().starts_with(token))
+        else {
+            return Err(self.error("expected a comparison"));
+        };
+        if !op.allowed(field.kind()) {
+            return Err(self.error(&format!("{field} can't be compared with {op}")));
+        }
+        self.pos += token.len();
+
+        let value = self.value()?;
+        validate(field, op, &value).map_err(|e| {
+            self.pos = start;
+            self.error(&e)
+        })?;
+        Ok(Expr::Compare { field, op, value })
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        self.skip_spaces();
+        let rest = self.rest();
+        if let Some(quote) = rest.chars().next().filter(|c| matches!(c, '\'' | '"')) {
+            let Some(len) = rest[1..].find(quote) else {
+                return Err(self.error("unterminated quote"));
+            };
+            self.pos += len + 2;
+            return Ok(rest[1..=len].to_owned());
+        }
+        let len = rest
+            .char_indices()
+            .find(|&(at, c)| {
+                c.is_whitespace()
+                    || c == ')'
+                    || rest[at..].starts_with("&&")
+                    || rest[at..].starts_with("||")
+            })
+            .map_or(rest.len(), |(at, _)| at);
+        if len == 0 {
+            return Err(self.error("expected a value"));
+        }
+        self.pos += len;
+        Ok(rest[..len].to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat_fields,
+    };
+    use std::{fs, sync::Arc};
+
+    fn compare(field: Field, op: Op, value: &str) -> Expr {
+        Expr::Compare {
+            field,
+            op,
+            value: value.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "user=alice && rss>1G && comm~^work".parse(),
+            Ok(Expr::And(vec![
+                compare(Field::User, Op::Eq, "alice"),
+                compare(Field::Rss, Op::Gt, "1G"),
+                compare(Field::Comm, Op::Matches, "^work"),
+            ]))
+        );
+        assert_eq!(
+            "!(pid <= 10 || state == Z)&&comm!='my app'".parse(),
+            Ok(Expr::And(vec![
+                Expr::Not(Box::new(Expr::Or(vec![
+                    compare(Field::Pid, Op::Le, "10"),
+                    compare(Field::State, Op::Eq, "Z"),
+                ]))),
+                compare(Field::Comm, Op::Ne, "my app"),
+            ]))
+        );
+        assert_eq!(
+            "age>1h ||cpu>=90s && uid!=0".parse(),
+            Ok(Expr::Or(vec![
+                compare(Field::Age, Op::Gt, "1h"),
+                Expr::And(vec![
+                    compare(Field::Cpu, Op::Ge, "90s"),
+                    compare(Field::Uid, Op::Ne, "0"),
+                ]),
+            ]))
+        );
+
+        let error = |s: &str| s.parse::<Expr>().unwrap_err();
+        assert_eq!(
+            error("rss>1G && nmae=x"),
+            "expected one of comm, user, state, uid, pid, ppid, pgrp, session, rss, cpu, age \
+             at column 11"
+        );
+        assert_eq!(
+            error("user>1000"),
+            "user can't be compared with > at column 5"
+        );
+        assert_eq!(error("rss>1T"), "rss: invalid size unit: T at column 1");
+        assert_eq!(error("pid=1 pid=2"), "expected && or || at column 7");
+        assert_eq!(error("(pid=1"), "expected ) at column 7");
+        assert_eq!(error("comm='x"), "unterminated quote at column 6");
+        assert_eq!(error("comm="), "expected a value at column 6");
+        assert!(error("state=ZZ").starts_with("state: "));
+
+        let nested = |depth| format!("{}pid=1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(nested(MAX_DEPTH).parse::<Expr>().is_ok());
+        assert_eq!(
+            error(&nested(MAX_DEPTH + 1)),
+            format!("expression nested too deeply at column {}", MAX_DEPTH + 1)
+        );
+        assert_eq!(
+            error(&format!("{}pid=1", "!".repeat(20_000))),
+            format!("expression nested too deeply at column {}", MAX_DEPTH + 1)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        for s in [
+            "user=alice && rss>1G && comm~^work",
+            "!(pid<=10 || state=Z) && comm!='my app'",
+            "age>1h || cpu>=90s && uid!=0",
+            "(pid=1 || pid=2) && !comm=\"it's mine\"",
+        ] {
+            let expr: Expr = s.parse().unwrap();
+            assert_eq!(expr.to_string(), s);
+            assert_eq!(expr.to_string().parse(), Ok(expr));
+        }
+    }
+
+    #[test]
+    fn test_compile() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "worker"), ("11", "worker"), ("12", "nginx")]);
+        // Started 1 and 5 seconds after boot, 10 seconds ago.
+        let ticks = clock_ticks();
+        write_fake_stat_fields(&tmp, "10", "worker", 'S', 1, 10, ticks);
+        write_fake_stat_fields(&tmp, "11", "worker", 'Z', 1, 11, 5 * ticks);
+        write_fake_stat_fields(&tmp, "12", "nginx", 'S', 1, 12, ticks);
+        fs::write(
+            tmp.join("11/status"),
+            "Name:\tworker\nUid:\t33\t33\t33\t33\n",
+        )
+        .unwrap();
+        fs::write(tmp.join("uptime"), "10.00 20.00\n").unwrap();
+
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        let selected = |s: &str| {
+            let filter = s.parse::<Expr>().unwrap().compile(&tmp).unwrap();
+            (10..=12)
+                .filter(|&raw| filter.matches(&ProcessInfo::by_pid(Arc::clone(&root), pid(raw))))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(selected("comm~ork && state!=Z"), [10]);
+        assert_eq!(selected("uid=33 || pid=12"), [11, 12]);
+        assert_eq!(selected("!uid=33"), [10, 12]);
+        assert_eq!(selected("pgrp>=11"), [11, 12]);
+        assert_eq!(selected("rss>0"), Vec::<i32>::new());
+        assert_eq!(selected("age>5s"), [10, 12]);
+        assert_eq!(selected("age>=5s"), [10, 11, 12]);
+
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,476 @@
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    processes::{FileId, ProcessInfo, open_path},
+    sys,
+};
+
+/// A predicate deciding whether a scanned process is selected.
+///
+/// Filters run inside the scan loop (in parallel with the `rayon` feature),
+/// so they must be `Send + Sync`.
+pub trait Filter: Send + Sync {
+    fn matches(&self, process: &ProcessInfo) -> bool;
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&ProcessInfo) -> bool + Send + Sync,
+{
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self(process)
+    }
+}
+
+impl Filter for Box<dyn Filter> {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        (**self).matches(process)
+    }
+}
+
+/// A filter shared by several pipelines, such as the one killall builds
+/// for each name.
+#[derive(Clone)]
+pub struct Shared(Arc<dyn Filter>);
+
+impl Shared {
+    #[must_use]
+    pub fn new(filter: impl Filter + 'static) -> Self {
+        Self(Arc::new(filter))
+    }
+}
+
+impl Filter for Shared {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self.0.matches(process)
+    }
+}
+
+/// Matches the processes `F` rejects, as `--not-user` does.
+#[derive(Debug, Clone)]
+pub struct Not<F>(pub F);
+
+impl<F: Filter> Filter for Not<F> {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        !self.0.matches(process)
+    }
+}
+
+/// Matches processes that at least one of its filters accepts, for
+/// `--any`. Without filters, it matches nothing.
+pub struct AnyOf(pub Vec<Box<dyn Filter>>);
+
+impl Filter for AnyOf {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self.0.iter().any(|filter| filter.matches(process))
+    }
+}
+
+/// Matches processes that every one of its filters accepts, as a pipeline
+/// does, for grouping inside `AnyOf`. Without filters, it matches all.
+pub struct AllOf(pub Vec<Box<dyn Filter>>);
+
+impl Filter for AllOf {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self.0.iter().all(|filter| filter.matches(process))
+    }
+}
+
+/// Matches processes whose command name is exactly `name`.
+#[derive(Debug, Clone)]
+pub struct NameFilter {
+    name: Vec<u8>,
+}
+
+impl NameFilter {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl Filter for NameFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        process.comm == self.name
+    }
+}
+
+/// Matches processes running the file at a path, which is what killall
+/// takes a name with a slash in it for.
+///
+/// The file is opened once with `O_PATH` and compared with what each
+/// process runs by device and inode, so that swapping a symlink or the
+/// file under the path while scanning can't get another program matched;
+/// the handle keeps the inode from being reused meanwhile. A process
+/// still running a file deleted since, as after an upgrade replaced it,
+/// has an `exe` reading as the path followed by ` (deleted)`, and matches
+/// too.
+#[derive(Debug, Clone)]
+pub struct ExeFilter {
+    _file: Arc<File>,
+    id: FileId,
+    deleted: PathBuf,
+}
+
+impl ExeFilter {
+    pub fn new(path: &Path) -> io::Result<Self> {
+        let path = path.canonicalize()?;
+        let file = open_path(&path)?;
+        let id = FileId::of(&file)?;
+        let mut deleted = path.into_os_string();
+        deleted.push(" (deleted)");
+        Ok(Self {
+            _file: Arc::new(file),
+            id,
+            deleted: deleted.into(),
+        })
+    }
+}
+
+impl Filter for ExeFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        process.exe_id().is_ok_and(|id| id == self.id)
+            || process.link("exe").is_ok_and(|exe| exe == self.deleted)
+    }
+}
+
+/// Matches process names against a pgrep-style pattern: an extended
+/// regular expression with the `regex` feature, a plain substring without
+/// it. With `exact`, the whole name must match.
+#[derive(Debug, Clone)]
+pub struct PatternFilter {
+    #[cfg(feature = "regex")]
+    regex: regex::bytes::Regex,
+    #[cfg(not(feature = "regex"))]
+    pattern: Vec<u8>,
+    #[cfg(not(feature = "regex"))]
+    exact: bool,
+}
+
+impl PatternFilter {
+    #[cfg(feature = "regex")]
+    pub fn new(pattern: &str, exact: bool) -> Result<Self, String> {
+        let pattern = if exact {
+            format!("^(?:{pattern})$")
+        } else {
+            pattern.to_owned()
+        };
+        let regex = regex::bytes::Regex::new(&pattern).map_err(|e| e.to_string())?;
+        Ok(Self { regex })
+    }
+
+    #[cfg(not(feature = "regex"))]
+    pub fn new(pattern: &str, exact: bool) -> Result<Self, String> {
+        Ok(Self {
+            pattern: pattern.as_bytes().to_vec(),
+            exact,
+        })
+    }
+}
+
+impl Filter for PatternFilter {
+    #[cfg(feature = "regex")]
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self.regex.is_match(&process.comm)
+    }
+
+    #[cfg(not(feature = "regex"))]
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        if self.exact {
+            process.comm == self.pattern
+        } else {
+            self.pattern.is_empty()
+                || process
+                    .comm
+                    .windows(self.pattern.len())
+                    .any(|w| w == self.pattern)
+        }
+    }
+}
+
+/// Matches processes belonging to one of the given process groups.
+#[derive(Debug, Clone)]
+pub struct GroupFilter {
+    groups: Vec<i32>,
+}
+
+impl GroupFilter {
+    #[must_use]
+    pub fn new(mut groups: Vec<i32>) -> Self {
+        groups.sort_unstable();
+        groups.dedup();
+        Self { groups }
+    }
+}
+
+impl Filter for GroupFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        process
+            .stat()
+            .is_some_and(|stat| self.groups.binary_search(&stat.pgrp).is_ok())
+    }
+}
+
+/// Matches processes whose real user is one of the given users.
+#[derive(Debug, Clone)]
+pub struct UserFilter {
+    uids: Vec<u32>,
+}
+
+impl UserFilter {
+    #[must_use]
+    pub fn new(mut uids: Vec<u32>) -> Self {
+        uids.sort_unstable();
+        uids.dedup();
+        Self { uids }
+    }
+
+    /// Resolves a user name or numeric UID.
+    pub fn from_user(user: &str) -> Result<Self, String> {
+        let uid = match user.parse() {
+            Ok(uid) => uid,
+            Err(_) => sys::user_id(user)
+                .map_err(|e| format!("{user}: {e}"))?
+                .ok_or_else(|| format!("{user}: no such user"))?,
+        };
+        Ok(Self::new(vec![uid]))
+    }
+
+    /// Resolves several user names or UIDs, any of which matches.
+    pub fn from_users(users: &[String]) -> Result<Self, String> {
+        let mut uids = Vec::with_capacity(users.len());
+        for user in users {
+            uids.extend(Self::from_user(user)?.uids);
+        }
+        Ok(Self::new(uids))
+    }
+}
+
+impl Filter for UserFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        process
+            .uid()
+            .is_some_and(|uid| self.uids.binary_search(&uid).is_ok())
+    }
+}
+
+/// Matches processes of the members of a group.
+///
+/// Those are the processes of the users the group database lists in it,
+/// and those with it as their own group or among their supplementary
+/// ones, which covers users having it as their primary group.
+#[derive(Debug, Clone)]
+pub struct MemberFilter {
+    gid: u32,
+    users: UserFilter,
+}
+
+impl MemberFilter {
+    /// Resolves a group name or numeric GID, and the users it lists.
+    pub fn from_group(group: &str) -> Result<Self, String> {
+        let entry = group
+            .parse()
+            .map_or_else(|_| sys::group_by_name(group), sys::group_by_gid)
+            .map_err(|e| format!("{group}: {e}"))?
+            .ok_or_else(|| format!("{group}: no such group"))?;
+        // A member without an account anymore runs nothing.
+        let uids = entry
+            .members
+            .iter()
+            .filter_map(|name| sys::user_id(name).ok().flatten())
+            .collect();
+        Ok(Self {
+            gid: entry.gid,
+            users: UserFilter::new(uids),
+        })
+    }
+}
+
+impl Filter for MemberFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self.users.matches(process) || process.gids().is_some_and(|gids| gids.contains(&self.gid))
+    }
+}
+
+/// Matches processes using a file under `path`, like `fuser -m`: through an
+/// open file descriptor, their working or root directory, their executable
+/// or a memory mapping.
+#[derive(Debug, Clone)]
+pub struct HoldingFilter {
+    path: PathBuf,
+}
+
+impl HoldingFilter {
+    /// Resolves `path` so it compares equal to the paths the kernel reports.
+    pub fn new(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            path: path.canonicalize()?,
+        })
+    }
+
+    fn holds(&self, path: &Path) -> bool {
+        path.starts_with(&self.path)
+    }
+}
+
+impl Filter for HoldingFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        ["cwd", "root", "exe"]
+            .iter()
+            .filter_map(|link| process.link(link).ok())
+            .any(|target| self.holds(&target))
+            || process
+                .fd_targets()
+                .is_ok_and(|targets| targets.iter().any(|target| self.holds(target)))
+            || process
+                .mapped_paths()
+                .is_ok_and(|paths| paths.iter().any(|path| self.holds(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::symlink};
+
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir};
+
+    fn process(comm: &str) -> ProcessInfo {
+        ProcessInfo::new(
+            Arc::from(Path::new("/nonexistent")),
+            pid(1),
+            comm.as_bytes().to_vec(),
+        )
+    }
+
+    #[test]
+    fn test_pattern_filter_substring() {
+        let filter = PatternFilter::new("ngin", false).unwrap();
+        assert!(filter.matches(&process("nginx")));
+        assert!(!filter.matches(&process("apache")));
+        assert!(
+            PatternFilter::new("", false)
+                .unwrap()
+                .matches(&process("x"))
+        );
+    }
+
+    #[test]
+    fn test_combinators() {
+        let name = |name: &'static str| -> Box<dyn Filter> { Box::new(NameFilter::new(name)) };
+        let nginx = process("nginx");
+        assert!(Not(NameFilter::new("sshd")).matches(&nginx));
+        assert!(!Not(NameFilter::new("nginx")).matches(&nginx));
+        assert!(AnyOf(vec![name("sshd"), name("nginx")]).matches(&nginx));
+        assert!(!AnyOf(Vec::new()).matches(&nginx));
+        assert!(!AllOf(vec![name("sshd"), name("nginx")]).matches(&nginx));
+        assert!(AllOf(Vec::new()).matches(&nginx));
+        let shared = Shared::new(Not(NameFilter::new("sshd")));
+        assert!(AllOf(vec![Box::new(shared.clone()), Box::new(shared)]).matches(&nginx));
+    }
+
+    #[test]
+    fn test_exe_filter() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "worker"), ("11", "other"), ("12", "worker")]);
+        let bin = tmp.canonicalize().unwrap().join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join("worker"), "v1").unwrap();
+        fs::write(bin.join("other"), "").unwrap();
+        symlink(bin.join("worker"), bin.join("current")).unwrap();
+        symlink(bin.join("worker"), tmp.join("10/exe")).unwrap();
+        symlink(bin.join("other"), tmp.join("11/exe")).unwrap();
+        // Running the worker an upgrade since replaced.
+        symlink(bin.join("worker (deleted)"), tmp.join("12/exe")).unwrap();
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        let process = |raw| ProcessInfo::by_pid(Arc::clone(&root), pid(raw));
+
+        let filter = ExeFilter::new(&bin.join("current")).unwrap();
+        assert!(filter.matches(&process(10)));
+        assert!(!filter.matches(&process(11)));
+        assert!(filter.matches(&process(12)));
+
+        // The file opened is the one matched, not what the path leads to.
+        fs::remove_file(bin.join("worker")).unwrap();
+        fs::write(bin.join("worker"), "v2").unwrap();
+        assert!(!filter.matches(&process(10)));
+        assert!(
+            ExeFilter::new(&bin.join("current"))
+                .unwrap()
+                .matches(&process(10))
+        );
+        assert!(ExeFilter::new(&bin.join("missing")).is_err());
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_member_filter() {
+        let tmp = unique_test_dir();
+        let processes = [
+            ("10", "1001", "1001", ""),
+            ("11", "1002", "4242", ""),
+            ("12", "1003", "1003", "27 4242"),
+            ("13", "1004", "1004", "27"),
+        ];
+        for (pid, uid, gid, groups) in processes {
+            setup_fake_proc(&tmp, &[(pid, "job")]);
+            fs::write(
+                tmp.join(pid).join("status"),
+                format!("Uid:\t{uid}\t{uid}\t{uid}\t{uid}\nGid:\t{gid}\t{gid}\t{gid}\t{gid}\nGroups:\t{groups}\n"),
+            )
+            .unwrap();
+        }
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        let filter = MemberFilter {
+            gid: 4242,
+            users: UserFilter::new(vec![1001]),
+        };
+        let matched: Vec<_> = processes
+            .iter()
+            .filter(|(raw, ..)| {
+                filter.matches(&ProcessInfo::by_pid(
+                    Arc::clone(&root),
+                    pid(raw.parse().unwrap()),
+                ))
+            })
+            .map(|(raw, ..)| *raw)
+            .collect();
+        assert_eq!(matched, ["10", "11", "12"]);
+
+        assert_eq!(MemberFilter::from_group("root").unwrap().gid, 0);
+        assert_eq!(MemberFilter::from_group("0").unwrap().gid, 0);
+        assert!(MemberFilter::from_group("no-such-group-faulx").is_err());
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_user_filter_from_user() {
+        assert_eq!(UserFilter::from_user("root").unwrap().uids, vec![0]);
+        assert_eq!(UserFilter::from_user("1000").unwrap().uids, vec![1000]);
+        assert!(UserFilter::from_user("no-such-user-faulx").is_err());
+    }
+
+    #[test]
+    fn test_pattern_filter_exact() {
+        let filter = PatternFilter::new("nginx", true).unwrap();
+        assert!(filter.matches(&process("nginx")));
+        assert!(!filter.matches(&process("nginx-worker")));
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_pattern_filter_regex() {
+        let filter = PatternFilter::new("^(ba|z)sh$", false).unwrap();
+        assert!(filter.matches(&process("bash")));
+        assert!(filter.matches(&process("zsh")));
+        assert!(!filter.matches(&process("fish")));
+        assert!(PatternFilter::new("(", false).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Where signals go: the kernel, or a recorder standing in for it in tests
+//! so that escalation, ordering and error handling can be checked without
+//! signalling anything.
+
+use std::{
+    collections::HashMap,
+    io,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use crate::{
+    pid::Pid,
+    signals::{Signal, probe_signal, send_signal, send_thread_signal},
+};
+
+/// Delivers signals to processes and threads.
+pub trait Killer {
+    /// Sends `signal` to the process `pid`.
+    fn kill(&self, pid: Pid, signal: Signal) -> io::Result<()>;
+
+    /// Sends `signal` to the thread `thread` of the process `pid` only.
+    fn kill_thread(&self, pid: Pid, thread: Pid, signal: Signal) -> io::Result<()>;
+
+    /// Checks that the process `pid` could be sent a signal, without
+    /// sending one.
+    fn probe(&self, pid: Pid) -> io::Result<()>;
+}
+
+impl<K: Killer + ?Sized> Killer for &K {
+    fn kill(&self, pid: Pid, signal: Signal) -> io::Result<()> {
+        (**self).kill(pid, signal)
+    }
+
+    fn kill_thread(&self, pid: Pid, thread: Pid, signal: Signal) -> io::Result<()> {
+        (**self).kill_thread(pid, thread, signal)
+    }
+
+    fn probe(&self, pid: Pid) -> io::Result<()> {
+        (**self).probe(pid)
+    }
+}
+
+impl<K: Killer + ?Sized> Killer for Arc<K> {
+    fn kill(&self, pid: Pid, signal: Signal) -> io::Result<()> {
+        (**self).kill(pid, signal)
+    }
+
+    fn kill_thread(&self, pid: Pid, thread: Pid, signal: Signal) -> io::Result<()> {
+        (**self).kill_thread(pid, thread, signal)
+    }
+
+    fn probe(&self, pid: Pid) -> io::Result<()> {
+        (**self).probe(pid)
+    }
+}
+
+/// Sends real signals, with `kill(2)` and `tgkill(2)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemKiller;
+
+impl Killer for SystemKiller {
+    fn kill(&self, pid: Pid, signal: Signal) -> io::Result<()> {
+        send_signal(pid, signal)
+    }
+
+    fn kill_thread(&self, pid: Pid, thread: Pid, signal: Signal) -> io::Result<()> {
+        send_thread_signal(pid, thread, signal)
+    }
+
+    fn probe(&self, pid: Pid) -> io::Result<()> {
+        probe_signal(pid)
+    }
+}
+
+/// A signal a `RecordingKiller` was asked to send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sent {
+    pub pid: Pid,
+    /// The thread, for signals sent to a single thread.
+    pub thread: Option<Pid>,
+    pub signal: Signal,
+}
+
+/// Records the signals it is asked to send instead of sending them, and
+/// fails those to the PIDs it was told to.
+#[derive(Debug, Default)]
+pub struct RecordingKiller {
+    sent: Mutex<Vec<Sent>>,
+    failures: HashMap<Pid, i32>,
+}
+
+impl RecordingKiller {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fails every signal to `pid` with the OS error `errno`, like
+    /// `libc::EPERM`.
+    #[must_use]
+    pub fn fail(mut self, pid: Pid, errno: i32) -> Self {
+        self.failures.insert(pid, errno);
+        self
+    }
+
+    /// Every signal asked for so far, in order, including those that failed.
+    #[must_use]
+    pub fn sent(&self) -> Vec<Sent> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn record(&self, sent: Sent) -> io::Result<()> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sent);
+        self.check(sent.pid)
+    }
+
+    fn check(&self, pid: Pid) -> io::Result<()> {
+        match self.failures.get(&pid) {
+            Some(&errno) => Err(io::Error::from_raw_os_error(errno)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Killer for RecordingKiller {
+    fn kill(&self, pid: Pid, signal: Signal) -> io::Result<()> {
+        self.record(Sent {
+            pid,
+            thread: None,
+            signal,
+        })
+    }
+
+    fn kill_thread(&self, pid: Pid, thread: Pid, signal: Signal) -> io::Result<()> {
+        self.record(Sent {
+            pid,
+            thread: Some(thread),
+            signal,
+        })
+    }
+
+    /// Fails like signals to `pid` would, without recording anything.
+    fn probe(&self, pid: Pid) -> io::Result<()> {
+        self.check(pid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pid;
+
+    #[test]
+    fn test_recording_killer() {
+        let killer = RecordingKiller::new().fail(pid(20), libc::EPERM);
+        assert!(killer.kill(pid(10), Signal::TERM).is_ok());
+        let err = killer
+            .kill_thread(pid(20), pid(21), Signal::KILL)
+            .unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        assert!(killer.probe(pid(10)).is_ok());
+        assert_eq!(
+            killer.probe(pid(20)).unwrap_err().raw_os_error(),
+            Some(libc::EPERM)
+        );
+        assert_eq!(
+            killer.sent(),
+            [
+                Sent {
+                    pid: pid(10),
+                    thread: None,
+                    signal: Signal::TERM,
+                },
+                Sent {
+                    pid: pid(20),
+                    thread: Some(pid(21)),
+                    signal: Signal::KILL,
+                },
+            ]
+        );
+    }
+}
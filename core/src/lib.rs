@@ -0,0 +1,35 @@
+//! Finding processes by name, pattern, user and the like in `/proc`, and
+//! signalling or otherwise acting on them.
+//!
+//! This is the library the `faulx` command line, in faulx-cli, is built on,
+//! and the one to depend on: it only takes breaking changes with its own
+//! major version, whatever becomes of the command line.
+
+pub mod actions;
+pub mod clock;
+pub mod comm;
+pub mod exit;
+pub mod expr;
+pub mod filters;
+pub mod killer;
+pub mod limits;
+pub mod permission;
+pub mod pid;
+pub mod pipeline;
+pub mod processes;
+pub mod query;
+pub mod ratelimit;
+pub mod signals;
+pub mod snapshot;
+pub mod sockets;
+pub mod sys;
+pub mod terminate;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod testing;
+pub mod time;
+pub mod units;
+pub mod wait;
+
+pub use signals::Signal;
+pub use terminate::{Policy, Termination, kill_with_policy};
@@ -0,0 +1,206 @@
+//! Keeps scans from running out of file descriptors: every file opened
+//! while scanning holds a permit, and there are only so many permits for
+//! the open file limit.
+
+use std::{
+    io,
+    os::unix::process::CommandExt,
+    process::Command,
+    sync::{Condvar, Mutex, OnceLock, PoisonError},
+};
+
+/// Upper bound on concurrently held permits, far more than the scan
+/// threads need.
+const MAX_PERMITS: usize = 256;
+
+/// The soft `RLIMIT_NOFILE` before `raise_nofile_limit` first ran.
+static ORIGINAL_NOFILE: OnceLock<libc::rlim_t> = OnceLock::new();
+
+/// Raises the soft `RLIMIT_NOFILE` to the hard limit, returning the new soft
+/// limit. Call it before scanning, as the permit count is derived from it.
+///
+/// Commands started afterwards inherit the raised limit, unless built with
+/// [`OriginalNofileLimit::original_nofile_limit`].
+pub fn raise_nofile_limit() -> io::Result<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is a valid rlimit to write to.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &raw mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    ORIGINAL_NOFILE.get_or_init(|| limit.rlim_cur);
+    if limit.rlim_cur < limit.rlim_max {
+        let raised = libc::rlimit {
+            rlim_cur: limit.rlim_max,
+            ..limit
+        };
+        // SAFETY: `raised` is a valid rlimit to read from.
+        if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raw const raised) } == 0 {
+            limit = raised;
+        }
+    }
+    Ok(limit.rlim_cur)
+}
+
+/// Sets the soft `RLIMIT_NOFILE` back to `soft`. Only makes system calls, so
+/// that it can run between `fork` and `exec`.
+fn restore_nofile_limit(soft: libc::rlim_t) -> io::Result<()> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is a valid rlimit to write to.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &raw mut limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    limit.rlim_cur = soft.min(limit.rlim_max);
+    // SAFETY: `limit` is a valid rlimit to read from.
+    if unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &raw const limit) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Starts a command with the open file limit faulx itself was started
+/// with, rather than the one `raise_nofile_limit` raised it to, which the
+/// programs it runs don't expect.
+pub trait OriginalNofileLimit {
+    fn original_nofile_limit(&mut self) -> &mut Self;
+}
+
+impl OriginalNofileLimit for Command {
+    fn original_nofile_limit(&mut self) -> &mut Self {
+        let Some(&soft) = ORIGINAL_NOFILE.get() else {
+            return self;
+        };
+        // SAFETY: the closure only makes the async-signal-safe getrlimit
+        // and setrlimit calls.
+        unsafe { self.pre_exec(move || restore_nofile_limit(soft)) }
+    }
+}
+
+/// Permits a quarter of the soft limit, leaving the rest to whatever else
+/// the process has open.
+fn permits_for_limit() -> usize {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is a valid rlimit to write to.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &raw mut limit) } != 0 {
+        return 1;
+    }
+    usize::try_from(limit.rlim_cur / 4)
+        .unwrap_or(MAX_PERMITS)
+        .clamp(1, MAX_PERMITS)
+}
+
+/// A counting semaphore.
+#[derive(Debug)]
+pub(crate) struct Permits {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+/// Returns its permit when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit<'a>(&'a Permits);
+
+impl Permits {
+    pub(crate) const fn new(count: usize) -> Self {
+        Self {
+            available: Mutex::new(count),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available.
+    pub(crate) fn acquire(&self) -> Permit<'_> {
+        let available = self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let mut available = self
+            .released
+            .wait_while(available, |available| *available == 0)
+            .unwrap_or_else(PoisonError::into_inner);
+        *available -= 1;
+        drop(available);
+        Permit(self)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self
+            .0
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) += 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// Takes one of the permits shared by every scan, to be held while a file
+/// is open.
+pub(crate) fn open_permit() -> Permit<'static> {
+    static PERMITS: OnceLock<Permits> = OnceLock::new();
+    PERMITS
+        .get_or_init(|| Permits::new(permits_for_limit()))
+        .acquire()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    #[test]
+    fn test_permits_bound_concurrency() {
+        let permits = Permits::new(3);
+        let held = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..16 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        let _permit = permits.acquire();
+                        let now = held.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        thread::sleep(Duration::from_micros(100));
+                        held.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+            }
+        });
+
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        assert_eq!(*permits.available.lock().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_raise_nofile_limit() {
+        assert!(raise_nofile_limit().unwrap() >= 1);
+        assert!((1..=MAX_PERMITS).contains(&permits_for_limit()));
+
+        let output = Command::new("sh")
+            .args(["-c", "ulimit -Sn"])
+            .original_nofile_limit()
+            .output()
+            .unwrap();
+        let soft = String::from_utf8(output.stdout).unwrap();
+        let original = ORIGINAL_NOFILE.get().unwrap();
+        if *original == libc::RLIM_INFINITY {
+            assert_eq!(soft.trim(), "unlimited");
+        } else {
+            assert_eq!(soft.trim(), original.to_string());
+        }
+    }
+}
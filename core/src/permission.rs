@@ -0,0 +1,138 @@
+//! Whether a process could be signalled, found out without signalling it,
+//! for `--all-or-nothing` and to grey out what the picker can't act on.
+
+use std::{fmt, io, path::Path, sync::Arc};
+
+use crate::{
+    pid::Pid,
+    processes::{PROC, ProcessInfo, Uids},
+    signals::probe_signal,
+    sys,
+};
+
+/// What a signal sent to a process would run into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionStatus {
+    Allowed,
+    /// The process belongs to a user this one may not signal.
+    NoPermission,
+    /// There is no such process, or no longer.
+    NotFound,
+}
+
+impl fmt::Display for PermissionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Allowed => "allowed",
+            Self::NoPermission => "not permitted",
+            Self::NotFound => "no such process",
+        })
+    }
+}
+
+/// Whether a signal sent to `pid` now would be delivered.
+#[must_use]
+pub fn can_kill(pid: Pid) -> PermissionStatus {
+    let process = ProcessInfo::by_pid(Arc::from(Path::new(PROC)), pid);
+    status(&probe_signal(pid), &process)
+}
+
+/// What `probe`, the result of sending the null signal to `process`, says
+/// about signalling it.
+///
+/// Where the null signal can't tell, as when a sandbox refuses `kill(2)`
+/// altogether, the user IDs of `process` are compared to this one's.
+#[must_use]
+pub fn status(probe: &io::Result<()>, process: &ProcessInfo) -> PermissionStatus {
+    match probe.as_ref().map_err(io::Error::raw_os_error) {
+        Ok(()) => PermissionStatus::Allowed,
+        Err(Some(libc::ESRCH)) => PermissionStatus::NotFound,
+        Err(Some(libc::EPERM)) => PermissionStatus::NoPermission,
+        Err(_) => match process.uids() {
+            Some(target) if uids_allow(sys::uid(), sys::euid(), target) => {
+                PermissionStatus::Allowed
+            }
+            Some(_) => PermissionStatus::NoPermission,
+            None => PermissionStatus::NotFound,
+        },
+    }
+}
+
+/// Whether a process with these real and effective user IDs may signal one
+/// with the user IDs `target`, by the rule of `kill(2)`: either of the
+/// former has to be the real or saved user ID of the target.
+///
+/// Root is assumed to be allowed; capabilities granted otherwise aren't
+/// looked at.
+#[must_use]
+pub const fn uids_allow(real: u32, effective: u32, target: Uids) -> bool {
+    effective == 0
+        || real == target.real
+        || real == target.saved
+        || effective == target.real
+        || effective == target.saved
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir};
+
+    #[test]
+    fn test_uids_allow() {
+        let target = Uids {
+            real: 1000,
+            effective: 0,
+            saved: 1001,
+        };
+        assert!(uids_allow(1000, 33, target));
+        assert!(uids_allow(33, 1001, target));
+        assert!(uids_allow(33, 0, target));
+        // An effective user ID matching the target's counts for nothing.
+        assert!(!uids_allow(
+            33,
+            33,
+            Uids {
+                effective: 33,
+                ..target
+            }
+        ));
+    }
+
+    #[test]
+    fn test_status() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx")]);
+        let uid = sys::uid();
+        fs::write(
+            tmp.join("10/status"),
+            format!("Name:\tnginx\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\n"),
+        )
+        .unwrap();
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        let process = ProcessInfo::by_pid(Arc::clone(&root), pid(10));
+        let errno = |errno| Err(io::Error::from_raw_os_error(errno));
+
+        assert_eq!(status(&Ok(()), &process), PermissionStatus::Allowed);
+        assert_eq!(
+            status(&errno(libc::EPERM), &process),
+            PermissionStatus::NoPermission
+        );
+        assert_eq!(
+            status(&errno(libc::ESRCH), &process),
+            PermissionStatus::NotFound
+        );
+        assert_eq!(
+            status(&errno(libc::ENOSYS), &process),
+            PermissionStatus::Allowed
+        );
+        let gone = ProcessInfo::by_pid(root, pid(11));
+        assert_eq!(
+            status(&errno(libc::ENOSYS), &gone),
+            PermissionStatus::NotFound
+        );
+        cleanup_fake_proc(&tmp);
+    }
+}
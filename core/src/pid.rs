@@ -0,0 +1,215 @@
+//! Process and thread IDs, kept within the range the kernel hands out so
+//! that the special values of `kill(2)`, 0 for the caller's process group
+//! and -1 for every process, can't be signalled by mistake.
+
+use std::{
+    fmt, fs,
+    io::{self, BufRead},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::sys;
+
+/// The highest `pid_max` the kernel accepts: 2^22 on 64-bit systems, 2^15
+/// on 32-bit ones.
+pub const PID_MAX_LIMIT: i32 = if cfg!(target_pointer_width = "64") {
+    1 << 22
+} else {
+    1 << 15
+};
+
+/// The most digits a PID below `PID_MAX_LIMIT` has.
+pub(crate) const MAX_DIGITS: usize = (PID_MAX_LIMIT - 1).ilog10() as usize + 1;
+
+/// A process or thread ID, from 1 to below `PID_MAX_LIMIT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Pid(i32);
+
+impl Pid {
+    /// `raw` as a PID, if it is in range.
+    #[must_use]
+    pub const fn new(raw: i32) -> Option<Self> {
+        if raw >= 1 && raw < PID_MAX_LIMIT {
+            Some(Self(raw))
+        } else {
+            None
+        }
+    }
+
+    /// The ID of this process.
+    #[must_use]
+    pub fn this() -> Self {
+        Self(sys::getpid())
+    }
+
+    #[must_use]
+    pub const fn as_raw(self) -> i32 {
+        self.0
+    }
+
+    /// Parses the name of a `/proc` entry, which is all digits for a
+    /// process, without leading zeros or a sign.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() || bytes.len() > MAX_DIGITS {
+            return None;
+        }
+        let mut raw: i32 = 0;
+        for &b in bytes {
+            if !b.is_ascii_digit() {
+                return None;
+            }
+            raw = raw * 10 + i32::from(b - b'0');
+        }
+        Self::new(raw)
+    }
+}
+
+impl FromStr for Pid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse()
+            .ok()
+            .and_then(Self::new)
+            .ok_or_else(|| format!("{s} is not a PID, from 1 to {}", PID_MAX_LIMIT - 1))
+    }
+}
+
+impl fmt::Display for Pid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Compares with IDs the kernel reports as plain numbers, such as a parent
+/// PID, which can be 0.
+impl PartialEq<i32> for Pid {
+    fn eq(&self, other: &i32) -> bool {
+        self.0 == *other
+    }
+}
+
+/// The highest PID the system under `root` hands out, from
+/// `sys/kernel/pid_max`.
+#[must_use]
+pub fn pid_max(root: &Path) -> Option<i32> {
+    fs::read_to_string(root.join("sys/kernel/pid_max"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// A PID read by `--stdin-pids`, with the name its process had when the
+/// list was made, if the list says.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listed {
+    pub pid: Pid,
+    pub comm: Option<String>,
+}
+
+/// Reads a PID per line, each optionally followed by the name of its
+/// process, as `ps -o pid=,comm=` prints them. Blank lines are skipped.
+pub fn read_list(reader: impl BufRead) -> io::Result<Vec<Listed>> {
+    let mut listed = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (pid, comm) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, None), |(pid, comm)| (pid, Some(comm.trim_start())));
+        let pid = pid.parse().map_err(|e: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {e}", number + 1),
+            )
+        })?;
+        listed.push(Listed {
+            pid,
+            comm: comm.map(str::to_owned),
+        });
+    }
+    Ok(listed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, unique_test_dir};
+
+    #[test]
+    fn test_new() {
+        assert_eq!(Pid::new(1).map(Pid::as_raw), Some(1));
+        assert!(Pid::new(PID_MAX_LIMIT - 1).is_some());
+        assert_eq!(Pid::new(PID_MAX_LIMIT), None);
+        assert_eq!(Pid::new(0), None);
+        assert_eq!(Pid::new(-1), None);
+        assert_eq!(
+            Pid::this().as_raw(),
+            i32::try_from(std::process::id()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        assert_eq!(Pid::from_bytes(b"1"), Pid::new(1));
+        assert_eq!(Pid::from_bytes(b"12345"), Pid::new(12345));
+        assert_eq!(
+            Pid::from_bytes((PID_MAX_LIMIT - 1).to_string().as_bytes()),
+            Pid::new(PID_MAX_LIMIT - 1)
+        );
+        assert_eq!(Pid::from_bytes(PID_MAX_LIMIT.to_string().as_bytes()), None);
+        assert_eq!(Pid::from_bytes(b"429496729"), None);
+        assert_eq!(Pid::from_bytes(b"18446744073"), None);
+        assert_eq!(Pid::from_bytes(b""), None);
+        assert_eq!(Pid::from_bytes(b"abc"), None);
+        assert_eq!(Pid::from_bytes(b"0000"), None);
+        assert_eq!(Pid::from_bytes(b"self"), None);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("42".parse(), Ok(Pid::new(42).unwrap()));
+        assert!("0".parse::<Pid>().is_err());
+        assert!("-1".parse::<Pid>().is_err());
+        assert!("99999999".parse::<Pid>().is_err());
+        assert!("ten".parse::<Pid>().is_err());
+    }
+
+    #[test]
+    fn test_read_list() {
+        let listed = |raw, comm: Option<&str>| Listed {
+            pid: Pid::new(raw).unwrap(),
+            comm: comm.map(str::to_owned),
+        };
+        assert_eq!(
+            read_list(&b"12\n\n  345 nginx\n 6 Web Content \n"[..]).unwrap(),
+            [
+                listed(12, None),
+                listed(345, Some("nginx")),
+                listed(6, Some("Web Content")),
+            ]
+        );
+        let e = read_list(&b"12\nnginx 12\n"[..]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert!(
+            e.to_string().starts_with("line 2: nginx is not a PID"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_pid_max() {
+        let tmp = unique_test_dir();
+        assert_eq!(pid_max(&tmp), None);
+        fs::create_dir_all(tmp.join("sys/kernel")).unwrap();
+        fs::write(tmp.join("sys/kernel/pid_max"), "32768\n").unwrap();
+        assert_eq!(pid_max(&tmp), Some(32768));
+        cleanup_fake_proc(&tmp);
+    }
+}
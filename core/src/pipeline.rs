@@ -0,0 +1,701 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::{
+    actions::Action,
+    filters::{Filter, GroupFilter},
+    pid::Pid,
+    processes::{ProcessInfo, Scanner, Stat},
+    query::ProcessQuery,
+    snapshot::ProcSnapshot,
+    sockets::held_sockets,
+};
+
+/// Decides which processes are acted upon once filtering is done.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Selector {
+    /// Exactly the processes accepted by the filters.
+    #[default]
+    Matched,
+    /// Every member of the process groups of the matched processes.
+    ProcessGroup,
+    /// One matched process per process group: the group leader if it was
+    /// matched, the lowest matched PID otherwise. Multi-process daemons
+    /// then get a single signal instead of one per worker.
+    GroupLeaders,
+    /// The matched processes and their descendants, from `min_depth` levels
+    /// below the nearest matched process to `max_depth`, if set. This
+    /// process is left out, should it be one of them.
+    Tree {
+        min_depth: usize,
+        max_depth: Option<usize>,
+    },
+}
+
+/// The order in which actions are applied to the selected processes.
+/// Scanning in parallel yields them in no particular order, so they are
+/// always sorted before the action runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum Order {
+    /// Ascending PID.
+    #[default]
+    Pid,
+    /// Oldest process first.
+    Start,
+    /// Ancestors before their descendants, following parent PIDs, so a
+    /// supervisor is gone before its workers are signalled.
+    #[cfg_attr(feature = "clap", value(alias = "tree"))]
+    ParentsFirst,
+    /// Descendants before their ancestors, so a supervisor doesn't see its
+    /// workers die and respawn them.
+    ChildrenFirst,
+}
+
+/// Puts the selected processes in the order the action is applied in. The
+/// command line's `Order`s are strategies, and so is `WorkersFirst`, which
+/// builds on any other.
+pub trait OrderStrategy: Send + Sync {
+    /// Sorts `processes`, reading what it needs to under `root`.
+    fn sort(&self, root: &Path, processes: &mut [ProcessInfo]);
+}
+
+impl OrderStrategy for Order {
+    /// Sorts `processes`, reading the parent chains under `root` for the
+    /// tree orders. Ties are broken by PID.
+    fn sort(&self, root: &Path, processes: &mut [ProcessInfo]) {
+        match self {
+            Self::Pid => processes.sort_unstable_by_key(|p| p.pid),
+            Self::Start => {
+                processes.sort_unstable_by_key(|p| (p.stat().map(|stat| stat.starttime), p.pid));
+            }
+            Self::ParentsFirst => {
+                let mut depths = HashMap::new();
+                processes.sort_by_cached_key(|p| (depth(root, p.pid.as_raw(), &mut depths), p.pid));
+            }
+            Self::ChildrenFirst => {
+                let mut depths = HashMap::new();
+                processes.sort_by_cached_key(|p| {
+                    (Reverse(depth(root, p.pid.as_raw(), &mut depths)), p.pid)
+                });
+            }
+        }
+    }
+}
+
+/// Orders like `inner`, except for the workers of a pool, which come first.
+///
+/// A pool is a group of processes sharing a socket, such as the master and
+/// workers of a prefork server. Its members are reordered descendants first
+/// within the places it took, since the master would otherwise see its
+/// workers die before its turn comes, and respawn them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WorkersFirst<O> {
+    inner: O,
+}
+
+impl<O: OrderStrategy> WorkersFirst<O> {
+    #[must_use]
+    pub const fn new(inner: O) -> Self {
+        Self { inner }
+    }
+}
+
+impl<O: OrderStrategy> OrderStrategy for WorkersFirst<O> {
+    fn sort(&self, root: &Path, processes: &mut [ProcessInfo]) {
+        self.inner.sort(root, processes);
+        let pools = socket_pools(processes);
+        if pools.is_empty() {
+            return;
+        }
+        let mut places: HashMap<Pid, usize> = processes
+            .iter()
+            .enumerate()
+            .map(|(place, p)| (p.pid, place))
+            .collect();
+        let mut depths = HashMap::new();
+        for pool in pools {
+            let mut members = pool.clone();
+            members.sort_by_cached_key(|&i| {
+                let pid = processes[i].pid;
+                (Reverse(depth(root, pid.as_raw(), &mut depths)), pid)
+            });
+            for (place, member) in pool.into_iter().zip(members) {
+                places.insert(processes[member].pid, place);
+            }
+        }
+        processes.sort_by_key(|p| places[&p.pid]);
+    }
+}
+
+/// The indices in `processes` of each group of two or more that share a
+/// socket, directly or through others of the group, in ascending order.
+fn socket_pools(processes: &[ProcessInfo]) -> Vec<Vec<usize>> {
+    fn find(pools: &mut [usize], mut i: usize) -> usize {
+        while pools[i] != i {
+            pools[i] = pools[pools[i]];
+            i = pools[i];
+        }
+        i
+    }
+
+    let mut pools: Vec<usize> = (0..processes.len()).collect();
+    let mut holders: HashMap<u64, usize> = HashMap::new();
+    for (i, process) in processes.iter().enumerate() {
+        for inode in held_sockets(process) {
+            let holder = *holders.entry(inode).or_insert(i);
+            let (a, b) = (find(&mut pools, holder), find(&mut pools, i));
+            pools[b] = a;
+        }
+    }
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..processes.len() {
+        members.entry(find(&mut pools, i)).or_default().push(i);
+    }
+    members
+        .into_values()
+        .filter(|pool| pool.len() > 1)
+        .collect()
+}
+
+/// Number of ancestors of `pid`, memoized in `depths`. Every ancestor has a
+/// smaller depth than its descendants, so sorting by depth puts parents
+/// first and sorting by reverse depth puts children first.
+fn depth(root: &Path, pid: i32, depths: &mut HashMap<i32, usize>) -> usize {
+    let mut chain = Vec::new();
+    let mut current = pid;
+    let mut base = 0;
+    while current > 0 {
+        if let Some(&known) = depths.get(&current) {
+            base = known + 1;
+            break;
+        }
+        // A corrupt chain could loop; no real tree is this deep.
+        if chain.len() > 4096 {
+            break;
+        }
+        chain.push(current);
+        current = fs::read_to_string(root.join(current.to_string()).join("stat"))
+            .ok()
+            .and_then(|contents| Stat::parse(&contents))
+            .map_or(0, |stat| stat.ppid);
+    }
+    for (i, &pid) in chain.iter().rev().enumerate() {
+        depths.insert(pid, base + i);
+    }
+    depths.get(&pid).copied().unwrap_or(base)
+}
+
+/// Matches the members of the process groups of `matched`, if any could be
+/// read.
+fn group_filter(matched: &[ProcessInfo]) -> Option<GroupFilter> {
+    let groups: Vec<i32> = matched
+        .iter()
+        .filter_map(|p| p.stat().map(|stat| stat.pgrp))
+        .collect();
+    (!groups.is_empty()).then(|| GroupFilter::new(groups))
+}
+
+/// The PIDs `Selector::Tree` selects below `matched` in `snapshot`, leaving
+/// out this process.
+fn tree_pids(
+    snapshot: &ProcSnapshot,
+    matched: &[ProcessInfo],
+    min_depth: usize,
+    max_depth: Option<usize>,
+) -> HashSet<Pid> {
+    let roots: Vec<_> = matched.iter().map(|p| p.pid).collect();
+    let own_pid = Pid::this();
+    snapshot
+        .below(&roots, max_depth)
+        .into_iter()
+        .filter(|&(depth, p)| depth >= min_depth && p.pid != own_pid)
+        .map(|(_, p)| p.pid)
+        .collect()
+}
+
+/// Keeps one process per process group out of `processes`, preferring the
+/// leader. Processes whose group can't be read are all kept.
+fn group_leaders(processes: Vec<ProcessInfo>) -> Vec<ProcessInfo> {
+    let mut representatives: HashMap<i32, ProcessInfo> = HashMap::new();
+    let mut unknown = Vec::new();
+    for process in processes {
+        let Some(pgrp) = process.stat().map(|stat| stat.pgrp) else {
+            unknown.push(process);
+            continue;
+        };
+        let better = representatives.get(&pgrp).is_none_or(|current| {
+            (current.pid != pgrp) && (process.pid == pgrp || process.pid < current.pid)
+        });
+        if better {
+            representatives.insert(pgrp, process);
+        }
+    }
+    unknown.extend(representatives.into_values());
+    unknown
+}
+
+/// The result of applying an action to one process.
+#[derive(Debug)]
+pub struct Outcome {
+    pub process: ProcessInfo,
+    pub result: io::Result<()>,
+}
+
+/// Scanner → Filters → Selector → Action.
+pub struct Pipeline {
+    query: ProcessQuery,
+    selector: Selector,
+    order: Box<dyn OrderStrategy>,
+    threads: bool,
+}
+
+impl Pipeline {
+    #[must_use]
+    pub fn new(scanner: Scanner) -> Self {
+        Self::from_query(ProcessQuery::new(scanner))
+    }
+
+    #[must_use]
+    pub fn from_query(query: ProcessQuery) -> Self {
+        Self {
+            query,
+            selector: Selector::Matched,
+            order: Box::new(Order::Pid),
+            threads: false,
+        }
+    }
+
+    #[must_use]
+    pub fn filter(mut self, filter: impl Filter + 'static) -> Self {
+        self.query = self.query.filter(filter);
+        self
+    }
+
+    #[must_use]
+    pub const fn selector(mut self, selector: Selector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Applies the action in the order of `order`, by ascending PID if not
+    /// set.
+    #[must_use]
+    pub fn order(mut self, order: impl OrderStrategy + 'static) -> Self {
+        self.order = Box::new(order);
+        self
+    }
+
+    /// Selects the threads of the selected processes instead, grouped by
+    /// process in the selection order.
+    #[must_use]
+    pub const fn threads(mut self, threads: bool) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    #[must_use]
+    pub const fn query(&self) -> &ProcessQuery {
+        &self.query
+    }
+
+    /// Scans and returns the processes matched by every filter.
+    pub fn matches(&self) -> io::Result<Vec<ProcessInfo>> {
+        self.query.matches()
+    }
+
+    /// Scans and returns the processes the action should be applied to, in
+    /// the order it should be applied in.
+    pub fn select(&self) -> io::Result<Vec<ProcessInfo>> {
+        let selected = self.select_unordered()?;
+        Ok(self.finish(selected))
+    }
+
+    /// Like `select`, but picks among `candidates` of `snapshot` instead of
+    /// scanning, and finds process groups and descendants in it too, so
+    /// that several pipelines can share one scan. `candidates` are all of
+    /// the snapshot's processes, or those its index has for one name.
+    #[must_use]
+    pub fn select_from<'a>(
+        &self,
+        snapshot: &'a ProcSnapshot,
+        candidates: impl IntoIterator<Item = &'a ProcessInfo>,
+    ) -> Vec<ProcessInfo> {
+        let matched: Vec<_> = candidates
+            .into_iter()
+            .filter(|p| self.query.is_match(p))
+            .cloned()
+            .collect();
+        let selected = match self.selector {
+            Selector::Matched => matched,
+            Selector::ProcessGroup => group_filter(&matched).map_or(matched, |filter| {
+                snapshot.select(&filter).cloned().collect()
+            }),
+            Selector::GroupLeaders => group_leaders(matched),
+            Selector::Tree {
+                min_depth,
+                max_depth,
+            } => {
+                let selected = tree_pids(snapshot, &matched, min_depth, max_depth);
+                snapshot
+                    .processes()
+                    .iter()
+                    .filter(|p| selected.contains(&p.pid))
+                    .cloned()
+                    .collect()
+            }
+        };
+        self.finish(selected)
+    }
+
+    /// Puts `selected` in order, and swaps them for their threads if asked.
+    fn finish(&self, mut selected: Vec<ProcessInfo>) -> Vec<ProcessInfo> {
+        self.order.sort(self.query.scanner().root(), &mut selected);
+        if self.threads {
+            // A process exiting now has no threads left to act upon.
+            selected = selected
+                .iter()
+                .flat_map(|p| p.tasks().unwrap_or_default())
+                .collect();
+        }
+        selected
+    }
+
+    fn select_unordered(&self) -> io::Result<Vec<ProcessInfo>> {
+        let matched = self.matches()?;
+
+        match self.selector {
+            Selector::Matched => Ok(matched),
+            Selector::ProcessGroup => group_filter(&matched).map_or_else(
+                || Ok(matched),
+                |filter| self.query.scanner().scan(|p| filter.matches(p)),
+            ),
+            Selector::GroupLeaders => Ok(group_leaders(matched)),
+            Selector::Tree {
+                min_depth,
+                max_depth,
+            } => {
+                let snapshot = ProcSnapshot::take(self.query.scanner())?;
+                let selected = tree_pids(&snapshot, &matched, min_depth, max_depth);
+                Ok(snapshot
+                    .into_processes()
+                    .into_iter()
+                    .filter(|p| selected.contains(&p.pid))
+                    .collect())
+            }
+        }
+    }
+
+    /// Selects processes and applies `action` to each of them.
+    ///
+    /// Each process's stat is read before the action runs, so
+    /// `ProcessInfo::ident` on an outcome describes the process the action
+    /// was applied to even if it has exited since.
+    pub fn run<A: Action + ?Sized>(&self, action: &mut A) -> io::Result<Vec<Outcome>> {
+        Ok(self
+            .select()?
+            .into_iter()
+            .map(|process| {
+                process.stat();
+                let result = action.apply(&process);
+                Outcome { process, result }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+    use crate::{
+        actions::Count,
+        filters::NameFilter,
+        pid::Pid,
+        testing::{
+            cleanup_fake_proc, setup_fake_proc, unique_test_dir, write_fake_stat,
+            write_fake_stat_fields,
+        },
+    };
+
+    #[test]
+    fn test_pipeline_name_filter() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "bash"), ("11", "sshd"), ("12", "bash")]);
+
+        let mut count = Count::default();
+        let outcomes = Pipeline::new(Scanner::new(&tmp))
+            .filter(NameFilter::new("bash"))
+            .run(&mut count)
+            .unwrap();
+
+        assert_eq!(count.count, 2);
+        assert!(outcomes.iter().all(|o| o.result.is_ok()));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_process_group_selector() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(
+            &tmp,
+            &[("10", "make"), ("11", "cc"), ("12", "cc"), ("20", "sshd")],
+        );
+        write_fake_stat(&tmp, "10", "make", 1, 10);
+        write_fake_stat(&tmp, "11", "cc", 10, 10);
+        write_fake_stat(&tmp, "12", "cc", 10, 12);
+        write_fake_stat(&tmp, "20", "sshd", 1, 20);
+
+        let selected: Vec<i32> = Pipeline::new(Scanner::new(&tmp))
+            .filter(NameFilter::new("make"))
+            .selector(Selector::ProcessGroup)
+            .select()
+            .unwrap()
+            .iter()
+            .map(|p| p.pid.as_raw())
+            .collect();
+
+        assert_eq!(selected, vec![10, 11]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_group_leaders_selector() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(
+            &tmp,
+            &[
+                ("10", "nginx"),
+                ("11", "nginx"),
+                ("12", "nginx"),
+                ("20", "nginx"),
+                ("21", "nginx"),
+            ],
+        );
+        // 11 leads its group; nothing matched leads 20 and 21's group 5.
+        write_fake_stat(&tmp, "10", "nginx", 11, 11);
+        write_fake_stat(&tmp, "11", "nginx", 1, 11);
+        write_fake_stat(&tmp, "12", "nginx", 11, 11);
+        write_fake_stat(&tmp, "20", "nginx", 1, 5);
+        write_fake_stat(&tmp, "21", "nginx", 1, 5);
+
+        let selected: Vec<i32> = Pipeline::new(Scanner::new(&tmp))
+            .filter(NameFilter::new("nginx"))
+            .selector(Selector::GroupLeaders)
+            .select()
+            .unwrap()
+            .iter()
+            .map(|p| p.pid.as_raw())
+            .collect();
+
+        assert_eq!(selected, vec![11, 20]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_tree_selector() {
+        let tmp = unique_test_dir();
+        let tree = [
+            ("10", "supervisor", 1),
+            ("11", "worker", 10),
+            ("12", "worker", 10),
+            ("13", "job", 11),
+            ("20", "sshd", 1),
+        ];
+        for (pid, comm, ppid) in tree {
+            setup_fake_proc(&tmp, &[(pid, comm)]);
+            write_fake_stat(&tmp, pid, comm, ppid, 1);
+        }
+
+        let selected = |min_depth, max_depth| -> Vec<i32> {
+            Pipeline::new(Scanner::new(&tmp))
+                .filter(NameFilter::new("supervisor"))
+                .selector(Selector::Tree {
+                    min_depth,
+                    max_depth,
+                })
+                .select()
+                .unwrap()
+                .iter()
+                .map(|p| p.pid.as_raw())
+                .collect()
+        };
+
+        assert_eq!(selected(0, None), vec![10, 11, 12, 13]);
+        assert_eq!(selected(0, Some(1)), vec![10, 11, 12]);
+        assert_eq!(selected(1, Some(1)), vec![11, 12]);
+        assert_eq!(selected(2, None), vec![13]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_select_from_snapshot() {
+        let tmp = unique_test_dir();
+        let tree = [
+            ("10", "supervisor", 1, 10),
+            ("11", "worker", 10, 10),
+            ("12", "job", 11, 12),
+            ("20", "sshd", 1, 20),
+        ];
+        for (pid, comm, ppid, pgrp) in tree {
+            setup_fake_proc(&tmp, &[(pid, comm)]);
+            write_fake_stat(&tmp, pid, comm, ppid, pgrp);
+        }
+        let scanner = Scanner::new(&tmp);
+        let snapshot = ProcSnapshot::take(&scanner).unwrap();
+        let pids = |processes: Vec<ProcessInfo>| -> Vec<i32> {
+            processes.iter().map(|p| p.pid.as_raw()).collect()
+        };
+
+        for selector in [
+            Selector::Matched,
+            Selector::ProcessGroup,
+            Selector::GroupLeaders,
+            Selector::Tree {
+                min_depth: 1,
+                max_depth: None,
+            },
+        ] {
+            let pipeline = Pipeline::new(scanner.clone())
+                .filter(NameFilter::new("supervisor"))
+                .selector(selector);
+            let expected = pids(pipeline.select().unwrap());
+            assert_eq!(
+                pids(pipeline.select_from(&snapshot, snapshot.processes())),
+                expected
+            );
+            assert_eq!(
+                pids(pipeline.select_from(&snapshot, snapshot.by_comm(b"supervisor"))),
+                expected
+            );
+        }
+        let pipeline = Pipeline::new(scanner).filter(NameFilter::new("supervisor"));
+        assert_eq!(
+            pids(pipeline.select_from(&snapshot, snapshot.by_comm(b"sshd"))),
+            Vec::<i32>::new()
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_threads() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "app"), ("20", "other")]);
+        for (tid, comm) in [("12", "worker"), ("10", "app")] {
+            let task = tmp.join("10/task").join(tid);
+            std::fs::create_dir_all(&task).unwrap();
+            std::fs::write(task.join("comm"), format!("{comm}\n")).unwrap();
+        }
+
+        let threads = Pipeline::new(Scanner::new(&tmp))
+            .filter(NameFilter::new("app"))
+            .threads(true)
+            .select()
+            .unwrap();
+        let threads: Vec<_> = threads
+            .iter()
+            .map(|t| (t.pid.as_raw(), t.tgid.map(Pid::as_raw), t.comm.as_bytes()))
+            .collect();
+        assert_eq!(
+            threads,
+            vec![(10, Some(10), &b"app"[..]), (12, Some(10), &b"worker"[..])]
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_order() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(
+            &tmp,
+            &[
+                ("10", "w"),
+                ("11", "w"),
+                ("12", "w"),
+                ("13", "sh"),
+                ("30", "w"),
+            ],
+        );
+        // 30 is the supervisor; 12 runs under it through a shell.
+        write_fake_stat_fields(&tmp, "30", "w", 'S', 1, 30, 100);
+        write_fake_stat_fields(&tmp, "13", "sh", 'S', 30, 30, 200);
+        write_fake_stat_fields(&tmp, "12", "w", 'S', 13, 30, 300);
+        write_fake_stat_fields(&tmp, "11", "w", 'S', 30, 30, 400);
+        write_fake_stat_fields(&tmp, "10", "w", 'S', 1, 10, 500);
+
+        let order = |order| -> Vec<i32> {
+            Pipeline::new(Scanner::new(&tmp))
+                .filter(NameFilter::new("w"))
+                .order(order)
+                .select()
+                .unwrap()
+                .iter()
+                .map(|p| p.pid.as_raw())
+                .collect()
+        };
+        assert_eq!(order(Order::Pid), vec![10, 11, 12, 30]);
+        assert_eq!(order(Order::Start), vec![30, 12, 11, 10]);
+        assert_eq!(order(Order::ParentsFirst), vec![10, 30, 11, 12]);
+        assert_eq!(order(Order::ChildrenFirst), vec![12, 11, 10, 30]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_pipeline_workers_first() {
+        let tmp = unique_test_dir();
+        let names = ["5", "8", "9", "10", "11", "12", "20"].map(|pid| (pid, "w"));
+        setup_fake_proc(&tmp, &names);
+        // 10 is the master of 11 and 12, 8 that of 9; 20 has a socket of
+        // its own.
+        for (pid, parent, sockets) in [
+            ("5", 1, &[][..]),
+            ("8", 1, &[55]),
+            ("9", 8, &[55]),
+            ("10", 1, &[77]),
+            ("11", 10, &[77]),
+            ("12", 10, &[77, 88]),
+            ("20", 1, &[99]),
+        ] {
+            write_fake_stat(&tmp, pid, "w", parent, parent);
+            fs::create_dir_all(tmp.join(pid).join("fd")).unwrap();
+            for (fd, inode) in sockets.iter().enumerate() {
+                symlink(
+                    format!("socket:[{inode}]"),
+                    tmp.join(pid).join(format!("fd/{fd}")),
+                )
+                .unwrap();
+            }
+        }
+
+        let order = |order: &dyn Fn(Pipeline) -> Pipeline| -> Vec<i32> {
+            order(Pipeline::new(Scanner::new(&tmp)).filter(NameFilter::new("w")))
+                .select()
+                .unwrap()
+                .iter()
+                .map(|p| p.pid.as_raw())
+                .collect()
+        };
+        assert_eq!(order(&|p| p), vec![5, 8, 9, 10, 11, 12, 20]);
+        assert_eq!(
+            order(&|p| p.order(WorkersFirst::new(Order::Pid))),
+            vec![5, 9, 8, 11, 12, 10, 20]
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+}
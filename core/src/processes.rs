@@ -0,0 +1,937 @@
+use std::{
+    cell::RefCell,
+    ffi::OsStr,
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{MetadataExt, OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+    thread,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    comm::Comm,
+    limits::open_permit,
+    pid::{MAX_DIGITS, Pid},
+};
+
+pub const PROC: &str = "/proc";
+
+/// Fields parsed from `/proc/<pid>/stat`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    pub state: u8,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub session: i32,
+    /// CPU time spent in user and kernel mode, in clock ticks.
+    pub utime: u64,
+    pub stime: u64,
+    /// Time the process started after system boot, in clock ticks.
+    pub starttime: u64,
+    /// Resident set size, in pages.
+    pub rss: u64,
+}
+
+/// Bytes per page, the unit of `Stat::rss`.
+#[must_use]
+pub fn page_size() -> u64 {
+    // SAFETY: sysconf has no preconditions.
+    u64::try_from(unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).unwrap_or(4096)
+}
+
+impl Stat {
+    /// Parses the contents of a stat file. The command name is enclosed in
+    /// parentheses and may itself contain spaces or parentheses, so fields
+    /// are read after the last `)`.
+    #[must_use]
+    pub fn parse(contents: &str) -> Option<Self> {
+        let rest = &contents[contents.rfind(')')? + 1..];
+        let mut parts = rest.split_whitespace();
+        let state = *parts.next()?.as_bytes().first()?;
+        let ppid = parts.next()?.parse().ok()?;
+        let pgrp = parts.next()?.parse().ok()?;
+        let session = parts.next()?.parse().ok()?;
+        // Skip tty_nr through cmajflt to reach field 14.
+        let utime = parts.nth(7)?.parse().ok()?;
+        let stime = parts.next()?.parse().ok()?;
+        // Skip cutime through itrealvalue to reach field 22.
+        let starttime = parts.nth(6)?.parse().ok()?;
+        // Skip vsize.
+        let rss = parts.nth(1)?.parse().ok()?;
+        Some(Self {
+            state,
+            ppid,
+            pgrp,
+            session,
+            utime,
+            stime,
+            starttime,
+            rss,
+        })
+    }
+}
+
+/// Identifies a process across time: a PID may be reused once its process
+/// exits, but never with the same start time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ProcessIdent {
+    pub pid: Pid,
+    pub starttime: u64,
+}
+
+impl ProcessIdent {
+    /// Reads the identity of the process currently running as `pid`.
+    #[must_use]
+    pub fn current(root: &Path, pid: Pid) -> Option<Self> {
+        current_stat(root, pid).map(|stat| Self {
+            pid,
+            starttime: stat.starttime,
+        })
+    }
+
+    /// Whether this exact process still exists, as opposed to its PID
+    /// having been reused by a newer one.
+    #[must_use]
+    pub fn is_running(&self, root: &Path) -> bool {
+        Self::current(root, self.pid).is_some_and(|current| current == *self)
+    }
+
+    /// Like `is_running`, but a zombie counts as exited: it is dead, and
+    /// lingers only until its parent reaps it.
+    ///
+    /// A zombie child of this process is left for the caller to reap, with
+    /// its exit status.
+    #[must_use]
+    pub fn is_alive(&self, root: &Path) -> bool {
+        let Some(stat) = current_stat(root, self.pid) else {
+            return false;
+        };
+        stat.starttime == self.starttime && stat.state != b'Z'
+    }
+}
+
+/// The user IDs of a process that signal permissions depend on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uids {
+    pub real: u32,
+    pub effective: u32,
+    pub saved: u32,
+}
+
+/// Which file a path led to, whatever it is named now.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+}
+
+impl FileId {
+    pub fn of(file: &File) -> io::Result<Self> {
+        let metadata = file.metadata()?;
+        Ok(Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+        })
+    }
+}
+
+/// Opens `path` with `O_PATH`, a handle on the file itself that can't be
+/// read but pins down which file it is, following symlinks once.
+pub fn open_path(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH)
+        .open(path)
+}
+
+fn current_stat(root: &Path, pid: Pid) -> Option<Stat> {
+    let _permit = open_permit();
+    let contents = fs::read_to_string(root.join(pid.to_string()).join("stat")).ok()?;
+    Stat::parse(&contents)
+}
+
+/// A process found while scanning `/proc`, or one of its threads as
+/// returned by `ProcessInfo::tasks`.
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    /// The PID, or the thread ID for a thread.
+    pub pid: Pid,
+    /// For a thread, the PID of its process.
+    pub tgid: Option<Pid>,
+    pub comm: Comm,
+    root: Arc<Path>,
+    stat: OnceLock<Option<Stat>>,
+}
+
+impl ProcessInfo {
+    #[must_use]
+    pub fn new(root: Arc<Path>, pid: Pid, comm: impl Into<Comm>) -> Self {
+        Self {
+            pid,
+            tgid: None,
+            comm: comm.into(),
+            root,
+            stat: OnceLock::new(),
+        }
+    }
+
+    /// The process `pid`, with its name if `root` has it. Unlike scanning,
+    /// this works without `/proc`, for acting on a PID given as is.
+    #[must_use]
+    pub fn by_pid(root: Arc<Path>, pid: Pid) -> Self {
+        let info = Self::new(root, pid, Vec::new());
+        let comm = info.with_path("comm", read_comm).unwrap_or_default();
+        Self { comm, ..info }
+    }
+
+    /// Path of a file inside this process's `/proc/<pid>` directory.
+    #[must_use]
+    pub fn path(&self, file: &str) -> PathBuf {
+        self.root.join(self.pid.to_string()).join(file)
+    }
+
+    /// Calls `f` with the path of `file` like `path`, built on the stack
+    /// when it fits, for the files read of every process while scanning.
+    fn with_path<R>(&self, file: &str, f: impl FnOnce(&Path) -> R) -> R {
+        match ProcPath::new(&self.root, self.pid, file) {
+            Some(path) => f(path.as_path()),
+            None => f(&self.path(file)),
+        }
+    }
+
+    /// Targets of the symlinks in `/proc/<pid>/fd`, e.g. `/dev/null` or
+    /// `socket:[12345]`.
+    pub fn fd_targets(&self) -> io::Result<Vec<PathBuf>> {
+        let _permit = open_permit();
+        Ok(fs::read_dir(self.path("fd"))?
+            .filter_map(|e| e.ok().and_then(|e| fs::read_link(e.path()).ok()))
+            .collect())
+    }
+
+    /// Real user ID, from the `Uid:` line of `/proc/<pid>/status`.
+    #[must_use]
+    pub fn uid(&self) -> Option<u32> {
+        self.uids().map(|uids| uids.real)
+    }
+
+    /// Real, effective and saved user IDs, from the `Uid:` line of
+    /// `/proc/<pid>/status`.
+    #[must_use]
+    pub fn uids(&self) -> Option<Uids> {
+        self.with_path("status", |path| {
+            with_proc_file_small(path, STATUS_CAPACITY, |status, _| {
+                let status = String::from_utf8_lossy(status);
+                let mut ids = status
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Uid:"))?
+                    .split_whitespace()
+                    .map(str::parse);
+                Some(Uids {
+                    real: ids.next()?.ok()?,
+                    effective: ids.next()?.ok()?,
+                    saved: ids.next()?.ok()?,
+                })
+            })
+        })
+        .ok()?
+    }
+
+    /// Real group ID and supplementary groups, from the `Gid:` and
+    /// `Groups:` lines of `/proc/<pid>/status`.
+    #[must_use]
+    pub fn gids(&self) -> Option<Vec<u32>> {
+        self.with_path("status", |path| {
+            with_proc_file_small(path, STATUS_CAPACITY, |status, _| {
+                let status = String::from_utf8_lossy(status);
+                let field = |name| status.lines().find_map(|line| line.strip_prefix(name));
+                let mut gids = vec![field("Gid:")?.split_whitespace().next()?.parse().ok()?];
+                gids.extend(
+                    field("Groups:")
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .filter_map(|gid| gid.parse::<u32>().ok()),
+                );
+                Some(gids)
+            })
+        })
+        .ok()?
+    }
+
+    /// Command line arguments, from `/proc/<pid>/cmdline`, cut off after
+    /// `CMDLINE_CAPACITY` bytes. Empty for kernel threads and zombies.
+    pub fn cmdline(&self) -> io::Result<Vec<Vec<u8>>> {
+        self.with_path("cmdline", |path| {
+            with_proc_file_small(path, CMDLINE_CAPACITY, |cmdline, _| {
+                let bytes = cmdline.strip_suffix(b"\0").unwrap_or(cmdline);
+                if bytes.is_empty() {
+                    return Vec::new();
+                }
+                bytes.split(|&b| b == 0).map(<[u8]>::to_vec).collect()
+            })
+        })
+    }
+
+    /// Target of a `/proc/<pid>` symlink such as `cwd`, `root` or `exe`.
+    pub fn link(&self, file: &str) -> io::Result<PathBuf> {
+        fs::read_link(self.path(file))
+    }
+
+    /// The executable the process runs, through `/proc/<pid>/exe`, which
+    /// leads to the file itself even once it was deleted or renamed.
+    pub fn exe_id(&self) -> io::Result<FileId> {
+        let _permit = open_permit();
+        FileId::of(&open_path(&self.path("exe"))?)
+    }
+
+    /// Files mapped into memory, from `/proc/<pid>/maps`.
+    pub fn mapped_paths(&self) -> io::Result<Vec<PathBuf>> {
+        let _permit = open_permit();
+        let maps = fs::read(self.path("maps"))?;
+        let mut paths: Vec<PathBuf> = maps
+            .split(|&b| b == b'\n')
+            .filter_map(parse_maps_path)
+            .map(|path| PathBuf::from(OsStr::from_bytes(path)))
+            .collect();
+        paths.dedup();
+        Ok(paths)
+    }
+
+    /// Thread IDs listed in `/proc/<pid>/task`.
+    pub fn threads(&self) -> io::Result<Vec<Pid>> {
+        let _permit = open_permit();
+        Ok(fs::read_dir(self.path("task"))?
+            .filter_map(|e| {
+                e.ok()
+                    .and_then(|e| Pid::from_bytes(e.file_name().as_bytes()))
+            })
+            .collect())
+    }
+
+    /// Every thread of this process, main thread included, in ascending
+    /// thread ID order and named from `/proc/<pid>/task/<tid>/comm`.
+    pub fn tasks(&self) -> io::Result<Vec<Self>> {
+        let root: Arc<Path> = Arc::from(self.path("task"));
+        let mut tids = self.threads()?;
+        tids.sort_unstable();
+        Ok(tids
+            .into_iter()
+            .map(|tid| {
+                let comm = read_comm(&root.join(tid.to_string()).join("comm"))
+                    .unwrap_or_else(|_| self.comm.clone());
+                Self {
+                    pid: tid,
+                    tgid: Some(self.pid),
+                    comm,
+                    root: Arc::clone(&root),
+                    stat: OnceLock::new(),
+                }
+            })
+            .collect())
+    }
+
+    #[must_use]
+    pub fn ident(&self) -> Option<ProcessIdent> {
+        self.stat().map(|stat| ProcessIdent {
+            pid: self.pid,
+            starttime: stat.starttime,
+        })
+    }
+
+    /// Lazily reads and caches `/proc/<pid>/stat`.
+    pub fn stat(&self) -> Option<&Stat> {
+        self.stat
+            .get_or_init(|| {
+                self.with_path("stat", |path| {
+                    with_proc_file_small(path, STAT_CAPACITY, |contents, _| {
+                        Stat::parse(&String::from_utf8_lossy(contents))
+                    })
+                })
+                .ok()?
+            })
+            .as_ref()
+    }
+}
+
+/// How many times a read failing with a transient error is attempted.
+const READ_ATTEMPTS: u32 = 3;
+
+/// What a failed read of a process file means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadFailure {
+    /// The process exited while being read; it is skipped silently.
+    Vanished,
+    /// Worth retrying: interrupted, or out of file descriptors because of
+    /// the files other scan threads have open.
+    Transient,
+    /// Anything else, e.g. permission denied, which is reported.
+    Other,
+}
+
+impl ReadFailure {
+    fn classify(err: &io::Error) -> Self {
+        match err.raw_os_error() {
+            Some(libc::ENOENT | libc::ESRCH) => Self::Vanished,
+            Some(libc::EMFILE | libc::ENFILE | libc::EINTR | libc::EAGAIN) => Self::Transient,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Enumerates the processes visible under a proc filesystem root.
+///
+/// Scanning skips processes that exit mid-scan. Other errors reading a
+/// process are recorded, once per kind, and can be retrieved with
+/// `take_errors`; clones share them.
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    root: Arc<Path>,
+    errors: Arc<Mutex<Vec<io::Error>>>,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new(PROC)
+    }
+}
+
+impl Scanner {
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: Arc::from(root.as_ref()),
+            errors: Arc::default(),
+        }
+    }
+
+    /// Returns the errors recorded while scanning since the last call,
+    /// one per kind.
+    #[must_use]
+    pub fn take_errors(&self) -> Vec<io::Error> {
+        self.errors
+            .lock()
+            .map(|mut errors| std::mem::take(&mut *errors))
+            .unwrap_or_default()
+    }
+
+    fn record(&self, err: io::Error) {
+        if let Ok(mut errors) = self.errors.lock()
+            && !errors.iter().any(|e| e.kind() == err.kind())
+        {
+            errors.push(err);
+        }
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Lazily iterates over the processes, one directory entry at a time.
+    #[allow(clippy::iter_not_returning_iterator)] // opening the directory can fail
+    pub fn iter(&self) -> io::Result<Processes<'_>> {
+        Ok(Processes {
+            scanner: self,
+            entries: fs::read_dir(&self.root)?,
+        })
+    }
+
+    /// Scans every process and keeps those accepted by `keep`.
+    pub fn scan<F>(&self, keep: F) -> io::Result<Vec<ProcessInfo>>
+    where
+        F: Fn(&ProcessInfo) -> bool + Send + Sync,
+    {
+        let entries = fs::read_dir(&self.root)?;
+
+        #[cfg(feature = "rayon")]
+        let iter = entries.par_bridge();
+
+        #[cfg(not(feature = "rayon"))]
+        let iter = entries.into_iter();
+
+        Ok(iter
+            .filter_map(|e| e.ok().and_then(|entry| self.read_entry(&entry)))
+            .filter(|info| keep(info))
+            .collect())
+    }
+
+    fn read_entry(&self, entry: &fs::DirEntry) -> Option<ProcessInfo> {
+        let pid = Pid::from_bytes(entry.file_name().as_bytes())?;
+        let info = ProcessInfo::new(Arc::clone(&self.root), pid, Comm::default());
+        let mut attempt = 1;
+        let comm = loop {
+            match info.with_path("comm", read_comm) {
+                Ok(comm) => break comm,
+                Err(e) => match ReadFailure::classify(&e) {
+                    ReadFailure::Transient if attempt < READ_ATTEMPTS => {
+                        attempt += 1;
+                        thread::yield_now();
+                    }
+                    ReadFailure::Vanished => return None,
+                    ReadFailure::Transient | ReadFailure::Other => {
+                        self.record(e);
+                        return None;
+                    }
+                },
+            }
+        };
+        Some(ProcessInfo { comm, ..info })
+    }
+}
+
+/// The path in the contents of a `cgroup` file within systemd's tree: that
+/// of the unified hierarchy, or of systemd's own one under cgroup v1.
+#[must_use]
+pub fn systemd_cgroup(contents: &str) -> Option<&str> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .or_else(|| {
+            contents
+                .lines()
+                .find_map(|line| line.split_once(":name=systemd:").map(|(_, path)| path))
+        })
+}
+
+/// Whether a process table is mounted at `root`: a missing or empty
+/// directory means `/proc` was never mounted, as in some minimal
+/// containers. Other errors are left for scanning to report.
+#[must_use]
+pub fn is_mounted(root: &Path) -> bool {
+    match fs::read_dir(root) {
+        Ok(mut entries) => entries.next().is_some(),
+        Err(e) => e.kind() != io::ErrorKind::NotFound,
+    }
+}
+
+/// Lazy iterator over the processes of a `Scanner`. Entries that vanish or
+/// cannot be read while iterating are skipped.
+#[derive(Debug)]
+pub struct Processes<'a> {
+    scanner: &'a Scanner,
+    entries: fs::ReadDir,
+}
+
+impl Iterator for Processes<'_> {
+    type Item = ProcessInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .by_ref()
+            .find_map(|e| e.ok().and_then(|entry| self.scanner.read_entry(&entry)))
+    }
+}
+
+/// Returns the PIDs whose name is `target_name`, or every member of their
+/// process groups when `use_group` is set.
+pub fn list_pids(target_name: &str, use_group: bool) -> io::Result<Vec<Pid>> {
+    let selector = if use_group {
+        crate::pipeline::Selector::ProcessGroup
+    } else {
+        crate::pipeline::Selector::Matched
+    };
+    let query = crate::query::ProcessQuery::new(Scanner::default())
+        .filter(crate::filters::NameFilter::new(target_name));
+    let processes = crate::pipeline::Pipeline::from_query(query)
+        .selector(selector)
+        .select()?;
+    Ok(processes.iter().map(|p| p.pid).collect())
+}
+
+/// Longest path built by `ProcPath`, with room for a deep test root.
+const PATH_CAPACITY: usize = 256;
+
+/// `<root>/<pid>/<file>`, built in a buffer on the stack rather than with
+/// `join` and `to_string`, which allocate three times per process.
+struct ProcPath {
+    buf: [u8; PATH_CAPACITY],
+    len: usize,
+}
+
+impl ProcPath {
+    /// `None` if the path is longer than `PATH_CAPACITY`.
+    fn new(root: &Path, pid: Pid, file: &str) -> Option<Self> {
+        let mut path = Self {
+            buf: [0; PATH_CAPACITY],
+            len: 0,
+        };
+        path.push(root.as_os_str().as_bytes())?;
+        path.push(b"/")?;
+        let mut digits = [0; MAX_DIGITS];
+        let mut start = digits.len();
+        let mut rest = pid.as_raw().unsigned_abs();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + u8::try_from(rest % 10).ok()?;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        path.push(&digits[start..])?;
+        path.push(b"/")?;
+        path.push(file.as_bytes())?;
+        Some(path)
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> Option<()> {
+        let end = self.len.checked_add(bytes.len())?;
+        self.buf.get_mut(self.len..end)?.copy_from_slice(bytes);
+        self.len = end;
+        Some(())
+    }
+
+    fn as_path(&self) -> &Path {
+        Path::new(OsStr::from_bytes(&self.buf[..self.len]))
+    }
+}
+
+/// Longest comm read. The kernel's `TASK_COMM_LEN` is 16 today.
+const COMM_CAPACITY: usize = 64;
+
+/// Longest `/proc/<pid>/stat` read, far more than its 52 fields take.
+const STAT_CAPACITY: usize = 1024;
+
+/// Longest `/proc/<pid>/status` read; the fields used come first.
+const STATUS_CAPACITY: usize = 4096;
+
+/// Longest `/proc/<pid>/cmdline` read.
+pub const CMDLINE_CAPACITY: usize = 32 * 1024;
+
+/// The start of a file read with `read_proc_file_small`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmallRead {
+    pub bytes: Vec<u8>,
+    /// The file went on past `capacity` bytes.
+    pub truncated: bool,
+}
+
+/// Reads at most `capacity` bytes of a small proc file such as `comm`,
+/// `status` or `cmdline`, noting whether there was more.
+///
+/// proc files are generated on read, so this reads until end of file
+/// rather than trusting their reported size.
+pub fn read_proc_file_small(path: &Path, capacity: usize) -> io::Result<SmallRead> {
+    let mut bytes = Vec::with_capacity(capacity.min(4096));
+    let truncated = read_small_into(path, capacity, &mut bytes)?;
+    Ok(SmallRead { bytes, truncated })
+}
+
+thread_local! {
+    /// The buffer `with_proc_file_small` reads into, one per thread, so
+    /// that scans on rayon's workers don't allocate one per file.
+    static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Reads like `read_proc_file_small`, into this thread's scratch buffer,
+/// and calls `f` with the bytes read and whether the file went on.
+pub fn with_proc_file_small<R>(
+    path: &Path,
+    capacity: usize,
+    f: impl FnOnce(&[u8], bool) -> R,
+) -> io::Result<R> {
+    SCRATCH.with(|scratch| {
+        // Only taken if `f` itself reads a file, which then gets its own.
+        let mut own = Vec::new();
+        let mut borrowed = scratch.try_borrow_mut();
+        let bytes = borrowed.as_deref_mut().unwrap_or(&mut own);
+        let truncated = read_small_into(path, capacity, bytes)?;
+        Ok(f(bytes, truncated))
+    })
+}
+
+/// Replaces the contents of `bytes` with at most `capacity` bytes of the
+/// file at `path`, returning whether there was more.
+fn read_small_into(path: &Path, capacity: usize, bytes: &mut Vec<u8>) -> io::Result<bool> {
+    let _permit = open_permit();
+    let file = fs::File::open(path)?;
+    bytes.clear();
+    // One byte past the capacity tells whether the file was cut short.
+    let limit = u64::try_from(capacity)
+        .unwrap_or(u64::MAX)
+        .saturating_add(1);
+    io::Read::read_to_end(&mut io::Read::take(file, limit), bytes)?;
+    let truncated = bytes.len() > capacity;
+    bytes.truncate(capacity);
+    Ok(truncated)
+}
+
+/// Reads a comm file into a buffer on the stack, so that only names past
+/// `Comm`'s inline size allocate.
+fn read_comm(path: &Path) -> io::Result<Comm> {
+    let _permit = open_permit();
+    let mut file = fs::File::open(path)?;
+    let mut buf = [0; COMM_CAPACITY];
+    let mut len = 0;
+    while len < buf.len() {
+        match io::Read::read(&mut file, &mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    let name = buf[..len].strip_suffix(b"\n").unwrap_or(&buf[..len]);
+    Ok(Comm::new(name))
+}
+
+/// Extracts the pathname of a `/proc/<pid>/maps` line, after the address,
+/// permissions, offset, device and inode fields. Pseudo entries like
+/// `[heap]` are skipped.
+fn parse_maps_path(line: &[u8]) -> Option<&[u8]> {
+    let mut rest = line;
+    for _ in 0..5 {
+        let start = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        rest = &rest[start..];
+        let end = rest.iter().position(u8::is_ascii_whitespace)?;
+        rest = &rest[end..];
+    }
+    let start = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+    let path = &rest[start..];
+    path.starts_with(b"/").then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pid::PID_MAX_LIMIT;
+    use crate::testing::{
+        cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat_fields,
+    };
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_maps_path() {
+        let line = |l: &str| parse_maps_path(l.as_bytes()).map(<[u8]>::to_vec);
+        assert_eq!(
+            line(
+                "7f1c2a000000-7f1c2a022000 r--p 00000000 08:01 1048602                    /usr/lib/libc.so.6"
+            ),
+            Some(b"/usr/lib/libc.so.6".to_vec())
+        );
+        assert_eq!(
+            line("7f1c2a000000-7f1c2a022000 rw-s 00000000 00:05 42 /mnt/data/my file (deleted)"),
+            Some(b"/mnt/data/my file (deleted)".to_vec())
+        );
+        assert_eq!(
+            line("55d0c0a00000-55d0c0a21000 rw-p 00000000 00:00 0                          [heap]"),
+            None
+        );
+        assert_eq!(
+            line("7ffd1e000000-7ffd1e021000 rw-p 00000000 00:00 0"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_stat_with_spaces_in_comm() {
+        let stat = Stat::parse(
+            "42 (tmux: server) S 1 42 42 0 -1 4194560 1 0 0 0 3 5 0 0 20 0 1 0 987654 1 2 3",
+        )
+        .unwrap();
+        assert_eq!(stat.state, b'S');
+        assert_eq!(stat.ppid, 1);
+        assert_eq!(stat.pgrp, 42);
+        assert_eq!(stat.session, 42);
+        assert_eq!((stat.utime, stat.stime), (3, 5));
+        assert_eq!(stat.starttime, 987_654);
+        assert_eq!(stat.rss, 2);
+        assert_eq!(Stat::parse("42 (short) S 1 42 42 0 -1"), None);
+    }
+
+    #[test]
+    fn test_proc_path() {
+        let path = ProcPath::new(Path::new("/proc"), pid(1234), "comm").unwrap();
+        assert_eq!(path.as_path(), Path::new("/proc/1234/comm"));
+        let highest = PID_MAX_LIMIT - 1;
+        let path = ProcPath::new(Path::new("/proc"), pid(highest), "stat").unwrap();
+        assert_eq!(path.as_path(), Path::new(&format!("/proc/{highest}/stat")));
+        let deep = PathBuf::from("/").join("x".repeat(PATH_CAPACITY));
+        assert!(ProcPath::new(&deep, pid(1), "comm").is_none());
+
+        let process = ProcessInfo::new(Arc::from(deep.as_path()), pid(42), "sh");
+        let built = process.with_path("comm", Path::to_path_buf);
+        assert_eq!(built, deep.join("42/comm"));
+    }
+
+    #[test]
+    fn test_is_mounted() {
+        let tmp = unique_test_dir();
+        assert!(!is_mounted(&tmp));
+        fs::create_dir_all(&tmp).unwrap();
+        assert!(!is_mounted(&tmp));
+        setup_fake_proc(&tmp, &[("10", "init")]);
+        assert!(is_mounted(&tmp));
+
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        assert_eq!(
+            ProcessInfo::by_pid(Arc::clone(&root), pid(10)).comm,
+            b"init"
+        );
+        assert!(ProcessInfo::by_pid(root, pid(11)).comm.is_empty());
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_list_pids_no_match() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("789", "sshd")]);
+
+        let result = Scanner::new(&tmp).scan(|p| p.comm == b"bash").unwrap();
+
+        assert!(result.is_empty());
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_process_ident_detects_pid_reuse() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("50", "nginx")]);
+        write_fake_stat_fields(&tmp, "50", "nginx", 'S', 1, 50, 1000);
+
+        let ident = ProcessIdent::current(&tmp, pid(50)).unwrap();
+        assert!(ident.is_running(&tmp));
+
+        write_fake_stat_fields(&tmp, "50", "bash", 'S', 1, 50, 2000);
+        assert!(!ident.is_running(&tmp));
+
+        fs::remove_dir_all(tmp.join("50")).unwrap();
+        assert!(!ident.is_running(&tmp));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_process_ident_zombie_is_not_alive() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("51", "nginx")]);
+        write_fake_stat_fields(&tmp, "51", "nginx", 'S', 1, 51, 1000);
+        let ident = ProcessIdent::current(&tmp, pid(51)).unwrap();
+        assert!(ident.is_alive(&tmp));
+
+        write_fake_stat_fields(&tmp, "51", "nginx", 'Z', 1, 51, 1000);
+        assert!(ident.is_running(&tmp));
+        assert!(!ident.is_alive(&tmp));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_process_ident_leaves_own_zombie() {
+        let mut child = std::process::Command::new("true").spawn().unwrap();
+        let pid = pid(i32::try_from(child.id()).unwrap());
+        let root = Path::new(PROC);
+        let ident = ProcessIdent::current(root, pid).unwrap();
+        while current_stat(root, pid).is_some_and(|stat| stat.state != b'Z') {
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+
+        assert!(!ident.is_alive(root));
+        assert!(child.wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_scan_reads_comm() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("12", "bash"), ("34", "sshd"), ("self", "x")]);
+
+        let mut result: Vec<i32> = Scanner::new(&tmp)
+            .scan(|_| true)
+            .unwrap()
+            .iter()
+            .map(|p| p.pid.as_raw())
+            .collect();
+        result.sort_unstable();
+
+        assert_eq!(result, vec![12, 34]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_scan_reports_unreadable_processes_once() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("12", "bash")]);
+        // Exited mid-scan: the directory is there but comm is gone.
+        fs::create_dir_all(tmp.join("13")).unwrap();
+        // Unreadable comm files, as a directory cannot be read.
+        fs::create_dir_all(tmp.join("14/comm")).unwrap();
+        fs::create_dir_all(tmp.join("15/comm")).unwrap();
+
+        let scanner = Scanner::new(&tmp);
+        let found: Vec<i32> = scanner
+            .scan(|_| true)
+            .unwrap()
+            .iter()
+            .map(|p| p.pid.as_raw())
+            .collect();
+        assert_eq!(found, vec![12]);
+
+        let errors = scanner.take_errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(ReadFailure::classify(&errors[0]), ReadFailure::Other);
+        assert!(scanner.take_errors().is_empty());
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_classify_read_failures() {
+        let classify = |errno| ReadFailure::classify(&io::Error::from_raw_os_error(errno));
+        assert_eq!(classify(libc::ENOENT), ReadFailure::Vanished);
+        assert_eq!(classify(libc::ESRCH), ReadFailure::Vanished);
+        assert_eq!(classify(libc::EMFILE), ReadFailure::Transient);
+        assert_eq!(classify(libc::EACCES), ReadFailure::Other);
+    }
+
+    #[test]
+    fn test_scan_many_processes() {
+        let tmp = unique_test_dir();
+        let pids: Vec<String> = (1..=3000).map(|pid| pid.to_string()).collect();
+        let entries: Vec<(&str, &str)> = pids.iter().map(|pid| (pid.as_str(), "worker")).collect();
+        setup_fake_proc(&tmp, &entries);
+
+        let scanner = Scanner::new(&tmp);
+        let found = scanner.scan(|p| p.comm == b"worker").unwrap();
+
+        assert_eq!(found.len(), 3000);
+        assert!(scanner.take_errors().is_empty());
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_read_proc_file_small() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("12", "bash")]);
+        let path = tmp.join("12/cmdline");
+        fs::write(&path, b"bash\0-c\0echo hi\0").unwrap();
+
+        let read = read_proc_file_small(&path, 4).unwrap();
+        assert_eq!(read.bytes, b"bash");
+        assert!(read.truncated);
+        let read = read_proc_file_small(&path, 16).unwrap();
+        assert!(!read.truncated);
+        let read = with_proc_file_small(&path, 4, |bytes, truncated| {
+            // A nested read gets a buffer of its own.
+            let inner = with_proc_file_small(&path, 16, |bytes, _| bytes.len()).unwrap();
+            (bytes.to_vec(), truncated, inner)
+        });
+        assert_eq!(read.unwrap(), (b"bash".to_vec(), true, 16));
+
+        let process = ProcessInfo::new(Arc::from(tmp.as_path()), pid(12), b"bash".to_vec());
+        assert_eq!(
+            process.cmdline().unwrap(),
+            vec![b"bash".to_vec(), b"-c".to_vec(), b"echo hi".to_vec()]
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+}
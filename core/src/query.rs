@@ -0,0 +1,77 @@
+use std::io;
+
+use crate::{
+    filters::Filter,
+    processes::{ProcessInfo, Scanner},
+};
+
+/// A scanner together with the filters a process must pass to match.
+///
+/// A process matches when every filter accepts it.
+pub struct ProcessQuery {
+    scanner: Scanner,
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl ProcessQuery {
+    #[must_use]
+    pub const fn new(scanner: Scanner) -> Self {
+        Self {
+            scanner,
+            filters: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn filter(mut self, filter: impl Filter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    #[must_use]
+    pub const fn scanner(&self) -> &Scanner {
+        &self.scanner
+    }
+
+    /// Whether `process` passes every filter.
+    #[must_use]
+    pub fn is_match(&self, process: &ProcessInfo) -> bool {
+        self.filters.iter().all(|f| f.matches(process))
+    }
+
+    /// Lazily yields matching processes, reading `/proc` only as far as the
+    /// caller consumes the iterator.
+    #[allow(clippy::iter_not_returning_iterator)] // opening the directory can fail
+    pub fn iter(&self) -> io::Result<impl Iterator<Item = ProcessInfo> + '_> {
+        Ok(self.scanner.iter()?.filter(|p| self.is_match(p)))
+    }
+
+    /// Collects every matching process, in parallel with the `rayon`
+    /// feature.
+    pub fn matches(&self) -> io::Result<Vec<ProcessInfo>> {
+        self.scanner.scan(|process| self.is_match(process))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filters::NameFilter,
+        testing::{cleanup_fake_proc, setup_fake_proc, unique_test_dir},
+    };
+
+    #[test]
+    fn test_iter_stops_early() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("1", "init"), ("2", "bash"), ("3", "bash")]);
+
+        let query = ProcessQuery::new(Scanner::new(&tmp)).filter(NameFilter::new("bash"));
+        let first = query.iter().unwrap().next().unwrap();
+
+        assert_eq!(first.comm, b"bash");
+        assert_eq!(query.iter().unwrap().count(), 2);
+
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,149 @@
+//! Token bucket bounding how often a long-running mode acts, so that a
+//! client in a respawn loop can't make faulx burn CPU or flood its logs.
+
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// At most `count` events per `interval`, written `N/INTERVAL` as in
+/// `10/s`, `5/30s`, `100/m` or `1/500ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub count: u32,
+    pub interval: Duration,
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, interval) = s
+            .split_once('/')
+            .ok_or_else(|| format!("{s}: expected N/INTERVAL, e.g. 10/s"))?;
+        let count: u32 = count
+            .parse()
+            .map_err(|e| format!("{s}: invalid count: {e}"))?;
+        if count == 0 {
+            return Err(format!("{s}: count must be at least 1"));
+        }
+        let split = interval
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(interval.len());
+        let (amount, unit) = interval.split_at(split);
+        let amount = if amount.is_empty() {
+            1
+        } else {
+            amount
+                .parse()
+                .map_err(|e| format!("{s}: invalid interval: {e}"))?
+        };
+        let unit = match unit {
+            "ms" => Duration::from_millis(1),
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(3600),
+            _ => return Err(format!("{s}: interval unit must be ms, s, m or h")),
+        };
+        let interval = unit * amount;
+        if interval.is_zero() {
+            return Err(format!("{s}: interval must not be zero"));
+        }
+        Ok(Self { count, interval })
+    }
+}
+
+/// Holds up to `count` tokens, refilled at `count` per `interval`. Every
+/// event takes one; events finding the bucket empty are refused.
+#[derive(Debug)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: Instant,
+    refused: u64,
+}
+
+impl TokenBucket {
+    /// A full bucket.
+    #[must_use]
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: f64::from(limit.count),
+            last: Instant::now(),
+            refused: 0,
+        }
+    }
+
+    /// Takes a token if there is one.
+    pub fn try_take(&mut self) -> bool {
+        self.try_take_at(Instant::now())
+    }
+
+    fn try_take_at(&mut self, now: Instant) -> bool {
+        let count = f64::from(self.limit.count);
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = count.mul_add(elapsed / self.limit.interval.as_secs_f64(), self.tokens);
+        self.tokens = self.tokens.min(count);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            self.refused += 1;
+            false
+        }
+    }
+
+    /// How many events were refused so far.
+    #[must_use]
+    pub const fn refused(&self) -> u64 {
+        self.refused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_limit() {
+        let limit: RateLimit = "10/s".parse().unwrap();
+        assert_eq!(limit.count, 10);
+        assert_eq!(limit.interval, Duration::from_secs(1));
+        assert_eq!(
+            "5/30s".parse::<RateLimit>().unwrap().interval,
+            Duration::from_secs(30)
+        );
+        assert_eq!(
+            "1/500ms".parse::<RateLimit>().unwrap().interval,
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            "100/m".parse::<RateLimit>().unwrap().interval,
+            Duration::from_secs(60)
+        );
+        for invalid in ["10", "0/s", "x/s", "1/0s", "1/2d", "1/"] {
+            assert!(invalid.parse::<RateLimit>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new("2/s".parse().unwrap());
+        let start = bucket.last;
+        assert!(bucket.try_take_at(start));
+        assert!(bucket.try_take_at(start));
+        assert!(!bucket.try_take_at(start));
+        assert_eq!(bucket.refused(), 1);
+        // Half a second refills one token.
+        assert!(bucket.try_take_at(start + Duration::from_millis(500)));
+        assert!(!bucket.try_take_at(start + Duration::from_millis(500)));
+        // A long pause refills up to the burst size only.
+        let later = start + Duration::from_secs(60);
+        assert!(bucket.try_take_at(later));
+        assert!(bucket.try_take_at(later));
+        assert!(!bucket.try_take_at(later));
+        assert_eq!(bucket.refused(), 3);
+    }
+}
@@ -0,0 +1,572 @@
+use std::{fmt, io, ops::RangeInclusive, str::FromStr};
+
+#[cfg(feature = "json")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+
+use crate::{pid::Pid, sys};
+
+/// A signal number, one of the standard signals or a real-time one.
+///
+/// It parses from what `--signal` takes and displays as its canonical
+/// name, as in `SIGTERM` or `SIGRTMIN+3`, which is also how serde writes it.
+/// Signals are read back from their names or numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Signal(libc::c_int);
+
+impl Signal {
+    pub const INT: Self = Self(libc::SIGINT);
+    pub const QUIT: Self = Self(libc::SIGQUIT);
+    pub const ABRT: Self = Self(libc::SIGABRT);
+    pub const TERM: Self = Self(libc::SIGTERM);
+    pub const KILL: Self = Self(libc::SIGKILL);
+
+    #[must_use]
+    pub const fn as_raw(self) -> libc::c_int {
+        self.0
+    }
+
+    /// The signal with this number, if the platform defines it.
+    #[must_use]
+    pub fn from_raw(number: libc::c_int) -> Option<Self> {
+        (STANDARD.contains(&number) || realtime_range().contains(&number)).then_some(Self(number))
+    }
+
+    #[must_use]
+    pub fn is_realtime(self) -> bool {
+        realtime_range().contains(&self.0)
+    }
+
+    /// The name `--list` shows, without `SIG`, unless the signal has none,
+    /// as a standard number unused on this architecture.
+    #[must_use]
+    pub fn name(self) -> Option<String> {
+        if self.is_realtime() {
+            return Some(realtime_name(self.0));
+        }
+        SIGNALS
+            .iter()
+            .find(|(_, signal)| *signal == self)
+            .map(|(name, _)| (*name).to_owned())
+    }
+}
+
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "SIG{name}"),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = String;
+
+    /// Parses a signal as `parse_signal` does, suggesting a known name
+    /// close to an unknown one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_signal(s).ok_or_else(|| {
+            let suggestion = suggest_signal(s)
+                .map(|suggestion| format!("; did you mean {suggestion}?"))
+                .unwrap_or_default();
+            format!("{s}: unknown signal{suggestion}")
+        })
+    }
+}
+
+impl From<Signal> for libc::c_int {
+    fn from(signal: Signal) -> Self {
+        signal.0
+    }
+}
+
+impl TryFrom<libc::c_int> for Signal {
+    type Error = String;
+
+    fn try_from(number: libc::c_int) -> Result<Self, Self::Error> {
+        Self::from_raw(number).ok_or_else(|| format!("{number}: not a signal on this system"))
+    }
+}
+
+#[cfg(feature = "nix")]
+impl From<nix::sys::signal::Signal> for Signal {
+    fn from(signal: nix::sys::signal::Signal) -> Self {
+        Self(signal as libc::c_int)
+    }
+}
+
+/// Fails with `EINVAL` for real-time signals, which nix doesn't represent.
+#[cfg(feature = "nix")]
+impl TryFrom<Signal> for nix::sys::signal::Signal {
+    type Error = nix::errno::Errno;
+
+    fn try_from(signal: Signal) -> Result<Self, Self::Error> {
+        Self::try_from(signal.0)
+    }
+}
+
+#[cfg(feature = "json")]
+impl Serialize for Signal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<'de> Deserialize<'de> for Signal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = Signal;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a signal name or number")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Signal, E> {
+                name.parse().map_err(E::custom)
+            }
+
+            fn visit_i64<E: de::Error>(self, number: i64) -> Result<Signal, E> {
+                libc::c_int::try_from(number)
+                    .map_err(|_| format!("{number}: not a signal on this system"))
+                    .and_then(Signal::try_from)
+                    .map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, number: u64) -> Result<Signal, E> {
+                i64::try_from(number).map_or_else(
+                    |_| Err(E::custom(format!("{number}: not a signal on this system"))),
+                    |number| self.visit_i64(number),
+                )
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Sends `signal` to `pid`.
+pub fn send_signal(pid: Pid, signal: Signal) -> io::Result<()> {
+    // SAFETY: kill only takes integer arguments.
+    let ret = unsafe { libc::kill(pid.as_raw(), signal.as_raw()) };
+    sys::check(ret)?;
+    Ok(())
+}
+
+/// Checks that `pid` exists and could be signalled, with the null signal,
+/// for which `kill(2)` goes through its checks without sending anything.
+pub fn probe_signal(pid: Pid) -> io::Result<()> {
+    // SAFETY: kill only takes integer arguments.
+    let ret = unsafe { libc::kill(pid.as_raw(), 0) };
+    sys::check(ret)?;
+    Ok(())
+}
+
+/// Sends `signal` to the thread `thread` of the process `pid` only, with
+/// `tgkill(2)`.
+pub fn send_thread_signal(pid: Pid, thread: Pid, signal: Signal) -> io::Result<()> {
+    // SAFETY: tgkill only takes integer arguments.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_tgkill,
+            libc::c_long::from(pid.as_raw()),
+            libc::c_long::from(thread.as_raw()),
+            libc::c_long::from(signal.as_raw()),
+        )
+    };
+    sys::check(ret)?;
+    Ok(())
+}
+
+/// The numbers of the standard signals, the same on every Linux
+/// architecture even where the signals they stand for differ.
+const STANDARD: RangeInclusive<libc::c_int> = 1..=31;
+
+/// The real-time signals usable by applications. The C library may reserve
+/// the first few, which is why this is queried at runtime.
+#[must_use]
+pub fn realtime_range() -> RangeInclusive<libc::c_int> {
+    libc::SIGRTMIN()..=libc::SIGRTMAX()
+}
+
+/// Signal names in `--list` order. Numbers come from `Signal`, which follows
+/// the target's libc, so they are correct on architectures such as MIPS or
+/// SPARC where the numbering differs from x86.
+static SIGNALS: &[(&str, Signal)] = &[
+    ("INT", Signal(libc::SIGINT)),
+    ("TERM", Signal(libc::SIGTERM)),
+    ("KILL", Signal(libc::SIGKILL)),
+    ("HUP", Signal(libc::SIGHUP)),
+    ("QUIT", Signal(libc::SIGQUIT)),
+    ("USR1", Signal(libc::SIGUSR1)),
+    ("USR2", Signal(libc::SIGUSR2)),
+    ("ALRM", Signal(libc::SIGALRM)),
+    ("CONT", Signal(libc::SIGCONT)),
+    ("STOP", Signal(libc::SIGSTOP)),
+    ("TSTP", Signal(libc::SIGTSTP)),
+    ("CHLD", Signal(libc::SIGCHLD)),
+    ("PIPE", Signal(libc::SIGPIPE)),
+    ("SEGV", Signal(libc::SIGSEGV)),
+    ("ABRT", Signal(libc::SIGABRT)),
+    ("ILL", Signal(libc::SIGILL)),
+    ("TRAP", Signal(libc::SIGTRAP)),
+    ("BUS", Signal(libc::SIGBUS)),
+    ("FPE", Signal(libc::SIGFPE)),
+    ("TTIN", Signal(libc::SIGTTIN)),
+    ("TTOU", Signal(libc::SIGTTOU)),
+    ("URG", Signal(libc::SIGURG)),
+    ("XCPU", Signal(libc::SIGXCPU)),
+    ("XFSZ", Signal(libc::SIGXFSZ)),
+    ("VTALRM", Signal(libc::SIGVTALRM)),
+    ("PROF", Signal(libc::SIGPROF)),
+    ("WINCH", Signal(libc::SIGWINCH)),
+    ("IO", Signal(libc::SIGIO)),
+    ("PWR", Signal(libc::SIGPWR)),
+    ("SYS", Signal(libc::SIGSYS)),
+];
+
+/// Historical names mapped to their canonical entry in `SIGNALS`.
+static ALIASES: &[(&str, &str)] = &[("IOT", "ABRT"), ("CLD", "CHLD"), ("POLL", "IO")];
+
+fn canonical_name(name: &str) -> &str {
+    ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map_or(name, |(_, canonical)| canonical)
+}
+
+fn aliases_of(name: &str) -> Vec<&'static str> {
+    ALIASES
+        .iter()
+        .filter(|(_, canonical)| *canonical == name)
+        .map(|(alias, _)| *alias)
+        .collect()
+}
+
+/// Parses `RTMIN`, `RTMIN+N`, `RTMAX` or `RTMAX-N`, rejecting offsets
+/// outside the real-time range.
+fn parse_realtime(name: &str) -> Option<Signal> {
+    let range = realtime_range();
+    let offset = |n: &str| -> Option<libc::c_int> {
+        if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        n.parse().ok()
+    };
+
+    let number = if let Some(rest) = name.strip_prefix("RTMIN") {
+        match rest.strip_prefix('+') {
+            Some(n) => range.start().checked_add(offset(n)?)?,
+            None if rest.is_empty() => *range.start(),
+            None => return None,
+        }
+    } else if let Some(rest) = name.strip_prefix("RTMAX") {
+        match rest.strip_prefix('-') {
+            Some(n) => range.end().checked_sub(offset(n)?)?,
+            None if rest.is_empty() => *range.end(),
+            None => return None,
+        }
+    } else {
+        return None;
+    };
+
+    range.contains(&number).then_some(Signal(number))
+}
+
+/// Name of a real-time signal in the notation used by `--list`: offsets
+/// from `RTMIN` for the lower half of the range and from `RTMAX` above it.
+fn realtime_name(number: libc::c_int) -> String {
+    let range = realtime_range();
+    let (min, max) = (*range.start(), *range.end());
+    let middle = min + (max - min) / 2;
+    match number {
+        n if n == min => "RTMIN".to_owned(),
+        n if n == max => "RTMAX".to_owned(),
+        n if n <= middle => format!("RTMIN+{}", n - min),
+        n => format!("RTMAX-{}", max - n),
+    }
+}
+
+/// Parses a signal number or name, case-insensitively and with an optional
+/// `SIG` prefix. Historical aliases such as `IOT` and real-time signals
+/// written as `RTMIN+N` or `RTMAX-N` are accepted.
+#[must_use]
+pub fn parse_signal(name: &str) -> Option<Signal> {
+    if !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit()) {
+        return name.parse().ok().and_then(Signal::from_raw);
+    }
+
+    let upper = name.to_uppercase();
+    let upper = upper.strip_prefix("SIG").unwrap_or(&upper);
+    let upper = canonical_name(upper);
+
+    SIGNALS
+        .iter()
+        .find(|(sig_name, _)| *sig_name == upper)
+        .map(|(_, signal)| *signal)
+        .or_else(|| parse_realtime(upper))
+}
+
+/// Optimal string alignment distance: insertions, deletions, substitutions
+/// and transpositions of adjacent characters each cost one.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut prev2 = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        cur[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                cur[j] = cur[j].min(prev2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut prev2, &mut prev);
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Suggests the known signal name closest to an unknown `name`, keeping
+/// the `SIG` prefix if it was given. Returns `None` when nothing is close.
+#[must_use]
+pub fn suggest_signal(name: &str) -> Option<String> {
+    let upper = name.to_uppercase();
+    let (prefix, bare) = upper
+        .strip_prefix("SIG")
+        .map_or(("", upper.as_str()), |bare| ("SIG", bare));
+    let max_distance = (bare.len() / 3).clamp(1, 2);
+
+    SIGNALS
+        .iter()
+        .map(|(name, _)| *name)
+        .chain(ALIASES.iter().map(|(alias, _)| *alias))
+        .map(|candidate| {
+            (
+                edit_distance(bare.as_bytes(), candidate.as_bytes()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| format!("{prefix}{candidate}"))
+}
+
+#[must_use]
+pub fn list_signals() -> String {
+    signal_infos()
+        .into_iter()
+        .map(|info| info.name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A signal as reported by `--list --output json`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json", derive(Serialize))]
+pub struct SignalInfo {
+    pub number: i32,
+    pub name: String,
+    pub aliases: Vec<&'static str>,
+}
+
+#[must_use]
+pub fn signal_infos() -> Vec<SignalInfo> {
+    let standard = SIGNALS.iter().map(|(name, signal)| SignalInfo {
+        number: signal.as_raw(),
+        name: (*name).to_owned(),
+        aliases: aliases_of(name),
+    });
+    let realtime = realtime_range().map(|number| SignalInfo {
+        number,
+        name: realtime_name(number),
+        aliases: Vec::new(),
+    });
+    standard.chain(realtime).collect()
+}
+
+#[cfg(feature = "json")]
+pub fn list_signals_json() -> serde_json::Result<String> {
+    serde_json::to_string(&signal_infos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_list_signals_json() {
+        let json: serde_json::Value = serde_json::from_str(&list_signals_json().unwrap()).unwrap();
+        let term = json
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["name"] == "TERM")
+            .unwrap();
+        assert_eq!(term["number"], 15);
+        assert!(term["aliases"].is_array());
+    }
+
+    #[test]
+    fn test_parse_signal_aliases() {
+        let sig = Signal::from_raw;
+        assert_eq!(parse_signal("IOT"), sig(libc::SIGABRT));
+        assert_eq!(parse_signal("sigiot"), sig(libc::SIGABRT));
+        assert_eq!(parse_signal("SIGCLD"), sig(libc::SIGCHLD));
+        assert_eq!(parse_signal("poll"), sig(libc::SIGIO));
+        assert_eq!(parse_signal("SIGTERM"), sig(libc::SIGTERM));
+        assert_eq!(parse_signal("SIG"), None);
+    }
+
+    #[test]
+    fn test_parse_signal_numbers() {
+        assert_eq!(parse_signal("9"), Some(Signal::KILL));
+        assert_eq!(parse_signal("15"), Some(Signal::TERM));
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal("99999999999"), None);
+    }
+
+    #[test]
+    fn test_parse_realtime_signals() {
+        let range = realtime_range();
+        let (min, max) = (*range.start(), *range.end());
+        let raw = |s: &str| parse_signal(s).map(Signal::as_raw);
+
+        assert_eq!(raw("RTMIN"), Some(min));
+        assert_eq!(raw("SIGRTMIN+1"), Some(min + 1));
+        assert_eq!(raw("rtmax-2"), Some(max - 2));
+        assert_eq!(raw("RTMAX"), Some(max));
+        assert_eq!(raw(&format!("RTMIN+{}", max - min)), Some(max));
+        assert_eq!(raw(&format!("RTMIN+{}", max - min + 1)), None);
+        assert_eq!(raw(&format!("RTMAX-{}", max - min + 1)), None);
+        assert_eq!(raw("RTMIN+"), None);
+        assert_eq!(raw("RTMIN-1"), None);
+        assert_eq!(raw("RTMIN+-1"), None);
+        assert!(parse_signal("RTMIN+1").unwrap().is_realtime());
+    }
+
+    #[test]
+    fn test_display_from_str() {
+        assert_eq!(Signal::TERM.to_string(), "SIGTERM");
+        assert_eq!("sigterm".parse(), Ok(Signal::TERM));
+        assert_eq!("9".parse(), Ok(Signal::KILL));
+        let rtmin3: Signal = "RTMIN+3".parse().unwrap();
+        assert_eq!(rtmin3.to_string(), "SIGRTMIN+3");
+        assert_eq!(rtmin3.to_string().parse(), Ok(rtmin3));
+        assert_eq!(
+            "SIGTREM".parse::<Signal>(),
+            Err("SIGTREM: unknown signal; did you mean SIGTERM?".to_owned())
+        );
+        assert_eq!(libc::c_int::from(Signal::KILL), libc::SIGKILL);
+        assert_eq!(Signal::try_from(libc::SIGQUIT), Ok(Signal::QUIT));
+        assert!(Signal::try_from(0).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "nix")]
+    fn test_nix_conversions() {
+        use nix::sys::signal::Signal as NixSignal;
+
+        assert_eq!(Signal::from(NixSignal::SIGTERM), Signal::TERM);
+        assert_eq!(NixSignal::try_from(Signal::KILL), Ok(NixSignal::SIGKILL));
+        let rtmin = parse_signal("RTMIN").unwrap();
+        assert!(NixSignal::try_from(rtmin).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_serde() {
+        assert_eq!(serde_json::to_string(&Signal::TERM).unwrap(), "\"SIGTERM\"");
+        let signals: Vec<Signal> = serde_json::from_str(r#"["HUP", 9, "sigterm"]"#).unwrap();
+        assert_eq!(signals, [Signal(libc::SIGHUP), Signal::KILL, Signal::TERM]);
+        assert!(serde_json::from_str::<Signal>("0").is_err());
+        assert!(serde_json::from_str::<Signal>("\"NOPE\"").is_err());
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance(b"TERM", b"TERM"), 0);
+        assert_eq!(edit_distance(b"TREM", b"TERM"), 1);
+        assert_eq!(edit_distance(b"TEM", b"TERM"), 1);
+        assert_eq!(edit_distance(b"", b"HUP"), 3);
+    }
+
+    #[test]
+    fn test_suggest_signal_common_typos() {
+        assert_eq!(suggest_signal("SIGTREM").as_deref(), Some("SIGTERM"));
+        assert_eq!(suggest_signal("sigterm1").as_deref(), Some("SIGTERM"));
+        assert_eq!(suggest_signal("KLL").as_deref(), Some("KILL"));
+        assert_eq!(suggest_signal("usr3").as_deref(), Some("USR1"));
+        assert_eq!(suggest_signal("STPO").as_deref(), Some("STOP"));
+        assert_eq!(suggest_signal("WINCHH").as_deref(), Some("WINCH"));
+        assert_eq!(suggest_signal("NOTASIGNAL"), None);
+    }
+
+    #[test]
+    fn test_list_realtime_signals_round_trip() {
+        for info in signal_infos() {
+            assert_eq!(
+                parse_signal(&info.name).map(Signal::as_raw),
+                Some(info.number)
+            );
+        }
+    }
+
+    #[test]
+    fn test_signal_infos_list_aliases() {
+        let abrt = signal_infos()
+            .into_iter()
+            .find(|s| s.name == "ABRT")
+            .unwrap();
+        assert_eq!(abrt.aliases, vec!["IOT"]);
+    }
+
+    fn number_of(name: &str) -> i32 {
+        signal_infos()
+            .into_iter()
+            .find(|s| s.name == name)
+            .unwrap()
+            .number
+    }
+
+    #[test]
+    #[cfg(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64",
+        target_arch = "riscv64"
+    ))]
+    fn test_signal_numbers_generic() {
+        assert_eq!(number_of("USR1"), 10);
+        assert_eq!(number_of("USR2"), 12);
+        assert_eq!(number_of("CHLD"), 17);
+        assert_eq!(number_of("CONT"), 18);
+        assert_eq!(number_of("STOP"), 19);
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    fn test_signal_numbers_mips() {
+        assert_eq!(number_of("USR1"), 16);
+        assert_eq!(number_of("USR2"), 17);
+        assert_eq!(number_of("CHLD"), 18);
+        assert_eq!(number_of("CONT"), 25);
+        assert_eq!(number_of("STOP"), 23);
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "sparc", target_arch = "sparc64"))]
+    fn test_signal_numbers_sparc() {
+        assert_eq!(number_of("USR1"), 30);
+        assert_eq!(number_of("USR2"), 31);
+        assert_eq!(number_of("CHLD"), 20);
+        assert_eq!(number_of("CONT"), 19);
+        assert_eq!(number_of("STOP"), 17);
+    }
+}
@@ -0,0 +1,368 @@
+//! A point-in-time scan that can be queried many times, for library users
+//! checking several names in a row, like a supervisor looking for each of
+//! its services.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    comm::Comm,
+    filters::Filter,
+    pid::Pid,
+    processes::{ProcessIdent, ProcessInfo, Scanner},
+};
+
+/// Every process of one scan, with their stat and user read up front and
+/// indexed by name, user and parent.
+///
+/// Processes may exit or be replaced after the snapshot is taken; check
+/// with `ProcessIdent::is_running` before acting on one much later.
+#[derive(Debug)]
+pub struct ProcSnapshot {
+    /// In ascending PID order.
+    processes: Vec<ProcessInfo>,
+    uids: Vec<Option<u32>>,
+    /// Left without the processes whose stat could not be read, which have
+    /// exited.
+    by_comm: HashMap<Comm, Vec<ProcessIdent>>,
+    by_uid: HashMap<u32, Vec<usize>>,
+    /// By raw parent PID, 0 for processes the kernel started.
+    by_ppid: HashMap<i32, Vec<usize>>,
+}
+
+impl ProcSnapshot {
+    /// Scans every process once, reading their stat and status in parallel
+    /// with the `rayon` feature.
+    pub fn take(scanner: &Scanner) -> io::Result<Self> {
+        let mut processes = scanner.scan(|process| {
+            process.stat();
+            true
+        })?;
+        processes.sort_unstable_by_key(|process| process.pid);
+
+        #[cfg(feature = "rayon")]
+        let uids: Vec<_> = processes.par_iter().map(ProcessInfo::uid).collect();
+        #[cfg(not(feature = "rayon"))]
+        let uids: Vec<_> = processes.iter().map(ProcessInfo::uid).collect();
+
+        let mut by_comm: HashMap<_, Vec<_>> = HashMap::new();
+        let mut by_uid: HashMap<_, Vec<_>> = HashMap::new();
+        let mut by_ppid: HashMap<_, Vec<_>> = HashMap::new();
+        for (index, process) in processes.iter().enumerate() {
+            if let Some(uid) = uids[index] {
+                by_uid.entry(uid).or_default().push(index);
+            }
+            if let Some(stat) = process.stat() {
+                by_ppid.entry(stat.ppid).or_default().push(index);
+                by_comm
+                    .entry(process.comm.clone())
+                    .or_default()
+                    .push(ProcessIdent {
+                        pid: process.pid,
+                        starttime: stat.starttime,
+                    });
+            }
+        }
+        Ok(Self {
+            processes,
+            uids,
+            by_comm,
+            by_uid,
+            by_ppid,
+        })
+    }
+
+    /// Every process, in ascending PID order.
+    #[must_use]
+    pub fn processes(&self) -> &[ProcessInfo] {
+        &self.processes
+    }
+
+    /// Every process, in ascending PID order, leaving the indexes behind.
+    #[must_use]
+    pub fn into_processes(self) -> Vec<ProcessInfo> {
+        self.processes
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.processes.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    /// The process `pid`, if it was running.
+    #[must_use]
+    pub fn get(&self, pid: Pid) -> Option<&ProcessInfo> {
+        self.processes
+            .binary_search_by_key(&pid, |process| process.pid)
+            .ok()
+            .map(|index| &self.processes[index])
+    }
+
+    /// The real user of `process`, as read when the snapshot was taken.
+    #[must_use]
+    pub fn uid_of(&self, process: &ProcessInfo) -> Option<u32> {
+        self.processes
+            .binary_search_by_key(&process.pid, |p| p.pid)
+            .ok()
+            .and_then(|index| self.uids[index])
+    }
+
+    /// The processes named exactly `comm`.
+    pub fn by_comm(&self, comm: &[u8]) -> impl Iterator<Item = &ProcessInfo> {
+        self.idents(comm)
+            .iter()
+            .filter_map(|ident| self.get(ident.pid))
+    }
+
+    /// The identities of the processes named exactly `comm`, in ascending
+    /// PID order, found in constant time.
+    #[must_use]
+    pub fn idents(&self, comm: &[u8]) -> &[ProcessIdent] {
+        self.by_comm.get(comm).map_or(&[], Vec::as_slice)
+    }
+
+    /// The identities of the processes named exactly any of `names`, in
+    /// ascending PID order, each once.
+    pub fn resolve<'a, I>(&self, names: I) -> Vec<ProcessIdent>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut idents: Vec<_> = names
+            .into_iter()
+            .flat_map(|name| self.idents(name))
+            .copied()
+            .collect();
+        idents.sort_unstable_by_key(|ident| ident.pid);
+        idents.dedup();
+        idents
+    }
+
+    /// The processes whose real user is `uid`.
+    pub fn by_uid(&self, uid: u32) -> impl Iterator<Item = &ProcessInfo> {
+        self.indexed(self.by_uid.get(&uid))
+    }
+
+    /// The children of `ppid`, in ascending PID order.
+    pub fn children(&self, ppid: Pid) -> impl Iterator<Item = &ProcessInfo> {
+        self.indexed(self.by_ppid.get(&ppid.as_raw()))
+            .filter(move |process| process.pid != ppid)
+    }
+
+    /// Every process below `pid`, depth first, each before its own
+    /// descendants and siblings in ascending PID order.
+    ///
+    /// PIDs reused between reads can make the parent links loop; every
+    /// process is then still listed once.
+    #[must_use]
+    pub fn descendants(&self, pid: Pid) -> Vec<&ProcessInfo> {
+        let mut seen = HashSet::from([pid]);
+        let mut found = Vec::new();
+        let mut stack: Vec<_> = self.children(pid).collect();
+        stack.reverse();
+        while let Some(process) = stack.pop() {
+            if !seen.insert(process.pid) {
+                continue;
+            }
+            found.push(process);
+            let siblings = stack.len();
+            stack.extend(self.children(process.pid));
+            stack[siblings..].reverse();
+        }
+        found
+    }
+
+    /// `roots` and the processes below them, each with how many levels below
+    /// the nearest of `roots` it is, 0 for those themselves, down to
+    /// `max_depth` if set. Each level comes before the next, in ascending
+    /// PID order within it; roots not in the snapshot are left out.
+    #[must_use]
+    pub fn below(&self, roots: &[Pid], max_depth: Option<usize>) -> Vec<(usize, &ProcessInfo)> {
+        let mut level: Vec<_> = roots.iter().filter_map(|&pid| self.get(pid)).collect();
+        level.sort_unstable_by_key(|process| process.pid);
+        level.dedup_by_key(|process| process.pid);
+        let mut seen: HashSet<_> = level.iter().map(|process| process.pid).collect();
+        let mut found = Vec::new();
+        for depth in 0.. {
+            if level.is_empty() || max_depth.is_some_and(|max| depth > max) {
+                break;
+            }
+            let mut next: Vec<_> = level
+                .iter()
+                .flat_map(|process| self.children(process.pid))
+                .filter(|child| seen.insert(child.pid))
+                .collect();
+            next.sort_unstable_by_key(|process| process.pid);
+            found.extend(level.into_iter().map(|process| (depth, process)));
+            level = next;
+        }
+        found
+    }
+
+    /// The parent of `pid`, its parent, and so on up to the first one not in
+    /// the snapshot, usually init.
+    #[must_use]
+    pub fn ancestors(&self, pid: Pid) -> Vec<&ProcessInfo> {
+        let mut seen = HashSet::from([pid]);
+        let mut found = Vec::new();
+        let mut current = self.get(pid);
+        while let Some(parent) = current
+            .and_then(ProcessInfo::stat)
+            .and_then(|stat| self.get(Pid::new(stat.ppid)?))
+        {
+            if !seen.insert(parent.pid) {
+                break;
+            }
+            found.push(parent);
+            current = Some(parent);
+        }
+        found
+    }
+
+    /// The processes `filter` accepts, without scanning again.
+    pub fn select<'a>(&'a self, filter: &'a dyn Filter) -> impl Iterator<Item = &'a ProcessInfo> {
+        self.processes
+            .iter()
+            .filter(|process| filter.matches(process))
+    }
+
+    fn indexed<'a>(
+        &'a self,
+        indices: Option<&'a Vec<usize>>,
+    ) -> impl Iterator<Item = &'a ProcessInfo> {
+        indices
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.processes[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::{
+        filters::NameFilter,
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat},
+    };
+
+    fn pids<'a>(processes: impl IntoIterator<Item = &'a ProcessInfo>) -> Vec<i32> {
+        processes
+            .into_iter()
+            .map(|process| process.pid.as_raw())
+            .collect()
+    }
+
+    #[test]
+    fn test_tree() {
+        let tmp = unique_test_dir();
+        let entries = [
+            ("1", "init"),
+            ("10", "sshd"),
+            ("11", "sshd"),
+            ("12", "bash"),
+            ("13", "vim"),
+            ("14", "cron"),
+            ("40", "a"),
+            ("41", "b"),
+        ];
+        setup_fake_proc(&tmp, &entries);
+        for (pid, comm, parent) in [
+            ("1", "init", 0),
+            ("10", "sshd", 1),
+            ("11", "sshd", 10),
+            ("12", "bash", 11),
+            ("13", "vim", 12),
+            ("14", "cron", 1),
+            // A loop left by reused PIDs.
+            ("40", "a", 41),
+            ("41", "b", 40),
+        ] {
+            write_fake_stat(&tmp, pid, comm, parent, 1);
+        }
+        let snapshot = ProcSnapshot::take(&Scanner::new(&tmp)).unwrap();
+        cleanup_fake_proc(&tmp);
+
+        assert_eq!(pids(snapshot.children(pid(1))), [10, 14]);
+        assert_eq!(pids(snapshot.descendants(pid(1))), [10, 11, 12, 13, 14]);
+        assert_eq!(pids(snapshot.descendants(pid(13))), Vec::<i32>::new());
+        assert_eq!(pids(snapshot.ancestors(pid(13))), [12, 11, 10, 1]);
+        assert_eq!(pids(snapshot.ancestors(pid(1))), Vec::<i32>::new());
+        assert_eq!(pids(snapshot.descendants(pid(40))), [41]);
+        assert_eq!(pids(snapshot.ancestors(pid(40))), [41]);
+
+        let below = |roots: &[i32], max_depth| {
+            let roots: Vec<_> = roots.iter().map(|&raw| pid(raw)).collect();
+            snapshot
+                .below(&roots, max_depth)
+                .into_iter()
+                .map(|(depth, process)| (depth, process.pid.as_raw()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            below(&[10, 14], None),
+            [(0, 10), (0, 14), (1, 11), (2, 12), (3, 13)]
+        );
+        assert_eq!(below(&[10], Some(1)), [(0, 10), (1, 11)]);
+        // Levels count from the nearest root.
+        assert_eq!(below(&[10, 12], None), [(0, 10), (0, 12), (1, 11), (1, 13)]);
+        assert_eq!(below(&[40, 99], None), [(0, 40), (1, 41)]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let tmp = unique_test_dir();
+        let entries = [
+            ("1", "init"),
+            ("20", "nginx"),
+            ("21", "nginx"),
+            ("30", "sshd"),
+        ];
+        setup_fake_proc(&tmp, &entries);
+        for (pid, comm) in entries {
+            let parent = match pid {
+                "1" => 0,
+                "21" => 20,
+                _ => 1,
+            };
+            write_fake_stat(&tmp, pid, comm, parent, 1);
+        }
+        fs::write(tmp.join("21/status"), "Uid:\t33\t33\t33\t33\n").unwrap();
+
+        let snapshot = ProcSnapshot::take(&Scanner::new(&tmp)).unwrap();
+        // Later changes are not seen.
+        cleanup_fake_proc(&tmp);
+
+        assert_eq!(snapshot.len(), 4);
+        assert_eq!(pids(snapshot.processes().iter()), [1, 20, 21, 30]);
+        assert_eq!(pids(snapshot.by_comm(b"nginx")), [20, 21]);
+        assert_eq!(pids(snapshot.by_comm(b"bash")), Vec::<i32>::new());
+        assert_eq!(pids(snapshot.by_uid(33)), [21]);
+        assert_eq!(pids(snapshot.children(pid(1))), [20, 30]);
+        assert_eq!(pids(snapshot.children(pid(20))), [21]);
+        assert_eq!(snapshot.get(pid(30)).unwrap().comm, b"sshd");
+        assert!(snapshot.get(pid(31)).is_none());
+        assert_eq!(snapshot.uid_of(snapshot.get(pid(21)).unwrap()), Some(33));
+        assert_eq!(snapshot.get(pid(21)).unwrap().stat().unwrap().ppid, 20);
+
+        let resolved = snapshot.resolve([&b"sshd"[..], b"nginx", b"bash", b"sshd"]);
+        assert_eq!(
+            resolved.iter().map(|ident| ident.pid).collect::<Vec<_>>(),
+            [20, 21, 30]
+        );
+        assert_eq!(snapshot.idents(b"nginx")[1].pid, 21);
+
+        let filter = NameFilter::new("sshd");
+        assert_eq!(pids(snapshot.select(&filter)), [30]);
+    }
+}
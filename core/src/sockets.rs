@@ -0,0 +1,247 @@
+use std::{collections::HashSet, fmt, fs, io, path::Path, str::FromStr, sync::Arc};
+
+use crate::{filters::Filter, processes::ProcessInfo};
+
+/// TCP state of a listening socket in `/proc/net/tcp`.
+const TCP_LISTEN: &str = "0A";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    const fn tables(self) -> [&'static str; 2] {
+        match self {
+            Self::Tcp => ["net/tcp", "net/tcp6"],
+            Self::Udp => ["net/udp", "net/udp6"],
+        }
+    }
+}
+
+/// A local port, optionally restricted to one protocol: `8080`,
+/// `8080/tcp` or `53/udp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortSpec {
+    pub port: u16,
+    pub protocol: Option<Protocol>,
+}
+
+impl FromStr for PortSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (port, protocol) = match s.split_once('/') {
+            Some((port, "tcp")) => (port, Some(Protocol::Tcp)),
+            Some((port, "udp")) => (port, Some(Protocol::Udp)),
+            Some((_, proto)) => return Err(format!("unknown protocol '{proto}'")),
+            None => (s, None),
+        };
+        let port = port
+            .parse()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(|| format!("invalid port '{port}'"))?;
+        Ok(Self { port, protocol })
+    }
+}
+
+impl fmt::Display for PortSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.protocol {
+            None => write!(f, "port {}", self.port),
+            Some(Protocol::Tcp) => write!(f, "port {}/tcp", self.port),
+            Some(Protocol::Udp) => write!(f, "port {}/udp", self.port),
+        }
+    }
+}
+
+/// Extracts the inodes of the sockets bound to `port` from the contents of
+/// a `/proc/net/{tcp,udp}{,6}` table. For TCP only listening sockets count.
+fn inet_inodes(contents: &str, port: u16, protocol: Protocol) -> impl Iterator<Item = u64> + '_ {
+    contents.lines().skip(1).filter_map(move |line| {
+        let mut fields = line.split_whitespace();
+        let local = fields.nth(1)?;
+        let state = fields.nth(1)?;
+        let inode = fields.nth(5)?;
+
+        let (_, local_port) = local.rsplit_once(':')?;
+        if u16::from_str_radix(local_port, 16).ok()? != port {
+            return None;
+        }
+        if protocol == Protocol::Tcp && state != TCP_LISTEN {
+            return None;
+        }
+        inode.parse().ok().filter(|inode| *inode != 0)
+    })
+}
+
+/// Inodes of the TCP listening and UDP bound sockets on a local port.
+pub fn port_inodes(root: &Path, spec: PortSpec) -> io::Result<HashSet<u64>> {
+    let protocols = match &spec.protocol {
+        Some(protocol) => std::slice::from_ref(protocol),
+        None => &[Protocol::Tcp, Protocol::Udp],
+    };
+    let mut inodes = HashSet::new();
+    for &protocol in protocols {
+        for table in protocol.tables() {
+            match fs::read_to_string(root.join(table)) {
+                Ok(contents) => inodes.extend(inet_inodes(&contents, spec.port, protocol)),
+                // IPv6 may be disabled.
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    Ok(inodes)
+}
+
+/// Extracts the inodes of the sockets bound to `path` from the contents of
+/// `/proc/net/unix`, whose lines end with the bound path if there is one.
+fn unix_inodes<'a>(contents: &'a str, path: &'a str) -> impl Iterator<Item = u64> + 'a {
+    contents.lines().skip(1).filter_map(move |line| {
+        let rest = line.trim_end().strip_suffix(path)?;
+        if !rest.ends_with(' ') {
+            return None;
+        }
+        let mut fields = rest.split_whitespace();
+        let inode = fields.nth(6)?;
+        // The path must be the eighth field, not part of a longer one.
+        if fields.next().is_some() {
+            return None;
+        }
+        inode.parse().ok()
+    })
+}
+
+/// Inodes of the unix domain sockets bound to `path`. The kernel records
+/// the path as passed to `bind`, so the canonical path is tried as well.
+pub fn unix_socket_inodes(root: &Path, path: &Path) -> io::Result<HashSet<u64>> {
+    let contents = fs::read_to_string(root.join("net/unix"))?;
+    let mut candidates = vec![path.to_path_buf()];
+    if let Ok(canonical) = fs::canonicalize(path)
+        && canonical != path
+    {
+        candidates.push(canonical);
+    }
+    Ok(candidates
+        .iter()
+        .filter_map(|path| path.to_str())
+        .flat_map(|path| unix_inodes(&contents, path))
+        .collect())
+}
+
+/// Parses a `socket:[12345]` fd link target.
+fn socket_inode(target: &Path) -> Option<u64> {
+    target
+        .to_str()?
+        .strip_prefix("socket:[")?
+        .strip_suffix(']')?
+        .parse()
+        .ok()
+}
+
+/// Matches processes holding a file descriptor on one of a set of sockets.
+#[derive(Debug, Clone)]
+pub struct SocketFilter {
+    inodes: Arc<HashSet<u64>>,
+}
+
+impl SocketFilter {
+    #[must_use]
+    pub fn new(inodes: HashSet<u64>) -> Self {
+        Self {
+            inodes: Arc::new(inodes),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.inodes.is_empty()
+    }
+}
+
+impl Filter for SocketFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        !self.inodes.is_empty()
+            && held_sockets(process)
+                .iter()
+                .any(|inode| self.inodes.contains(inode))
+    }
+}
+
+/// The inodes of the sockets `process` has file descriptors on, none if
+/// they can't be read.
+pub(crate) fn held_sockets(process: &ProcessInfo) -> Vec<u64> {
+    process
+        .fd_targets()
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|target| socket_inode(target))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 4242 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:1F90 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 4343 1 0000000000000000 20 4 30 10 -1
+   2: 00000000:0016 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 17 1 0000000000000000 100 0 0 10 0
+";
+
+    const TCP6: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 5151 1 0000000000000000 100 0 0 10 0
+";
+
+    #[test]
+    fn test_inet_inodes_listening_only() {
+        let inodes: Vec<u64> = inet_inodes(TCP, 8080, Protocol::Tcp).collect();
+        assert_eq!(inodes, vec![4242]);
+        let inodes: Vec<u64> = inet_inodes(TCP6, 8080, Protocol::Tcp).collect();
+        assert_eq!(inodes, vec![5151]);
+        assert_eq!(inet_inodes(TCP, 8080, Protocol::Udp).count(), 2);
+    }
+
+    #[test]
+    fn test_unix_inodes() {
+        let unix = "Num       RefCount Protocol Flags    Type St Inode Path
+0000000000000000: 00000002 00000000 00010000 0001 01 31337 /run/foo.sock
+0000000000000000: 00000003 00000000 00000000 0001 03 31338 /run/foo.sock
+0000000000000000: 00000002 00000000 00010000 0001 01 31339 /run/other/run/foo.sock
+0000000000000000: 00000002 00000000 00010000 0001 01 31340 @/run/foo.sock
+0000000000000000: 00000003 00000000 00000000 0001 03 31341
+";
+        let inodes: Vec<u64> = unix_inodes(unix, "/run/foo.sock").collect();
+        assert_eq!(inodes, vec![31337, 31338]);
+        let inodes: Vec<u64> = unix_inodes(unix, "@/run/foo.sock").collect();
+        assert_eq!(inodes, vec![31340]);
+    }
+
+    #[test]
+    fn test_parse_port_spec() {
+        assert_eq!(
+            "8080".parse(),
+            Ok(PortSpec {
+                port: 8080,
+                protocol: None
+            })
+        );
+        assert_eq!(
+            "53/udp".parse::<PortSpec>().map(|s| s.protocol),
+            Ok(Some(Protocol::Udp))
+        );
+        assert!("0".parse::<PortSpec>().is_err());
+        assert!("70000".parse::<PortSpec>().is_err());
+        assert!("80/sctp".parse::<PortSpec>().is_err());
+    }
+
+    #[test]
+    fn test_socket_inode() {
+        assert_eq!(socket_inode(Path::new("socket:[4242]")), Some(4242));
+        assert_eq!(socket_inode(Path::new("pipe:[4242]")), None);
+        assert_eq!(socket_inode(Path::new("/dev/null")), None);
+    }
+}
@@ -0,0 +1,317 @@
+//! The system calls faulx makes that std doesn't wrap, in one place.
+//!
+//! They go through nix by default, and straight through libc without the
+//! `nix` feature, for deployments that keep the code they audit to a
+//! minimum. The rest of the crate only sees this module, so both behave the
+//! same.
+
+use std::{
+    ffi::CStr,
+    fs::File,
+    io,
+    os::fd::{BorrowedFd, FromRawFd, OwnedFd},
+    time::Duration,
+};
+
+use crate::{pid::Pid, signals::Signal};
+
+#[cfg(not(feature = "nix"))]
+#[path = "sys/libc.rs"]
+mod imp;
+#[cfg(feature = "nix")]
+#[path = "sys/nix.rs"]
+mod imp;
+
+/// `ret`, or the error in `errno` if it is -1, as system calls report
+/// failures.
+pub fn check<T: Copy + PartialEq + From<i8>>(ret: T) -> io::Result<T> {
+    if ret == T::from(-1) {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+/// The real UID of this process.
+#[must_use]
+pub fn uid() -> u32 {
+    // SAFETY: getuid always succeeds.
+    unsafe { libc::getuid() }
+}
+
+/// The effective UID of this process, the one permissions are checked
+/// against.
+#[must_use]
+pub fn euid() -> u32 {
+    // SAFETY: geteuid always succeeds.
+    unsafe { libc::geteuid() }
+}
+
+/// The ID of this process.
+#[must_use]
+pub fn getpid() -> i32 {
+    // SAFETY: getpid always succeeds.
+    unsafe { libc::getpid() }
+}
+
+/// The name of the user `uid`, from the password database.
+pub fn user_name(uid: u32) -> io::Result<Option<String>> {
+    imp::user_name(uid)
+}
+
+/// The UID of the user called `name`.
+pub fn user_id(name: &str) -> io::Result<Option<u32>> {
+    imp::user_id(name)
+}
+
+/// The name of the group `gid`.
+pub fn group_name(gid: u32) -> io::Result<Option<String>> {
+    imp::group_name(gid)
+}
+
+/// A group, as the group database has it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupEntry {
+    pub gid: u32,
+    /// The users listed as members, which leaves out those having it as
+    /// their primary group.
+    pub members: Vec<String>,
+}
+
+/// The group called `name`.
+pub fn group_by_name(name: &str) -> io::Result<Option<GroupEntry>> {
+    imp::group_by_name(name)
+}
+
+/// The group `gid`.
+pub fn group_by_gid(gid: u32) -> io::Result<Option<GroupEntry>> {
+    imp::group_by_gid(gid)
+}
+
+/// How a child that was waited for changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// It exited with this status.
+    Exited(Pid, i32),
+    /// This signal killed it.
+    Signaled(Pid, i32),
+    /// It stopped or continued.
+    Changed(Pid),
+    /// Without blocking, and no child has changed.
+    StillAlive,
+}
+
+impl WaitStatus {
+    #[must_use]
+    pub const fn pid(self) -> Option<Pid> {
+        match self {
+            Self::Exited(pid, _) | Self::Signaled(pid, _) | Self::Changed(pid) => Some(pid),
+            Self::StillAlive => None,
+        }
+    }
+}
+
+/// Waits for the child `pid`, or any child, to change. With `nohang`, only
+/// checks.
+pub fn waitpid(pid: Option<Pid>, nohang: bool) -> io::Result<WaitStatus> {
+    imp::waitpid(pid, nohang)
+}
+
+/// Signals blocked and waited for together, as for `--init`.
+#[derive(Clone, Copy)]
+pub struct SignalSet(imp::SignalSet);
+
+impl SignalSet {
+    /// The set of `signals`, skipping those the platform doesn't define.
+    #[must_use]
+    pub fn new(signals: &[Signal]) -> Self {
+        Self(imp::signal_set(signals))
+    }
+
+    /// Blocks the signals in the calling thread, so that they stay pending
+    /// until waited for.
+    pub fn block(&self) -> io::Result<()> {
+        imp::block(&self.0)
+    }
+
+    /// Unblocks the signals in the calling thread. Only makes async-signal-
+    /// safe calls, so it can run between `fork` and `exec`.
+    pub fn unblock(&self) -> io::Result<()> {
+        imp::unblock(&self.0)
+    }
+
+    /// Waits for one of the signals, which must be blocked, and takes it.
+    pub fn wait(&self) -> io::Result<Signal> {
+        imp::wait(&self.0)
+    }
+}
+
+/// What a signal did before `catch` replaced it.
+pub struct Disposition(imp::Disposition);
+
+/// Calls `handler` on `signal` from now on, restarting the system calls it
+/// interrupts, and returns what was there before.
+///
+/// # Safety
+///
+/// `handler` must only make async-signal-safe calls.
+pub unsafe fn catch(
+    signal: Signal,
+    handler: extern "C" fn(libc::c_int),
+) -> io::Result<Disposition> {
+    // SAFETY: as promised by the caller.
+    unsafe { imp::catch(signal, handler) }.map(Disposition)
+}
+
+/// Puts back what `signal` did before `catch`.
+pub fn restore(signal: Signal, old: &Disposition) -> io::Result<()> {
+    // SAFETY: the disposition was in place before, so it is as sound now
+    // as it was then.
+    unsafe { imp::restore(signal, &old.0) }
+}
+
+/// A namespace `setns` can move into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Namespace {
+    Pid,
+    Mount,
+}
+
+/// Moves into the `kind` namespace that `file`, under `/proc/PID/ns`,
+/// refers to.
+pub fn setns(file: &File, kind: Namespace) -> io::Result<()> {
+    imp::setns(file, kind)
+}
+
+/// Which side of `fork` it returned on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fork {
+    Child,
+    Parent(Pid),
+}
+
+/// Forks this process.
+///
+/// # Safety
+///
+/// In a multithreaded process, the child may only make async-signal-safe
+/// calls.
+pub unsafe fn fork() -> io::Result<Fork> {
+    // SAFETY: as promised by the caller.
+    unsafe { imp::fork() }
+}
+
+/// Makes this process the reaper of its orphaned descendants, as PID 1 is.
+pub fn set_child_subreaper() -> io::Result<()> {
+    imp::set_child_subreaper()
+}
+
+/// Renames the calling thread, and so the process if it is the main one,
+/// as `/proc/PID/comm` shows it. Async-signal-safe.
+pub fn set_name(name: &CStr) -> io::Result<()> {
+    imp::set_name(name)
+}
+
+/// Has `signal` sent to this process when its parent exits.
+/// Async-signal-safe.
+pub fn set_parent_death_signal(signal: Signal) -> io::Result<()> {
+    imp::set_parent_death_signal(signal)
+}
+
+/// How many CPUs an affinity mask can hold.
+#[must_use]
+pub const fn max_cpus() -> usize {
+    imp::max_cpus()
+}
+
+/// Restricts the thread `tid` to `cpus`, each below `max_cpus`.
+pub fn set_affinity(tid: Pid, cpus: &[usize]) -> io::Result<()> {
+    imp::set_affinity(tid, cpus)
+}
+
+/// A file descriptor referring to the process `pid`, which becomes readable
+/// once it exits. Fails with `ENOSYS` before Linux 5.3.
+pub fn pidfd_open(pid: Pid) -> io::Result<OwnedFd> {
+    // nix doesn't wrap pidfd_open, so both go through libc here.
+    // SAFETY: pidfd_open only reads its integer arguments.
+    let fd = check(unsafe { libc::syscall(libc::SYS_pidfd_open, pid.as_raw(), 0) })?;
+    let fd = i32::try_from(fd).map_err(|_| io::Error::from_raw_os_error(libc::EBADF))?;
+    // SAFETY: the descriptor was just opened, and nothing else owns it.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Waits up to `timeout` for any of `fds` to become readable, returning
+/// which of them are, in order.
+pub fn poll_readable(fds: &[BorrowedFd<'_>], timeout: Duration) -> io::Result<Vec<bool>> {
+    imp::poll_readable(fds, timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_users() {
+        assert_eq!(user_name(0).unwrap().as_deref(), Some("root"));
+        assert_eq!(user_id("root").unwrap(), Some(0));
+        assert_eq!(user_id("no such user").unwrap(), None);
+        assert_eq!(user_id("ro\0ot").unwrap(), None);
+        assert_eq!(group_name(0).unwrap().as_deref(), Some("root"));
+        let root = group_by_name("root").unwrap().unwrap();
+        assert_eq!(root.gid, 0);
+        assert_eq!(group_by_gid(0).unwrap(), Some(root));
+        assert_eq!(group_by_name("no such group").unwrap(), None);
+    }
+
+    #[test]
+    #[allow(clippy::zombie_processes)] // reaped by waitpid
+    fn test_waitpid() {
+        let child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        let pid = Pid::new(i32::try_from(child.id()).unwrap()).unwrap();
+        assert_eq!(
+            waitpid(Some(pid), false).unwrap(),
+            WaitStatus::Exited(pid, 3)
+        );
+
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = Pid::new(i32::try_from(child.id()).unwrap()).unwrap();
+        assert_eq!(waitpid(Some(pid), true).unwrap(), WaitStatus::StillAlive);
+        child.kill().unwrap();
+        assert_eq!(
+            waitpid(Some(pid), false).unwrap(),
+            WaitStatus::Signaled(pid, libc::SIGKILL)
+        );
+    }
+
+    #[test]
+    fn test_pidfd_poll() {
+        use std::os::fd::AsFd;
+
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let pid = Pid::new(i32::try_from(child.id()).unwrap()).unwrap();
+        let Ok(pidfd) = pidfd_open(pid) else {
+            // Before Linux 5.3.
+            child.kill().unwrap();
+            child.wait().unwrap();
+            return;
+        };
+        let fds = [pidfd.as_fd()];
+        assert_eq!(poll_readable(&fds, Duration::ZERO).unwrap(), [false]);
+        child.kill().unwrap();
+        assert_eq!(poll_readable(&fds, Duration::from_secs(5)).unwrap(), [true]);
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_set_affinity() {
+        assert!(max_cpus() >= 1024);
+        let this = Pid::this();
+        assert_eq!(
+            set_affinity(this, &[max_cpus()])
+                .unwrap_err()
+                .raw_os_error(),
+            Some(libc::EINVAL)
+        );
+    }
+}
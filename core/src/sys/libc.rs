@@ -0,0 +1,287 @@
+//! The system calls of `sys`, straight through libc.
+
+use std::{
+    ffi::{CStr, CString},
+    fs::File,
+    io,
+    mem::{self, MaybeUninit},
+    os::fd::{AsRawFd, BorrowedFd},
+    ptr,
+    time::Duration,
+};
+
+use super::{Fork, GroupEntry, Namespace, WaitStatus, check};
+use crate::{pid::Pid, signals::Signal};
+
+pub type SignalSet = libc::sigset_t;
+pub type Disposition = libc::sigaction;
+
+/// Buffers for the password and group databases grow up to this, for
+/// entries with many members.
+const MAX_BUFFER: usize = 1 << 20;
+
+/// Runs a `getpwuid_r` style lookup, growing the buffer the strings go in
+/// until they fit, and reads the entry found.
+fn lookup<E, R>(
+    call: impl Fn(*mut E, *mut libc::c_char, usize, *mut *mut E) -> libc::c_int,
+    read: impl FnOnce(&E) -> R,
+) -> io::Result<Option<R>> {
+    let mut buf: Vec<libc::c_char> = vec![0; 1024];
+    loop {
+        let mut entry = MaybeUninit::<E>::uninit();
+        let mut found = ptr::null_mut();
+        match call(
+            entry.as_mut_ptr(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &raw mut found,
+        ) {
+            0 if found.is_null() => return Ok(None),
+            // SAFETY: on success, found points to entry, whose strings are
+            // in buf, both still alive.
+            0 => return Ok(Some(read(unsafe { &*found }))),
+            libc::ERANGE if buf.len() < MAX_BUFFER => buf.resize(buf.len() * 2, 0),
+            errno => return Err(io::Error::from_raw_os_error(errno)),
+        }
+    }
+}
+
+/// # Safety
+///
+/// `name` must point to a string terminated by a NUL byte.
+unsafe fn string(name: *const libc::c_char) -> String {
+    // SAFETY: as promised by the caller.
+    unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn user_name(uid: u32) -> io::Result<Option<String>> {
+    lookup(
+        // SAFETY: every pointer is valid for the length given.
+        |entry, buf, len, found| unsafe { libc::getpwuid_r(uid, entry, buf, len, found) },
+        // SAFETY: the database fills in pw_name.
+        |entry: &libc::passwd| unsafe { string(entry.pw_name) },
+    )
+}
+
+pub fn user_id(name: &str) -> io::Result<Option<u32>> {
+    let Ok(name) = CString::new(name) else {
+        return Ok(None);
+    };
+    lookup(
+        // SAFETY: every pointer is valid for the length given.
+        |entry, buf, len, found| unsafe { libc::getpwnam_r(name.as_ptr(), entry, buf, len, found) },
+        |entry: &libc::passwd| entry.pw_uid,
+    )
+}
+
+pub fn group_name(gid: u32) -> io::Result<Option<String>> {
+    lookup(
+        // SAFETY: every pointer is valid for the length given.
+        |entry, buf, len, found| unsafe { libc::getgrgid_r(gid, entry, buf, len, found) },
+        // SAFETY: the database fills in gr_name.
+        |entry: &libc::group| unsafe { string(entry.gr_name) },
+    )
+}
+
+pub fn group_by_name(name: &str) -> io::Result<Option<GroupEntry>> {
+    let Ok(name) = CString::new(name) else {
+        return Ok(None);
+    };
+    lookup(
+        // SAFETY: every pointer is valid for the length given.
+        |entry, buf, len, found| unsafe { libc::getgrnam_r(name.as_ptr(), entry, buf, len, found) },
+        // SAFETY: the database fills in gr_mem.
+        |entry: &libc::group| unsafe { group_entry(entry) },
+    )
+}
+
+pub fn group_by_gid(gid: u32) -> io::Result<Option<GroupEntry>> {
+    lookup(
+        // SAFETY: every pointer is valid for the length given.
+        |entry, buf, len, found| unsafe { libc::getgrgid_r(gid, entry, buf, len, found) },
+        // SAFETY: the database fills in gr_mem.
+        |entry: &libc::group| unsafe { group_entry(entry) },
+    )
+}
+
+/// # Safety
+///
+/// `entry.gr_mem` must point to an array of strings ended by a null
+/// pointer, each terminated by a NUL byte.
+unsafe fn group_entry(entry: &libc::group) -> GroupEntry {
+    let mut members = Vec::new();
+    let mut member = entry.gr_mem;
+    // SAFETY: as promised by the caller, the array ends with a null
+    // pointer, before which each is a string.
+    unsafe {
+        while !(*member).is_null() {
+            members.push(string(*member));
+            member = member.add(1);
+        }
+    }
+    GroupEntry {
+        gid: entry.gr_gid,
+        members,
+    }
+}
+
+pub fn waitpid(pid: Option<Pid>, nohang: bool) -> io::Result<WaitStatus> {
+    let mut status = 0;
+    let flags = if nohang { libc::WNOHANG } else { 0 };
+    // SAFETY: status outlives the call.
+    let ret = check(unsafe { libc::waitpid(pid.map_or(-1, Pid::as_raw), &raw mut status, flags) })?;
+    let Some(pid) = Pid::new(ret) else {
+        return Ok(WaitStatus::StillAlive);
+    };
+    Ok(if libc::WIFEXITED(status) {
+        WaitStatus::Exited(pid, libc::WEXITSTATUS(status))
+    } else if libc::WIFSIGNALED(status) {
+        WaitStatus::Signaled(pid, libc::WTERMSIG(status))
+    } else {
+        WaitStatus::Changed(pid)
+    })
+}
+
+pub fn signal_set(signals: &[Signal]) -> SignalSet {
+    let mut set = MaybeUninit::uninit();
+    // SAFETY: sigemptyset initializes the set, and sigaddset only fails
+    // on numbers out of range, which are then left out.
+    unsafe {
+        libc::sigemptyset(set.as_mut_ptr());
+        for signal in signals {
+            libc::sigaddset(set.as_mut_ptr(), signal.as_raw());
+        }
+        set.assume_init()
+    }
+}
+
+fn mask(how: libc::c_int, set: &SignalSet) -> io::Result<()> {
+    // SAFETY: set is initialized, and the old mask isn't asked for.
+    match unsafe { libc::pthread_sigmask(how, set, ptr::null_mut()) } {
+        0 => Ok(()),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+pub fn block(set: &SignalSet) -> io::Result<()> {
+    mask(libc::SIG_BLOCK, set)
+}
+
+pub fn unblock(set: &SignalSet) -> io::Result<()> {
+    mask(libc::SIG_UNBLOCK, set)
+}
+
+pub fn wait(set: &SignalSet) -> io::Result<Signal> {
+    let mut signal = 0;
+    // SAFETY: set is initialized, and signal outlives the call.
+    match unsafe { libc::sigwait(set, &raw mut signal) } {
+        0 => Signal::from_raw(signal).ok_or_else(|| io::Error::from_raw_os_error(libc::EINVAL)),
+        errno => Err(io::Error::from_raw_os_error(errno)),
+    }
+}
+
+pub unsafe fn catch(
+    signal: Signal,
+    handler: extern "C" fn(libc::c_int),
+) -> io::Result<Disposition> {
+    // SAFETY: all zeroes is a valid sigaction, with an empty mask.
+    let mut action: libc::sigaction = unsafe { mem::zeroed() };
+    action.sa_sigaction = handler as libc::sighandler_t;
+    action.sa_flags = libc::SA_RESTART;
+    let mut old = MaybeUninit::uninit();
+    // SAFETY: the handler is as the caller promised, and old is filled in
+    // on success.
+    unsafe {
+        check(libc::sigaction(
+            signal.as_raw(),
+            &raw const action,
+            old.as_mut_ptr(),
+        ))?;
+        Ok(old.assume_init())
+    }
+}
+
+pub unsafe fn restore(signal: Signal, old: &Disposition) -> io::Result<()> {
+    // SAFETY: as promised by the caller.
+    check(unsafe { libc::sigaction(signal.as_raw(), old, ptr::null_mut()) })?;
+    Ok(())
+}
+
+pub fn setns(file: &File, kind: Namespace) -> io::Result<()> {
+    let flags = match kind {
+        Namespace::Pid => libc::CLONE_NEWPID,
+        Namespace::Mount => libc::CLONE_NEWNS,
+    };
+    // SAFETY: the descriptor is open for as long as file is borrowed.
+    check(unsafe { libc::setns(file.as_raw_fd(), flags) })?;
+    Ok(())
+}
+
+pub unsafe fn fork() -> io::Result<Fork> {
+    // SAFETY: as promised by the caller.
+    let ret = check(unsafe { libc::fork() })?;
+    Ok(Pid::new(ret).map_or(Fork::Child, Fork::Parent))
+}
+
+pub fn set_child_subreaper() -> io::Result<()> {
+    // SAFETY: PR_SET_CHILD_SUBREAPER only reads its integer argument.
+    check(unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1) })?;
+    Ok(())
+}
+
+pub fn set_name(name: &CStr) -> io::Result<()> {
+    // SAFETY: the name is NUL-terminated, and the kernel reads at most 16
+    // bytes of it.
+    check(unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) })?;
+    Ok(())
+}
+
+pub fn set_parent_death_signal(signal: Signal) -> io::Result<()> {
+    // SAFETY: PR_SET_PDEATHSIG only reads its integer argument.
+    check(unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, signal.as_raw()) })?;
+    Ok(())
+}
+
+pub const fn max_cpus() -> usize {
+    8 * mem::size_of::<libc::cpu_set_t>()
+}
+
+pub fn set_affinity(tid: Pid, cpus: &[usize]) -> io::Result<()> {
+    // SAFETY: all zeroes is the empty set.
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= max_cpus() {
+            return Err(io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        // SAFETY: cpu is within the set.
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // SAFETY: set outlives the call, which only reads it.
+    check(unsafe {
+        libc::sched_setaffinity(
+            tid.as_raw(),
+            mem::size_of::<libc::cpu_set_t>(),
+            &raw const set,
+        )
+    })?;
+    Ok(())
+}
+
+pub fn poll_readable(fds: &[BorrowedFd<'_>], timeout: Duration) -> io::Result<Vec<bool>> {
+    let mut polled: Vec<_> = fds
+        .iter()
+        .map(|fd| libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+    let timeout = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+    let count = libc::nfds_t::try_from(polled.len())
+        .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+    // SAFETY: polled holds count entries and outlives the call.
+    check(unsafe { libc::poll(polled.as_mut_ptr(), count, timeout) })?;
+    Ok(polled.iter().map(|fd| fd.revents != 0).collect())
+}
@@ -0,0 +1,164 @@
+//! The system calls of `sys`, through nix.
+
+use std::{ffi::CStr, fs::File, io, os::fd::BorrowedFd, time::Duration};
+
+use nix::{
+    errno::Errno,
+    poll::{self, PollFd, PollFlags, PollTimeout},
+    sched::{self, CloneFlags, CpuSet},
+    sys::{
+        prctl,
+        signal::{self, SaFlags, SigAction, SigHandler, SigSet},
+        wait::{self, WaitPidFlag},
+    },
+    unistd::{self, ForkResult, Gid, Group, Uid, User},
+};
+
+use super::{Fork, GroupEntry, Namespace, WaitStatus};
+use crate::{pid::Pid, signals::Signal};
+
+pub type SignalSet = SigSet;
+pub type Disposition = SigAction;
+
+pub fn user_name(uid: u32) -> io::Result<Option<String>> {
+    Ok(User::from_uid(Uid::from_raw(uid))?.map(|user| user.name))
+}
+
+pub fn user_id(name: &str) -> io::Result<Option<u32>> {
+    Ok(User::from_name(name)?.map(|user| user.uid.as_raw()))
+}
+
+pub fn group_name(gid: u32) -> io::Result<Option<String>> {
+    Ok(Group::from_gid(Gid::from_raw(gid))?.map(|group| group.name))
+}
+
+pub fn group_by_name(name: &str) -> io::Result<Option<GroupEntry>> {
+    Ok(Group::from_name(name)?.map(entry))
+}
+
+pub fn group_by_gid(gid: u32) -> io::Result<Option<GroupEntry>> {
+    Ok(Group::from_gid(Gid::from_raw(gid))?.map(entry))
+}
+
+fn entry(group: Group) -> GroupEntry {
+    GroupEntry {
+        gid: group.gid.as_raw(),
+        members: group.mem,
+    }
+}
+
+const fn to_nix_pid(pid: Pid) -> unistd::Pid {
+    unistd::Pid::from_raw(pid.as_raw())
+}
+
+fn from_nix(pid: unistd::Pid) -> io::Result<Pid> {
+    Pid::new(pid.as_raw()).ok_or_else(|| Errno::EINVAL.into())
+}
+
+pub fn waitpid(pid: Option<Pid>, nohang: bool) -> io::Result<WaitStatus> {
+    let flags = nohang.then_some(WaitPidFlag::WNOHANG);
+    Ok(match wait::waitpid(pid.map(to_nix_pid), flags)? {
+        wait::WaitStatus::Exited(pid, code) => WaitStatus::Exited(from_nix(pid)?, code),
+        wait::WaitStatus::Signaled(pid, signal, _) => {
+            WaitStatus::Signaled(from_nix(pid)?, signal as i32)
+        }
+        wait::WaitStatus::StillAlive => WaitStatus::StillAlive,
+        status => WaitStatus::Changed(from_nix(status.pid().ok_or(Errno::EINVAL)?)?),
+    })
+}
+
+fn to_nix(signal: Signal) -> io::Result<signal::Signal> {
+    Ok(signal::Signal::try_from(signal.as_raw())?)
+}
+
+pub fn signal_set(signals: &[Signal]) -> SignalSet {
+    let mut set = SigSet::empty();
+    for &signal in signals {
+        if let Ok(signal) = to_nix(signal) {
+            set.add(signal);
+        }
+    }
+    set
+}
+
+pub fn block(set: &SignalSet) -> io::Result<()> {
+    Ok(set.thread_block()?)
+}
+
+pub fn unblock(set: &SignalSet) -> io::Result<()> {
+    Ok(set.thread_unblock()?)
+}
+
+pub fn wait(set: &SignalSet) -> io::Result<Signal> {
+    let signal = set.wait()?;
+    Signal::from_raw(signal as i32).ok_or_else(|| Errno::EINVAL.into())
+}
+
+pub unsafe fn catch(
+    signal: Signal,
+    handler: extern "C" fn(libc::c_int),
+) -> io::Result<Disposition> {
+    let action = SigAction::new(
+        SigHandler::Handler(handler),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    // SAFETY: as promised by the caller.
+    Ok(unsafe { signal::sigaction(to_nix(signal)?, &action) }?)
+}
+
+pub unsafe fn restore(signal: Signal, old: &Disposition) -> io::Result<()> {
+    // SAFETY: as promised by the caller.
+    unsafe { signal::sigaction(to_nix(signal)?, old) }?;
+    Ok(())
+}
+
+pub fn setns(file: &File, kind: Namespace) -> io::Result<()> {
+    let flags = match kind {
+        Namespace::Pid => CloneFlags::CLONE_NEWPID,
+        Namespace::Mount => CloneFlags::CLONE_NEWNS,
+    };
+    Ok(sched::setns(file, flags)?)
+}
+
+pub unsafe fn fork() -> io::Result<Fork> {
+    // SAFETY: as promised by the caller.
+    Ok(match unsafe { unistd::fork() }? {
+        ForkResult::Child => Fork::Child,
+        ForkResult::Parent { child } => Fork::Parent(from_nix(child)?),
+    })
+}
+
+pub fn set_child_subreaper() -> io::Result<()> {
+    Ok(prctl::set_child_subreaper(true)?)
+}
+
+pub fn set_name(name: &CStr) -> io::Result<()> {
+    Ok(prctl::set_name(name)?)
+}
+
+pub fn set_parent_death_signal(signal: Signal) -> io::Result<()> {
+    Ok(prctl::set_pdeathsig(to_nix(signal)?)?)
+}
+
+pub const fn max_cpus() -> usize {
+    CpuSet::count()
+}
+
+pub fn set_affinity(tid: Pid, cpus: &[usize]) -> io::Result<()> {
+    let mut set = CpuSet::new();
+    for &cpu in cpus {
+        set.set(cpu)?;
+    }
+    Ok(sched::sched_setaffinity(to_nix_pid(tid), &set)?)
+}
+
+pub fn poll_readable(fds: &[BorrowedFd<'_>], timeout: Duration) -> io::Result<Vec<bool>> {
+    let mut polled: Vec<_> = fds
+        .iter()
+        .map(|&fd| PollFd::new(fd, PollFlags::POLLIN))
+        .collect();
+    let timeout = PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX);
+    poll::poll(&mut polled, timeout)?;
+    Ok(polled.iter().map(|fd| fd.any().unwrap_or(false)).collect())
+}
@@ -0,0 +1,247 @@
+use std::{io, path::Path, time::Duration};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    killer::{Killer, SystemKiller},
+    processes::{PROC, ProcessIdent},
+    signals::Signal,
+    wait::wait_for_exit_timeout,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How to terminate a process: send `signal`, wait up to `grace` for it to
+/// exit, then send `escalate_to` if it is still running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub signal: Signal,
+    pub grace: Duration,
+    pub escalate_to: Option<Signal>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            signal: Signal::TERM,
+            grace: Duration::from_secs(5),
+            escalate_to: Some(Signal::KILL),
+        }
+    }
+}
+
+/// What `kill_with_policy` observed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The process was gone before any signal was sent.
+    NotRunning,
+    /// The process exited within the grace period.
+    Exited,
+    /// The process only exited after the escalation signal.
+    Escalated,
+    /// The process was still running after the last signal and grace period.
+    StillRunning,
+}
+
+/// Send-wait-escalate for a single process. The process is identified by
+/// PID and start time, so a PID reused in the meantime is never signalled.
+pub fn kill_with_policy(ident: ProcessIdent, policy: Policy) -> io::Result<Termination> {
+    kill_with_policy_in(Path::new(PROC), &SystemKiller, &SystemClock, ident, policy)
+}
+
+/// `kill_with_policy` against a proc filesystem mounted at `root`, sending
+/// the signals through `killer` and timing the grace periods with `clock`.
+pub fn kill_with_policy_in(
+    root: &Path,
+    killer: &dyn Killer,
+    clock: &dyn Clock,
+    ident: ProcessIdent,
+    policy: Policy,
+) -> io::Result<Termination> {
+    if !ident.is_alive(root) {
+        return Ok(Termination::NotRunning);
+    }
+    killer.kill(ident.pid, policy.signal)?;
+    if wait_for_exit_timeout(root, &[ident], POLL_INTERVAL, policy.grace, clock).is_empty() {
+        return Ok(Termination::Exited);
+    }
+
+    let Some(escalation) = policy.escalate_to else {
+        return Ok(Termination::StillRunning);
+    };
+    if !ident.is_alive(root) {
+        return Ok(Termination::Exited);
+    }
+    killer.kill(ident.pid, escalation)?;
+    if wait_for_exit_timeout(root, &[ident], POLL_INTERVAL, policy.grace, clock).is_empty() {
+        Ok(Termination::Escalated)
+    } else {
+        Ok(Termination::StillRunning)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        killer::{RecordingKiller, Sent},
+        pid::{PID_MAX_LIMIT, Pid},
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat},
+    };
+    use std::{
+        fs,
+        path::PathBuf,
+        process::{Command, Stdio},
+        thread,
+        time::UNIX_EPOCH,
+    };
+
+    /// Spawns `sh -c script` and reaps it in the background so that its PID
+    /// disappears from /proc once it exits.
+    fn spawn_reaped(script: &str) -> ProcessIdent {
+        let mut child = Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let pid = pid(i32::try_from(child.id()).unwrap());
+        let ident = ProcessIdent::current(Path::new(PROC), pid).unwrap();
+        thread::spawn(move || child.wait());
+        ident
+    }
+
+    #[test]
+    fn test_kill_with_policy_exits_on_first_signal() {
+        let ident = spawn_reaped("exec sleep 30");
+        let policy = Policy {
+            grace: Duration::from_secs(5),
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            kill_with_policy(ident, policy).unwrap(),
+            Termination::Exited
+        );
+    }
+
+    #[test]
+    fn test_kill_with_policy_escalates() {
+        let ident = spawn_reaped("trap '' TERM; while :; do sleep 0.05; done");
+        thread::sleep(Duration::from_millis(100));
+        let policy = Policy {
+            grace: Duration::from_millis(300),
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            kill_with_policy(ident, policy).unwrap(),
+            Termination::Escalated
+        );
+    }
+
+    #[test]
+    fn test_kill_with_policy_not_running() {
+        let ident = ProcessIdent {
+            pid: pid(PID_MAX_LIMIT - 1),
+            starttime: 0,
+        };
+
+        assert_eq!(
+            kill_with_policy(ident, Policy::default()).unwrap(),
+            Termination::NotRunning
+        );
+    }
+
+    /// A fake process 50, for the killer to pretend to signal.
+    fn fake_process() -> (PathBuf, ProcessIdent) {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("50", "nginx")]);
+        write_fake_stat(&tmp, "50", "nginx", 1, 50);
+        let ident = ProcessIdent::current(&tmp, pid(50)).unwrap();
+        (tmp, ident)
+    }
+
+    const fn sent(signal: Signal) -> Sent {
+        Sent {
+            pid: pid(50),
+            thread: None,
+            signal,
+        }
+    }
+
+    #[test]
+    fn test_kill_with_policy_in_records_escalation() {
+        let (tmp, ident) = fake_process();
+        let killer = RecordingKiller::new();
+        let clock = MockClock::new(UNIX_EPOCH);
+        let policy = Policy::default();
+
+        assert_eq!(
+            kill_with_policy_in(&tmp, &killer, &clock, ident, policy).unwrap(),
+            Termination::StillRunning
+        );
+        assert_eq!(killer.sent(), [sent(Signal::TERM), sent(Signal::KILL)]);
+        // Both grace periods ran out, without sleeping.
+        assert_eq!(clock.elapsed(), policy.grace * 2);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    /// Makes the fake process exit when it gets `signal`.
+    struct ExitsOn<'a> {
+        root: &'a Path,
+        signal: Signal,
+        sent: RecordingKiller,
+    }
+
+    impl Killer for ExitsOn<'_> {
+        fn kill(&self, pid: Pid, signal: Signal) -> io::Result<()> {
+            if signal == self.signal {
+                fs::remove_dir_all(self.root.join(pid.to_string()))?;
+            }
+            self.sent.kill(pid, signal)
+        }
+
+        fn kill_thread(&self, pid: Pid, thread: Pid, signal: Signal) -> io::Result<()> {
+            self.sent.kill_thread(pid, thread, signal)
+        }
+
+        fn probe(&self, pid: Pid) -> io::Result<()> {
+            self.sent.probe(pid)
+        }
+    }
+
+    #[test]
+    fn test_kill_with_policy_in_exits_after_escalation() {
+        let (tmp, ident) = fake_process();
+        let killer = ExitsOn {
+            root: &tmp,
+            signal: Signal::KILL,
+            sent: RecordingKiller::new(),
+        };
+        let clock = MockClock::new(UNIX_EPOCH);
+        let policy = Policy::default();
+
+        assert_eq!(
+            kill_with_policy_in(&tmp, &killer, &clock, ident, policy).unwrap(),
+            Termination::Escalated
+        );
+        assert_eq!(clock.elapsed(), policy.grace);
+        assert_eq!(killer.sent.sent(), [sent(Signal::TERM), sent(Signal::KILL)]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_kill_with_policy_in_stops_on_error() {
+        let (tmp, ident) = fake_process();
+        let killer = RecordingKiller::new().fail(pid(50), libc::EPERM);
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let err = kill_with_policy_in(&tmp, &killer, &clock, ident, Policy::default()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+        assert_eq!(killer.sent(), [sent(Signal::TERM)]);
+
+        cleanup_fake_proc(&tmp);
+    }
+}
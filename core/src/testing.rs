@@ -0,0 +1,68 @@
+//! Fake `/proc` trees and the like for tests, here and in the crates built
+//! on this one, with the `test-util` feature.
+#![allow(clippy::missing_panics_doc)]
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::pid::Pid;
+
+/// `raw` as a `Pid`, for test data.
+#[must_use]
+pub const fn pid(raw: i32) -> Pid {
+    Pid::new(raw).unwrap()
+}
+
+#[must_use]
+pub fn unique_test_dir() -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("fake_proc_{nanos}_{n}"))
+}
+
+pub fn setup_fake_proc(tmp: &Path, entries: &[(&str, &str)]) {
+    fs::create_dir_all(tmp).unwrap();
+    for (pid, comm) in entries {
+        let proc_dir = tmp.join(pid);
+        fs::create_dir_all(&proc_dir).unwrap();
+        let comm_path = proc_dir.join("comm");
+        let mut f = File::create(comm_path).unwrap();
+        writeln!(f, "{comm}").unwrap();
+    }
+}
+
+pub fn write_fake_stat(tmp: &Path, pid: &str, comm: &str, parent: i32, pgrp: i32) {
+    write_fake_stat_fields(tmp, pid, comm, 'S', parent, pgrp, 100);
+}
+
+pub fn write_fake_stat_fields(
+    tmp: &Path,
+    pid: &str,
+    comm: &str,
+    state: char,
+    parent: i32,
+    pgrp: i32,
+    starttime: u64,
+) {
+    let mut f = File::create(tmp.join(pid).join("stat")).unwrap();
+    writeln!(
+        f,
+        "{pid} ({comm}) {state} {parent} {pgrp} {pgrp} 0 -1 0 0 0 0 0 0 0 0 0 20 0 1 0 {starttime} 0 0"
+    )
+    .unwrap();
+}
+
+pub fn cleanup_fake_proc(tmp: &Path) {
+    if tmp.exists() {
+        fs::remove_dir_all(tmp).unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+//! Converts the clock tick counts of `/proc/<pid>/stat` to wall-clock times
+//! and formats them for display.
+
+use std::{
+    fmt, fs, io,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+/// How process start times are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum TimeFormat {
+    /// Time since the process started, as in `2h13m`.
+    #[default]
+    Human,
+    /// When the process started, in ISO 8601 UTC.
+    Iso,
+}
+
+/// Clock ticks per second, the unit of the times in `stat`.
+#[must_use]
+pub fn clock_ticks() -> u64 {
+    // SAFETY: sysconf has no preconditions.
+    u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) }).unwrap_or(100)
+}
+
+/// Seconds since boot, from `/proc/uptime`.
+#[must_use]
+pub fn uptime(root: &Path) -> Option<f64> {
+    fs::read_to_string(root.join("uptime"))
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Turns start times in clock ticks since boot into wall-clock times, using
+/// the boot time from the `btime` line of `/proc/stat`.
+#[derive(Debug, Clone, Copy)]
+pub struct BootClock {
+    boot: SystemTime,
+    ticks_per_sec: u64,
+}
+
+impl BootClock {
+    pub fn new(root: &Path) -> io::Result<Self> {
+        let stat = fs::read_to_string(root.join("stat"))?;
+        let btime = stat
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|btime| btime.trim().parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no btime in stat"))?;
+        Ok(Self {
+            boot: UNIX_EPOCH + Duration::from_secs(btime),
+            ticks_per_sec: clock_ticks(),
+        })
+    }
+
+    /// When a process whose `stat` start time is `starttime` started.
+    #[must_use]
+    pub fn start_time(&self, starttime: u64) -> SystemTime {
+        let secs = starttime / self.ticks_per_sec;
+        let nanos = starttime % self.ticks_per_sec * 1_000_000_000 / self.ticks_per_sec;
+        self.boot + Duration::from_secs(secs) + Duration::from_nanos(nanos)
+    }
+
+    /// Formats the start time `starttime` as of `now`.
+    #[must_use]
+    pub fn format(&self, starttime: u64, format: TimeFormat, now: SystemTime) -> String {
+        let start = self.start_time(starttime);
+        match format {
+            TimeFormat::Human => {
+                HumanDuration(now.duration_since(start).unwrap_or_default()).to_string()
+            }
+            TimeFormat::Iso => Iso8601(start).to_string(),
+        }
+    }
+}
+
+/// Shows a duration with its two largest units, like `45s`, `3m12s`,
+/// `2h13m` or `4d2h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HumanDuration(pub Duration);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.0.as_secs();
+        let (days, hours, mins) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+        match (days, hours, mins) {
+            (0, 0, 0) => write!(f, "{secs}s"),
+            (0, 0, _) => write!(f, "{mins}m{}s", secs % 60),
+            (0, _, _) => write!(f, "{hours}h{mins}m"),
+            _ => write!(f, "{days}d{hours}h"),
+        }
+    }
+}
+
+/// Shows a time as `2026-10-14T05:03:22Z`, to the second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iso8601(pub SystemTime);
+
+impl fmt::Display for Iso8601 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self
+            .0
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let (year, month, day) = civil_from_days(secs / 86400);
+        let (hour, min, sec) = (secs / 3600 % 24, secs / 60 % 60, secs % 60);
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02}T{hour:02}:{min:02}:{sec:02}Z"
+        )
+    }
+}
+
+/// The proleptic Gregorian date `days` days after 1970-01-01, after Howard
+/// Hinnant's `civil_from_days`.
+const fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_human_duration() {
+        let human = |secs| HumanDuration(Duration::from_secs(secs)).to_string();
+        assert_eq!(human(45), "45s");
+        assert_eq!(human(192), "3m12s");
+        assert_eq!(human(2 * 3600 + 13 * 60 + 5), "2h13m");
+        assert_eq!(human(4 * 86400 + 2 * 3600), "4d2h");
+    }
+
+    #[test]
+    fn test_iso8601() {
+        let iso = |secs| Iso8601(UNIX_EPOCH + Duration::from_secs(secs)).to_string();
+        assert_eq!(iso(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(iso(1_791_955_402), "2026-10-14T05:23:22Z");
+    }
+
+    #[test]
+    fn test_boot_clock() {
+        let clock = BootClock {
+            boot: UNIX_EPOCH + Duration::from_secs(1000),
+            ticks_per_sec: 100,
+        };
+        assert_eq!(
+            clock.start_time(250),
+            UNIX_EPOCH + Duration::from_millis(1_002_500)
+        );
+        let now = UNIX_EPOCH + Duration::from_secs(1000 + 2 * 3600 + 13 * 60 + 2);
+        assert_eq!(clock.format(200, TimeFormat::Human, now), "2h13m");
+        assert_eq!(
+            clock.format(200, TimeFormat::Iso, now),
+            "1970-01-01T00:16:42Z"
+        );
+        assert!(BootClock::new(Path::new("/proc")).is_ok());
+    }
+}
@@ -0,0 +1,92 @@
+//! Sizes and durations given on the command line, as in `10M` or `2h`.
+
+use std::time::Duration;
+
+/// Splits `s` into its leading digits and the unit after them.
+fn split_unit(s: &str) -> (&str, &str) {
+    match s.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((at, _)) => s.split_at(at),
+        None => (s, ""),
+    }
+}
+
+/// Parses a size in bytes, with an optional `K`, `M` or `G` suffix in
+/// powers of 1024, as in `512K` or `10M`.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let (digits, unit) = split_unit(s);
+    let shift = match unit {
+        "" => 0,
+        "K" | "k" => 10,
+        "M" | "m" => 20,
+        "G" | "g" => 30,
+        _ => return Err(format!("invalid size unit: {unit}")),
+    };
+    let value: u64 = digits.parse().map_err(|_| format!("invalid size: {s}"))?;
+    value
+        .checked_mul(1 << shift)
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("invalid size: {s}"))
+}
+
+/// Parses a whole number of milliseconds, seconds, minutes, hours or days,
+/// as in `250ms`, `90`, `90s`, `5m`, `2h` or `1d`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (digits, unit) = split_unit(s);
+    let scale = match unit {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => return Err(format!("invalid duration unit: {unit}")),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration: {s}"))?;
+    value
+        .checked_mul(scale)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("invalid duration: {s}"))
+}
+
+/// Parses a duration as `parse_duration` does, but not zero, for how long
+/// to sleep between checks.
+pub fn parse_interval(s: &str) -> Result<Duration, String> {
+    let interval = parse_duration(s)?;
+    if interval.is_zero() {
+        return Err(format!("invalid interval: {s}, must be longer than 0"));
+    }
+    Ok(interval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("0s"), Ok(Duration::ZERO));
+        assert!(parse_duration("2w").is_err());
+        assert!(parse_duration("h").is_err());
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(parse_interval("500ms"), Ok(Duration::from_millis(500)));
+        assert!(parse_interval("0s").is_err());
+    }
+}
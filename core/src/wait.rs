@@ -0,0 +1,645 @@
+use std::{
+    fmt,
+    os::fd::{AsFd, OwnedFd},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    processes::{PROC, ProcessIdent},
+    sys,
+};
+
+/// How often `wait_for_exit` checks on the remaining processes by default,
+/// matching killall's once-per-second polling.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to sleep between checks on the processes waited for.
+///
+/// It is the same interval every time, or one doubling after each check up
+/// to a maximum, so that a long wait on a slow shutdown doesn't keep
+/// reading `/proc`. Unless told otherwise, the wait also wakes as soon as
+/// one of the processes exits, where the kernel has pidfds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Polling {
+    interval: Duration,
+    max: Duration,
+    wake_on_exit: bool,
+}
+
+impl Polling {
+    /// Checks every `interval`.
+    #[must_use]
+    pub const fn fixed(interval: Duration) -> Self {
+        Self {
+            interval,
+            max: interval,
+            wake_on_exit: true,
+        }
+    }
+
+    /// Checks after `initial`, then doubles the interval after each check,
+    /// up to `max`.
+    #[must_use]
+    pub fn backoff(initial: Duration, max: Duration) -> Self {
+        Self {
+            interval: initial,
+            max: max.max(initial),
+            wake_on_exit: true,
+        }
+    }
+
+    /// Only checks at each interval, without waking early when a process
+    /// exits.
+    #[must_use]
+    pub const fn without_wakeups(self) -> Self {
+        Self {
+            wake_on_exit: false,
+            ..self
+        }
+    }
+
+    /// How long to sleep before the next check, moving on to the interval
+    /// after it.
+    pub fn next_interval(&mut self) -> Duration {
+        let interval = self.interval;
+        self.interval = interval.saturating_mul(2).min(self.max);
+        interval
+    }
+}
+
+impl Default for Polling {
+    fn default() -> Self {
+        Self::fixed(DEFAULT_INTERVAL)
+    }
+}
+
+impl From<Duration> for Polling {
+    fn from(interval: Duration) -> Self {
+        Self::fixed(interval)
+    }
+}
+
+/// Watch at most this many processes, as each pidfd takes a file descriptor
+/// and those are limited, to 1024 by default.
+const MAX_WATCHED: usize = 256;
+
+/// The pidfds of the processes waited for, which become readable when they
+/// exit, so that the wait can wake then rather than at the next interval.
+///
+/// `/proc` sends no inotify events when a process directory goes away, so
+/// that is no alternative. Without pidfds, before Linux 5.3, or for a `/proc`
+/// other than this system's, nothing is watched and the wait only polls.
+struct ExitWatch {
+    pidfds: Vec<Option<OwnedFd>>,
+}
+
+impl ExitWatch {
+    fn open(root: &Path, targets: &[(ProcessIdent, Option<Instant>)], polling: Polling) -> Self {
+        let watch = polling.wake_on_exit && root == Path::new(PROC);
+        let pidfds = targets
+            .iter()
+            .enumerate()
+            .map(|(index, (ident, _))| {
+                let watched = watch && index < MAX_WATCHED;
+                watched.then(|| sys::pidfd_open(ident.pid).ok()).flatten()
+            })
+            .collect();
+        Self { pidfds }
+    }
+
+    /// Stops watching the `index`th process, as once it exited.
+    fn forget(&mut self, index: usize) {
+        self.pidfds[index] = None;
+    }
+
+    /// Sleeps for `duration` on `clock`, or until a process watched exits,
+    /// and returns whether one did.
+    ///
+    /// A process found to exit is no longer watched: its pidfd stays
+    /// readable, and the check after waking tells if it really is gone.
+    fn sleep(&mut self, clock: &dyn Clock, duration: Duration) -> bool {
+        let (indices, fds): (Vec<_>, Vec<_>) = self
+            .pidfds
+            .iter()
+            .enumerate()
+            .filter_map(|(index, fd)| Some((index, fd.as_ref()?.as_fd())))
+            .unzip();
+        let ready = clock.sleep_until_readable(duration, &fds);
+        let exited: Vec<_> = indices
+            .into_iter()
+            .zip(ready)
+            .filter_map(|(index, ready)| ready.then_some(index))
+            .collect();
+        for &index in &exited {
+            self.forget(index);
+        }
+        !exited.is_empty()
+    }
+}
+
+/// How often `faulx waitfor` looks for processes matching.
+pub const WAITFOR_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How often `wait_for_exit_until` checks whether to stop while waiting
+/// out an interval.
+pub const STOP_CHECK: Duration = Duration::from_millis(100);
+
+/// How far a wait has got, reported after each check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub exited: usize,
+    pub total: usize,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "waiting: {} of {} exited ({}s)",
+            self.exited,
+            self.total,
+            self.elapsed.as_secs()
+        )
+    }
+}
+
+/// `--timeout` or `--timeout-per-pid`: how long signalled processes may
+/// take to exit before they are killed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// One period for all, from when the last of them was signalled.
+    Global(Duration),
+    /// A period each, from when it was signalled, so that a process acted
+    /// upon late or slow to exit doesn't extend the others'.
+    PerPid(Duration),
+}
+
+impl Timeout {
+    /// When the time of each of the processes runs out, given when it was
+    /// signalled.
+    #[must_use]
+    pub fn deadlines(self, signalled: &[(ProcessIdent, Instant)]) -> Vec<(ProcessIdent, Instant)> {
+        match self {
+            Self::Global(timeout) => {
+                let Some(last) = signalled.iter().map(|&(_, at)| at).max() else {
+                    return Vec::new();
+                };
+                signalled
+                    .iter()
+                    .map(|&(ident, _)| (ident, last + timeout))
+                    .collect()
+            }
+            Self::PerPid(timeout) => signalled
+                .iter()
+                .map(|&(ident, at)| (ident, at + timeout))
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    pub const fn duration(self) -> Duration {
+        match self {
+            Self::Global(timeout) | Self::PerPid(timeout) => timeout,
+        }
+    }
+}
+
+/// Blocks until none of `targets` is running any more. A PID now used by a
+/// different process counts as exited, and so does a zombie, which would
+/// otherwise hang the wait until its parent reaps it.
+pub fn wait_for_exit(root: &Path, targets: &[ProcessIdent], polling: impl Into<Polling>) {
+    wait_for_exit_with(root, targets, polling, &SystemClock, |_| {});
+}
+
+/// Like `wait_for_exit`, timed by `clock`, calling `progress` after every
+/// check that still found processes running.
+pub fn wait_for_exit_with(
+    root: &Path,
+    targets: &[ProcessIdent],
+    polling: impl Into<Polling>,
+    clock: &dyn Clock,
+    progress: impl FnMut(Progress),
+) {
+    let _ = wait_for_exit_until(root, targets, polling, clock, progress, || false);
+}
+
+/// Like `wait_for_exit_with`, but stops early once `stop` is true, which
+/// is checked at least every `STOP_CHECK`. Returns the processes still
+/// running then, or none if they all exited.
+#[must_use]
+pub fn wait_for_exit_until(
+    root: &Path,
+    targets: &[ProcessIdent],
+    polling: impl Into<Polling>,
+    clock: &dyn Clock,
+    progress: impl FnMut(Progress),
+    stop: impl Fn() -> bool,
+) -> Vec<ProcessIdent> {
+    let targets: Vec<_> = targets.iter().map(|&ident| (ident, None)).collect();
+    wait_for_exit_deadlines(root, &targets, polling, clock, progress, stop, |_| {})
+        .into_iter()
+        .filter(|waited| waited.exited.is_none())
+        .map(|waited| waited.ident)
+        .collect()
+}
+
+/// How the wait for one process went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Waited {
+    pub ident: ProcessIdent,
+    /// When it was first found gone, if it was before the wait stopped.
+    pub exited: Option<Instant>,
+    /// Whether it outlasted its deadline.
+    pub expired: bool,
+}
+
+/// Like `wait_for_exit_until`, but returns how the wait went for each of
+/// `targets`, in order.
+///
+/// `expired` is called once on each process still running at its deadline,
+/// if it has one, which is checked at least then. The process is waited for
+/// without a deadline afterwards.
+#[must_use]
+pub fn wait_for_exit_deadlines(
+    root: &Path,
+    targets: &[(ProcessIdent, Option<Instant>)],
+    polling: impl Into<Polling>,
+    clock: &dyn Clock,
+    mut progress: impl FnMut(Progress),
+    stop: impl Fn() -> bool,
+    mut expired: impl FnMut(ProcessIdent),
+) -> Vec<Waited> {
+    let mut polling = polling.into();
+    let mut watch = ExitWatch::open(root, targets, polling);
+    let start = clock.now();
+    let mut waited: Vec<_> = targets
+        .iter()
+        .map(|&(ident, _)| Waited {
+            ident,
+            exited: None,
+            expired: false,
+        })
+        .collect();
+    let mut deadlines: Vec<_> = targets.iter().map(|&(_, deadline)| deadline).collect();
+    loop {
+        let now = clock.now();
+        let mut running = 0;
+        for (index, (waited, deadline)) in waited.iter_mut().zip(&mut deadlines).enumerate() {
+            if waited.exited.is_some() {
+                continue;
+            }
+            if !waited.ident.is_alive(root) {
+                waited.exited = Some(now);
+                watch.forget(index);
+                continue;
+            }
+            running += 1;
+            if deadline.is_some_and(|deadline| deadline <= now) {
+                expired(waited.ident);
+                waited.expired = true;
+                *deadline = None;
+            }
+        }
+        if running == 0 || stop() {
+            return waited;
+        }
+        progress(Progress {
+            exited: targets.len() - running,
+            total: targets.len(),
+            elapsed: now - start,
+        });
+        let next = waited
+            .iter()
+            .zip(&deadlines)
+            .filter(|(waited, _)| waited.exited.is_none())
+            .filter_map(|(_, &deadline)| deadline)
+            .min();
+        let interval = polling.next_interval();
+        let wait = next.map_or(interval, |next| interval.min(next - now));
+        let mut slept = Duration::ZERO;
+        while slept < wait && !stop() {
+            let nap = STOP_CHECK.min(wait - slept);
+            if watch.sleep(clock, nap) {
+                break;
+            }
+            slept += nap;
+        }
+    }
+}
+
+/// Like `wait_for_exit`, timed by `clock`, but gives up after `timeout`
+/// and returns the processes still running at that point.
+#[must_use]
+pub fn wait_for_exit_timeout(
+    root: &Path,
+    targets: &[ProcessIdent],
+    polling: impl Into<Polling>,
+    timeout: Duration,
+    clock: &dyn Clock,
+) -> Vec<ProcessIdent> {
+    let deadline = clock.now() + timeout;
+    let targets: Vec<_> = targets
+        .iter()
+        .map(|&ident| (ident, Some(deadline)))
+        .collect();
+    let stop = || clock.now() >= deadline;
+    wait_for_exit_deadlines(root, &targets, polling, clock, |_| {}, stop, |_| {})
+        .into_iter()
+        .filter(|waited| waited.exited.is_none())
+        .map(|waited| waited.ident)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        pid::Pid,
+        testing::{
+            cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat_fields,
+        },
+    };
+    use std::{fs, process::Command, thread, time::UNIX_EPOCH};
+
+    #[test]
+    fn test_wait_returns_once_pid_is_reused() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("60", "sleep")]);
+        write_fake_stat_fields(&tmp, "60", "sleep", 'S', 1, 60, 10);
+        let ident = ProcessIdent::current(&tmp, pid(60)).unwrap();
+
+        let handle = {
+            let tmp = tmp.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                write_fake_stat_fields(&tmp, "60", "other", 'S', 1, 60, 20);
+            })
+        };
+        wait_for_exit(&tmp, &[ident], Duration::from_millis(5));
+        handle.join().unwrap();
+
+        assert!(!ident.is_running(&tmp));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_wait_reports_progress() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("62", "sleep"), ("63", "sleep")]);
+        write_fake_stat_fields(&tmp, "62", "sleep", 'S', 1, 62, 10);
+        let targets = [
+            ProcessIdent::current(&tmp, pid(62)).unwrap(),
+            ProcessIdent {
+                pid: pid(63),
+                starttime: 1,
+            },
+        ];
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let mut reports = Vec::new();
+        let interval = Duration::from_secs(1);
+        wait_for_exit_with(&tmp, &targets, interval, &clock, |progress| {
+            reports.push((progress.exited, progress.total, progress.elapsed));
+            if progress.elapsed == interval {
+                write_fake_stat_fields(&tmp, "62", "other", 'S', 1, 62, 20);
+            }
+        });
+
+        assert_eq!(
+            reports,
+            vec![(1, 2, Duration::ZERO), (1, 2, Duration::from_secs(1))]
+        );
+        assert_eq!(clock.elapsed(), Duration::from_secs(2));
+        let progress = Progress {
+            exited: 1,
+            total: 2,
+            elapsed: Duration::from_millis(3500),
+        };
+        assert_eq!(progress.to_string(), "waiting: 1 of 2 exited (3s)");
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_wait_backs_off() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("68", "java")]);
+        write_fake_stat_fields(&tmp, "68", "java", 'S', 1, 68, 10);
+        let ident = ProcessIdent::current(&tmp, pid(68)).unwrap();
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let mut checks = Vec::new();
+        let polling = Polling::backoff(Duration::from_secs(1), Duration::from_secs(4));
+        wait_for_exit_with(&tmp, &[ident], polling, &clock, |progress| {
+            checks.push(progress.elapsed.as_secs());
+            if progress.elapsed >= Duration::from_secs(11) {
+                write_fake_stat_fields(&tmp, "68", "java", 'Z', 1, 68, 10);
+            }
+        });
+
+        assert_eq!(checks, [0, 1, 3, 7, 11]);
+        assert_eq!(clock.elapsed(), Duration::from_secs(15));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_wait_wakes_on_exit() {
+        let root = Path::new(PROC);
+        let interval = Duration::from_secs(1);
+        let latency = |polling: Polling| {
+            let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+            let pid = Pid::new(i32::try_from(child.id()).unwrap()).unwrap();
+            let ident = ProcessIdent::current(root, pid).unwrap();
+            let start = Instant::now();
+            wait_for_exit(root, &[ident], polling);
+            let took = start.elapsed();
+            assert!(child.wait().unwrap().success());
+            took
+        };
+
+        let polled = latency(Polling::fixed(interval).without_wakeups());
+        assert!(polled >= interval, "{polled:?}");
+        if sys::pidfd_open(Pid::this()).is_err() {
+            return;
+        }
+        let woken = latency(Polling::fixed(interval));
+        assert!(woken < interval / 2, "{woken:?}");
+    }
+
+    #[test]
+    fn test_wait_returns_on_zombie() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("64", "sleep")]);
+        write_fake_stat_fields(&tmp, "64", "sleep", 'S', 1, 64, 10);
+        let ident = ProcessIdent::current(&tmp, pid(64)).unwrap();
+        write_fake_stat_fields(&tmp, "64", "sleep", 'Z', 1, 64, 10);
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let mut checks = 0;
+        wait_for_exit_with(&tmp, &[ident], DEFAULT_INTERVAL, &clock, |_| checks += 1);
+
+        assert_eq!(checks, 0);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_wait_until_stopped() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("65", "sleep")]);
+        write_fake_stat_fields(&tmp, "65", "sleep", 'S', 1, 65, 10);
+        let ident = ProcessIdent::current(&tmp, pid(65)).unwrap();
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let stop_at = Duration::from_millis(1300);
+        let remaining = wait_for_exit_until(
+            &tmp,
+            &[ident],
+            DEFAULT_INTERVAL,
+            &clock,
+            |_| {},
+            || clock.elapsed() >= stop_at,
+        );
+
+        assert_eq!(remaining, [ident]);
+        assert_eq!(clock.elapsed(), stop_at);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_timeout_deadlines() {
+        let start = Instant::now();
+        let ident = |raw| ProcessIdent {
+            pid: pid(raw),
+            starttime: 1,
+        };
+        let signalled = [
+            (ident(10), start),
+            (ident(11), start + Duration::from_secs(30)),
+        ];
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            Timeout::Global(timeout).deadlines(&signalled),
+            [
+                (ident(10), start + Duration::from_secs(35)),
+                (ident(11), start + Duration::from_secs(35)),
+            ]
+        );
+        assert_eq!(
+            Timeout::PerPid(timeout).deadlines(&signalled),
+            [
+                (ident(10), start + Duration::from_secs(5)),
+                (ident(11), start + Duration::from_secs(35)),
+            ]
+        );
+        assert!(Timeout::Global(timeout).deadlines(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_wait_deadlines() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("66", "sleep"), ("67", "sleep")]);
+        write_fake_stat_fields(&tmp, "66", "sleep", 'S', 1, 66, 10);
+        write_fake_stat_fields(&tmp, "67", "sleep", 'S', 1, 67, 10);
+        let stubborn = ProcessIdent::current(&tmp, pid(66)).unwrap();
+        let quick = ProcessIdent::current(&tmp, pid(67)).unwrap();
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let start = clock.now();
+        let mut expired = Vec::new();
+        let waited = wait_for_exit_deadlines(
+            &tmp,
+            &[
+                (stubborn, Some(start + Duration::from_millis(2500))),
+                (quick, Some(start + Duration::from_secs(10))),
+            ],
+            DEFAULT_INTERVAL,
+            &clock,
+            |progress| {
+                if progress.elapsed == Duration::from_secs(1) {
+                    write_fake_stat_fields(&tmp, "67", "other", 'S', 1, 67, 20);
+                }
+            },
+            || false,
+            |ident| {
+                expired.push((ident, clock.elapsed()));
+                fs::remove_dir_all(tmp.join("66")).unwrap();
+            },
+        );
+
+        // Killed at its deadline, without waiting out the interval, and
+        // found gone at the next check.
+        assert_eq!(expired, [(stubborn, Duration::from_millis(2500))]);
+        assert_eq!(
+            waited,
+            [
+                Waited {
+                    ident: stubborn,
+                    exited: Some(start + Duration::from_millis(3500)),
+                    expired: true,
+                },
+                Waited {
+                    ident: quick,
+                    exited: Some(start + Duration::from_secs(2)),
+                    expired: false,
+                },
+            ]
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_wait_timeout_returns_survivors() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("61", "sleep")]);
+        write_fake_stat_fields(&tmp, "61", "sleep", 'S', 1, 61, 10);
+        let ident = ProcessIdent::current(&tmp, pid(61)).unwrap();
+
+        let clock = MockClock::new(UNIX_EPOCH);
+        let survivors = wait_for_exit_timeout(
+            &tmp,
+            &[ident],
+            Duration::from_secs(2),
+            Duration::from_secs(5),
+            &clock,
+        );
+
+        assert_eq!(survivors, vec![ident]);
+        assert_eq!(clock.elapsed(), Duration::from_secs(5));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_wait_timeout_wakes_on_exit() {
+        if sys::pidfd_open(Pid::this()).is_err() {
+            return;
+        }
+        let mut child = Command::new("sleep").arg("0.1").spawn().unwrap();
+        let pid = Pid::new(i32::try_from(child.id()).unwrap()).unwrap();
+        let ident = ProcessIdent::current(Path::new(PROC), pid).unwrap();
+        let start = Instant::now();
+        let interval = Duration::from_secs(1);
+        let survivors = wait_for_exit_timeout(
+            Path::new(PROC),
+            &[ident],
+            interval,
+            Duration::from_secs(5),
+            &SystemClock,
+        );
+        let took = start.elapsed();
+        assert!(child.wait().unwrap().success());
+
+        assert!(survivors.is_empty());
+        assert!(took < interval / 2, "{took:?}");
+    }
+}
@@ -0,0 +1,49 @@
+#ifndef FAULX_H
+#define FAULX_H
+
+/* Generated by cbindgen from ffi/src/lib.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+// Match every member of the matched processes' groups.
+#define FAULX_PROCESS_GROUP 1
+
+// Treat the name as a regular expression matched against the process
+// name, like pgrep.
+#define FAULX_PATTERN (1 << 1)
+
+// With `FAULX_PATTERN`, the whole process name must match.
+#define FAULX_EXACT (1 << 2)
+
+// Finds the processes matching `name`, writing up to `capacity` of their
+// PIDs, in ascending order, to `out_pids`.
+//
+// Returns the number of matches, which may exceed `capacity`, so that a
+// caller can retry with a larger buffer.
+//
+// # Safety
+//
+// `name` must point to a NUL-terminated string and `out_pids` to space for
+// `capacity` PIDs. `out_pids` may be null if `capacity` is 0.
+int64_t faulx_match(const char *name, uint32_t flags, int32_t *out_pids, size_t capacity);
+
+// Sends `signal` to the processes matching `name`.
+//
+// Returns the number of processes signalled, or a negative errno if none
+// could be, e.g. `-ESRCH` when nothing matched.
+//
+// # Safety
+//
+// `name` must point to a NUL-terminated string.
+int64_t faulx_kill(const char *name, uint32_t flags, int signal);
+
+// Returns the number of the signal called `name` (`"HUP"`, `"SIGKILL"`,
+// `"RTMIN+1"`, `"9"`), or -1 if there is none.
+//
+// # Safety
+//
+// `name` must be null or point to a NUL-terminated string.
+int faulx_signal_number(const char *name);
+
+#endif  /* FAULX_H */
@@ -0,0 +1,205 @@
+//! C API over the faulx matching engine, built as `libfaulx.so` and
+//! `libfaulx.a`. The matching header is `include/faulx.h`, generated with
+//! `cbindgen --config cbindgen.toml --output include/faulx.h`.
+//!
+//! Functions return a negative errno on failure. The calling process is
+//! never matched.
+
+use std::{
+    ffi::{CStr, c_char, c_int},
+    io, slice,
+};
+
+use engine::{
+    actions::Kill,
+    filters::{NameFilter, PatternFilter},
+    pid::Pid,
+    pipeline::{Pipeline, Selector},
+    processes::{ProcessInfo, Scanner},
+    signals::{Signal, parse_signal},
+};
+
+/// Match every member of the matched processes' groups.
+pub const FAULX_PROCESS_GROUP: u32 = 1;
+/// Treat the name as a regular expression matched against the process
+/// name, like pgrep.
+pub const FAULX_PATTERN: u32 = 1 << 1;
+/// With `FAULX_PATTERN`, the whole process name must match.
+pub const FAULX_EXACT: u32 = 1 << 2;
+
+const KNOWN_FLAGS: u32 = FAULX_PROCESS_GROUP | FAULX_PATTERN | FAULX_EXACT;
+
+fn errno(err: &io::Error) -> i64 {
+    -i64::from(err.raw_os_error().unwrap_or(libc::EIO))
+}
+
+/// Builds the pipeline for `name` and `flags`, or returns a negative errno.
+///
+/// # Safety
+///
+/// `name` must be null or point to a NUL-terminated string.
+unsafe fn pipeline(name: *const c_char, flags: u32) -> Result<Pipeline, i64> {
+    if name.is_null() || flags & !KNOWN_FLAGS != 0 {
+        return Err(-i64::from(libc::EINVAL));
+    }
+    // SAFETY: the caller guarantees `name` is NUL-terminated.
+    let name = unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .map_err(|_| -i64::from(libc::EINVAL))?;
+
+    let own_pid = Pid::this();
+    let mut pipeline =
+        Pipeline::new(Scanner::default()).filter(move |p: &ProcessInfo| p.pid != own_pid);
+    pipeline = if flags & FAULX_PATTERN == 0 {
+        pipeline.filter(NameFilter::new(name))
+    } else {
+        let filter = PatternFilter::new(name, flags & FAULX_EXACT != 0)
+            .map_err(|_| -i64::from(libc::EINVAL))?;
+        pipeline.filter(filter)
+    };
+    if flags & FAULX_PROCESS_GROUP != 0 {
+        pipeline = pipeline.selector(Selector::ProcessGroup);
+    }
+    Ok(pipeline)
+}
+
+/// Finds the processes matching `name`, writing up to `capacity` of their
+/// PIDs, in ascending order, to `out_pids`.
+///
+/// Returns the number of matches, which may exceed `capacity`, so that a
+/// caller can retry with a larger buffer.
+///
+/// # Safety
+///
+/// `name` must point to a NUL-terminated string and `out_pids` to space for
+/// `capacity` PIDs. `out_pids` may be null if `capacity` is 0.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faulx_match(
+    name: *const c_char,
+    flags: u32,
+    out_pids: *mut i32,
+    capacity: usize,
+) -> i64 {
+    if out_pids.is_null() && capacity > 0 {
+        return -i64::from(libc::EINVAL);
+    }
+    // SAFETY: forwarded from the caller.
+    let pipeline = match unsafe { pipeline(name, flags) } {
+        Ok(pipeline) => pipeline,
+        Err(e) => return e,
+    };
+    let matches = match pipeline.select() {
+        Ok(matches) => matches,
+        Err(e) => return errno(&e),
+    };
+    if capacity > 0 {
+        // SAFETY: the caller guarantees room for `capacity` PIDs.
+        let out = unsafe { slice::from_raw_parts_mut(out_pids, capacity) };
+        for (slot, process) in out.iter_mut().zip(&matches) {
+            *slot = process.pid.as_raw();
+        }
+    }
+    i64::try_from(matches.len()).unwrap_or(i64::MAX)
+}
+
+/// Sends `signal` to the processes matching `name`.
+///
+/// Returns the number of processes signalled, or a negative errno if none
+/// could be, e.g. `-ESRCH` when nothing matched.
+///
+/// # Safety
+///
+/// `name` must point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faulx_kill(name: *const c_char, flags: u32, signal: c_int) -> i64 {
+    let Some(signal) = Signal::from_raw(signal) else {
+        return -i64::from(libc::EINVAL);
+    };
+    // SAFETY: forwarded from the caller.
+    let pipeline = match unsafe { pipeline(name, flags) } {
+        Ok(pipeline) => pipeline,
+        Err(e) => return e,
+    };
+    let outcomes = match pipeline.run(&mut Kill::new(signal)) {
+        Ok(outcomes) => outcomes,
+        Err(e) => return errno(&e),
+    };
+    let signalled = outcomes.iter().filter(|o| o.result.is_ok()).count();
+    match outcomes.iter().find_map(|o| o.result.as_ref().err()) {
+        Some(e) if signalled == 0 => errno(e),
+        _ if outcomes.is_empty() => -i64::from(libc::ESRCH),
+        _ => i64::try_from(signalled).unwrap_or(i64::MAX),
+    }
+}
+
+/// Returns the number of the signal called `name` (`"HUP"`, `"SIGKILL"`,
+/// `"RTMIN+1"`, `"9"`), or -1 if there is none.
+///
+/// # Safety
+///
+/// `name` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn faulx_signal_number(name: *const c_char) -> c_int {
+    if name.is_null() {
+        return -1;
+    }
+    // SAFETY: the caller guarantees `name` is NUL-terminated.
+    unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .ok()
+        .and_then(parse_signal)
+        .map_or(-1, Signal::as_raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        ffi::CString,
+        fs,
+        process::{self, Command},
+        ptr,
+    };
+
+    #[test]
+    fn test_signal_number() {
+        let name = CString::new("SIGHUP").unwrap();
+        assert_eq!(unsafe { faulx_signal_number(name.as_ptr()) }, libc::SIGHUP);
+        let name = CString::new("NOPE").unwrap();
+        assert_eq!(unsafe { faulx_signal_number(name.as_ptr()) }, -1);
+        assert_eq!(unsafe { faulx_signal_number(ptr::null()) }, -1);
+    }
+
+    #[test]
+    fn test_match_and_kill_child() {
+        // A copy of sleep under a name nothing else runs as.
+        let name = format!("fxffi{}", process::id());
+        let exe = std::env::temp_dir().join(&name);
+        fs::copy("/bin/sleep", &exe).unwrap();
+        let mut child = Command::new(&exe).arg("30").spawn().unwrap();
+        let pid = i32::try_from(child.id()).unwrap();
+        let name = CString::new(name).unwrap();
+
+        let mut pids = [0; 4];
+        let found = unsafe { faulx_match(name.as_ptr(), 0, pids.as_mut_ptr(), pids.len()) };
+        assert_eq!(found, 1);
+        assert_eq!(pids[0], pid);
+
+        let pattern = CString::new("^fxffi[0-9]+$").unwrap();
+        let count = unsafe { faulx_match(pattern.as_ptr(), FAULX_PATTERN, ptr::null_mut(), 0) };
+        assert!(count >= 1);
+        assert_eq!(
+            unsafe { faulx_match(name.as_ptr(), 1 << 10, ptr::null_mut(), 0) },
+            -i64::from(libc::EINVAL)
+        );
+
+        assert_eq!(unsafe { faulx_kill(name.as_ptr(), 0, libc::SIGKILL) }, 1);
+        child.wait().unwrap();
+        assert_eq!(
+            unsafe { faulx_kill(name.as_ptr(), 0, libc::SIGKILL) },
+            -i64::from(libc::ESRCH)
+        );
+
+        fs::remove_file(&exe).unwrap();
+    }
+}
@@ -0,0 +1,122 @@
+//! Python bindings for the faulx matching engine, built with maturin as the
+//! `faulx` extension module.
+
+use pyo3::pymodule;
+
+#[pymodule]
+mod faulx {
+    use engine::{
+        actions::Kill,
+        filters::{NameFilter, PatternFilter, UserFilter},
+        pid::Pid,
+        pipeline::{Pipeline, Selector},
+        processes::{ProcessInfo, Scanner},
+        signals::{Signal, parse_signal},
+    };
+    use pyo3::{
+        exceptions::{PyProcessLookupError, PyValueError},
+        prelude::*,
+    };
+
+    /// A signal given by name (`"HUP"`, `"SIGKILL"`) or number.
+    #[derive(FromPyObject)]
+    enum SignalArg {
+        Name(String),
+        Number(i32),
+    }
+
+    impl SignalArg {
+        fn resolve(self) -> PyResult<Signal> {
+            match self {
+                Self::Name(name) => parse_signal(&name),
+                Self::Number(number) => Signal::from_raw(number),
+            }
+            .ok_or_else(|| PyValueError::new_err("unknown signal"))
+        }
+    }
+
+    /// Matching options shared by `match` and `kill`.
+    fn pipeline(
+        name: &str,
+        pattern: bool,
+        exact: bool,
+        user: Option<&str>,
+        process_group: bool,
+    ) -> PyResult<Pipeline> {
+        let own_pid = Pid::this();
+        let mut pipeline =
+            Pipeline::new(Scanner::default()).filter(move |p: &ProcessInfo| p.pid != own_pid);
+        pipeline = if pattern {
+            pipeline.filter(PatternFilter::new(name, exact).map_err(PyValueError::new_err)?)
+        } else {
+            pipeline.filter(NameFilter::new(name))
+        };
+        if let Some(user) = user {
+            pipeline = pipeline.filter(UserFilter::from_user(user).map_err(PyValueError::new_err)?);
+        }
+        if process_group {
+            pipeline = pipeline.selector(Selector::ProcessGroup);
+        }
+        Ok(pipeline)
+    }
+
+    /// Returns the PIDs of the processes named `name`, in ascending order.
+    /// With `pattern`, `name` is a regular expression as in pgrep. The
+    /// calling process is never matched.
+    #[pyfunction]
+    #[pyo3(
+        name = "match",
+        signature = (name, *, pattern = false, exact = false, user = None, process_group = false)
+    )]
+    fn find(
+        py: Python<'_>,
+        name: &str,
+        pattern: bool,
+        exact: bool,
+        user: Option<&str>,
+        process_group: bool,
+    ) -> PyResult<Vec<i32>> {
+        let pipeline = pipeline(name, pattern, exact, user, process_group)?;
+        let matches = py.detach(|| pipeline.select())?;
+        Ok(matches.iter().map(|p| p.pid.as_raw()).collect())
+    }
+
+    /// Sends `sig` to the processes matched as by `match`, returning how
+    /// many were signalled. Raises `ProcessLookupError` if none matched,
+    /// and `OSError` if none could be signalled.
+    #[pyfunction]
+    #[pyo3(
+        signature = (name, sig = SignalArg::Name("TERM".to_owned()), *, pattern = false, exact = false, user = None, process_group = false)
+    )]
+    fn kill(
+        py: Python<'_>,
+        name: &str,
+        sig: SignalArg,
+        pattern: bool,
+        exact: bool,
+        user: Option<&str>,
+        process_group: bool,
+    ) -> PyResult<usize> {
+        let signal = sig.resolve()?;
+        let pipeline = pipeline(name, pattern, exact, user, process_group)?;
+        let outcomes = py.detach(|| pipeline.run(&mut Kill::new(signal)))?;
+        if outcomes.is_empty() {
+            return Err(PyProcessLookupError::new_err(format!(
+                "{name}: no process found"
+            )));
+        }
+        let signalled = outcomes.iter().filter(|o| o.result.is_ok()).count();
+        match outcomes.into_iter().find_map(|o| o.result.err()) {
+            Some(e) if signalled == 0 => Err(e.into()),
+            _ => Ok(signalled),
+        }
+    }
+
+    /// Returns the number of the signal called `name`.
+    #[pyfunction]
+    fn signal_number(name: &str) -> PyResult<i32> {
+        SignalArg::Name(name.to_owned())
+            .resolve()
+            .map(Signal::as_raw)
+    }
+}
@@ -0,0 +1,47 @@
+import os
+import shutil
+import subprocess
+import tempfile
+
+import pytest
+
+import faulx
+
+
+@pytest.fixture
+def sleeper():
+    # A copy of sleep under a name nothing else runs as.
+    name = f"fxpy{os.getpid()}"
+    exe = os.path.join(tempfile.gettempdir(), name)
+    shutil.copy("/bin/sleep", exe)
+    child = subprocess.Popen([exe, "30"])
+    yield name, child
+    child.kill()
+    child.wait()
+    os.remove(exe)
+
+
+def test_signal_number():
+    assert faulx.signal_number("SIGHUP") == 1
+    with pytest.raises(ValueError):
+        faulx.signal_number("NOPE")
+
+
+def test_match_and_kill(sleeper):
+    name, child = sleeper
+    assert faulx.match(name) == [child.pid]
+    assert child.pid in faulx.match("^fxpy[0-9]+$", pattern=True)
+
+    assert faulx.kill(name, sig="KILL") == 1
+    child.wait()
+    with pytest.raises(ProcessLookupError):
+        faulx.kill(name, sig=9)
+
+
+def test_bad_arguments():
+    with pytest.raises(ValueError):
+        faulx.kill("x", sig="NOPE")
+    with pytest.raises(ValueError):
+        faulx.match("(", pattern=True)
+    with pytest.raises(ValueError):
+        faulx.match("x", user="no-such-user-faulx")
@@ -0,0 +1,35 @@
+#!/bin/sh
+# Checks that every combination of optional features compiles, without
+# relying on CI. Extra arguments are passed to `cargo clippy`, e.g.
+# `--target x86_64-unknown-linux-musl`.
+set -eu
+
+features="cli clap color dbus json nix notify rayon regex tiny-cli tui window"
+
+combinations() {
+    set -- $features
+    n=$#
+    i=0
+    while [ "$i" -lt $((1 << n)) ]; do
+        combo=""
+        j=0
+        for f in "$@"; do
+            if [ $((i >> j & 1)) -eq 1 ]; then
+                combo="${combo:+$combo,}$f"
+            fi
+            j=$((j + 1))
+        done
+        echo "$combo"
+        i=$((i + 1))
+    done
+}
+
+for combo in "" $(combinations); do
+    case ",$combo," in
+    *,cli,* | *,clap,* | *,tiny-cli,*) targets=--all-targets ;;
+    # Without a command line parser there is only the library.
+    *) targets=--lib ;;
+    esac
+    echo "==> features: ${combo:-<none>}"
+    cargo clippy --quiet $targets --no-default-features --features "$combo" "$@" -- -D warnings
+done
@@ -0,0 +1,188 @@
+//! `--log-file`: an append-only record of what faulx did, one JSON object
+//! per line, so that who signalled what on a shared host can be audited
+//! after the fact.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+
+use crate::{pipeline::Outcome, sys, time::Iso8601};
+
+/// The size past which the log is rotated, unless `--log-max-size` says
+/// otherwise.
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How many rotated logs are kept, from `<path>.1`, the newest, up to
+/// `<path>.3`.
+pub const KEEP: u32 = 3;
+
+/// One line of the log.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    time: String,
+    uid: u32,
+    user: Option<&'a str>,
+    /// Who ran faulx through sudo, if it was.
+    sudo_user: Option<&'a str>,
+    pid: i32,
+    /// The name, with what isn't UTF-8 in it replaced.
+    comm: String,
+    /// The name as it is, byte for byte.
+    comm_bytes: &'a [u8],
+    action: &'a str,
+    /// `--reason`, if given.
+    reason: Option<&'a str>,
+    /// `ok`, or the error the action failed with.
+    result: String,
+}
+
+/// Where `--log-file` appends, with the user and `--reason` every entry is
+/// recorded with.
+#[derive(Debug)]
+pub struct ActionLog {
+    path: PathBuf,
+    max_size: u64,
+    uid: u32,
+    user: Option<String>,
+    sudo_user: Option<String>,
+    reason: Option<String>,
+}
+
+impl ActionLog {
+    #[must_use]
+    pub fn new(path: &Path, max_size: u64, reason: Option<String>) -> Self {
+        let uid = sys::uid();
+        Self {
+            path: path.to_owned(),
+            max_size,
+            uid,
+            user: sys::user_name(uid).ok().flatten(),
+            sudo_user: env::var("SUDO_USER").ok(),
+            reason,
+        }
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the result of applying `action` to a process, rotating the
+    /// log first if the line would take it past its maximum size.
+    pub fn record(&self, time: SystemTime, outcome: &Outcome, action: &str) -> io::Result<()> {
+        let entry = Entry {
+            time: Iso8601(time).to_string(),
+            uid: self.uid,
+            user: self.user.as_deref(),
+            sudo_user: self.sudo_user.as_deref(),
+            pid: outcome.process.pid.as_raw(),
+            comm: String::from_utf8_lossy(&outcome.process.comm).into_owned(),
+            comm_bytes: &outcome.process.comm,
+            action,
+            reason: self.reason.as_deref(),
+            result: outcome
+                .result
+                .as_ref()
+                .map_or_else(ToString::to_string, |()| "ok".to_owned()),
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        self.rotate_for(line.len() as u64)?;
+        // A single write of the whole line, so that runs appending at the
+        // same time don't interleave their entries.
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&line)
+    }
+
+    /// Shifts `<path>` to `<path>.1`, and so on up to `KEEP`, if adding
+    /// `len` bytes would take a nonempty log past its maximum size.
+    fn rotate_for(&self, len: u64) -> io::Result<()> {
+        let size = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if size == 0 || size + len <= self.max_size {
+            return Ok(());
+        }
+        for n in (1..KEEP).rev() {
+            match fs::rename(self.rotated(n), self.rotated(n + 1)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        fs::rename(&self.path, self.rotated(1))
+    }
+
+    fn rotated(&self, n: u32) -> PathBuf {
+        let mut path = OsString::from(&self.path);
+        path.push(format!(".{n}"));
+        PathBuf::from(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{processes::ProcessInfo, testing::pid};
+    use std::{sync::Arc, time::UNIX_EPOCH};
+
+    #[test]
+    fn test_record_and_rotate() {
+        let dir = std::env::temp_dir().join(format!("faulx-actionlog-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("faulx.log");
+        let root: Arc<Path> = Arc::from(Path::new("/nonexistent"));
+        let outcome = |pid, result| Outcome {
+            process: ProcessInfo::new(Arc::clone(&root), pid, b"nginx".to_vec()),
+            result,
+        };
+        let log = ActionLog::new(&path, 500, Some("INC-1234".to_owned()));
+
+        log.record(UNIX_EPOCH, &outcome(pid(10), Ok(())), "signal 15")
+            .unwrap();
+        let failed = outcome(pid(11), Err(io::Error::from_raw_os_error(libc::EPERM)));
+        log.record(UNIX_EPOCH, &failed, "signal 15").unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["time"], "1970-01-01T00:00:00Z");
+        assert_eq!(lines[0]["pid"], 10);
+        assert_eq!(lines[0]["comm"], "nginx");
+        assert_eq!(lines[0]["action"], "signal 15");
+        assert_eq!(lines[0]["reason"], "INC-1234");
+        assert_eq!(lines[0]["result"], "ok");
+        assert_eq!(lines[0]["uid"], sys::uid());
+        assert!(
+            lines[1]["result"]
+                .as_str()
+                .unwrap()
+                .contains("not permitted")
+        );
+
+        // Past 500 bytes, the log starts over and the old one is kept.
+        for raw in 12..20 {
+            log.record(UNIX_EPOCH, &outcome(pid(raw), Ok(())), "signal 15")
+                .unwrap();
+        }
+        assert!(fs::metadata(&path).unwrap().len() <= 500);
+        assert!(log.rotated(1).exists());
+        assert!(!log.rotated(KEEP + 1).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,3118 @@
+//! The killall, pgrep and pkill commands, from parsed arguments to what they
+//! did, with the process table and the output injected through `Env` so
+//! that whole runs can be tested against a fake `/proc`.
+
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    ffi::{OsStr, OsString},
+    io::{self, BufRead, IsTerminal, Write},
+    os::unix::process::CommandExt,
+    path::Path,
+    process,
+    sync::{Arc, Mutex, PoisonError, atomic::Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "json")]
+use crate::actionlog::{ActionLog, DEFAULT_MAX_SIZE};
+use crate::{
+    actions::{
+        Action, Affinity, CgroupMove, Chain, Count, Ionice, Kill, OomScoreAdj, Print, Renice,
+    },
+    archive::Archive,
+    capture::Capture,
+    cli::{
+        Command, FaulxArgs, FilterArgs, ListArgs, MAX_NAMES, OutputFormat, PatternArgs, PgrepArgs,
+        PkillArgs, RestartArgs, TreeArgs, WaitforArgs,
+    },
+    clock::Clock,
+    color::{Palettes, Style},
+    comm,
+    coredump::{self, CorePattern, CoreWatch, Dumping, Found, SYSTEMD_COREDUMP_DIR},
+    doctor::{self, Finding, Severity},
+    exit::ExitCode,
+    filters::{
+        AnyOf, ExeFilter, Filter, HoldingFilter, MemberFilter, NameFilter, Not, PatternFilter,
+        Shared, UserFilter,
+    },
+    grouping::{GroupBy, Groups},
+    hooks::{self, Hook},
+    interrupt::Interrupts,
+    killer::Killer,
+    limits::OriginalNofileLimit,
+    listing::{self, Sorting},
+    macros::QUIET,
+    metrics::Metrics,
+    permission::{self, PermissionStatus},
+    pid::{Pid, pid_max, read_list},
+    pipeline::{OrderStrategy, Outcome, Pipeline, Selector, WorkersFirst},
+    policy::{POLICY_PATH, RequireReason, SitePolicy},
+    processes::{ProcessIdent, ProcessInfo, Scanner, is_mounted},
+    profile::{Phase, Profile, add_filter, timed},
+    qprintln,
+    remote::{Remote, forwarded_args, prefix_lines},
+    selftest::{self, Dummy},
+    session::{Session, SessionFilter},
+    signals::{Signal, list_signals},
+    snapshot::ProcSnapshot,
+    sockets::{SocketFilter, port_inodes, unix_socket_inodes},
+    sudo::all_denied,
+    sys,
+    time::BootClock,
+    tree,
+    wait::{
+        DEFAULT_INTERVAL, Polling, Progress, Timeout, WAITFOR_INTERVAL, Waited,
+        wait_for_exit_deadlines,
+    },
+    wsl::Interop,
+};
+
+/// A command line, parsed for the tool it invokes.
+#[derive(Debug)]
+pub enum Invocation {
+    /// killall, with the command line `args` came from for `--host`.
+    Killall {
+        args: Box<FaulxArgs>,
+        command_line: Vec<OsString>,
+    },
+    Pgrep(PgrepArgs),
+    Pkill(PkillArgs),
+}
+
+impl Invocation {
+    /// The command `--init` runs, with the names to forward signals to
+    /// instead, unless the run is forwarded with `--host`.
+    #[must_use]
+    pub fn init(&self) -> Option<(&[OsString], &[String])> {
+        match self {
+            Self::Killall { args, .. } if args.host.is_empty() && !args.init.is_empty() => {
+                Some((&args.init, &args.forward_to))
+            }
+            _ => None,
+        }
+    }
+
+    /// The command line to run again under sudo with `--sudo`.
+    #[must_use]
+    pub fn sudo(&self) -> Option<&[OsString]> {
+        match self {
+            Self::Killall { args, command_line } if args.sudo => Some(command_line),
+            _ => None,
+        }
+    }
+
+    /// The process whose namespaces `--ns-of` asks to run in, unless the
+    /// run is forwarded with `--host`, where the hosts enter them.
+    #[must_use]
+    pub fn ns_of(&self) -> Option<Pid> {
+        match self {
+            Self::Killall { args, .. } if args.host.is_empty() => args.ns_of,
+            _ => None,
+        }
+    }
+
+    /// Limits matching by name to the login session, as
+    /// `--session-scope` does, unless `--all-sessions` or `--ns-of`: the
+    /// default in graphical sessions.
+    pub fn scope_to_session(&mut self) {
+        if let Self::Killall { args, .. } = self
+            && args.ns_of.is_none()
+        {
+            let command = match &mut args.command {
+                Some(Command::Renice(renice)) => Some(&mut renice.filters),
+                Some(Command::Waitfor(waitfor)) => Some(&mut waitfor.filters),
+                _ => None,
+            };
+            for filters in [Some(&mut args.filters), command].into_iter().flatten() {
+                filters.session_scope |= !filters.all_sessions;
+            }
+        }
+    }
+}
+
+/// What a run reads and writes besides its arguments. Complaints still go
+/// to stderr.
+pub struct Env<'a> {
+    /// Where processes are read from, `/proc` outside of tests.
+    pub root: &'a Path,
+    /// Where PIDs and `--verbose` or `--echo` lines are written.
+    pub out: &'a mut dyn Write,
+    /// What sends the signals, `SystemKiller` outside of tests.
+    pub killer: Arc<dyn Killer>,
+    /// What waits are timed by, `SystemClock` outside of tests.
+    pub clock: Arc<dyn Clock>,
+    /// What the administrator allows, read from `POLICY_PATH` outside of
+    /// tests.
+    pub policy: SitePolicy,
+}
+
+/// What a run did.
+#[derive(Debug)]
+pub struct Report {
+    /// Each process the action was applied to, in order, with its result.
+    /// pgrep's action is printing or counting them.
+    pub outcomes: Vec<Outcome>,
+    /// `Matched`, or `NoneMatched` if nothing matched or the action failed.
+    pub code: ExitCode,
+    /// With `--wait`, how the wait went for each process the action
+    /// succeeded on, with when it was signalled.
+    pub waited: Vec<(Instant, Waited)>,
+    /// With `--persistent`, how many of `outcomes` are of processes that
+    /// were respawned during the run.
+    pub respawns: u64,
+}
+
+impl Report {
+    const fn new(outcomes: Vec<Outcome>, matched: bool) -> Self {
+        let code = if matched {
+            ExitCode::Matched
+        } else {
+            ExitCode::NoneMatched
+        };
+        Self {
+            outcomes,
+            code,
+            waited: Vec::new(),
+            respawns: 0,
+        }
+    }
+
+    const fn interrupted(outcomes: Vec<Outcome>) -> Self {
+        Self {
+            outcomes,
+            code: ExitCode::Interrupted,
+            waited: Vec::new(),
+            respawns: 0,
+        }
+    }
+
+    fn waited(self, waited: Vec<(Instant, Waited)>) -> Self {
+        Self { waited, ..self }
+    }
+
+    const fn respawns(mut self, respawns: u64) -> Self {
+        self.respawns = respawns;
+        self
+    }
+}
+
+/// Runs `invocation` in `env`. Fails with `Usage`, `Internal` or `NoProc`
+/// once the problem is reported on stderr.
+pub fn run(invocation: Invocation, env: &mut Env<'_>) -> Result<Report, ExitCode> {
+    if let Some(tool) = needs_proc(&invocation)
+        && !is_mounted(env.root)
+    {
+        qprintln!(
+            "{tool}: {} is not mounted; only --pid, --list and --host work without it",
+            env.root.display()
+        );
+        return Err(ExitCode::NoProc);
+    }
+    match invocation {
+        Invocation::Pgrep(args) => pgrep(&args, env),
+        Invocation::Pkill(args) => pkill(&args, env),
+        Invocation::Killall { args, command_line } if !args.host.is_empty() => {
+            remote(&args, &command_line, env)
+        }
+        Invocation::Killall { args, .. } => {
+            let sudo = args.sudo;
+            let report = killall(&args, env)?;
+            if !sudo && sys::euid() != 0 && all_denied(&report.outcomes) {
+                qprintln!(
+                    "{}: permission denied for every process matched; run it again with \
+                     sudo, or with --sudo to be offered to",
+                    env!("CARGO_CRATE_NAME")
+                );
+            }
+            Ok(report)
+        }
+    }
+}
+
+/// The name of the tool, if `invocation` reads the local process table.
+fn needs_proc(invocation: &Invocation) -> Option<&'static str> {
+    match invocation {
+        Invocation::Pgrep(_) => Some("pgrep"),
+        Invocation::Pkill(_) => Some("pkill"),
+        Invocation::Killall { args, .. } => {
+            let without = !args.host.is_empty()
+                || args.list
+                || !args.pids.is_empty()
+                || matches!(args.command, Some(Command::Doctor));
+            (!without).then_some(env!("CARGO_CRATE_NAME"))
+        }
+    }
+}
+
+/// Parses a signal name, or reports it suggesting the closest known name.
+fn parse_signal_arg(name: &str) -> Result<Signal, ExitCode> {
+    name.parse().map_err(|e| {
+        qprintln!("{e}");
+        ExitCode::Usage
+    })
+}
+
+/// Builds the pipeline shared by pgrep and pkill, excluding this process.
+fn pattern_pipeline(args: &PatternArgs, root: &Path) -> Result<Pipeline, ExitCode> {
+    let filter = PatternFilter::new(&args.pattern, args.exact).map_err(|e| {
+        eprintln!("{}: invalid pattern: {e}", args.pattern);
+        ExitCode::Usage
+    })?;
+    let own_pid = Pid::this();
+    Ok(Pipeline::new(Scanner::new(root))
+        .filter(filter)
+        .filter(move |p: &ProcessInfo| p.pid != own_pid)
+        .threads(args.lightweight))
+}
+
+/// Applies `action` to each of `processes` in turn.
+fn apply_each(processes: Vec<ProcessInfo>, action: &mut dyn Action) -> Vec<Outcome> {
+    processes
+        .into_iter()
+        .map(|process| {
+            let result = action.apply(&process);
+            Outcome { process, result }
+        })
+        .collect()
+}
+
+/// `pgrep`: prints matching PIDs in ascending order, or how many there are
+/// with `--count`.
+fn pgrep(args: &PgrepArgs, env: &mut Env<'_>) -> Result<Report, ExitCode> {
+    let pipeline = pattern_pipeline(&args.pattern, env.root)?;
+    let matches = pipeline.select().map_err(|e| {
+        eprintln!("pgrep: {e}");
+        ExitCode::Internal
+    })?;
+    report_scan_errors(pipeline.query().scanner());
+    if args.count {
+        let mut count = Count::default();
+        let outcomes = apply_each(matches, &mut count);
+        writeln!(env.out, "{}", count.count).map_err(|_| ExitCode::Internal)?;
+        return Ok(Report::new(outcomes, count.count > 0));
+    }
+
+    let mut print = Print::new(&mut *env.out).with_name(args.list_name);
+    if let Some(format) = args.time_format {
+        match BootClock::new(env.root) {
+            Ok(boot) => print = print.with_times(boot, format, env.clock.system_time()),
+            Err(e) => {
+                eprintln!("pgrep: boot time: {e}");
+                return Err(ExitCode::Internal);
+            }
+        }
+    }
+    if args.print0 {
+        print = print.with_delimiters(b"\0", b"\0");
+    } else if let Some(delimiter) = &args.delimiter {
+        print = print.with_delimiters(delimiter.as_bytes(), b"\n");
+    }
+    let outcomes = apply_each(matches, &mut print);
+    if outcomes.iter().any(|outcome| outcome.result.is_err()) || print.finish().is_err() {
+        return Err(ExitCode::Internal);
+    }
+    let found = !outcomes.is_empty();
+    Ok(Report::new(outcomes, found))
+}
+
+/// `pkill`: signals matching processes, matching only if one of them could
+/// be signalled.
+fn pkill(args: &PkillArgs, env: &mut Env<'_>) -> Result<Report, ExitCode> {
+    let sig = args
+        .signal
+        .as_deref()
+        .map_or(Ok(Signal::TERM), parse_signal_arg)?;
+    let pipeline = pattern_pipeline(&args.pattern, env.root)?;
+    let kill = Kill::with_killer(sig, Arc::clone(&env.killer));
+    let mut kill = Interop::new(env.root, kill, false);
+    let outcomes = pipeline.run(&mut kill).map_err(|e| {
+        eprintln!("pkill: {e}");
+        ExitCode::Internal
+    })?;
+    report_scan_errors(pipeline.query().scanner());
+    for outcome in &outcomes {
+        match &outcome.result {
+            Err(err) => eprintln!("pkill: killing pid {} failed: {err}", outcome.process.pid),
+            Ok(()) if args.echo => writeln!(
+                env.out,
+                "{} killed (pid {})",
+                outcome.process.comm.escaped(),
+                outcome.process.pid
+            )
+            .map_err(|_| ExitCode::Internal)?,
+            Ok(()) => {}
+        }
+    }
+    let signalled = outcomes.iter().any(|outcome| outcome.result.is_ok());
+    Ok(Report::new(outcomes, signalled))
+}
+
+fn killall(args: &FaulxArgs, env: &mut Env<'_>) -> Result<Report, ExitCode> {
+    QUIET.store(args.quiet, Ordering::Relaxed);
+    let detail = Detail::from_flags(args.verbose, args.echo);
+
+    #[cfg(feature = "dbus")]
+    if args.daemon {
+        return match crate::daemon::serve(env.root, args.rate_limit) {
+            Ok(()) => Ok(Report::new(Vec::new(), true)),
+            Err(e) => {
+                qprintln!("Error: {e}");
+                Err(ExitCode::Internal)
+            }
+        };
+    }
+
+    if args.self_test {
+        return self_test(env);
+    }
+
+    if let Some(command) = &args.command {
+        return subcommand(command, args, detail, env);
+    }
+
+    if args.list {
+        return print_signals(env, args.output);
+    }
+
+    let sig = match args.core {
+        Some(core) => core.signal(),
+        None => args
+            .signal
+            .as_deref()
+            .map_or(Ok(Signal::TERM), parse_signal_arg)?,
+    };
+    let dumping = Arc::new(Mutex::new(Vec::new()));
+    let (mut action, kill_only) = killall_action(args, env, sig, &dumping)?;
+    let options = killall_options(args, detail, kill_only.then_some(sig));
+
+    let report = if args.stdin_pids {
+        let pids = listed_pids(env.root, io::stdin().lock())?;
+        act_on_pids(env, &pids, &mut action, &options)
+    } else if args.pids.is_empty() {
+        #[cfg(feature = "window")]
+        if args.window {
+            return by_window(env, &args.process_names, &mut action, &options);
+        }
+        act(
+            env,
+            &args.process_names,
+            &args.filters,
+            &mut action,
+            &options,
+        )
+    } else {
+        act_on_pids(env, &args.pids, &mut action, &options)
+    };
+    if let Some(profile) = &options.profile {
+        let _ = profile.write_to(&mut io::stderr());
+    }
+    #[cfg(feature = "json")]
+    if args.output == OutputFormat::Json
+        && let Ok(report) = &report
+    {
+        write_results(env, report)?;
+    }
+    if args.core.is_some() {
+        let dumping = dumping.lock().unwrap_or_else(PoisonError::into_inner);
+        report_dumps(env, &dumping, options.colors)?;
+    }
+    #[cfg(feature = "notify")]
+    if args.notify
+        && let Ok(report) = &report
+    {
+        notify(args, report);
+    }
+    report
+}
+
+/// Sends the `--notify` notification of `report`, warning if it can't.
+#[cfg(feature = "notify")]
+fn notify(args: &FaulxArgs, report: &Report) {
+    let targets = if args.pids.is_empty() {
+        args.process_names.join(", ")
+    } else {
+        let pids: Vec<_> = args.pids.iter().map(ToString::to_string).collect();
+        pids.join(", ")
+    };
+    if let Err(e) = crate::notify::send(&targets, report) {
+        qprintln!("Warning: --notify: {e}");
+    }
+}
+
+/// Runs the subcommand `command`, given along with `args`.
+fn subcommand(
+    command: &Command,
+    args: &FaulxArgs,
+    detail: Detail,
+    env: &mut Env<'_>,
+) -> Result<Report, ExitCode> {
+    match command {
+        Command::List(list) => list_processes(env, list),
+        Command::Tree(tree) => show_tree(env, tree),
+        Command::Doctor => doctor(env),
+        Command::Waitfor(waitfor) => wait_for(env, waitfor),
+        Command::Restart(restart) => restart_with(env, restart),
+        Command::Renice(renice) => {
+            let mut action = Renice::new(renice.priority);
+            let options = Options {
+                detail,
+                group_by: args.group_by,
+                wait: false,
+                timeout: None,
+                polling: Polling::default(),
+                signal: None,
+                metrics_file: None,
+                reason: args.reason.as_deref(),
+                all_or_nothing: false,
+                persistent: 0,
+                pick: false,
+                pre_hook: args.pre_hook.as_deref(),
+                post_hook: args.post_hook.as_deref(),
+                profile: None,
+                #[cfg(feature = "json")]
+                log: None,
+                colors: Palettes::new(args.color),
+            };
+            act(
+                env,
+                &renice.process_names,
+                &renice.filters,
+                &mut action,
+                &options,
+            )
+        }
+    }
+}
+
+/// The options of a killall run, where `signal` is set if sending it is
+/// all the action does.
+fn killall_options(args: &FaulxArgs, detail: Detail, signal: Option<Signal>) -> Options<'_> {
+    let timeout = args
+        .timeout
+        .map(Timeout::Global)
+        .or_else(|| args.timeout_per_pid.map(Timeout::PerPid));
+    Options {
+        detail: if args.output == OutputFormat::Text {
+            detail
+        } else {
+            Detail::Silent
+        },
+        group_by: args.group_by,
+        // Dumps are written as processes exit.
+        wait: args.wait || args.core.is_some() || timeout.is_some(),
+        timeout,
+        polling: polling(args),
+        signal,
+        metrics_file: args.metrics_file.as_deref(),
+        reason: args.reason.as_deref(),
+        all_or_nothing: args.all_or_nothing,
+        persistent: args.persistent.unwrap_or(0),
+        #[cfg(feature = "tui")]
+        pick: args.tui,
+        #[cfg(not(feature = "tui"))]
+        pick: false,
+        pre_hook: args.pre_hook.as_deref(),
+        post_hook: args.post_hook.as_deref(),
+        profile: args.profile.then(Profile::new),
+        #[cfg(feature = "json")]
+        log: args.log_file.as_deref().map(|path| {
+            let max_size = args.log_max_size.unwrap_or(DEFAULT_MAX_SIZE);
+            ActionLog::new(path, max_size, args.reason.clone())
+        }),
+        colors: Palettes::new(args.color),
+    }
+}
+
+/// The actions of a killall run, with whether sending `sig` is all they
+/// do. With `--core`, the processes signalled are added to `dumping`; with
+/// `--capture-backtrace` and `--snapshot-dir`, each is captured or archived
+/// first.
+fn killall_action(
+    args: &FaulxArgs,
+    env: &Env<'_>,
+    sig: Signal,
+    dumping: &Arc<Mutex<Vec<Dumping>>>,
+) -> Result<(Chain, bool), ExitCode> {
+    let mut action = other_actions(args)?;
+    // Other actions replace the signal unless one was explicitly requested.
+    let kill_only = action.is_empty();
+    if kill_only || args.signal.is_some() || args.core.is_some() {
+        let kill = Kill::with_killer(sig, Arc::clone(&env.killer));
+        let kill = Interop::new(env.root, kill, args.windows_interop);
+        if args.core.is_some() {
+            action.push(CoreWatch::new(kill, Arc::clone(dumping)));
+        } else {
+            action.push(kill);
+        }
+    }
+    if let Some(dir) = &args.capture_backtrace {
+        let capture = Capture::new(dir, action).map_err(|e| {
+            qprintln!("--capture-backtrace: {}: {e}", dir.display());
+            ExitCode::Usage
+        })?;
+        action = Chain::new();
+        action.push(capture);
+    }
+    // Outermost, before a sampler attaches to the process.
+    if let Some(parent) = &args.snapshot_dir {
+        let archive = Archive::new(parent, env.clock.system_time(), action).map_err(|e| {
+            qprintln!("--snapshot-dir: {}: {e}", parent.display());
+            ExitCode::Usage
+        })?;
+        qprintln!("Saving snapshots to {}", archive.dir().display());
+        action = Chain::new();
+        action.push(archive);
+    }
+    Ok((action, kill_only))
+}
+
+/// `--core`: shows where the dumps of the processes signalled went, giving
+/// those not there yet `coredump::GRACE` to show up.
+fn report_dumps(env: &mut Env<'_>, dumping: &[Dumping], colors: Palettes) -> Result<(), ExitCode> {
+    let pattern = CorePattern::read(env.root).map_err(|e| {
+        qprintln!("--core: cannot read core_pattern: {e}");
+        ExitCode::Internal
+    })?;
+    let find = |dumping| pattern.find(dumping, Path::new(SYSTEMD_COREDUMP_DIR));
+    let mut found: Vec<_> = dumping.iter().map(find).collect();
+    let started = Instant::now();
+    while found.iter().any(|found| matches!(found, Found::Missing(_)))
+        && started.elapsed() < coredump::GRACE
+    {
+        thread::sleep(coredump::POLL_INTERVAL);
+        for (found, dumping) in found.iter_mut().zip(dumping) {
+            if matches!(found, Found::Missing(_)) {
+                *found = find(dumping);
+            }
+        }
+    }
+    for (found, dumping) in found.iter().zip(dumping) {
+        let name = comm::escape(&dumping.comm).to_string();
+        let pid = dumping.pid;
+        let (out, err) = (
+            colors.out.paint(Style::Name, &name),
+            colors.err.paint(Style::Name, &name),
+        );
+        match found {
+            Found::File(path) => {
+                writeln!(env.out, "{out}({pid}) dumped core to {}", path.display())
+                    .map_err(|_| ExitCode::Internal)?;
+            }
+            Found::Handler(program) => writeln!(env.out, "{out}({pid}) dumped core to |{program}")
+                .map_err(|_| ExitCode::Internal)?,
+            Found::Missing(dir) => {
+                qprintln!("{err}({pid}): no core dump found in {}", dir.display());
+            }
+            Found::Disabled => {
+                qprintln!("{err}({pid}): no core dump, as its core file size limit is 0");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `--host`: runs the command line on every host over SSH, printing each
+/// host's output prefixed with its name. Matches only if it succeeded
+/// everywhere.
+fn remote(
+    args: &FaulxArgs,
+    command_line: &[OsString],
+    env: &mut Env<'_>,
+) -> Result<Report, ExitCode> {
+    let remote = Remote::default().program(args.remote_program.as_str());
+    let mut failed = false;
+    for result in remote.run(&args.host, &forwarded_args(command_line)) {
+        failed |= !result.success();
+        match &result.output {
+            Ok(output) => {
+                write!(env.out, "{}", prefix_lines(&result.host, &output.stdout))
+                    .map_err(|_| ExitCode::Internal)?;
+                eprint!("{}", prefix_lines(&result.host, &output.stderr));
+            }
+            Err(e) => qprintln!("{}: ssh: {e}", result.host),
+        }
+    }
+    Ok(Report::new(Vec::new(), !failed))
+}
+
+/// The actions other than a signal asked for, such as `--renice`, in the
+/// order they are applied.
+fn other_actions(args: &FaulxArgs) -> Result<Chain, ExitCode> {
+    let mut action = Chain::new();
+    if let Some(niceness) = args.renice {
+        action.push(Renice::new(niceness));
+    }
+    if let Some(priority) = args.ionice {
+        action.push(Ionice::new(priority));
+    }
+    if let Some(value) = args.oom_score_adj {
+        action.push(OomScoreAdj::new(value));
+    }
+    if let Some(mask) = &args.taskset {
+        action.push(Affinity::new(mask.clone(), args.taskset_threads));
+    }
+    if let Some(cgroup) = &args.move_to_cgroup {
+        match CgroupMove::new(cgroup) {
+            Ok(mv) => action.push(mv),
+            Err(e) => {
+                qprintln!("{e}");
+                return Err(ExitCode::Usage);
+            }
+        }
+    }
+    Ok(action)
+}
+
+/// `-l`: prints the signal names. Process names given along with it are
+/// left alone, as with psmisc's killall.
+fn print_signals(env: &mut Env<'_>, output: OutputFormat) -> Result<Report, ExitCode> {
+    let listed = match output {
+        OutputFormat::Text => writeln!(env.out, "{}", list_signals()),
+        #[cfg(feature = "json")]
+        OutputFormat::Json => match crate::signals::list_signals_json() {
+            Ok(json) => writeln!(env.out, "{json}"),
+            Err(e) => {
+                qprintln!("Error: {e}");
+                return Err(ExitCode::Internal);
+            }
+        },
+    };
+    listed.map_err(|_| ExitCode::Internal)?;
+    Ok(Report::new(Vec::new(), true))
+}
+
+/// `faulx list`: every process, sorted as asked.
+fn list_processes(env: &mut Env<'_>, args: &ListArgs) -> Result<Report, ExitCode> {
+    let scanner = Scanner::new(env.root);
+    let mut rows = listing::rows(&scanner).map_err(|e| {
+        qprintln!("Error: {e}");
+        ExitCode::Internal
+    })?;
+    report_scan_errors(&scanner);
+    listing::sort(
+        &mut rows,
+        Sorting {
+            by: args.sort,
+            version: args.version_sort,
+            reverse: args.reverse,
+        },
+    );
+    listing::write_rows(env.out, &rows).map_err(|_| ExitCode::Internal)?;
+    Ok(Report::new(Vec::new(), true))
+}
+
+/// `faulx doctor`: prints what was found, failing if anything stands in
+/// the way.
+fn doctor(env: &mut Env<'_>) -> Result<Report, ExitCode> {
+    let findings = doctor::diagnose(env.root, Path::new(doctor::SYS));
+    doctor::write_findings(env.out, &findings).map_err(|_| ExitCode::Internal)?;
+    let fine = findings
+        .iter()
+        .all(|finding| finding.severity != Severity::Problem);
+    Ok(Report::new(Vec::new(), fine))
+}
+
+/// `faulx --self-test`: forks children under a name of their own, one of
+/// them ignoring SIGTERM, then kills them as `faulx --timeout` would, and
+/// checks each step along the way.
+fn self_test(env: &mut Env<'_>) -> Result<Report, ExitCode> {
+    let name = format!("fxst{}", std::process::id());
+    let dummies: io::Result<Vec<_>> = [false, false, true]
+        .into_iter()
+        .map(|stubborn| Dummy::spawn(&name, stubborn))
+        .collect();
+    let mut findings = Vec::new();
+    match dummies {
+        Ok(dummies) => {
+            findings.push(Finding::new(
+                Severity::Ok,
+                "fork",
+                format!("forked {} children", dummies.len()),
+            ));
+            self_test_children(env, &name, &dummies, &mut findings);
+        }
+        Err(e) => findings.push(Finding::new(Severity::Problem, "fork", e.to_string())),
+    }
+    doctor::write_findings(env.out, &findings).map_err(|_| ExitCode::Internal)?;
+    let passed = findings
+        .iter()
+        .all(|finding| finding.severity == Severity::Ok);
+    Ok(Report::new(Vec::new(), passed))
+}
+
+/// The checks of `--self-test` on its children, `dummies`, named `name`,
+/// stopping at the first that fails.
+fn self_test_children(
+    env: &mut Env<'_>,
+    name: &str,
+    dummies: &[Dummy],
+    findings: &mut Vec<Finding>,
+) {
+    let mut check = |check, passed, message: String| {
+        let severity = if passed {
+            Severity::Ok
+        } else {
+            Severity::Problem
+        };
+        findings.push(Finding::new(severity, check, message));
+        passed
+    };
+    let renamed = dummies.iter().all(|dummy| dummy.renamed(env.root, name));
+    if !check(
+        "rename",
+        renamed,
+        format!("children renamed themselves {name}"),
+    ) {
+        return;
+    }
+
+    let mut expected: Vec<_> = dummies.iter().map(|dummy| dummy.pid).collect();
+    expected.sort_unstable();
+    let names = [name.to_owned()];
+    let scanner = Scanner::new(env.root);
+    let mut matched: Vec<_> = select_by_name(&scanner, &names, &FilterArgs::default(), false, None)
+        .map(|selection| selection.processes.iter().map(|p| p.pid).collect())
+        .unwrap_or_default();
+    matched.sort_unstable();
+    let message = if matched == expected {
+        format!("{name} matched the {} of them", matched.len())
+    } else {
+        let list = |pids: &[Pid]| pids.iter().map(ToString::to_string).collect::<Vec<_>>();
+        format!(
+            "{name} matched {}, not {}",
+            list(&matched).join(","),
+            list(&expected).join(",")
+        )
+    };
+    if !check("match", matched == expected, message) {
+        return;
+    }
+
+    let args = FaulxArgs {
+        process_names: names.to_vec(),
+        quiet: true,
+        timeout: Some(selftest::TIMEOUT),
+        ..FaulxArgs::default()
+    };
+    let quiet = QUIET.load(Ordering::Relaxed);
+    let started = Instant::now();
+    let report = killall(&args, env);
+    let took = started.elapsed();
+    QUIET.store(quiet, Ordering::Relaxed);
+    let signalled = report.as_ref().map_or(0, |report| {
+        report
+            .outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_ok())
+            .count()
+    });
+    let message = format!("sent SIGTERM to {signalled} of {}", dummies.len());
+    if !check("signal", signalled == dummies.len(), message) {
+        return;
+    }
+
+    let running = dummies
+        .iter()
+        .filter(|dummy| dummy.running().unwrap_or(true))
+        .count();
+    let escalated = took >= selftest::TIMEOUT;
+    let message = if running > 0 {
+        format!("{running} still running once the wait returned")
+    } else if escalated {
+        format!("all gone after {took:.1?}, the one ignoring SIGTERM through SIGKILL")
+    } else {
+        format!("returned after {took:.1?}, before the one ignoring SIGTERM was due SIGKILL")
+    };
+    check("wait", running == 0 && escalated, message);
+}
+
+/// `faulx waitfor`: scans until no process matches, or until one does,
+/// matching once it happened and not if `--timeout` ran out first. Nothing
+/// tells when a process starts, so both ways poll.
+fn wait_for(env: &Env<'_>, args: &WaitforArgs) -> Result<Report, ExitCode> {
+    let interrupts = Interrupts::catch();
+    let deadline = args.timeout.map(|timeout| env.clock.now() + timeout);
+    let mut first = true;
+    loop {
+        let scanner = Scanner::new(env.root);
+        let selection = select_by_name(&scanner, &args.process_names, &args.filters, false, None)?;
+        // Once is enough to warn about what will stay unreadable.
+        if first {
+            report_scan_errors(&scanner);
+            first = false;
+        }
+        if selection.processes.is_empty() != args.present {
+            return Ok(Report::new(Vec::new(), true));
+        }
+        if interrupts.received().is_some() {
+            return Ok(Report::interrupted(Vec::new()));
+        }
+        let now = env.clock.now();
+        if let Some(deadline) = deadline
+            && now >= deadline
+        {
+            let names = if args.process_names.is_empty() {
+                describe_selection(&args.filters)
+            } else {
+                args.process_names.join(", ")
+            };
+            let state = if args.present {
+                "no process found"
+            } else {
+                "still running"
+            };
+            qprintln!(
+                "{names}: {state} after {:?}",
+                args.timeout.unwrap_or_default()
+            );
+            return Ok(Report::new(Vec::new(), false));
+        }
+        let left = deadline.map_or(WAITFOR_INTERVAL, |deadline| deadline - now);
+        env.clock.sleep(left.min(WAITFOR_INTERVAL));
+    }
+}
+
+/// `faulx restart`: signals the processes matching the pattern, waits for
+/// every one of them to exit, with SIGKILL for those left after
+/// `--timeout`, then runs the command in place of faulx.
+///
+/// The command is run straight away if nothing matched, and not at all if a
+/// process couldn't be signalled, so it never starts next to the old one.
+fn restart_with(env: &mut Env<'_>, args: &RestartArgs) -> Result<Report, ExitCode> {
+    let Some((command, command_args)) = args.command.split_first() else {
+        qprintln!("restart: no command to run");
+        return Err(ExitCode::Usage);
+    };
+    let program = command.to_string_lossy();
+    let filter = PatternFilter::new(&args.pattern, args.exact).map_err(|e| {
+        qprintln!("{}: invalid pattern: {e}", args.pattern);
+        ExitCode::Usage
+    })?;
+    let sig = args
+        .signal
+        .as_deref()
+        .map_or(Ok(Signal::TERM), parse_signal_arg)?;
+    let own_pid = Pid::this();
+    let pipeline = Pipeline::new(Scanner::new(env.root))
+        .filter(filter)
+        .filter(move |p: &ProcessInfo| p.pid != own_pid);
+    let mut kill = Kill::with_killer(sig, Arc::clone(&env.killer));
+    let outcomes = pipeline.run(&mut kill).map_err(|e| {
+        qprintln!("Error: {e}");
+        ExitCode::Internal
+    })?;
+    report_scan_errors(pipeline.query().scanner());
+    if outcomes.is_empty() {
+        qprintln!("{}: no process found", args.pattern);
+    }
+
+    let now = env.clock.now();
+    let mut targets = Vec::new();
+    let mut left = 0;
+    for outcome in &outcomes {
+        match &outcome.result {
+            Ok(()) => targets.extend(outcome.process.ident().map(|ident| (ident, now))),
+            // Gone already, as it was about to be.
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+            Err(e) => {
+                qprintln!("Failed to send signal to {}: {e}", outcome.process.pid);
+                left += 1;
+            }
+        }
+    }
+    if left > 0 {
+        qprintln!("{left} processes left running, {program} not started");
+        return Ok(Report::new(outcomes, false));
+    }
+    let (_, interrupted) = wait_with_progress(
+        env,
+        &targets,
+        args.timeout.map(Timeout::Global),
+        Polling::default(),
+    );
+    if interrupted {
+        return Ok(Report::interrupted(outcomes));
+    }
+
+    env.out.flush().map_err(|_| ExitCode::Internal)?;
+    let e = process::Command::new(command)
+        .args(command_args)
+        .original_nofile_limit()
+        .exec();
+    qprintln!("{program}: {e}");
+    Err(ExitCode::Internal)
+}
+
+/// `faulx tree`: draws the processes matching the pattern with their
+/// descendants.
+fn show_tree(env: &mut Env<'_>, args: &TreeArgs) -> Result<Report, ExitCode> {
+    let filter = PatternFilter::new(&args.pattern, args.exact).map_err(|e| {
+        qprintln!("{}: invalid pattern: {e}", args.pattern);
+        ExitCode::Usage
+    })?;
+    let scanner = Scanner::new(env.root);
+    let snapshot = ProcSnapshot::take(&scanner).map_err(|e| {
+        qprintln!("Error: {e}");
+        ExitCode::Internal
+    })?;
+    report_scan_errors(&scanner);
+    let own_pid = Pid::this();
+    let matched: Vec<_> = snapshot
+        .select(&filter)
+        .filter(|p| p.pid != own_pid)
+        .collect();
+    if matched.is_empty() {
+        qprintln!("{}: no process found", args.pattern);
+        return Ok(Report::new(Vec::new(), false));
+    }
+    let roots = tree::roots(&snapshot, &matched);
+    tree::write_tree(env.out, &snapshot, &roots).map_err(|_| ExitCode::Internal)?;
+    Ok(Report::new(Vec::new(), true))
+}
+
+/// Warns about the processes `scanner` could not read, once per kind of
+/// error, since they might have been missed.
+fn report_scan_errors(scanner: &Scanner) {
+    for e in scanner.take_errors() {
+        qprintln!("Warning: some processes could not be read: {e}");
+    }
+}
+
+/// Waits for `targets`, each given with when it was signalled, to exit,
+/// showing how many have on stderr when it is a terminal. Those still
+/// running once `timeout` runs out for them are sent SIGKILL.
+///
+/// Returns how the wait went for each of `targets`, and whether a SIGINT
+/// or SIGTERM interrupted it.
+fn wait_with_progress(
+    env: &Env<'_>,
+    targets: &[(ProcessIdent, Instant)],
+    timeout: Option<Timeout>,
+    polling: Polling,
+) -> (Vec<Waited>, bool) {
+    let show = !QUIET.load(Ordering::Relaxed) && io::stderr().is_terminal();
+    let start = env.clock.now();
+    let mut shown = false;
+    let clock = &*env.clock;
+    let interrupts = Interrupts::catch();
+    let targets: Vec<_> = timeout.map_or_else(
+        || targets.iter().map(|&(ident, _)| (ident, None)).collect(),
+        |timeout| {
+            let deadlines = timeout.deadlines(targets).into_iter();
+            deadlines
+                .map(|(ident, deadline)| (ident, Some(deadline)))
+                .collect()
+        },
+    );
+    let waited = wait_for_exit_deadlines(
+        env.root,
+        &targets,
+        polling,
+        clock,
+        |progress| {
+            if show {
+                eprint!("\r{progress}\x1b[K");
+                shown = true;
+            }
+        },
+        || interrupts.received().is_some(),
+        |ident| {
+            let waited = timeout.map_or(0, |timeout| timeout.duration().as_secs());
+            // Over the progress line.
+            if show {
+                eprint!("\r\x1b[K");
+            }
+            qprintln!(
+                "{} still running after {waited}s, sending SIGKILL",
+                ident.pid
+            );
+            if let Err(e) = env.killer.kill(ident.pid, Signal::KILL) {
+                qprintln!("Failed to send signal to {}: {e}", ident.pid);
+            }
+        },
+    );
+    let remaining: Vec<_> = waited
+        .iter()
+        .filter(|waited| waited.exited.is_none())
+        .map(|waited| waited.ident)
+        .collect();
+    if shown {
+        let done = Progress {
+            exited: targets.len() - remaining.len(),
+            total: targets.len(),
+            elapsed: env.clock.now() - start,
+        };
+        eprintln!("\r{done}\x1b[K");
+    }
+    let signal = interrupts.received();
+    if let Some(signal) = signal {
+        forward_interrupt(env, signal, &remaining);
+    }
+    (waited, signal.is_some())
+}
+
+/// With `--wait`, waits for the processes `signalled` at the instants
+/// given, timing the wait for `--profile`.
+///
+/// Returns how the wait went for each of them, with when it was signalled,
+/// and whether a SIGINT or SIGTERM interrupted it.
+fn wait_if_asked(
+    env: &Env<'_>,
+    signalled: &[(ProcessIdent, Instant)],
+    options: &Options<'_>,
+) -> (Vec<(Instant, Waited)>, bool) {
+    if !options.wait {
+        return (Vec::new(), false);
+    }
+    let (waited, interrupted) = timed(options.profile.as_ref(), Phase::Wait, || {
+        wait_with_progress(env, signalled, options.timeout, options.polling)
+    });
+    let waited = signalled.iter().map(|&(_, at)| at).zip(waited).collect();
+    (waited, interrupted)
+}
+
+/// `--output json`: writes what became of each process `report` is about.
+#[cfg(feature = "json")]
+fn write_results(env: &mut Env<'_>, report: &Report) -> Result<(), ExitCode> {
+    let json = crate::results::to_json(&report.outcomes, &report.waited).map_err(|e| {
+        qprintln!("Error: {e}");
+        ExitCode::Internal
+    })?;
+    writeln!(env.out, "{json}").map_err(|_| ExitCode::Internal)
+}
+
+/// Says how far a run got before `signal` interrupted it: how many of the
+/// processes selected were acted upon, and, with `--verbose`, which ones
+/// were skipped.
+fn summarize_interrupt(
+    env: &mut Env<'_>,
+    signal: Signal,
+    metrics: &Metrics,
+    skipped: &[ProcessInfo],
+    options: &Options<'_>,
+) -> Result<(), ExitCode> {
+    qprintln!(
+        "{} by signal {}: acted on {} of {} processes ({} failed), skipped {}",
+        options.colors.err.paint(Style::Failure, "Interrupted"),
+        signal.as_raw(),
+        metrics.matched,
+        metrics.matched + skipped.len() as u64,
+        metrics.failed,
+        skipped.len(),
+    );
+    if options.detail == Detail::Verbose {
+        for process in skipped {
+            let palette = options.colors.out;
+            writeln!(
+                env.out,
+                "{} {}({})",
+                palette.paint(Style::Skipped, "Skipped"),
+                palette.paint(Style::Name, process.comm.escaped()),
+                process.pid
+            )
+            .map_err(|_| ExitCode::Internal)?;
+        }
+    }
+    Ok(())
+}
+
+/// Passes on `signal`, which interrupted the wait, to the processes still
+/// running, so that they are not left behind unknowingly.
+fn forward_interrupt(env: &Env<'_>, signal: Signal, remaining: &[ProcessIdent]) {
+    let mut forwarded = 0;
+    for ident in remaining {
+        match env.killer.kill(ident.pid, signal) {
+            Ok(()) => forwarded += 1,
+            Err(e) => qprintln!("Failed to send signal to {}: {e}", ident.pid),
+        }
+    }
+    let plural = if forwarded == 1 { "" } else { "es" };
+    qprintln!(
+        "Interrupted by signal {}: sent it to the {forwarded} process{plural} still running",
+        signal.as_raw()
+    );
+}
+
+/// What to print about the processes an action succeeded on. Failures are
+/// always reported, unless `--quiet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Detail {
+    Silent,
+    /// `--echo`: a short line per process, like `pkill -e`.
+    Echo,
+    /// `--verbose`: killall's message.
+    Verbose,
+}
+
+impl Options<'_> {
+    /// Where to gather the lines about each process before writing them,
+    /// if grouped and there are any.
+    fn groups(&self) -> Option<Groups> {
+        self.group_by
+            .filter(|_| self.detail != Detail::Silent)
+            .map(Groups::new)
+    }
+}
+
+impl Detail {
+    const fn from_flags(verbose: bool, echo: bool) -> Self {
+        if verbose {
+            Self::Verbose
+        } else if echo {
+            Self::Echo
+        } else {
+            Self::Silent
+        }
+    }
+}
+
+/// How often to check on the processes waited for, as `--interval` and
+/// `--backoff` have it.
+fn polling(args: &FaulxArgs) -> Polling {
+    let interval = args.interval.unwrap_or(DEFAULT_INTERVAL);
+    args.backoff.map_or_else(
+        || Polling::fixed(interval),
+        |max| Polling::backoff(interval, max),
+    )
+}
+
+/// How killall goes about acting on the processes it selected.
+struct Options<'a> {
+    detail: Detail,
+    /// `--group-by`: what to gather the lines of `detail` under.
+    group_by: Option<GroupBy>,
+    /// Return only once every process the action succeeded on has exited.
+    wait: bool,
+    /// `--timeout` or `--timeout-per-pid`, with `wait`.
+    timeout: Option<Timeout>,
+    /// `--interval` and `--backoff`: how often `wait` checks.
+    polling: Polling,
+    /// Set when the action only sends this signal, so messages keep
+    /// killall's wording.
+    signal: Option<Signal>,
+    /// Where to write the counts before returning.
+    metrics_file: Option<&'a Path>,
+    /// `--reason`, which the policy may require.
+    reason: Option<&'a str>,
+    /// `--all-or-nothing`: act on none of the processes unless all of them
+    /// can be signalled.
+    all_or_nothing: bool,
+    /// `--persistent`: how many times to rescan for respawned processes
+    /// once done, none if 0.
+    persistent: u8,
+    /// `--tui`: names are patterns, and the user picks which of the
+    /// processes selected to act upon.
+    pick: bool,
+    /// `--pre-hook`: run before acting, which it can call off by failing.
+    pre_hook: Option<&'a OsStr>,
+    /// `--post-hook`: run once done, with the processes acted upon.
+    post_hook: Option<&'a OsStr>,
+    /// `--profile`: what phase timings and filter counts are gathered in.
+    profile: Option<Profile>,
+    /// `--log-file`: where to record each process acted upon.
+    #[cfg(feature = "json")]
+    log: Option<ActionLog>,
+    /// `--color`, as it applies to stdout and stderr.
+    colors: Palettes,
+}
+
+/// Writes the result of applying `action` to a process matched by `name`,
+/// or by the other filters if there is none, to `out`, or complains about
+/// it on stderr.
+fn report(
+    out: &mut dyn Write,
+    name: Option<&str>,
+    outcome: &Outcome,
+    action: &dyn Action,
+    options: &Options<'_>,
+) -> io::Result<()> {
+    let pid = outcome.process.pid;
+    let name = name.map_or_else(|| outcome.process.comm.escaped().to_string(), Into::into);
+    let (palette, failed) = (
+        options.colors.out,
+        options.colors.err.paint(Style::Failure, "Failed"),
+    );
+    let name = palette.paint(Style::Name, &name);
+    match (&outcome.result, options.signal) {
+        (Err(err), Some(_)) => qprintln!("{failed} to send signal to {pid}: {err}"),
+        (Err(err), None) => {
+            qprintln!("{failed} to apply {} to {pid}: {err}", action.describe());
+        }
+        (Ok(()), _) if options.detail == Detail::Silent => {}
+        (Ok(()), Some(_)) if options.detail == Detail::Echo => {
+            let killed = palette.paint(Style::Success, "killed");
+            writeln!(out, "{name} {killed} (pid {pid})")?;
+        }
+        (Ok(()), None) if options.detail == Detail::Echo => {
+            let applied = palette.paint(Style::Success, "applied");
+            writeln!(out, "{name} {applied} {} (pid {pid})", action.describe())?;
+        }
+        (Ok(()), Some(sig)) => {
+            let killed = palette.paint(Style::Success, "Killed");
+            writeln!(out, "{killed} {name}({pid}) with signal {}", sig.as_raw())?;
+        }
+        (Ok(()), None) => {
+            let applied = palette.paint(Style::Success, "Applied");
+            writeln!(out, "{applied} {} to {name}({pid})", action.describe())?;
+        }
+    }
+    Ok(())
+}
+
+/// `--tui`: lets the user pick which of the `selected` processes to act
+/// upon.
+#[cfg(feature = "tui")]
+fn pick(scanner: &Scanner, selected: Vec<ProcessInfo>) -> Result<Vec<ProcessInfo>, ExitCode> {
+    if selected.is_empty() {
+        return Ok(selected);
+    }
+    crate::tui::pick(scanner.root(), selected).map_err(|e| {
+        qprintln!("Error: {e}");
+        ExitCode::Internal
+    })
+}
+
+/// `--window`: applies `action` to the process owning the window the user
+/// clicks, if it runs on this host and has one of `process_names`, when
+/// given: the window only claims to be that process's.
+#[cfg(feature = "window")]
+fn by_window(
+    env: &mut Env<'_>,
+    process_names: &[String],
+    action: &mut dyn Action,
+    options: &Options<'_>,
+) -> Result<Report, ExitCode> {
+    let clicked = crate::window::pick().map_err(|e| {
+        qprintln!("--window: {e}");
+        ExitCode::Internal
+    })?;
+    let Some(clicked) = clicked else {
+        qprintln!("--window: cancelled");
+        return Ok(Report::new(Vec::new(), false));
+    };
+    let hostname =
+        std::fs::read_to_string(env.root.join("sys/kernel/hostname")).unwrap_or_default();
+    let pid = clicked.owner(hostname.trim()).map_err(|e| {
+        qprintln!("--window: {e}");
+        ExitCode::NoProc
+    })?;
+    let process = ProcessInfo::by_pid(Arc::from(env.root), pid);
+    let title = clicked.title.as_deref().unwrap_or("untitled");
+    if process.comm.is_empty() {
+        qprintln!("--window: {title}: process {pid} is not running");
+        return Ok(Report::new(Vec::new(), false));
+    }
+    if !process_names.is_empty()
+        && !process_names
+            .iter()
+            .any(|name| NameFilter::new(name).matches(&process))
+    {
+        qprintln!(
+            "--window: {title} belongs to {}({pid}), not to {}",
+            process.comm.escaped(),
+            process_names.join(", "),
+        );
+        return Ok(Report::new(Vec::new(), false));
+    }
+    act_on_pids(env, &[pid], action, options)
+}
+
+/// The filters other than names, `--user`, `--port`, `--unix-socket`,
+/// `--holding` and their `--not-*` counterparts, `--users-from-group` and
+/// `--where`, each with the label `--profile` shows it under. Fails if a
+/// user, group or path is unknown, or if the socket tables can't be read.
+fn criteria(scanner: &Scanner, filters: &FilterArgs) -> Result<Vec<(String, Shared)>, ExitCode> {
+    let root = scanner.root();
+    let sockets = |what: String, inodes: io::Result<HashSet<u64>>| match inodes {
+        Ok(inodes) => Ok(SocketFilter::new(inodes)),
+        Err(e) => {
+            qprintln!("{what}: {e}");
+            Err(ExitCode::Internal)
+        }
+    };
+    let mut criteria = Vec::new();
+    for (negated, users, port, unix_socket, holding) in [
+        (
+            false,
+            &filters.user,
+            filters.port,
+            &filters.unix_socket,
+            &filters.holding,
+        ),
+        (
+            true,
+            &filters.not_user,
+            filters.not_port,
+            &filters.not_unix_socket,
+            &filters.not_holding,
+        ),
+    ] {
+        let mut add = |what: String, filter: Shared| {
+            if negated {
+                criteria.push((format!("not {what}"), Shared::new(Not(filter))));
+            } else {
+                criteria.push((what, filter));
+            }
+        };
+        if !users.is_empty() {
+            let filter = UserFilter::from_users(users).map_err(|e| {
+                qprintln!("{e}");
+                ExitCode::Usage
+            })?;
+            add(format!("user {}", users.join(",")), Shared::new(filter));
+        }
+        if let Some(spec) = port {
+            let filter = sockets(spec.to_string(), port_inodes(root, spec))?;
+            add(format!("port {spec}"), Shared::new(filter));
+        }
+        if let Some(path) = unix_socket {
+            let what = path.display().to_string();
+            let filter = sockets(what.clone(), unix_socket_inodes(root, path))?;
+            add(format!("unix-socket {what}"), Shared::new(filter));
+        }
+        if let Some(path) = holding {
+            let filter = HoldingFilter::new(path).map_err(|e| {
+                qprintln!("{}: {e}", path.display());
+                ExitCode::Usage
+            })?;
+            add(format!("holding {}", path.display()), Shared::new(filter));
+        }
+    }
+    if let Some(group) = &filters.users_from_group {
+        let filter = MemberFilter::from_group(group).map_err(|e| {
+            qprintln!("{e}");
+            ExitCode::Usage
+        })?;
+        criteria.push((format!("group {group}"), Shared::new(filter)));
+    }
+    if let Some(expr) = &filters.where_clause {
+        let filter = expr.compile(root).map_err(|e| {
+            qprintln!("--where: {e}");
+            ExitCode::Usage
+        })?;
+        criteria.push((format!("where {expr}"), Shared::new(filter)));
+    }
+    Ok(criteria)
+}
+
+/// The filter of `--session-scope`, with its label. Not one of the
+/// criteria, which `--any` would let other filters stand in for. Fails
+/// outside of a login session.
+fn session_scope(
+    scanner: &Scanner,
+    filters: &FilterArgs,
+) -> Result<Option<(String, SessionFilter)>, ExitCode> {
+    if !filters.session_scope {
+        return Ok(None);
+    }
+    let session = Session::current(scanner.root()).ok_or_else(|| {
+        qprintln!("--session-scope: not in a login session");
+        ExitCode::Usage
+    })?;
+    Ok(Some((
+        format!("session {}", session.id),
+        SessionFilter::new(session),
+    )))
+}
+
+/// Names what `--user`, `--users-from-group`, `--port`, `--unix-socket`,
+/// `--holding` and `--where` select, for messages when no process name was given.
+fn describe_selection(filters: &FilterArgs) -> String {
+    let users = filters.user.iter().map(|user| format!("user {user}"));
+    let group = filters
+        .users_from_group
+        .iter()
+        .map(|group| format!("group {group}"));
+    let port = filters.port.map(|spec| spec.to_string());
+    let paths = [&filters.unix_socket, &filters.holding]
+        .into_iter()
+        .flatten()
+        .map(|path| path.display().to_string());
+    let expr = filters
+        .where_clause
+        .iter()
+        .map(|expr| format!("where {expr}"));
+    users
+        .chain(group)
+        .chain(port)
+        .chain(paths)
+        .chain(expr)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Checks that there are at most `MAX_NAMES` names, and that they compile
+/// if they are patterns.
+fn check_names(process_names: &[String], options: &Options<'_>) -> Result<(), ExitCode> {
+    if process_names.len() > MAX_NAMES {
+        qprintln!(
+            "{}: Maximum number of names is {} and you gave {}",
+            env!("CARGO_CRATE_NAME"),
+            MAX_NAMES,
+            process_names.len(),
+        );
+        return Err(ExitCode::Usage);
+    }
+    if options.pick {
+        for pattern in process_names {
+            PatternFilter::new(pattern, false).map_err(|e| {
+                qprintln!("{pattern}: invalid pattern: {e}");
+                ExitCode::Usage
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies `action` to the processes matching any of the names, matching
+/// only if it succeeded on a process of every name.
+fn act(
+    env: &mut Env<'_>,
+    process_names: &[String],
+    filters: &FilterArgs,
+    action: &mut dyn Action,
+    options: &Options<'_>,
+) -> Result<Report, ExitCode> {
+    check_names(process_names, options)?;
+
+    let scanner = Scanner::new(env.root);
+    let Selection {
+        processes: selected,
+        matched_by,
+        zombies,
+        unmatched,
+    } = timed(options.profile.as_ref(), Phase::Scan, || {
+        select_by_name(
+            &scanner,
+            process_names,
+            filters,
+            options.pick,
+            options.profile.as_ref(),
+        )
+    })?;
+    report_scan_errors(&scanner);
+    for warning in unmatched {
+        qprintln!("{warning}");
+    }
+    #[cfg(feature = "tui")]
+    let selected = if options.pick {
+        pick(&scanner, selected)?
+    } else {
+        selected
+    };
+    check_reason(env, &selected, options)?;
+    check_all_signallable(env, &selected, options)?;
+    run_pre_hook(env, &selected, options)?;
+    let mut tally = Tally {
+        metrics: Metrics {
+            zombies_skipped: zombies,
+            ..Metrics::default()
+        },
+        ..Tally::default()
+    };
+    let interrupts = Interrupts::catch();
+    let started = Instant::now();
+    let skipped = apply_selected(
+        env,
+        selected,
+        &matched_by,
+        action,
+        options,
+        &interrupts,
+        &mut tally,
+    )?;
+    if let Some(profile) = &options.profile {
+        profile.add(Phase::Act, started.elapsed());
+    }
+    if zombies > 0 && options.detail == Detail::Verbose {
+        let plural = if zombies == 1 { "" } else { "es" };
+        writeln!(
+            env.out,
+            "Skipped {zombies} zombie process{plural}, which only their parent can reap"
+        )
+        .map_err(|_| ExitCode::Internal)?;
+    }
+
+    write_metrics(options.metrics_file, &tally.metrics);
+    if let Some(signal) = interrupts.received() {
+        summarize_interrupt(env, signal, &tally.metrics, &skipped, options)?;
+        return Ok(Report::interrupted(tally.outcomes));
+    }
+    let (mut waited, mut interrupted) = wait_if_asked(env, &tally.waiting, options);
+    let respawns = if !interrupted && options.persistent > 0 {
+        let persisted = persist(
+            env,
+            process_names,
+            filters,
+            action,
+            options,
+            &interrupts,
+            &mut tally,
+        )?;
+        waited.extend(persisted.waited);
+        interrupted = persisted.interrupted;
+        persisted.respawns
+    } else {
+        0
+    };
+    run_post_hook(env, &tally.outcomes, options);
+    // With --tui, the user chose which names to act upon.
+    let every_name = options.pick
+        || process_names
+            .iter()
+            .all(|name| tally.acted_on.contains(name.as_str()));
+    let report = if interrupted {
+        Report::interrupted(tally.outcomes)
+    } else {
+        Report::new(tally.outcomes, tally.metrics.succeeded > 0 && every_name)
+    };
+    Ok(report.waited(waited).respawns(respawns))
+}
+
+/// What acting on the selected processes added up to so far.
+#[derive(Default)]
+struct Tally<'a> {
+    outcomes: Vec<Outcome>,
+    metrics: Metrics,
+    /// The processes to wait for, with when each was signalled.
+    waiting: Vec<(ProcessIdent, Instant)>,
+    /// Names with a process the action succeeded on.
+    acted_on: HashSet<&'a str>,
+}
+
+/// Applies `action` to each of `selected`, reported under the names that
+/// matched it, and adds the outcomes to `tally`. Stops early on an
+/// interrupt, returning the processes left out.
+fn apply_selected<'a>(
+    env: &mut Env<'_>,
+    selected: Vec<ProcessInfo>,
+    matched_by: &MatchedBy<'a>,
+    action: &mut dyn Action,
+    options: &Options<'_>,
+    interrupts: &Interrupts,
+    tally: &mut Tally<'a>,
+) -> Result<Vec<ProcessInfo>, ExitCode> {
+    let mut groups = options.groups();
+    let mut selected = selected.into_iter();
+    for process in selected.by_ref() {
+        let names = &matched_by[&dedup_key(&process)];
+        let joined = names.join(", ");
+        let target = (!joined.is_empty()).then_some(joined.as_str());
+        let result = action.apply(&process);
+        let outcome = Outcome { process, result };
+        tally.metrics.record(&outcome);
+        #[cfg(feature = "json")]
+        log_outcome(env, options, &outcome, action);
+        if outcome.result.is_ok() {
+            tally.acted_on.extend(names.iter().copied());
+            if options.wait {
+                let now = env.clock.now();
+                tally
+                    .waiting
+                    .extend(outcome.process.ident().map(|ident| (ident, now)));
+            }
+        }
+        let out = match &mut groups {
+            Some(groups) => groups.buffer(env.root, &outcome.process),
+            None => &mut *env.out,
+        };
+        report(out, target, &outcome, action, options).map_err(|_| ExitCode::Internal)?;
+        tally.outcomes.push(outcome);
+        if interrupts.received().is_some() {
+            break;
+        }
+    }
+    write_groups(env, groups.as_ref())?;
+    Ok(selected.collect())
+}
+
+/// How long `--persistent` gives a supervisor to respawn what it lost
+/// before each rescan.
+const RESPAWN_GRACE: Duration = Duration::from_millis(500);
+
+/// What `--persistent` found and did once the first round was over.
+struct Persisted {
+    /// The processes acted upon that were respawned during the run.
+    respawns: u64,
+    waited: Vec<(Instant, Waited)>,
+    interrupted: bool,
+}
+
+/// `--persistent`: rescans for processes matching `names` and `filters`
+/// that `tally` has no outcome for and that have the name of one the action
+/// succeeded on, and acts on those too, up to `options.persistent` times or
+/// until a rescan finds none.
+fn persist<'a>(
+    env: &mut Env<'_>,
+    names: &'a [String],
+    filters: &FilterArgs,
+    action: &mut dyn Action,
+    options: &Options<'_>,
+    interrupts: &Interrupts,
+    tally: &mut Tally<'a>,
+) -> Result<Persisted, ExitCode> {
+    let scanner = Scanner::new(env.root);
+    let mut seen: HashSet<ProcessIdent> = tally
+        .outcomes
+        .iter()
+        .map(|outcome| dedup_key(&outcome.process))
+        .collect();
+    let comms: HashSet<Vec<u8>> = tally
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_ok())
+        .map(|outcome| outcome.process.comm.to_vec())
+        .collect();
+    let mut persisted = Persisted {
+        respawns: 0,
+        waited: Vec::new(),
+        interrupted: false,
+    };
+    let mut found = 0;
+    for _ in 0..options.persistent {
+        env.clock.sleep(RESPAWN_GRACE);
+        let Selection {
+            processes: mut respawned,
+            matched_by,
+            ..
+        } = select_by_name(&scanner, names, filters, options.pick, None)?;
+        respawned.retain(|process| {
+            seen.insert(dedup_key(process)) && comms.contains(process.comm.as_ref())
+        });
+        found = respawned.len();
+        if found == 0 {
+            break;
+        }
+        persisted.respawns += found as u64;
+        let waiting = tally.waiting.len();
+        apply_selected(
+            env,
+            respawned,
+            &matched_by,
+            action,
+            options,
+            interrupts,
+            tally,
+        )?;
+        write_metrics(options.metrics_file, &tally.metrics);
+        let (waited, interrupted) = wait_if_asked(env, &tally.waiting[waiting..], options);
+        persisted.waited.extend(waited);
+        if interrupted || interrupts.received().is_some() {
+            persisted.interrupted = true;
+            break;
+        }
+    }
+    if persisted.respawns > 0 {
+        let plural = if persisted.respawns == 1 { "" } else { "es" };
+        qprintln!(
+            "{} process{plural} respawned during the run, acted upon too",
+            persisted.respawns
+        );
+    }
+    // Acted upon in the last rescan, too: one more shows whether they are
+    // back again.
+    if found > 0 && !persisted.interrupted {
+        env.clock.sleep(RESPAWN_GRACE);
+        let again = select_by_name(&scanner, names, filters, options.pick, None)?;
+        if again.processes.iter().any(|process| {
+            !seen.contains(&dedup_key(process)) && comms.contains(process.comm.as_ref())
+        }) {
+            qprintln!(
+                "Warning: still respawning after {} rescans; stop whatever restarts them instead",
+                options.persistent
+            );
+        }
+    }
+    Ok(persisted)
+}
+
+/// `--pid`: applies `action` to each of `pids`, matching only if it
+/// succeeded on all of them. Needs `/proc` only for names in messages and
+/// for `--wait`.
+fn act_on_pids(
+    env: &mut Env<'_>,
+    pids: &[Pid],
+    action: &mut dyn Action,
+    options: &Options<'_>,
+) -> Result<Report, ExitCode> {
+    // The kernel hands out PIDs below pid_max, which can be lower than
+    // the highest value --pid accepts.
+    if let Some(max) = pid_max(env.root)
+        && let Some(pid) = pids.iter().find(|pid| pid.as_raw() >= max)
+    {
+        qprintln!("{pid}: not a PID on this system, where pid_max is {max}");
+        return Err(ExitCode::Usage);
+    }
+    let root: Arc<Path> = Arc::from(env.root);
+    let mut metrics = Metrics::default();
+    let mut waiting = Vec::new();
+    let mut outcomes = Vec::with_capacity(pids.len());
+    let mut groups = options.groups();
+    let processes: Vec<_> = pids
+        .iter()
+        .map(|&pid| ProcessInfo::by_pid(Arc::clone(&root), pid))
+        .collect();
+    check_reason(env, &processes, options)?;
+    check_all_signallable(env, &processes, options)?;
+    run_pre_hook(env, &processes, options)?;
+    let interrupts = Interrupts::catch();
+    let started = Instant::now();
+    let mut processes = processes.into_iter();
+    for process in processes.by_ref() {
+        let pid = process.pid;
+        // Before the action, while the process is surely still there.
+        let ident = options.wait.then(|| process.ident()).flatten();
+        let result = action.apply(&process);
+        let outcome = Outcome { process, result };
+        metrics.record(&outcome);
+        #[cfg(feature = "json")]
+        log_outcome(env, options, &outcome, action);
+        if outcome.result.is_ok() {
+            let now = env.clock.now();
+            waiting.extend(ident.map(|ident| (ident, now)));
+        }
+        let name = pid.to_string();
+        let target = outcome.process.comm.is_empty().then_some(name.as_str());
+        let out = match &mut groups {
+            Some(groups) => groups.buffer(env.root, &outcome.process),
+            None => &mut *env.out,
+        };
+        report(out, target, &outcome, action, options).map_err(|_| ExitCode::Internal)?;
+        outcomes.push(outcome);
+        if interrupts.received().is_some() {
+            break;
+        }
+    }
+    if let Some(profile) = &options.profile {
+        profile.add(Phase::Act, started.elapsed());
+    }
+    write_groups(env, groups.as_ref())?;
+
+    write_metrics(options.metrics_file, &metrics);
+    if let Some(signal) = interrupts.received() {
+        let skipped: Vec<_> = processes.collect();
+        summarize_interrupt(env, signal, &metrics, &skipped, options)?;
+        return Ok(Report::interrupted(outcomes));
+    }
+    if options.wait && !is_mounted(env.root) {
+        qprintln!("Warning: cannot wait without {}", env.root.display());
+    }
+    let (waited, interrupted) = wait_if_asked(env, &waiting, options);
+    run_post_hook(env, &outcomes, options);
+    let all = metrics.failed == 0 && metrics.succeeded > 0;
+    let report = if interrupted {
+        Report::interrupted(outcomes)
+    } else {
+        Report::new(outcomes, all)
+    };
+    Ok(report.waited(waited))
+}
+
+/// `--stdin-pids`: the PIDs listed in `reader`, less those whose process no
+/// longer has the name listed with them, as when their PID was reused.
+fn listed_pids(root: &Path, reader: impl BufRead) -> Result<Vec<Pid>, ExitCode> {
+    let listed = read_list(reader).map_err(|e| {
+        qprintln!("--stdin-pids: {e}");
+        ExitCode::Usage
+    })?;
+    let root: Arc<Path> = Arc::from(root);
+    let still_named = |pid, comm: &str| {
+        let process = ProcessInfo::by_pid(Arc::clone(&root), pid);
+        let named = NameFilter::new(comm).matches(&process);
+        if !named {
+            qprintln!("{pid}: no longer {comm}, skipped");
+        }
+        named
+    };
+    Ok(listed
+        .into_iter()
+        .filter(|listed| {
+            listed
+                .comm
+                .as_deref()
+                .is_none_or(|comm| still_named(listed.pid, comm))
+        })
+        .map(|listed| listed.pid)
+        .collect())
+}
+
+/// The names that matched each selected process.
+type MatchedBy<'a> = HashMap<ProcessIdent, Vec<&'a str>>;
+
+/// What `select_by_name` found.
+struct Selection<'a> {
+    processes: Vec<ProcessInfo>,
+    matched_by: MatchedBy<'a>,
+    /// Zombies matched and left out, unless `--include-zombies`.
+    zombies: u64,
+    /// What to warn about each name that matched nothing, left to the
+    /// caller along with the processes `Scanner` could not read.
+    unmatched: Vec<String>,
+}
+
+/// Selects the processes matching any of the names, or the other filters
+/// alone without names, in the `--order` requested. Each process comes
+/// once, with the names that matched it.
+///
+/// Zombies are left out unless `--include-zombies`: they are already dead,
+/// and waiting for them would hang until their parent reaps them.
+fn select_by_name<'a>(
+    scanner: &Scanner,
+    process_names: &'a [String],
+    filters: &FilterArgs,
+    patterns: bool,
+    profile: Option<&Profile>,
+) -> Result<Selection<'a>, ExitCode> {
+    let selector = selector(filters)?;
+
+    let criteria = criteria(scanner, filters)?;
+    let session = session_scope(scanner, filters)?;
+    // Without names, the other filters alone select the processes.
+    let targets: Vec<Option<&str>> = if process_names.is_empty() {
+        vec![None]
+    } else {
+        process_names
+            .iter()
+            .map(|name| Some(name.as_str()))
+            .collect()
+    };
+
+    // With several names, /proc is read once, for each of them to select
+    // from, rather than once per name. Failing that, each name scans on its
+    // own, and reports why that fails too.
+    let snapshot = (targets.len() > 1)
+        .then(|| ProcSnapshot::take(scanner).ok())
+        .flatten();
+
+    // A process matched by several names is acted upon once, reported
+    // under all of them.
+    let mut selected = Vec::new();
+    let mut matched_by = MatchedBy::new();
+    let mut zombies = HashSet::new();
+    let mut unmatched = Vec::new();
+    for target in targets {
+        let mut pipeline = Pipeline::new(scanner.clone()).selector(selector);
+        if let Some((label, filter)) = &session {
+            pipeline = add_filter(pipeline, profile, label, filter.clone());
+        }
+        if let Some(name) = target {
+            pipeline = match add_name_filter(pipeline, profile, name, patterns) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    unmatched.push(format!("{name}: {e}"));
+                    continue;
+                }
+            };
+        }
+        pipeline = add_criteria(pipeline, profile, &criteria, filters.any);
+
+        let mut matches = match select_target(&pipeline, snapshot.as_ref(), target, patterns) {
+            Ok(matches) => matches,
+            Err(e) => {
+                qprintln!("Error: {e}");
+                continue;
+            }
+        };
+        let found = matches.len();
+        if !filters.include_zombies {
+            matches.retain(|process| {
+                let zombie = process.stat().is_some_and(|stat| stat.state == b'Z');
+                if zombie {
+                    zombies.insert(dedup_key(process));
+                }
+                !zombie
+            });
+        }
+
+        if matches.is_empty() {
+            let what = target.map_or_else(|| describe_selection(filters), str::to_owned);
+            if found == 0 {
+                unmatched.push(format!("{what}: no process found"));
+            } else {
+                unmatched.push(format!("{what}: no process found, only zombies"));
+            }
+        }
+
+        for process in matches {
+            match matched_by.entry(dedup_key(&process)) {
+                Entry::Occupied(mut names) => {
+                    let names = names.get_mut();
+                    if let Some(name) = target
+                        && !names.contains(&name)
+                    {
+                        names.push(name);
+                    }
+                }
+                Entry::Vacant(names) => {
+                    names.insert(target.into_iter().collect());
+                    selected.push(process);
+                }
+            }
+        }
+    }
+    if filters.reverse_order {
+        WorkersFirst::new(filters.order).sort(scanner.root(), &mut selected);
+    } else {
+        filters.order.sort(scanner.root(), &mut selected);
+    }
+    Ok(Selection {
+        processes: selected,
+        matched_by,
+        zombies: zombies.len() as u64,
+        unmatched,
+    })
+}
+
+/// Adds `criteria` to `pipeline`, all of which must pass, or with `any`
+/// only one of.
+fn add_criteria(
+    mut pipeline: Pipeline,
+    profile: Option<&Profile>,
+    criteria: &[(String, Shared)],
+    any: bool,
+) -> Pipeline {
+    if any && criteria.len() > 1 {
+        let any = criteria
+            .iter()
+            .map(|(label, filter)| -> Box<dyn Filter> {
+                match profile {
+                    Some(profile) => Box::new(profile.wrap(label, filter.clone())),
+                    None => Box::new(filter.clone()),
+                }
+            })
+            .collect();
+        return pipeline.filter(AnyOf(any));
+    }
+    for (label, filter) in criteria {
+        pipeline = add_filter(pipeline, profile, label, filter.clone());
+    }
+    pipeline
+}
+
+/// The processes `pipeline` selects for `target`: from `snapshot` if one
+/// was taken, where a plain name is looked up in the index rather than
+/// checked on each process, or from a scan of its own.
+fn select_target(
+    pipeline: &Pipeline,
+    snapshot: Option<&ProcSnapshot>,
+    target: Option<&str>,
+    patterns: bool,
+) -> io::Result<Vec<ProcessInfo>> {
+    let Some(snapshot) = snapshot else {
+        return pipeline.select();
+    };
+    if let Some(name) = target.filter(|name| !patterns && !name.contains('/')) {
+        return Ok(pipeline.select_from(snapshot, snapshot.by_comm(name.as_bytes())));
+    }
+    Ok(pipeline.select_from(snapshot, snapshot.processes()))
+}
+
+/// What the matched processes lead to acting upon, as `-g`,
+/// `--signal-group-leader-only` or `--tree` within `--min-depth` and
+/// `--max-depth` have it.
+fn selector(filters: &FilterArgs) -> Result<Selector, ExitCode> {
+    if filters.process_group {
+        return Ok(Selector::ProcessGroup);
+    }
+    if filters.signal_group_leader_only {
+        return Ok(Selector::GroupLeaders);
+    }
+    if !filters.tree {
+        return Ok(Selector::Matched);
+    }
+    let min_depth = filters.min_depth.unwrap_or(0);
+    if let Some(max_depth) = filters.max_depth
+        && min_depth > max_depth
+    {
+        qprintln!("--min-depth {min_depth} is deeper than --max-depth {max_depth}");
+        return Err(ExitCode::Usage);
+    }
+    Ok(Selector::Tree {
+        min_depth,
+        max_depth: filters.max_depth,
+    })
+}
+
+/// Adds the filter for one of killall's names to `pipeline`: with a slash
+/// in it, as with psmisc's killall, a path for the processes running that
+/// file, which fails if there is none.
+fn add_name_filter(
+    pipeline: Pipeline,
+    profile: Option<&Profile>,
+    name: &str,
+    pattern: bool,
+) -> io::Result<Pipeline> {
+    if pattern {
+        let filter = PatternFilter::new(name, false)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let own_pid = Pid::this();
+        let pipeline = pipeline.filter(move |p: &ProcessInfo| p.pid != own_pid);
+        return Ok(add_filter(
+            pipeline,
+            profile,
+            &format!("pattern {name}"),
+            filter,
+        ));
+    }
+    if name.contains('/') {
+        let filter = ExeFilter::new(Path::new(name))?;
+        return Ok(add_filter(
+            pipeline,
+            profile,
+            &format!("exe {name}"),
+            filter,
+        ));
+    }
+    let label = format!("name {name}");
+    Ok(add_filter(pipeline, profile, &label, NameFilter::new(name)))
+}
+
+/// Tells processes apart across names, even if a PID was reused between
+/// the scans for two of them.
+fn dedup_key(process: &ProcessInfo) -> ProcessIdent {
+    process.ident().unwrap_or(ProcessIdent {
+        pid: process.pid,
+        starttime: 0,
+    })
+}
+
+/// Refuses to act on `processes` without a `--reason` where the policy
+/// requires one.
+fn check_reason(
+    env: &Env<'_>,
+    processes: &[ProcessInfo],
+    options: &Options<'_>,
+) -> Result<(), ExitCode> {
+    let given = options
+        .reason
+        .is_some_and(|reason| !reason.trim().is_empty());
+    if given || !env.policy.needs_reason(sys::uid(), processes) {
+        return Ok(());
+    }
+    let whose = match env.policy.require_reason {
+        RequireReason::OtherUsers => "processes of other users",
+        RequireReason::Never | RequireReason::Always => "processes",
+    };
+    qprintln!(
+        "{}: {POLICY_PATH} requires a --reason to act on {whose}",
+        env!("CARGO_CRATE_NAME")
+    );
+    Err(ExitCode::Usage)
+}
+
+/// Refuses to act on any of `processes` under `--all-or-nothing` unless
+/// each of them can be signalled, listing those that can't.
+///
+/// Processes that exited since they were matched pass, as there is nothing
+/// left to act on for them.
+fn check_all_signallable(
+    env: &Env<'_>,
+    processes: &[ProcessInfo],
+    options: &Options<'_>,
+) -> Result<(), ExitCode> {
+    if !options.all_or_nothing {
+        return Ok(());
+    }
+    let mut refused = 0;
+    for process in processes {
+        let status = permission::status(&env.killer.probe(process.pid), process);
+        if status == PermissionStatus::NoPermission {
+            qprintln!("{}({}): {status}", process.comm.escaped(), process.pid);
+            refused += 1;
+        }
+    }
+    if refused == 0 {
+        return Ok(());
+    }
+    qprintln!(
+        "--all-or-nothing: {refused} of {} processes can't be signalled, none was acted upon",
+        processes.len()
+    );
+    Err(ExitCode::NoneMatched)
+}
+
+/// Runs the `--pre-hook`, if set, for `processes`, acting on none of them
+/// unless it succeeds.
+fn run_pre_hook(
+    env: &mut Env<'_>,
+    processes: &[ProcessInfo],
+    options: &Options<'_>,
+) -> Result<(), ExitCode> {
+    let Some(command) = options.pre_hook else {
+        return Ok(());
+    };
+    if processes.is_empty() {
+        return Ok(());
+    }
+    // What the hook prints comes after what was written so far.
+    env.out.flush().map_err(|_| ExitCode::Internal)?;
+    match hooks::run(command, Hook::Pre, processes) {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => {
+            qprintln!("--pre-hook failed with {status}, none was acted upon");
+            Err(ExitCode::NoneMatched)
+        }
+        Err(e) => {
+            qprintln!("--pre-hook: {e}, none was acted upon");
+            Err(ExitCode::NoneMatched)
+        }
+    }
+}
+
+/// Runs the `--post-hook`, if set, for the processes of `outcomes` the
+/// action succeeded on, warning if it fails.
+fn run_post_hook(env: &mut Env<'_>, outcomes: &[Outcome], options: &Options<'_>) {
+    let Some(command) = options.post_hook else {
+        return;
+    };
+    let acted_on: Vec<_> = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_ok())
+        .map(|outcome| &outcome.process)
+        .collect();
+    if acted_on.is_empty() {
+        return;
+    }
+    let _ = env.out.flush();
+    match hooks::run(command, Hook::Post, acted_on) {
+        Ok(status) if status.success() => {}
+        Ok(status) => qprintln!("Warning: --post-hook failed with {status}"),
+        Err(e) => qprintln!("Warning: --post-hook: {e}"),
+    }
+}
+
+/// Writes the lines gathered by `--group-by`, if any.
+fn write_groups(env: &mut Env<'_>, groups: Option<&Groups>) -> Result<(), ExitCode> {
+    groups.map_or(Ok(()), |groups| {
+        groups.write_to(env.out).map_err(|_| ExitCode::Internal)
+    })
+}
+
+/// Records `outcome` in the `--log-file`, if set, warning if it can't.
+#[cfg(feature = "json")]
+fn log_outcome(env: &Env<'_>, options: &Options<'_>, outcome: &Outcome, action: &dyn Action) {
+    if let Some(log) = &options.log
+        && let Err(e) = log.record(env.clock.system_time(), outcome, &action.describe())
+    {
+        qprintln!("Warning: {}: {e}", log.path().display());
+    }
+}
+
+/// Writes `metrics` to `path`, if set, warning if it can't.
+fn write_metrics(path: Option<&Path>, metrics: &Metrics) {
+    if let Some(path) = path
+        && let Err(e) = metrics.write_to(path)
+    {
+        qprintln!("Warning: {}: {e}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        color::ColorChoice,
+        coredump::CoreSignal,
+        killer::{RecordingKiller, Sent},
+        listing::ListSort,
+        testing::{
+            cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat,
+            write_fake_stat_fields,
+        },
+        time::TimeFormat,
+    };
+    use std::{
+        fs,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    fn test_env<'a>(root: &'a Path, out: &'a mut Vec<u8>) -> Env<'a> {
+        Env {
+            root,
+            out,
+            killer: Arc::new(RecordingKiller::new()),
+            clock: Arc::new(MockClock::new(UNIX_EPOCH)),
+            policy: SitePolicy::default(),
+        }
+    }
+
+    fn pgrep_args(pattern: &str) -> PgrepArgs {
+        PgrepArgs {
+            pattern: PatternArgs {
+                pattern: pattern.to_owned(),
+                ..PatternArgs::default()
+            },
+            ..PgrepArgs::default()
+        }
+    }
+
+    #[test]
+    fn test_run_pgrep() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "bash"), ("11", "sshd"), ("12", "bash")]);
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+
+        let report = run(Invocation::Pgrep(pgrep_args("bash")), &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::Matched);
+        assert_eq!(report.outcomes.len(), 2);
+
+        let counted = PgrepArgs {
+            count: true,
+            ..pgrep_args("bash")
+        };
+        run(Invocation::Pgrep(counted), &mut env).unwrap();
+        let report = run(Invocation::Pgrep(pgrep_args("nginx")), &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::NoneMatched);
+        assert_eq!(out, b"10\n12\n2\n");
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_pgrep_times_by_clock() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "bash")]);
+        write_fake_stat_fields(&tmp, "10", "bash", 'S', 1, 10, 0);
+        fs::write(tmp.join("stat"), "cpu 0 0 0 0\nbtime 1000\n").unwrap();
+        let mut out = Vec::new();
+        let mut env = Env {
+            clock: Arc::new(MockClock::new(
+                UNIX_EPOCH + Duration::from_secs(1000 + 3 * 60 + 12),
+            )),
+            ..test_env(&tmp, &mut out)
+        };
+        let args = PgrepArgs {
+            time_format: Some(TimeFormat::Human),
+            ..pgrep_args("bash")
+        };
+
+        run(Invocation::Pgrep(args), &mut env).unwrap();
+        assert_eq!(out, b"10 3m12s\n");
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_killall_without_match() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "bash")]);
+        write_fake_stat(&tmp, "10", "bash", 1, 10);
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+        let args = FaulxArgs {
+            process_names: vec!["nginx".to_owned()],
+            verbose: true,
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::NoneMatched);
+        assert!(report.outcomes.is_empty());
+
+        let args = FaulxArgs {
+            signal: Some("NOPE".to_owned()),
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+        assert_eq!(run(invocation, &mut env).unwrap_err(), ExitCode::Usage);
+        assert!(out.is_empty());
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_killall_color() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "bash")]);
+        write_fake_stat(&tmp, "10", "bash", 1, 1);
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+        for color in [ColorChoice::Never, ColorChoice::Always] {
+            let args = FaulxArgs {
+                process_names: vec!["bash".to_owned()],
+                verbose: true,
+                color,
+                ..FaulxArgs::default()
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(args),
+                command_line: Vec::new(),
+            };
+            run(invocation, &mut env).unwrap();
+        }
+        let expected = if cfg!(feature = "color") {
+            "\x1b[32mKilled\x1b[0m \x1b[1mbash\x1b[0m(10) with signal 15\n"
+        } else {
+            "Killed bash(10) with signal 15\n"
+        };
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("Killed bash(10) with signal 15\n{expected}")
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_hostile_names() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "x"), ("11", "x")]);
+        fs::write(tmp.join("10/comm"), b"ev\x1b[2Jil\n").unwrap();
+        fs::write(tmp.join("11/comm"), b"ev\xffil\n").unwrap();
+        write_fake_stat(&tmp, "11", "ev", 1, 1);
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+
+        let listed = PgrepArgs {
+            list_name: true,
+            ..pgrep_args("ev")
+        };
+        run(Invocation::Pgrep(listed), &mut env).unwrap();
+        // Names match as bytes, not as the text they print as.
+        for name in ["ev\u{fffd}il", "ev\\xffil"] {
+            let args = FaulxArgs {
+                process_names: vec![name.to_owned()],
+                ..FaulxArgs::default()
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(args),
+                command_line: Vec::new(),
+            };
+            let report = run(invocation, &mut env).unwrap();
+            assert_eq!(report.code, ExitCode::NoneMatched, "{name}");
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "10 ev\\x1b[2Jil\n11 ev\\xffil\n"
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_listed_pids() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "bash")]);
+        let listed = b"10 nginx\n11 nginx\n12\n";
+        assert_eq!(listed_pids(&tmp, &listed[..]).unwrap(), [pid(10), pid(12)]);
+        assert_eq!(
+            listed_pids(&tmp, &b"10\nnot-a-pid\n"[..]).unwrap_err(),
+            ExitCode::Usage
+        );
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_killall_records_signals() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "bash"), ("11", "sshd"), ("12", "bash")]);
+        for (pid, comm) in [("10", "bash"), ("11", "sshd"), ("12", "bash")] {
+            write_fake_stat(&tmp, pid, comm, 1, 1);
+        }
+        let killer = Arc::new(RecordingKiller::new().fail(pid(12), libc::EPERM));
+        let mut out = Vec::new();
+        let mut env = Env {
+            killer: Arc::clone(&killer) as Arc<dyn Killer>,
+            ..test_env(&tmp, &mut out)
+        };
+        let args = FaulxArgs {
+            process_names: vec!["bash".to_owned(), "sshd".to_owned()],
+            signal: Some("HUP".to_owned()),
+            echo: true,
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::Matched);
+        assert_eq!(report.outcomes.len(), 3);
+        assert!(report.outcomes[2].result.is_err());
+        let hup = Signal::from_raw(libc::SIGHUP).unwrap();
+        let sent = |pid| Sent {
+            pid,
+            thread: None,
+            signal: hup,
+        };
+        assert_eq!(killer.sent(), [sent(pid(10)), sent(pid(11)), sent(pid(12))]);
+        assert_eq!(out, b"bash killed (pid 10)\nsshd killed (pid 11)\n");
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_requires_reason() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 1);
+        let killer = Arc::new(RecordingKiller::new());
+        let mut out = Vec::new();
+        let mut env = Env {
+            killer: Arc::clone(&killer) as Arc<dyn Killer>,
+            policy: SitePolicy {
+                require_reason: RequireReason::Always,
+            },
+            ..test_env(&tmp, &mut out)
+        };
+        let invocation = |reason: Option<&str>| Invocation::Killall {
+            args: Box::new(FaulxArgs {
+                process_names: vec!["nginx".to_owned()],
+                reason: reason.map(str::to_owned),
+                ..FaulxArgs::default()
+            }),
+            command_line: Vec::new(),
+        };
+
+        assert_eq!(
+            run(invocation(None), &mut env).unwrap_err(),
+            ExitCode::Usage
+        );
+        assert_eq!(
+            run(invocation(Some(" ")), &mut env).unwrap_err(),
+            ExitCode::Usage
+        );
+        assert!(killer.sent().is_empty());
+        let report = run(invocation(Some("INC-1234")), &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::Matched);
+        assert_eq!(killer.sent().len(), 1);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_all_or_nothing() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 1);
+        write_fake_stat(&tmp, "11", "nginx", 1, 1);
+        let killed = |killer: RecordingKiller| {
+            let killer = Arc::new(killer);
+            let mut out = Vec::new();
+            let mut env = Env {
+                killer: Arc::clone(&killer) as Arc<dyn Killer>,
+                ..test_env(&tmp, &mut out)
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(FaulxArgs {
+                    process_names: vec!["nginx".to_owned()],
+                    all_or_nothing: true,
+                    ..FaulxArgs::default()
+                }),
+                command_line: Vec::new(),
+            };
+            let code = run(invocation, &mut env).map_or_else(|code| code, |report| report.code);
+            (code, killer.sent().len())
+        };
+
+        assert_eq!(
+            killed(RecordingKiller::new().fail(pid(11), libc::EPERM)),
+            (ExitCode::NoneMatched, 0)
+        );
+        // One that is gone by then doesn't hold back the others.
+        assert_eq!(
+            killed(RecordingKiller::new().fail(pid(11), libc::ESRCH)),
+            (ExitCode::Matched, 2)
+        );
+        assert_eq!(killed(RecordingKiller::new()), (ExitCode::Matched, 2));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 1);
+        write_fake_stat(&tmp, "11", "nginx", 1, 1);
+        let saved = tmp.join("saved");
+        let killed = |killer: RecordingKiller, pre_hook: &str| {
+            let killer = Arc::new(killer);
+            let mut out = Vec::new();
+            let mut env = Env {
+                killer: Arc::clone(&killer) as Arc<dyn Killer>,
+                ..test_env(&tmp, &mut out)
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(FaulxArgs {
+                    process_names: vec!["nginx".to_owned()],
+                    pre_hook: Some(pre_hook.into()),
+                    post_hook: Some(format!("echo $FAULX_PIDS > '{}'", saved.display()).into()),
+                    ..FaulxArgs::default()
+                }),
+                command_line: Vec::new(),
+            };
+            let code = run(invocation, &mut env).map_or_else(|code| code, |report| report.code);
+            (code, killer.sent().len())
+        };
+
+        assert_eq!(
+            killed(RecordingKiller::new(), "exit 1"),
+            (ExitCode::NoneMatched, 0)
+        );
+        assert!(!saved.exists());
+        // The post-hook only hears of those the action succeeded on.
+        assert_eq!(
+            killed(RecordingKiller::new().fail(pid(10), libc::EPERM), "true"),
+            (ExitCode::Matched, 2)
+        );
+        assert_eq!(fs::read_to_string(&saved).unwrap(), "11\n");
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    /// Stands in for a supervisor: each process killed is replaced by one
+    /// of the same name, 100 PIDs on, below PID 300.
+    struct Respawner {
+        root: std::path::PathBuf,
+        killed: Mutex<Vec<i32>>,
+    }
+
+    impl Killer for Respawner {
+        fn kill(&self, pid: Pid, _: Signal) -> io::Result<()> {
+            self.killed.lock().unwrap().push(pid.as_raw());
+            fs::remove_dir_all(self.root.join(pid.to_string()))?;
+            let respawned = pid.as_raw() + 100;
+            if respawned < 300 {
+                let respawned = respawned.to_string();
+                setup_fake_proc(&self.root, &[(&respawned, "nginx")]);
+                write_fake_stat(&self.root, &respawned, "nginx", 1, 1);
+            }
+            Ok(())
+        }
+
+        fn kill_thread(&self, _: Pid, _: Pid, _: Signal) -> io::Result<()> {
+            unreachable!()
+        }
+
+        fn probe(&self, _: Pid) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_run_persistent() {
+        let killed = |persistent| {
+            let tmp = unique_test_dir();
+            setup_fake_proc(&tmp, &[("10", "nginx"), ("20", "bash")]);
+            write_fake_stat(&tmp, "10", "nginx", 1, 1);
+            write_fake_stat(&tmp, "20", "bash", 1, 1);
+            let killer = Arc::new(Respawner {
+                root: tmp.clone(),
+                killed: Mutex::default(),
+            });
+            let mut out = Vec::new();
+            let mut env = Env {
+                killer: Arc::clone(&killer) as Arc<dyn Killer>,
+                ..test_env(&tmp, &mut out)
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(FaulxArgs {
+                    process_names: vec!["nginx".to_owned()],
+                    persistent,
+                    ..FaulxArgs::default()
+                }),
+                command_line: Vec::new(),
+            };
+            let report = run(invocation, &mut env).unwrap();
+            cleanup_fake_proc(&tmp);
+            let pids = killer.killed.lock().unwrap().clone();
+            (report.code, pids, report.respawns)
+        };
+
+        assert_eq!(killed(None), (ExitCode::Matched, vec![10], 0));
+        assert_eq!(killed(Some(1)), (ExitCode::Matched, vec![10, 110], 1));
+        // The rescan after 210 was killed finds nothing new, and ends it.
+        assert_eq!(killed(Some(5)), (ExitCode::Matched, vec![10, 110, 210], 2));
+    }
+
+    #[test]
+    fn test_run_tree_depths() {
+        let tmp = unique_test_dir();
+        for (pid, comm, ppid) in [("10", "supervisor", 1), ("11", "worker", 10)] {
+            setup_fake_proc(&tmp, &[(pid, comm)]);
+            write_fake_stat(&tmp, pid, comm, ppid, 1);
+        }
+        let killer = Arc::new(RecordingKiller::new());
+        let mut out = Vec::new();
+        let mut env = Env {
+            killer: Arc::clone(&killer) as Arc<dyn Killer>,
+            ..test_env(&tmp, &mut out)
+        };
+        let invocation = |min_depth, max_depth| Invocation::Killall {
+            args: Box::new(FaulxArgs {
+                process_names: vec!["supervisor".to_owned()],
+                filters: FilterArgs {
+                    tree: true,
+                    min_depth,
+                    max_depth,
+                    ..FilterArgs::default()
+                },
+                ..FaulxArgs::default()
+            }),
+            command_line: Vec::new(),
+        };
+
+        assert_eq!(
+            run(invocation(Some(2), Some(1)), &mut env).unwrap_err(),
+            ExitCode::Usage
+        );
+        assert!(killer.sent().is_empty());
+        run(invocation(Some(1), None), &mut env).unwrap();
+        let sent: Vec<_> = killer.sent().iter().map(|sent| sent.pid).collect();
+        assert_eq!(sent, [pid(11)]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn test_run_tui_filters() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 1);
+        fs::write(tmp.join("10/status"), "Name:\tnginx\nUid:\t0\t0\t0\t0\n").unwrap();
+        let run_tui = |pattern: &str| {
+            let killer = Arc::new(RecordingKiller::new());
+            let mut out = Vec::new();
+            let mut env = Env {
+                killer: Arc::clone(&killer) as Arc<dyn Killer>,
+                ..test_env(&tmp, &mut out)
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(FaulxArgs {
+                    process_names: vec![pattern.to_owned()],
+                    tui: true,
+                    filters: FilterArgs {
+                        user: vec!["33".to_owned()],
+                        ..FilterArgs::default()
+                    },
+                    ..FaulxArgs::default()
+                }),
+                command_line: Vec::new(),
+            };
+            let code = run(invocation, &mut env).map_or_else(|code| code, |report| report.code);
+            (code, killer.sent().len())
+        };
+
+        // The picker isn't shown the root process --user leaves out.
+        assert_eq!(run_tui("ngi"), (ExitCode::NoneMatched, 0));
+        assert_eq!(run_tui("ngi("), (ExitCode::Usage, 0));
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_combines_filters() {
+        let tmp = unique_test_dir();
+        let entries = [("10", "nginx", 33), ("11", "nginx", 0), ("12", "sshd", 33)];
+        for (pid, comm, uid) in entries {
+            setup_fake_proc(&tmp, &[(pid, comm)]);
+            write_fake_stat(&tmp, pid, comm, 1, 1);
+            fs::write(
+                tmp.join(pid).join("status"),
+                format!("Name:\t{comm}\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\n"),
+            )
+            .unwrap();
+        }
+        let killed = |process_names: &[&str], filters: FilterArgs| {
+            let killer = Arc::new(RecordingKiller::new());
+            let mut out = Vec::new();
+            let mut env = Env {
+                killer: Arc::clone(&killer) as Arc<dyn Killer>,
+                ..test_env(&tmp, &mut out)
+            };
+            let args = FaulxArgs {
+                process_names: process_names.iter().map(|&name| name.to_owned()).collect(),
+                filters,
+                ..FaulxArgs::default()
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(args),
+                command_line: Vec::new(),
+            };
+            let _ = run(invocation, &mut env);
+            killer
+                .sent()
+                .iter()
+                .map(|sent| sent.pid)
+                .collect::<Vec<_>>()
+        };
+
+        let not_root = FilterArgs {
+            not_user: vec!["0".to_owned()],
+            ..FilterArgs::default()
+        };
+        assert_eq!(killed(&["nginx"], not_root), [10]);
+        let www = FilterArgs {
+            user: vec!["33".to_owned()],
+            ..FilterArgs::default()
+        };
+        assert_eq!(killed(&[], www), [10, 12]);
+        let both = || FilterArgs {
+            user: vec!["0".to_owned()],
+            not_user: vec!["33".to_owned(), "0".to_owned()],
+            ..FilterArgs::default()
+        };
+        assert!(killed(&[], both()).is_empty());
+        let any = FilterArgs {
+            any: true,
+            ..both()
+        };
+        assert_eq!(killed(&[], any), [11]);
+        let expr = FilterArgs {
+            where_clause: Some("uid=33 && comm!=nginx || pid=11".parse().unwrap()),
+            ..FilterArgs::default()
+        };
+        assert_eq!(killed(&[], expr), [11, 12]);
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_skips_zombies() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(
+            &tmp,
+            &[("10", "worker"), ("11", "worker"), ("12", "reaped")],
+        );
+        write_fake_stat(&tmp, "10", "worker", 1, 10);
+        write_fake_stat_fields(&tmp, "11", "worker", 'Z', 1, 10, 100);
+        write_fake_stat_fields(&tmp, "12", "reaped", 'Z', 1, 12, 100);
+        let killer = Arc::new(RecordingKiller::new());
+        let mut out = Vec::new();
+        let mut env = Env {
+            killer: Arc::clone(&killer) as Arc<dyn Killer>,
+            ..test_env(&tmp, &mut out)
+        };
+        let args = FaulxArgs {
+            process_names: vec!["worker".to_owned(), "reaped".to_owned()],
+            verbose: true,
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+
+        let report = run(invocation, &mut env).unwrap();
+        // reaped only matched a zombie.
+        assert_eq!(report.code, ExitCode::NoneMatched);
+        assert_eq!(
+            killer
+                .sent()
+                .iter()
+                .map(|sent| sent.pid)
+                .collect::<Vec<_>>(),
+            [10]
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Killed worker(10) with signal 15\n\
+             Skipped 2 zombie processes, which only their parent can reap\n"
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_list() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "worker10"), ("11", "worker2")]);
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+        let args = FaulxArgs {
+            command: Some(Command::List(ListArgs {
+                sort: ListSort::Name,
+                version_sort: true,
+                reverse: false,
+            })),
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::Matched);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "    PID USER     COMMAND\n     11 ?        worker2\n     10 ?        worker10\n"
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_doctor_without_proc() {
+        let tmp = unique_test_dir();
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+        let args = FaulxArgs {
+            command: Some(Command::Doctor),
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::NoneMatched);
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .starts_with("problem  proc: ")
+        );
+    }
+
+    #[test]
+    fn test_run_tree() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx"), ("12", "sh")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 10);
+        write_fake_stat(&tmp, "11", "nginx", 10, 10);
+        write_fake_stat(&tmp, "12", "sh", 11, 10);
+        let mut out = Vec::new();
+        let mut env = test_env(&tmp, &mut out);
+        let args = FaulxArgs {
+            command: Some(Command::Tree(TreeArgs {
+                pattern: "ngin".to_owned(),
+                exact: false,
+            })),
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::Matched);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "nginx(10)\n└─ nginx(11)\n   └─ sh(12)\n"
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_waitfor() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx")]);
+        let clock = Arc::new(MockClock::new(UNIX_EPOCH));
+        let mut out = Vec::new();
+        let mut env = Env {
+            clock: clock.clone(),
+            ..test_env(&tmp, &mut out)
+        };
+        let mut waitfor = |name: &str, present: bool, timeout| {
+            let args = FaulxArgs {
+                command: Some(Command::Waitfor(WaitforArgs {
+                    gone: !present,
+                    present,
+                    timeout,
+                    process_names: vec![name.to_owned()],
+                    ..WaitforArgs::default()
+                })),
+                ..FaulxArgs::default()
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(args),
+                command_line: Vec::new(),
+            };
+            run(invocation, &mut env).unwrap().code
+        };
+
+        assert_eq!(waitfor("nginx", true, None), ExitCode::Matched);
+        assert_eq!(waitfor("apache", false, None), ExitCode::Matched);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+        let timeout = Some(Duration::from_millis(1100));
+        assert_eq!(waitfor("nginx", false, timeout), ExitCode::NoneMatched);
+        assert_eq!(clock.elapsed(), Duration::from_millis(1100));
+        assert_eq!(waitfor("apache", true, timeout), ExitCode::NoneMatched);
+        assert_eq!(clock.elapsed(), Duration::from_millis(2200));
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_restart_without_exec() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 1);
+        let restart = |pattern: &str, killer: RecordingKiller| {
+            let killer = Arc::new(killer);
+            let mut out = Vec::new();
+            let mut env = Env {
+                killer: Arc::clone(&killer) as Arc<dyn Killer>,
+                ..test_env(&tmp, &mut out)
+            };
+            let args = FaulxArgs {
+                command: Some(Command::Restart(RestartArgs {
+                    pattern: pattern.to_owned(),
+                    command: vec!["/nonexistent/faulx-restarted".into()],
+                    ..RestartArgs::default()
+                })),
+                ..FaulxArgs::default()
+            };
+            let invocation = Invocation::Killall {
+                args: Box::new(args),
+                command_line: Vec::new(),
+            };
+            let code = run(invocation, &mut env).map_or_else(|code| code, |report| report.code);
+            (code, killer.sent().len())
+        };
+
+        // What couldn't be signalled keeps the command from starting.
+        assert_eq!(
+            restart("nginx", RecordingKiller::new().fail(pid(10), libc::EPERM)),
+            (ExitCode::NoneMatched, 1)
+        );
+        // Nothing to wait for, so the command is run, and fails to start.
+        assert_eq!(
+            restart("apache", RecordingKiller::new()),
+            (ExitCode::Internal, 0)
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_run_core() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx")]);
+        fs::create_dir_all(tmp.join("sys/kernel")).unwrap();
+        fs::create_dir_all(tmp.join("crash")).unwrap();
+        let pattern = format!("{}/crash/core.%e.%p.%t\n", tmp.display());
+        fs::write(tmp.join("sys/kernel/core_pattern"), pattern).unwrap();
+        let dump = fs::File::create(tmp.join("crash/core.nginx.10.1700000000")).unwrap();
+        // Written after the signal.
+        dump.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        fs::write(
+            tmp.join("11/limits"),
+            "Max core file size        0                    unlimited            bytes\n",
+        )
+        .unwrap();
+        let killer = Arc::new(RecordingKiller::new());
+        let mut out = Vec::new();
+        let mut env = Env {
+            killer: Arc::clone(&killer) as Arc<dyn Killer>,
+            ..test_env(&tmp, &mut out)
+        };
+        let args = FaulxArgs {
+            process_names: vec!["nginx".to_owned()],
+            core: Some(CoreSignal::Abrt),
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::Matched);
+        let sent = |pid| Sent {
+            pid,
+            thread: None,
+            signal: Signal::ABRT,
+        };
+        assert_eq!(killer.sent(), [sent(pid(10)), sent(pid(11))]);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "nginx(10) dumped core to {}\n",
+                tmp.join("crash/core.nginx.10.1700000000").display()
+            )
+        );
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_scope_to_session() {
+        let scoped = |args: FaulxArgs| {
+            let mut invocation = Invocation::Killall {
+                args: Box::new(args),
+                command_line: Vec::new(),
+            };
+            invocation.scope_to_session();
+            match invocation {
+                Invocation::Killall { args, .. } => args.filters.session_scope,
+                _ => unreachable!(),
+            }
+        };
+        assert!(scoped(FaulxArgs::default()));
+        let all_sessions = FaulxArgs {
+            filters: FilterArgs {
+                all_sessions: true,
+                ..FilterArgs::default()
+            },
+            ..FaulxArgs::default()
+        };
+        assert!(!scoped(all_sessions));
+        let ns_of = FaulxArgs {
+            ns_of: Some(pid(10)),
+            ..FaulxArgs::default()
+        };
+        assert!(!scoped(ns_of));
+    }
+
+    #[test]
+    fn test_run_without_proc() {
+        let tmp = unique_test_dir();
+        let killer = Arc::new(RecordingKiller::new().fail(pid(11), libc::ESRCH));
+        let mut out = Vec::new();
+        let mut env = Env {
+            killer: Arc::clone(&killer) as Arc<dyn Killer>,
+            ..test_env(&tmp, &mut out)
+        };
+        let invocation = Invocation::Pgrep(pgrep_args("bash"));
+        assert_eq!(run(invocation, &mut env).unwrap_err(), ExitCode::NoProc);
+
+        let args = FaulxArgs {
+            pids: vec![pid(10), pid(11)],
+            echo: true,
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+        let report = run(invocation, &mut env).unwrap();
+        assert_eq!(report.code, ExitCode::NoneMatched);
+        let sent = |pid| Sent {
+            pid,
+            thread: None,
+            signal: Signal::TERM,
+        };
+        assert_eq!(killer.sent(), [sent(pid(10)), sent(pid(11))]);
+
+        fs::create_dir_all(tmp.join("sys/kernel")).unwrap();
+        fs::write(tmp.join("sys/kernel/pid_max"), "11\n").unwrap();
+        let args = FaulxArgs {
+            pids: vec![pid(10), pid(11)],
+            ..FaulxArgs::default()
+        };
+        let invocation = Invocation::Killall {
+            args: Box::new(args),
+            command_line: Vec::new(),
+        };
+        assert_eq!(run(invocation, &mut env).unwrap_err(), ExitCode::Usage);
+        assert_eq!(killer.sent().len(), 2);
+        assert_eq!(out, b"10 killed (pid 10)\n");
+
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,182 @@
+//! `--snapshot-dir`: archives `/proc` files of each process about to be
+//! acted upon, for post-mortem analysis once it is gone.
+//!
+//! A run's archive is a directory named after the time it started, with a
+//! directory per process holding its `cmdline`, `status` and `environ`, a
+//! list of its file descriptors, `fds`, and a summary of its memory
+//! mappings, `maps`. Files that couldn't be read are listed in `errors`.
+
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::SystemTime,
+};
+
+use crate::{actions::Action, macros::QUIET, processes::ProcessInfo, qprintln, time::Iso8601};
+
+/// Archives each process, then applies `inner`. Failing to archive is
+/// warned about and doesn't stop `inner`.
+pub struct Archive<A> {
+    dir: PathBuf,
+    inner: A,
+}
+
+impl<A: Action> Archive<A> {
+    /// Creates the directory of a run started at `now` in `parent`, as in
+    /// `parent/2026-10-14T050322Z`.
+    pub fn new(parent: &Path, now: SystemTime, inner: A) -> io::Result<Self> {
+        let dir = parent.join(Iso8601(now).to_string().replace(':', ""));
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, inner })
+    }
+
+    /// The directory of this run.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn save(&self, process: &ProcessInfo) -> io::Result<()> {
+        let comm = String::from_utf8_lossy(&process.comm).replace('/', "_");
+        let dir = self.dir.join(format!("{comm}-{}", process.pid));
+        fs::create_dir_all(&dir)?;
+        let mut errors = String::new();
+        let mut save = |name: &str, contents: io::Result<Vec<u8>>| match contents {
+            Ok(contents) => fs::write(dir.join(name), contents),
+            Err(e) => {
+                let _ = writeln!(errors, "{name}: {e}");
+                Ok(())
+            }
+        };
+        for name in ["cmdline", "status", "environ"] {
+            save(name, fs::read(process.path(name)))?;
+        }
+        save("fds", fd_list(process))?;
+        save(
+            "maps",
+            fs::read(process.path("maps")).map(|maps| summarize_maps(&maps)),
+        )?;
+        if !errors.is_empty() {
+            fs::write(dir.join("errors"), errors)?;
+        }
+        Ok(())
+    }
+}
+
+impl<A: Action> Action for Archive<A> {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        if let Err(e) = self.save(process) {
+            qprintln!(
+                "Warning: --snapshot-dir: {}({}): {e}",
+                process.comm.escaped(),
+                process.pid
+            );
+        }
+        self.inner.apply(process)
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+}
+
+/// The file descriptors of `process` and what they point to, one per line
+/// as `3 -> socket:[12345]`, in ascending order.
+fn fd_list(process: &ProcessInfo) -> io::Result<Vec<u8>> {
+    let mut fds: Vec<(u32, PathBuf)> = fs::read_dir(process.path("fd"))?
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let fd = entry.file_name().to_str()?.parse().ok()?;
+            Some((fd, fs::read_link(entry.path()).ok()?))
+        })
+        .collect();
+    fds.sort_unstable();
+    let mut list = String::new();
+    for (fd, target) in fds {
+        let _ = writeln!(list, "{fd} -> {}", target.display());
+    }
+    Ok(list.into_bytes())
+}
+
+/// How much of the address space each mapped file takes, largest first, as
+/// `1024 kB /usr/lib/libc.so.6`. Anonymous mappings are counted together,
+/// `[heap]` and the like each on their own.
+fn summarize_maps(maps: &[u8]) -> Vec<u8> {
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    for line in String::from_utf8_lossy(maps).lines() {
+        let mut fields = line.splitn(6, ' ');
+        let Some((start, end)) = fields.next().and_then(|range| range.split_once('-')) else {
+            continue;
+        };
+        let (Ok(start), Ok(end)) = (u64::from_str_radix(start, 16), u64::from_str_radix(end, 16))
+        else {
+            continue;
+        };
+        let name = fields.nth(4).map_or("", str::trim_start);
+        let name = if name.is_empty() { "[anonymous]" } else { name };
+        *sizes.entry(name.to_owned()).or_default() += end.saturating_sub(start);
+    }
+    let mut sizes: Vec<_> = sizes.into_iter().collect();
+    sizes.sort_unstable_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+    let mut summary = String::new();
+    for (name, size) in sizes {
+        let _ = writeln!(summary, "{} kB {name}", size / 1024);
+    }
+    summary.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::symlink, sync::Arc, time::UNIX_EPOCH};
+
+    use super::*;
+    use crate::{
+        actions::Count,
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir},
+    };
+
+    #[test]
+    fn test_archive() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "worker")]);
+        fs::write(tmp.join("10/cmdline"), "worker\0--queue\0jobs\0").unwrap();
+        fs::write(tmp.join("10/status"), "Name:\tworker\n").unwrap();
+        fs::create_dir_all(tmp.join("10/fd")).unwrap();
+        symlink("/dev/null", tmp.join("10/fd/0")).unwrap();
+        symlink("socket:[12345]", tmp.join("10/fd/10")).unwrap();
+        symlink("/var/log/worker.log", tmp.join("10/fd/2")).unwrap();
+        fs::write(
+            tmp.join("10/maps"),
+            "00400000-00500000 r-xp 00000000 08:01 42 /usr/bin/worker\n\
+             00600000-00602000 rw-p 00100000 08:01 42 /usr/bin/worker\n\
+             01000000-01400000 rw-p 00000000 00:00 0          [heap]\n\
+             7f0000000000-7f0000002000 rw-p 00000000 00:00 0 \n",
+        )
+        .unwrap();
+
+        let now = UNIX_EPOCH + std::time::Duration::from_secs(1_791_954_202);
+        let mut archive = Archive::new(&tmp.join("snapshots"), now, Count::default()).unwrap();
+        assert_eq!(archive.dir(), tmp.join("snapshots/2026-10-14T050322Z"));
+        let process = ProcessInfo::by_pid(Arc::from(tmp.as_path()), pid(10));
+        archive.apply(&process).unwrap();
+        assert_eq!(archive.inner.count, 1);
+
+        let saved = archive.dir().join("worker-10");
+        let read = |name: &str| fs::read_to_string(saved.join(name)).unwrap();
+        assert_eq!(read("cmdline"), "worker\0--queue\0jobs\0");
+        assert_eq!(read("status"), "Name:\tworker\n");
+        assert_eq!(
+            read("fds"),
+            "0 -> /dev/null\n2 -> /var/log/worker.log\n10 -> socket:[12345]\n"
+        );
+        assert_eq!(
+            read("maps"),
+            "4096 kB [heap]\n1032 kB /usr/bin/worker\n8 kB [anonymous]\n"
+        );
+        assert!(read("errors").starts_with("environ: "));
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,238 @@
+//! `--capture-backtrace`: saves what each process was doing before acting
+//! on it, so that killing a wedged process doesn't destroy the evidence.
+//!
+//! Each process gets a file in the capture directory with its status and,
+//! per thread, its kernel stack (readable by root only), wait channel and
+//! current system call, followed by a user space backtrace from `eu-stack`,
+//! or `gdb` without it.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    actions::Action, limits::OriginalNofileLimit, macros::QUIET, pid::Pid, processes::ProcessInfo,
+    qprintln,
+};
+
+/// How long a sampler may take, as attaching to a process stuck in the
+/// kernel can hang.
+pub const SAMPLER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A program printing the user space backtrace of a process.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    pub program: OsString,
+    pub args: fn(Pid) -> Vec<OsString>,
+}
+
+/// `eu-stack`, then `gdb`, the first installed of which is used.
+#[must_use]
+pub fn default_samplers() -> Vec<Sampler> {
+    vec![
+        Sampler {
+            program: "eu-stack".into(),
+            args: |pid| vec!["-p".into(), pid.to_string().into()],
+        },
+        Sampler {
+            program: "gdb".into(),
+            args: |pid| {
+                [
+                    "-p",
+                    &pid.to_string(),
+                    "-batch",
+                    "-nx",
+                    "-ex",
+                    "thread apply all bt",
+                ]
+                .map(OsString::from)
+                .to_vec()
+            },
+        },
+    ]
+}
+
+/// Captures each process into `dir`, then applies `inner`. Failing to
+/// capture is warned about and doesn't stop `inner`.
+pub struct Capture<A> {
+    dir: PathBuf,
+    samplers: Vec<Sampler>,
+    inner: A,
+}
+
+impl<A: Action> Capture<A> {
+    /// Creates `dir` if needed.
+    pub fn new(dir: &Path, inner: A) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            samplers: default_samplers(),
+            inner,
+        })
+    }
+
+    #[must_use]
+    pub fn samplers(self, samplers: Vec<Sampler>) -> Self {
+        Self { samplers, ..self }
+    }
+
+    /// Writes the capture of `process`, returning its path.
+    fn capture(&self, process: &ProcessInfo) -> io::Result<PathBuf> {
+        let comm = String::from_utf8_lossy(&process.comm).replace('/', "_");
+        let path = self.dir.join(format!("{comm}-{}.txt", process.pid));
+        let mut file = File::create(&path)?;
+        writeln!(file, "{comm}({})", process.pid)?;
+        if let Ok(args) = process.cmdline() {
+            let args: Vec<_> = args
+                .iter()
+                .map(|arg| String::from_utf8_lossy(arg))
+                .collect();
+            writeln!(file, "cmdline: {}", args.join(" "))?;
+        }
+        section(&mut file, "status", fs::read(process.path("status")))?;
+        let mut threads = process.threads().unwrap_or_else(|_| vec![process.pid]);
+        threads.sort_unstable();
+        for tid in threads {
+            let task = |file: &str| process.path(&format!("task/{tid}/{file}"));
+            writeln!(file, "\n== thread {tid} ==")?;
+            for name in ["wchan", "syscall"] {
+                match fs::read_to_string(task(name)) {
+                    Ok(value) => writeln!(file, "{name}: {}", value.trim_end())?,
+                    Err(e) => writeln!(file, "{name}: {e}")?,
+                }
+            }
+            section(&mut file, "stack", fs::read(task("stack")))?;
+        }
+        self.sample(&mut file, process.pid)?;
+        Ok(path)
+    }
+
+    /// Appends the output of the first sampler installed to `file`.
+    fn sample(&self, file: &mut File, pid: Pid) -> io::Result<()> {
+        for sampler in &self.samplers {
+            let name = sampler.program.to_string_lossy();
+            writeln!(file, "\n== {name} ==")?;
+            let child = Command::new(&sampler.program)
+                .original_nofile_limit()
+                .args((sampler.args)(pid))
+                .stdin(Stdio::null())
+                .stdout(file.try_clone()?)
+                .stderr(file.try_clone()?)
+                .spawn();
+            let mut child = match child {
+                Ok(child) => child,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    writeln!(file, "not installed")?;
+                    continue;
+                }
+                Err(e) => return writeln!(file, "{e}"),
+            };
+            let started = Instant::now();
+            while child.try_wait()?.is_none() {
+                if started.elapsed() >= SAMPLER_TIMEOUT {
+                    child.kill()?;
+                    child.wait()?;
+                    writeln!(file, "timed out after {}s", SAMPLER_TIMEOUT.as_secs())?;
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            return Ok(());
+        }
+        Ok(())
+    }
+}
+
+impl<A: Action> Action for Capture<A> {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        if let Err(e) = self.capture(process) {
+            qprintln!(
+                "Warning: --capture-backtrace: {}({}): {e}",
+                process.comm.escaped(),
+                process.pid
+            );
+        }
+        self.inner.apply(process)
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+}
+
+/// Writes a titled section of `contents`, or why it couldn't be read.
+fn section(file: &mut File, title: &str, contents: io::Result<Vec<u8>>) -> io::Result<()> {
+    writeln!(file, "{title}:")?;
+    match contents {
+        Ok(contents) => {
+            file.write_all(&contents)?;
+            if !contents.ends_with(b"\n") && !contents.is_empty() {
+                writeln!(file)?;
+            }
+            Ok(())
+        }
+        Err(e) => writeln!(file, "{e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        actions::Count,
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir},
+    };
+
+    #[test]
+    fn test_capture() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "worker")]);
+        fs::write(
+            tmp.join("10/status"),
+            "Name:\tworker\nState:\tD (disk sleep)\n",
+        )
+        .unwrap();
+        fs::create_dir_all(tmp.join("10/task/10")).unwrap();
+        fs::write(tmp.join("10/task/10/wchan"), "io_schedule").unwrap();
+        fs::write(
+            tmp.join("10/task/10/stack"),
+            "[<0>] io_schedule+0x12/0x40\n",
+        )
+        .unwrap();
+        let samplers = vec![
+            Sampler {
+                program: "faulx-no-such-sampler".into(),
+                args: |_| Vec::new(),
+            },
+            Sampler {
+                program: "echo".into(),
+                args: |pid| vec![format!("#0 main in {pid}").into()],
+            },
+        ];
+        let dir = tmp.join("captures");
+        let mut capture = Capture::new(&dir, Count::default())
+            .unwrap()
+            .samplers(samplers);
+        let process = ProcessInfo::by_pid(Arc::from(tmp.as_path()), pid(10));
+        capture.apply(&process).unwrap();
+        assert_eq!(capture.inner.count, 1);
+
+        let text = fs::read_to_string(dir.join("worker-10.txt")).unwrap();
+        assert!(text.starts_with("worker(10)\n"), "{text}");
+        assert!(text.contains("status:\nName:\tworker\nState:\tD (disk sleep)\n"));
+        assert!(text.contains("== thread 10 ==\nwchan: io_schedule\n"));
+        assert!(text.contains("stack:\n[<0>] io_schedule+0x12/0x40\n"));
+        assert!(text.contains("== faulx-no-such-sampler ==\nnot installed\n"));
+        assert!(text.ends_with("== echo ==\n#0 main in 10\n"), "{text}");
+        cleanup_fake_proc(&tmp);
+    }
+}
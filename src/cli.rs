@@ -1,42 +1,932 @@
-use clap::{
-    Parser,
-    builder::{Styles, styling::AnsiColor},
-    command,
+use std::{
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
+#[cfg(all(feature = "clap", feature = "color"))]
+use clap::builder::{Styles, styling::AnsiColor};
+#[cfg(feature = "clap")]
+use clap::{Args, Parser, Subcommand, ValueEnum, command};
+
+#[cfg(feature = "clap")]
+use crate::actions::OomScoreAdj;
+use crate::{
+    actions::{CpuMask, IoPriority},
+    color::ColorChoice,
+    coredump::CoreSignal,
+    expr::Expr,
+    grouping::GroupBy,
+    listing::ListSort,
+    pid::Pid,
+    pipeline::Order,
+    signals::parse_signal,
+    sockets::PortSpec,
+    time::TimeFormat,
+};
+
+#[cfg(any(feature = "tiny-cli", test))]
+pub mod tiny;
+
 pub const MAX_NAMES: usize = std::mem::size_of::<usize>() * 8;
 
+/// Prefix of the environment variables providing option defaults, e.g.
+/// `FAULX_SIGNAL` for `--signal`.
+pub const ENV_PREFIX: &str = "FAULX_";
+
+/// The environment variable providing the default of the option `id`.
+#[must_use]
+pub fn env_name(id: &str) -> String {
+    format!("{ENV_PREFIX}{}", id.to_ascii_uppercase().replace('-', "_"))
+}
+
+/// Lets every option of `cmd` and its subcommands take its default from a
+/// `FAULX_*` environment variable. Options given on the command line take
+/// precedence.
+#[cfg(feature = "clap")]
+#[must_use]
+pub fn with_env(cmd: clap::Command) -> clap::Command {
+    cmd.mut_args(|arg| {
+        if arg.is_positional() {
+            return arg;
+        }
+        let name = env_name(arg.get_id().as_str());
+        let arg = arg.env(name);
+        // Accept `FAULX_VERBOSE=1` or `=yes`, not only `true`.
+        if matches!(arg.get_action(), clap::ArgAction::SetTrue) {
+            arg.value_parser(clap::builder::BoolishValueParser::new())
+        } else {
+            arg
+        }
+    })
+    .mut_subcommands(with_env)
+}
+
+/// The tool the binary behaves as, chosen from the name it was invoked
+/// under so that one binary can be symlinked as `killall`, `pkill` and
+/// `pgrep`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Personality {
+    Killall,
+    Pkill,
+    Pgrep,
+}
+
+impl Personality {
+    #[must_use]
+    pub fn from_argv0(argv0: &OsStr) -> Self {
+        match Path::new(argv0).file_name().and_then(OsStr::to_str) {
+            Some("pkill") => Self::Pkill,
+            Some("pgrep") => Self::Pgrep,
+            _ => Self::Killall,
+        }
+    }
+}
+
+/// Rewrites the `-SIGNAL` shorthand (`-9`, `-HUP`) accepted by killall and
+/// pkill into `--signal=SIGNAL`. Stops at `--`.
+#[must_use]
+pub fn expand_signal_shorthand(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut positional_only = false;
+    args.into_iter()
+        .map(|arg| {
+            if positional_only {
+                return arg;
+            }
+            if arg == "--" {
+                positional_only = true;
+                return arg;
+            }
+            match arg.to_str().and_then(|a| a.strip_prefix('-')) {
+                Some(name) if !name.starts_with('-') && parse_signal(name).is_some() => {
+                    OsString::from(format!("--signal={name}"))
+                }
+                _ => arg,
+            }
+        })
+        .collect()
+}
+
+#[cfg(all(feature = "clap", feature = "color"))]
 const STYLES: Styles = Styles::styled()
     .header(AnsiColor::Green.on_default().bold())
     .usage(AnsiColor::Green.on_default().bold())
     .literal(AnsiColor::Cyan.on_default().bold())
     .placeholder(AnsiColor::Cyan.on_default());
 
-#[derive(Parser, Debug)]
-#[command(styles = STYLES)]
-#[command(author, version, about, long_about = None)]
+/// The options that select processes without names.
+#[cfg(feature = "clap")]
+const NAMES_UNLESS: &[&str] = &[
+    "list",
+    "pids",
+    "stdin_pids",
+    "port",
+    "unix_socket",
+    "holding",
+    "user",
+    "where",
+    "users_from_group",
+    "init",
+    "self_test",
+    #[cfg(feature = "dbus")]
+    "daemon",
+    #[cfg(feature = "window")]
+    "window",
+];
+
+/// Parsed command line. Derives its clap parser with the `clap` feature;
+/// `tiny::parse` fills the same struct with the `tiny-cli` feature.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Parser))]
+#[cfg_attr(all(feature = "clap", feature = "color"), command(styles = STYLES))]
+#[cfg_attr(feature = "clap", command(name = "faulx", author, version, about, long_about = None))]
+#[cfg_attr(
+    feature = "clap",
+    command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)
+)]
+// The last of repeated options wins, so that options override a --preset.
+#[cfg_attr(
+    feature = "clap",
+    command(disable_help_subcommand = true, args_override_self = true)
+)]
 pub struct FaulxArgs {
+    #[cfg_attr(feature = "clap", command(subcommand))]
+    pub command: Option<Command>,
+
     /// process name to kill
-    #[arg(required_unless_present = "list")]
+    #[cfg_attr(feature = "clap", arg(required_unless_present_any = NAMES_UNLESS))]
     pub process_names: Vec<String>,
 
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub filters: FilterArgs,
+
     /// list all known signal names
-    #[arg(short = 'l', long)]
+    #[cfg_attr(feature = "clap", arg(short = 'l', long))]
     pub list: bool,
 
+    /// Output format for --list, and of what became of each process acted
+    /// upon
+    #[cfg_attr(feature = "clap", arg(long, value_enum, default_value_t = OutputFormat::Text))]
+    pub output: OutputFormat,
+
     /// Send this signal instead of SIGTERM
-    #[arg(short = 's', long)]
+    #[cfg_attr(feature = "clap", arg(short = 's', long))]
     pub signal: Option<String>,
 
+    /// Make matched processes dump core with SIGQUIT, or SIGABRT for those
+    /// catching it, wait for them to exit and show where the dumps went
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "SIGNAL",
+            value_enum,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "quit",
+            conflicts_with = "signal"
+        )
+    )]
+    pub core: Option<CoreSignal>,
+
+    /// Before acting on each process, save its status, kernel stacks and a
+    /// backtrace from eu-stack or gdb to a file in this directory
+    #[cfg_attr(feature = "clap", arg(long, value_name = "DIR"))]
+    pub capture_backtrace: Option<PathBuf>,
+
+    /// Before acting on each process, archive its cmdline, status, environ,
+    /// file descriptors and memory map summary under a directory named
+    /// after the time of the run in this one
+    #[cfg_attr(feature = "clap", arg(long, value_name = "DIR"))]
+    pub snapshot_dir: Option<PathBuf>,
+
+    /// Signal these PIDs instead of matching names, which works without
+    /// /proc
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long = "pid",
+            value_name = "PID",
+            value_delimiter = ',',
+            conflicts_with = "process_names"
+        )
+    )]
+    pub pids: Vec<Pid>,
+
+    /// Signal the PIDs read from stdin, one per line, skipping those whose
+    /// process no longer has the name following the PID, if any
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, conflicts_with_all = ["process_names", "pids"])
+    )]
+    pub stdin_pids: bool,
+
+    /// Run this command as a container's init: forward the signals faulx
+    /// receives to it and reap zombies until it exits, then exit like it.
+    /// Takes the rest of the command line
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "COMMAND",
+            num_args = 1..,
+            allow_hyphen_values = true,
+            value_parser = clap::value_parser!(OsString),
+            conflicts_with_all = ["process_names", "pids", "list"]
+        )
+    )]
+    pub init: Vec<OsString>,
+
+    /// With --init, forward signals to the processes with these names
+    /// instead of the command
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "NAME,...",
+            value_delimiter = ',',
+            requires = "init"
+        )
+    )]
+    pub forward_to: Vec<String>,
+
+    /// Enter the PID and mount namespaces of this process first, such as a
+    /// container's, to match and signal as it sees; needs root
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "PID",
+            visible_alias = "namespace-enter",
+            global = true
+        )
+    )]
+    pub ns_of: Option<Pid>,
+
     /// Don't print complaints
-    #[arg(short = 'q', long)]
+    #[cfg_attr(feature = "clap", arg(short = 'q', long, global = true))]
     pub quiet: bool,
 
+    /// Report if the signal was successfully sent
+    #[cfg_attr(feature = "clap", arg(long, global = true))]
+    pub verbose: bool,
+
+    /// Print one line per process signalled, like `pkill -e`
+    #[cfg_attr(feature = "clap", arg(long, global = true))]
+    pub echo: bool,
+
+    /// When to color output
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_enum, value_name = "WHEN", default_value_t, global = true)
+    )]
+    pub color: ColorChoice,
+
+    /// With --verbose or --echo, gather the lines under the parent, session
+    /// or systemd unit of each process
+    #[cfg_attr(feature = "clap", arg(long, value_enum, value_name = "BY"))]
+    pub group_by: Option<GroupBy>,
+
+    /// Why, for the --log-file; required to act on processes of other
+    /// users where /etc/faulx/policy says so
+    #[cfg_attr(feature = "clap", arg(long, value_name = "TEXT", global = true))]
+    pub reason: Option<String>,
+
+    /// If permission was denied for every process matched, offer to run
+    /// the same command again under sudo
+    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "host"))]
+    pub sudo: bool,
+
+    /// Print how long scanning, acting and waiting took, and how many
+    /// processes each filter matched, on stderr at the end
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub profile: bool,
+
+    /// Take the options saved under this name in ~/.config/faulx/presets
+    /// or /etc/faulx/presets; options given after it override them
+    // Replaced by the options themselves before parsing, see `preset::expand`.
+    #[cfg_attr(feature = "clap", arg(long, value_name = "NAME"))]
+    pub preset: Option<String>,
+
+    /// Wait for all signalled processes to die
+    #[cfg_attr(feature = "clap", arg(short = 'w', long))]
+    pub wait: bool,
+
+    /// Wait, and send SIGKILL to the processes still running this long
+    /// after the last of them was signalled
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DURATION", value_parser = crate::units::parse_duration)
+    )]
+    pub timeout: Option<Duration>,
+
+    /// Like --timeout, but counted from when each process was signalled, so
+    /// that one acted upon late doesn't extend the others' grace period
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "DURATION",
+            value_parser = crate::units::parse_duration,
+            conflicts_with = "timeout"
+        )
+    )]
+    pub timeout_per_pid: Option<Duration>,
+
+    /// While waiting, check on the processes this often, 1s by default
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DURATION", value_parser = crate::units::parse_interval)
+    )]
+    pub interval: Option<Duration>,
+
+    /// While waiting, double the interval after each check, up to this, so
+    /// that a long wait doesn't keep reading /proc
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DURATION", value_parser = crate::units::parse_interval)
+    )]
+    pub backoff: Option<Duration>,
+
+    /// Check first that every matched process can be signalled, and act on
+    /// none of them unless all can
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub all_or_nothing: bool,
+
+    /// Once done, look again for processes of the same names started in the
+    /// meantime, as when a supervisor respawns them, and act on those too;
+    /// as many times as given, up to 10, or once, stopping at a rescan that
+    /// finds none
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "ROUNDS",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "1",
+            value_parser = clap::value_parser!(u8).range(1..=10)
+        )
+    )]
+    pub persistent: Option<u8>,
+
+    /// Run this shell command before acting on the matched processes, which
+    /// it gets on stdin, a PID and a name per line; if it fails, none is
+    /// acted upon
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "COMMAND", value_parser = clap::value_parser!(OsString))
+    )]
+    pub pre_hook: Option<OsString>,
+
+    /// Run this shell command once done, given the processes acted upon as
+    /// --pre-hook is; its failure is only warned about
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "COMMAND", value_parser = clap::value_parser!(OsString))
+    )]
+    pub post_hook: Option<OsString>,
+
+    /// Show a desktop notification of how the run went once it is done
+    #[cfg(feature = "notify")]
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub notify: bool,
+
+    /// Under WSL, end matched Windows programs with taskkill.exe, as
+    /// signals do not reach them
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub windows_interop: bool,
+
+    /// Write counts of matched, succeeded and failed processes to this
+    /// file, in the Prometheus textfile collector format
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Append a JSON line per process acted upon to this file, with when,
+    /// by whom and with what result, for auditing
+    #[cfg(feature = "json")]
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub log_file: Option<PathBuf>,
+
+    /// Rotate the --log-file once it would grow past this size, 10M by
+    /// default, keeping the last three as PATH.1 to PATH.3
+    #[cfg(feature = "json")]
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "SIZE",
+            value_parser = crate::units::parse_size,
+            requires = "log_file"
+        )
+    )]
+    pub log_max_size: Option<u64>,
+
+    /// Set the nice value (-20 to 19) of matched processes instead of signalling them
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "N",
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i32).range(-20..=19),
+        )
+    )]
+    pub renice: Option<i32>,
+
+    /// Adjust the OOM killer score (-1000 to 1000) instead of signalling
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "N",
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i16).range(i64::from(OomScoreAdj::MIN)..=i64::from(OomScoreAdj::MAX)),
+        )
+    )]
+    pub oom_score_adj: Option<i16>,
+
+    /// Set the I/O scheduling class and level (CLASS[:LEVEL]) instead of signalling
+    #[cfg_attr(feature = "clap", arg(long, value_name = "CLASS[:LEVEL]"))]
+    pub ionice: Option<IoPriority>,
+
+    /// Set the CPU affinity (hex mask or list like 0-3,8) instead of signalling
+    #[cfg_attr(feature = "clap", arg(long, value_name = "MASK"))]
+    pub taskset: Option<CpuMask>,
+
+    /// Apply --taskset to every thread of the matched processes
+    #[cfg_attr(feature = "clap", arg(long, requires = "taskset"))]
+    pub taskset_threads: bool,
+
+    /// Move matched processes into this cgroup instead of signalling
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub move_to_cgroup: Option<PathBuf>,
+
+    /// Treat names as patterns and pick the processes to act upon
+    /// interactively
+    #[cfg(feature = "tui")]
+    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "process_group"))]
+    pub tui: bool,
+
+    /// Act on the process owning the window clicked next, like xkill but
+    /// ending the process; names, if given, are those it must have
+    #[cfg(feature = "window")]
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, conflicts_with_all = ["pids", "stdin_pids", "process_group"])
+    )]
+    pub window: bool,
+
+    /// Run on these hosts over SSH instead of locally, prefixing each line
+    /// of output with the host it came from
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "HOST,...", value_delimiter = ',')
+    )]
+    pub host: Vec<String>,
+
+    /// Program to run on the --host machines
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "PROG", default_value = crate::remote::DEFAULT_PROGRAM, requires = "host")
+    )]
+    pub remote_program: String,
+
+    /// Serve the org.faulx.Killer D-Bus interface on the system bus, letting
+    /// users signal their own processes as polkit allows
+    #[cfg(feature = "dbus")]
+    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "process_names"))]
+    pub daemon: bool,
+
+    /// With --daemon, accept at most N kill requests per interval (10/s,
+    /// 5/30s, 100/m)
+    #[cfg(feature = "dbus")]
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_name = "N/INTERVAL",
+            requires = "daemon",
+            conflicts_with = "process_names"
+        )
+    )]
+    pub rate_limit: Option<crate::ratelimit::RateLimit>,
+
+    /// Fork children, then check that faulx matches them by the names they
+    /// give themselves, signals them and waits for them as it should
+    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "process_names"))]
+    pub self_test: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    #[cfg(feature = "json")]
+    Json,
+}
+
+/// Options selecting which processes are acted upon and in which order,
+/// shared by every command.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct FilterArgs {
     /// kill process group instead of process
-    #[arg(short = 'g', long)]
+    #[cfg_attr(feature = "clap", arg(short = 'g', long))]
     pub process_group: bool,
 
-    /// Report if the signal was successfully sent
-    #[arg(long)]
-    pub verbose: bool,
+    /// Only act on one process per process group, its leader if matched
+    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "process_group"))]
+    pub signal_group_leader_only: bool,
+
+    /// Also act on the descendants of matched processes
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, conflicts_with_all = ["process_group", "signal_group_leader_only"])
+    )]
+    pub tree: bool,
+
+    /// With --tree, go down at most N levels below a matched process: 1 for
+    /// its children but not their own
+    #[cfg_attr(feature = "clap", arg(long, value_name = "N", requires = "tree"))]
+    pub max_depth: Option<usize>,
+
+    /// With --tree, leave alone what is less than N levels below a matched
+    /// process: 1 to spare the process itself but not its children
+    #[cfg_attr(feature = "clap", arg(long, value_name = "N", requires = "tree"))]
+    pub min_depth: Option<usize>,
+
+    /// Only match processes listening on this local TCP or UDP port
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PORT[/PROTO]"))]
+    pub port: Option<PortSpec>,
+
+    /// Only match processes holding this unix domain socket
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub unix_socket: Option<PathBuf>,
+
+    /// Only match processes using files under this path (open files,
+    /// working or root directory, executable or memory mappings)
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub holding: Option<PathBuf>,
+
+    /// Only match processes of this user, by name or UID; repeat for any
+    /// of several users
+    #[cfg_attr(feature = "clap", arg(short = 'u', long, value_name = "USER"))]
+    pub user: Vec<String>,
+
+    /// Only match processes of the members of this group, by name or GID:
+    /// the users it lists, and those with it as their primary or a
+    /// supplementary group
+    #[cfg_attr(feature = "clap", arg(long, value_name = "GROUP"))]
+    pub users_from_group: Option<String>,
+
+    /// Leave out processes of this user; repeat for several
+    #[cfg_attr(feature = "clap", arg(long, value_name = "USER"))]
+    pub not_user: Vec<String>,
+
+    /// Leave out processes listening on this local TCP or UDP port
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PORT[/PROTO]"))]
+    pub not_port: Option<PortSpec>,
+
+    /// Leave out processes holding this unix domain socket
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub not_unix_socket: Option<PathBuf>,
+
+    /// Leave out processes using files under this path
+    #[cfg_attr(feature = "clap", arg(long, value_name = "PATH"))]
+    pub not_holding: Option<PathBuf>,
+
+    /// Only match processes for which this expression holds, as in
+    /// 'user=alice && rss>1G && comm~^work'; see the README for the fields
+    #[cfg_attr(
+        feature = "clap",
+        arg(id = "where", long = "where", value_name = "EXPR")
+    )]
+    pub where_clause: Option<Expr>,
+
+    /// Match processes passing any of the --user, --port, --unix-socket,
+    /// --holding, --where and --not-* filters, instead of all of them
+    #[cfg_attr(feature = "clap", arg(long, overrides_with = "all"))]
+    pub any: bool,
+
+    /// Match processes passing all of those filters, the default
+    #[cfg_attr(feature = "clap", arg(long, overrides_with = "any"))]
+    pub all: bool,
+
+    /// Only match processes of the login session faulx runs in, or of its
+    /// user's service manager, which desktops start applications with; the
+    /// default in graphical sessions
+    #[cfg_attr(feature = "clap", arg(long, overrides_with = "all_sessions"))]
+    pub session_scope: bool,
+
+    /// Match processes of every session, even from a graphical one
+    #[cfg_attr(feature = "clap", arg(long, overrides_with = "session_scope"))]
+    pub all_sessions: bool,
+
+    /// Also act on zombie processes, which are already dead and only go
+    /// away once their parent reaps them
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub include_zombies: bool,
+
+    /// Order in which matched processes are acted upon
+    #[cfg_attr(feature = "clap", arg(long, value_enum, default_value_t = Order::Pid))]
+    pub order: Order,
+
+    /// Within each pool of processes sharing a socket, like the master and
+    /// workers of a prefork server, act on the workers before the master,
+    /// so that it doesn't respawn them; in the --order given otherwise
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub reverse_order: bool,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "clap", derive(Subcommand))]
+pub enum Command {
+    /// Change the nice value of matched processes
+    Renice(ReniceArgs),
+    /// List running processes with their users, like a minimal ps
+    List(ListArgs),
+    /// Show matched processes and their descendants as a tree, like pstree
+    Tree(TreeArgs),
+    /// Check what could keep processes from being seen or signalled, such
+    /// as /proc mount options, and exit with 1 if something will
+    Doctor,
+    /// Wait until no process matches, or until one does
+    Waitfor(WaitforArgs),
+    /// Signal matched processes, wait for them to exit, then run a command
+    /// in place of faulx
+    Restart(RestartArgs),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct ReniceArgs {
+    /// New nice value
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            short = 'n',
+            long,
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i32).range(-20..=19),
+        )
+    )]
+    pub priority: i32,
+
+    /// process name to renice
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            required_unless_present_any = [
+                "port",
+                "unix_socket",
+                "holding",
+                "user",
+                "users_from_group",
+                "where",
+            ]
+        )
+    )]
+    pub process_names: Vec<String>,
+
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub filters: FilterArgs,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct ListArgs {
+    /// What to sort processes by; ties are sorted by PID
+    #[cfg_attr(feature = "clap", arg(long, value_enum, default_value_t = ListSort::Pid))]
+    pub sort: ListSort,
+
+    /// Sort numbers within names by value, so worker2 comes before worker10
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub version_sort: bool,
+
+    /// Reverse the order
+    #[cfg_attr(feature = "clap", arg(short = 'r', long))]
+    pub reverse: bool,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct TreeArgs {
+    /// Pattern matched against process names, as with pgrep
+    pub pattern: String,
+
+    /// Require the whole process name to match
+    #[cfg_attr(feature = "clap", arg(short = 'x', long))]
+    pub exact: bool,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct WaitforArgs {
+    /// Wait until no process matches
+    #[cfg_attr(feature = "clap", arg(long, required_unless_present = "present"))]
+    pub gone: bool,
+
+    /// Wait until a process matches
+    #[cfg_attr(feature = "clap", arg(long, conflicts_with = "gone"))]
+    pub present: bool,
+
+    /// Give up after this long, exiting with 1
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DURATION", value_parser = crate::units::parse_duration)
+    )]
+    pub timeout: Option<Duration>,
+
+    /// process name to wait for
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            required_unless_present_any = [
+                "port",
+                "unix_socket",
+                "holding",
+                "user",
+                "users_from_group",
+                "where",
+            ]
+        )
+    )]
+    pub process_names: Vec<String>,
+
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub filters: FilterArgs,
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct RestartArgs {
+    /// Pattern matched against process names, as with pgrep
+    pub pattern: String,
+
+    /// Require the whole process name to match
+    #[cfg_attr(feature = "clap", arg(short = 'x', long))]
+    pub exact: bool,
+
+    /// Send this signal instead of SIGTERM
+    #[cfg_attr(feature = "clap", arg(short = 's', long))]
+    pub signal: Option<String>,
+
+    /// Send SIGKILL to the processes still running after this long
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, value_name = "DURATION", value_parser = crate::units::parse_duration)
+    )]
+    pub timeout: Option<Duration>,
+
+    /// Command to run once every matched process has exited, even if none
+    /// was running
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            last = true,
+            required = true,
+            value_name = "COMMAND",
+            value_parser = clap::value_parser!(OsString)
+        )
+    )]
+    pub command: Vec<OsString>,
+}
+
+/// Options shared by `pgrep` and `pkill`.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Args))]
+pub struct PatternArgs {
+    /// Pattern matched against process names
+    pub pattern: String,
+
+    /// Only match processes whose name exactly matches the pattern
+    #[cfg_attr(feature = "clap", arg(short = 'x', long))]
+    pub exact: bool,
+
+    /// Act on the threads of the matched processes, by thread ID
+    #[cfg_attr(feature = "clap", arg(short = 'w', long))]
+    pub lightweight: bool,
+}
+
+/// Arguments when invoked as `pgrep`.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Parser))]
+#[cfg_attr(all(feature = "clap", feature = "color"), command(styles = STYLES))]
+#[cfg_attr(
+    feature = "clap",
+    command(name = "pgrep", version, about = "List processes matching a pattern")
+)]
+pub struct PgrepArgs {
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub pattern: PatternArgs,
+
+    /// List the process name as well as the PID
+    #[cfg_attr(feature = "clap", arg(short = 'l', long))]
+    pub list_name: bool,
+
+    /// Separate the PIDs with this string instead of a newline
+    #[cfg_attr(feature = "clap", arg(short = 'd', long, value_name = "STR"))]
+    pub delimiter: Option<String>,
+
+    /// Also show when each process started: how long ago, or as an ISO 8601
+    /// time
+    #[cfg_attr(
+        feature = "clap",
+        arg(
+            long,
+            value_enum,
+            value_name = "FORMAT",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "human",
+        )
+    )]
+    pub time_format: Option<TimeFormat>,
+
+    /// Print only the number of matching processes
+    #[cfg_attr(
+        feature = "clap",
+        arg(short = 'c', long, conflicts_with_all = ["list_name", "delimiter", "print0"])
+    )]
+    pub count: bool,
+
+    /// Terminate each PID with a NUL, for `xargs -0`
+    #[cfg_attr(feature = "clap", arg(short = '0', long, conflicts_with = "delimiter"))]
+    pub print0: bool,
+}
+
+/// Arguments when invoked as `pkill`.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "clap", derive(Parser))]
+#[cfg_attr(all(feature = "clap", feature = "color"), command(styles = STYLES))]
+#[cfg_attr(
+    feature = "clap",
+    command(name = "pkill", version, about = "Signal processes matching a pattern")
+)]
+pub struct PkillArgs {
+    #[cfg_attr(feature = "clap", command(flatten))]
+    pub pattern: PatternArgs,
+
+    /// Send this signal instead of SIGTERM
+    #[cfg_attr(feature = "clap", arg(long))]
+    pub signal: Option<String>,
+
+    /// Print the name and PID of every process signalled
+    #[cfg_attr(feature = "clap", arg(short = 'e', long))]
+    pub echo: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "clap")]
+    fn test_with_env_names_every_option() {
+        use clap::CommandFactory;
+
+        let cmd = with_env(FaulxArgs::command());
+        let env = |cmd: &clap::Command, id: &str| {
+            cmd.get_arguments()
+                .find(|arg| arg.get_id() == id)
+                .and_then(|arg| arg.get_env().map(ToOwned::to_owned))
+        };
+        assert_eq!(env(&cmd, "signal"), Some("FAULX_SIGNAL".into()));
+        assert_eq!(
+            env(&cmd, "taskset_threads"),
+            Some("FAULX_TASKSET_THREADS".into())
+        );
+        assert_eq!(env(&cmd, "process_names"), None);
+        let renice = cmd.find_subcommand("renice").unwrap();
+        assert_eq!(env(renice, "priority"), Some("FAULX_PRIORITY".into()));
+    }
+
+    #[test]
+    fn test_personality_from_argv0() {
+        let of = |s: &str| Personality::from_argv0(OsStr::new(s));
+        assert_eq!(of("/usr/bin/pkill"), Personality::Pkill);
+        assert_eq!(of("pgrep"), Personality::Pgrep);
+        assert_eq!(of("killall"), Personality::Killall);
+        assert_eq!(of("./target/release/faulx"), Personality::Killall);
+    }
+
+    #[test]
+    fn test_expand_signal_shorthand() {
+        let expand = |args: &[&str]| -> Vec<String> {
+            expand_signal_shorthand(args.iter().map(OsString::from))
+                .into_iter()
+                .map(|a| a.into_string().unwrap())
+                .collect()
+        };
+        assert_eq!(
+            expand(&["pkill", "-9", "-HUP", "-x", "--", "-KILL"]),
+            vec!["pkill", "--signal=9", "--signal=HUP", "-x", "--", "-KILL"]
+        );
+        assert_eq!(
+            expand(&["killall", "-l", "-q"]),
+            vec!["killall", "-l", "-q"]
+        );
+    }
 }
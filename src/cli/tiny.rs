@@ -0,0 +1,587 @@
+//! Minimal hand-rolled parser for the core killall flags, used instead of
+//! clap with the `tiny-cli` feature to keep static binaries small.
+
+use std::{env, ffi::OsString, fmt, process};
+
+use super::{FaulxArgs, PatternArgs, PgrepArgs, PkillArgs, env_name};
+use crate::expr::Expr;
+
+const USAGE: &str = concat!(
+    "Usage: ",
+    env!("CARGO_CRATE_NAME"),
+    " [OPTIONS] <PROCESS_NAMES>..."
+);
+const PGREP_USAGE: &str = "Usage: pgrep [OPTIONS] <PATTERN>";
+const PKILL_USAGE: &str = "Usage: pkill [OPTIONS] <PATTERN>";
+
+const HELP: &str = "
+Arguments:
+  <PROCESS_NAMES>...     process name to kill
+
+Options:
+  -g, --process-group    kill process group instead of process
+  -l, --list             list all known signal names
+  -s, --signal <SIGNAL>  Send this signal instead of SIGTERM
+      --pid <PID>        Signal these PIDs instead of matching names, which works without /proc
+  -q, --quiet            Don't print complaints
+      --verbose          Report if the signal was successfully sent
+      --echo             Print one line per process signalled, like `pkill -e`
+  -w, --wait             Wait for all signalled processes to die
+      --reason <TEXT>    Why; required to act on processes of other users where /etc/faulx/policy says so
+      --session-scope    Only match processes of the login session faulx runs in; the default in graphical sessions
+      --all-sessions     Match processes of every session, even from a graphical one
+      --renice <N>       Set the nice value (-20 to 19) of matched processes instead of signalling them
+      --where <EXPR>     Only match processes for which this expression holds, as in 'user=alice && rss>1G'
+  -h, --help             Print help
+  -V, --version          Print version";
+
+const PGREP_HELP: &str = "
+Options:
+  -x, --exact        Only match processes whose name exactly matches the pattern
+  -w, --lightweight  Act on the threads of the matched processes, by thread ID
+  -l, --list-name    List the process name as well as the PID
+  -h, --help         Print help
+  -V, --version      Print version";
+
+const PKILL_HELP: &str = "
+Options:
+  -x, --exact              Only match processes whose name exactly matches the pattern
+  -w, --lightweight        Act on the threads of the matched processes, by thread ID
+  -SIGNAL, --signal <SIG>  Send this signal instead of SIGTERM
+  -e, --echo               Print the name and PID of every process signalled
+  -h, --help               Print help
+  -V, --version            Print version";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Help,
+    Version,
+    UnknownOption(String),
+    MissingValue(&'static str),
+    UnexpectedValue(String),
+    InvalidPid(String),
+    InvalidValue {
+        option: &'static str,
+        value: String,
+        error: String,
+    },
+    PidWithNames,
+    MissingProcessName,
+    MissingPattern,
+    InvalidUnicode,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Help => f.write_str("help requested"),
+            Self::Version => f.write_str("version requested"),
+            Self::UnknownOption(opt) => write!(f, "unexpected argument '{opt}' found"),
+            Self::MissingValue(opt) => {
+                write!(f, "a value is required for '{opt}' but none was supplied")
+            }
+            Self::UnexpectedValue(opt) => write!(f, "unexpected value for '{opt}'"),
+            Self::InvalidPid(value) => write!(f, "invalid value '{value}' for '--pid'"),
+            Self::InvalidValue {
+                option,
+                value,
+                error,
+            } => write!(f, "invalid value '{value}' for '{option}': {error}"),
+            Self::PidWithNames => {
+                f.write_str("the argument '--pid <PID>' cannot be used with '<PROCESS_NAMES>...'")
+            }
+            Self::MissingProcessName => f.write_str(
+                "the following required arguments were not provided:\n  <PROCESS_NAMES>...",
+            ),
+            Self::MissingPattern => {
+                f.write_str("the following required arguments were not provided:\n  <PATTERN>")
+            }
+            Self::InvalidUnicode => {
+                f.write_str("invalid UTF-8 was detected in one or more arguments")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The value of a long option, given as `--option=value` or as the next
+/// argument.
+fn option_value(
+    value: Option<&str>,
+    args: &mut impl Iterator<Item = Result<String, Error>>,
+    option: &'static str,
+) -> Result<String, Error> {
+    match value {
+        Some(value) => Ok(value.to_owned()),
+        None => args.next().ok_or(Error::MissingValue(option))?,
+    }
+}
+
+/// A nice value for `--renice`, from -20 to 19 like clap checks.
+fn niceness(value: &str) -> Result<i32, String> {
+    let niceness: i32 = value.parse().map_err(|e| format!("{e}"))?;
+    if (-20..=19).contains(&niceness) {
+        Ok(niceness)
+    } else {
+        Err(format!("{niceness} is not in -20..=19"))
+    }
+}
+
+/// Parses the long option `name` into `parsed` if it takes a value,
+/// returning whether it did.
+fn parse_long_value(
+    parsed: &mut FaulxArgs,
+    name: &str,
+    value: Option<&str>,
+    args: &mut impl Iterator<Item = Result<String, Error>>,
+) -> Result<bool, Error> {
+    match name {
+        "signal" => parsed.signal = Some(option_value(value, args, "--signal")?),
+        "reason" => parsed.reason = Some(option_value(value, args, "--reason")?),
+        "where" => {
+            let value = option_value(value, args, "--where")?;
+            let expr = value.parse::<Expr>().map_err(|error| Error::InvalidValue {
+                option: "--where <EXPR>",
+                value: value.clone(),
+                error,
+            })?;
+            parsed.filters.where_clause = Some(expr);
+        }
+        "renice" => {
+            let value = option_value(value, args, "--renice")?;
+            let niceness = niceness(&value).map_err(|error| Error::InvalidValue {
+                option: "--renice <N>",
+                value,
+                error,
+            })?;
+            parsed.renice = Some(niceness);
+        }
+        "pid" => {
+            let value = option_value(value, args, "--pid")?;
+            for pid in value.split(',') {
+                let pid = pid.parse().map_err(|_| Error::InvalidPid(pid.to_owned()))?;
+                parsed.pids.push(pid);
+            }
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Parses `args`, whose first item is the program name.
+pub fn try_parse_from<I, T>(args: I) -> Result<FaulxArgs, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let mut args = args
+        .into_iter()
+        .skip(1)
+        .map(|arg| arg.into().into_string().map_err(|_| Error::InvalidUnicode));
+    let mut parsed = FaulxArgs::default();
+    let mut positional_only = false;
+
+    while let Some(arg) = args.next() {
+        let arg = arg?;
+        if positional_only || arg == "-" || !arg.starts_with('-') {
+            parsed.process_names.push(arg);
+        } else if arg == "--" {
+            positional_only = true;
+        } else if let Some(long) = arg.strip_prefix("--") {
+            let (name, value) = long
+                .split_once('=')
+                .map_or((long, None), |(n, v)| (n, Some(v)));
+            if parse_long_value(&mut parsed, name, value, &mut args)? {
+                continue;
+            }
+            if value.is_some() {
+                return Err(Error::UnexpectedValue(format!("--{name}")));
+            }
+            match name {
+                "list" => parsed.list = true,
+                "quiet" => parsed.quiet = true,
+                "process-group" => parsed.filters.process_group = true,
+                "verbose" => parsed.verbose = true,
+                "echo" => parsed.echo = true,
+                "wait" => parsed.wait = true,
+                "session-scope" => {
+                    parsed.filters.session_scope = true;
+                    parsed.filters.all_sessions = false;
+                }
+                "all-sessions" => {
+                    parsed.filters.all_sessions = true;
+                    parsed.filters.session_scope = false;
+                }
+                "help" => return Err(Error::Help),
+                "version" => return Err(Error::Version),
+                _ => return Err(Error::UnknownOption(arg)),
+            }
+        } else {
+            let flags = &arg[1..];
+            for (i, flag) in flags.char_indices() {
+                match flag {
+                    'l' => parsed.list = true,
+                    'q' => parsed.quiet = true,
+                    'g' => parsed.filters.process_group = true,
+                    'w' => parsed.wait = true,
+                    'h' => return Err(Error::Help),
+                    'V' => return Err(Error::Version),
+                    's' => {
+                        let rest = &flags[i + 1..];
+                        parsed.signal = Some(if rest.is_empty() {
+                            args.next().ok_or(Error::MissingValue("-s"))??
+                        } else {
+                            rest.to_owned()
+                        });
+                        break;
+                    }
+                    _ => return Err(Error::UnknownOption(format!("-{flag}"))),
+                }
+            }
+        }
+    }
+
+    if !parsed.pids.is_empty() && !parsed.process_names.is_empty() {
+        return Err(Error::PidWithNames);
+    }
+    if parsed.process_names.is_empty() && !parsed.list && parsed.pids.is_empty() {
+        return Err(Error::MissingProcessName);
+    }
+    Ok(parsed)
+}
+
+/// Shared parser for `pgrep` and `pkill`: `-x`, plus `-l` for pgrep or
+/// `--signal` and `-e` for pkill. `-l` and `-e` both print names, and set the
+/// same flag.
+fn parse_pattern_tool<I, T>(
+    args: I,
+    pkill: bool,
+) -> Result<(PatternArgs, bool, Option<String>), Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let mut pattern = None;
+    let mut exact = false;
+    let mut lightweight = false;
+    let mut with_name = false;
+    let mut signal = None;
+    let mut positional_only = false;
+    let mut args = args
+        .into_iter()
+        .skip(1)
+        .map(|arg| arg.into().into_string().map_err(|_| Error::InvalidUnicode));
+
+    while let Some(arg) = args.next() {
+        let arg = arg?;
+        match arg.as_str() {
+            _ if positional_only || arg == "-" || !arg.starts_with('-') => {
+                if pattern.replace(arg.clone()).is_some() {
+                    return Err(Error::UnknownOption(arg));
+                }
+            }
+            "--" => positional_only = true,
+            "-x" | "--exact" => exact = true,
+            "-w" | "--lightweight" => lightweight = true,
+            "-l" | "--list-name" if !pkill => with_name = true,
+            "-e" | "--echo" if pkill => with_name = true,
+            "--signal" if pkill => {
+                signal = Some(args.next().ok_or(Error::MissingValue("--signal"))??);
+            }
+            "-h" | "--help" => return Err(Error::Help),
+            "-V" | "--version" => return Err(Error::Version),
+            _ => match arg.strip_prefix("--signal=") {
+                Some(value) if pkill => signal = Some(value.to_owned()),
+                _ => return Err(Error::UnknownOption(arg)),
+            },
+        }
+    }
+
+    let pattern = pattern.ok_or(Error::MissingPattern)?;
+    let pattern = PatternArgs {
+        pattern,
+        exact,
+        lightweight,
+    };
+    Ok((pattern, with_name, signal))
+}
+
+pub fn try_parse_pgrep_from<I, T>(args: I) -> Result<PgrepArgs, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let (pattern, list_name, _) = parse_pattern_tool(args, false)?;
+    Ok(PgrepArgs {
+        pattern,
+        list_name,
+        ..PgrepArgs::default()
+    })
+}
+
+pub fn try_parse_pkill_from<I, T>(args: I) -> Result<PkillArgs, Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let (pattern, echo, signal) = parse_pattern_tool(args, true)?;
+    Ok(PkillArgs {
+        pattern,
+        signal,
+        echo,
+    })
+}
+
+/// Whether the environment variable for the flag `id` is set to a true
+/// value, with the spellings clap accepts.
+fn env_flag(var: &impl Fn(&str) -> Option<String>, id: &str) -> bool {
+    var(&env_name(id)).is_some_and(|value| {
+        matches!(
+            value.to_ascii_lowercase().as_str(),
+            "y" | "yes" | "t" | "true" | "on" | "1"
+        )
+    })
+}
+
+/// Fills options not given on the command line from `FAULX_*` variables,
+/// looked up with `var`, like the clap parser does.
+fn apply_env(args: &mut FaulxArgs, var: impl Fn(&str) -> Option<String>) {
+    if args.signal.is_none() {
+        args.signal = var(&env_name("signal"));
+    }
+    if args.reason.is_none() {
+        args.reason = var(&env_name("reason"));
+    }
+    args.quiet |= env_flag(&var, "quiet");
+    args.verbose |= env_flag(&var, "verbose");
+    args.echo |= env_flag(&var, "echo");
+    args.wait |= env_flag(&var, "wait");
+    args.filters.process_group |= env_flag(&var, "process_group");
+    args.filters.session_scope |= env_flag(&var, "session_scope");
+    args.filters.all_sessions |= env_flag(&var, "all_sessions");
+}
+
+/// Returns the parsed arguments, or prints help or an error and exits like
+/// clap does when they are not a valid invocation.
+fn exit_on_error<A>(result: Result<A, Error>, usage: &str, help: &str) -> A {
+    match result {
+        Ok(args) => args,
+        Err(Error::Help) => {
+            println!("{}\n\n{usage}\n{help}", env!("CARGO_PKG_DESCRIPTION"));
+            process::exit(0);
+        }
+        Err(Error::Version) => {
+            println!("{} {}", env!("CARGO_CRATE_NAME"), env!("CARGO_PKG_VERSION"));
+            process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{usage}\n\nFor more information, try '--help'.");
+            process::exit(2);
+        }
+    }
+}
+
+#[must_use]
+pub fn parse_from(args: Vec<OsString>) -> FaulxArgs {
+    let mut args = exit_on_error(try_parse_from(args), USAGE, HELP);
+    apply_env(&mut args, |name| env::var(name).ok());
+    args
+}
+
+#[must_use]
+pub fn parse_pgrep_from(args: Vec<OsString>) -> PgrepArgs {
+    let mut args = exit_on_error(try_parse_pgrep_from(args), PGREP_USAGE, PGREP_HELP);
+    let var = |name: &str| env::var(name).ok();
+    args.pattern.exact |= env_flag(&var, "exact");
+    args.pattern.lightweight |= env_flag(&var, "lightweight");
+    args.list_name |= env_flag(&var, "list_name");
+    args
+}
+
+#[must_use]
+pub fn parse_pkill_from(args: Vec<OsString>) -> PkillArgs {
+    let mut args = exit_on_error(try_parse_pkill_from(args), PKILL_USAGE, PKILL_HELP);
+    let var = |name: &str| env::var(name).ok();
+    args.pattern.exact |= env_flag(&var, "exact");
+    args.pattern.lightweight |= env_flag(&var, "lightweight");
+    args.echo |= env_flag(&var, "echo");
+    if args.signal.is_none() {
+        args.signal = var(&env_name("signal"));
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<FaulxArgs, Error> {
+        try_parse_from(std::iter::once("faulx").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_tiny_parses_core_flags() {
+        let args = parse(&["-qgw", "--verbose", "-s", "KILL", "nginx", "--", "-odd"]).unwrap();
+        assert!(args.quiet && args.filters.process_group && args.wait && args.verbose);
+        assert_eq!(args.signal.as_deref(), Some("KILL"));
+        assert_eq!(args.process_names, vec!["nginx", "-odd"]);
+    }
+
+    /// The tiny parser reads what clap reads from the same command lines.
+    #[cfg(feature = "clap")]
+    #[test]
+    fn test_tiny_matches_clap() {
+        use clap::Parser as _;
+
+        for args in [
+            &["-qgw", "--verbose", "-s", "KILL", "nginx"][..],
+            &["--pid", "10,11", "--reason=INC-1"],
+            &["--echo", "--all-sessions", "x"],
+            &["--where", "pid=1", "-l"],
+            &["--where=user=alice && rss>1G", "x"],
+            &["--renice", "-5", "x"],
+            &["--renice=19", "x"],
+        ] {
+            let argv = std::iter::once("faulx").chain(args.iter().copied());
+            let mut clap = FaulxArgs::try_parse_from(argv).unwrap();
+            // Only used with --host, which is not in the tiny parser.
+            clap.remote_program.clear();
+            assert_eq!(
+                format!("{:?}", parse(args).unwrap()),
+                format!("{clap:?}"),
+                "{args:?}"
+            );
+        }
+        assert_eq!(
+            parse(&["--where", "pid=", "x"]).unwrap_err().to_string(),
+            "invalid value 'pid=' for '--where <EXPR>': expected a value at column 5"
+        );
+    }
+
+    #[test]
+    fn test_tiny_session_flags() {
+        let args = parse(&["--session-scope", "--all-sessions", "x"]).unwrap();
+        assert!(args.filters.all_sessions && !args.filters.session_scope);
+        let args = parse(&["--all-sessions", "--session-scope", "x"]).unwrap();
+        assert!(args.filters.session_scope && !args.filters.all_sessions);
+
+        let mut args = parse(&["x"]).unwrap();
+        apply_env(&mut args, |name| {
+            (name == "FAULX_ALL_SESSIONS").then(|| "yes".to_owned())
+        });
+        assert!(args.filters.all_sessions);
+    }
+
+    #[test]
+    fn test_tiny_pids() {
+        assert_eq!(
+            parse(&["--pid", "10,11", "--pid=12"]).unwrap().pids,
+            [10, 11, 12]
+        );
+        assert_eq!(
+            parse(&["--pid", "ten"]).unwrap_err(),
+            Error::InvalidPid("ten".to_owned())
+        );
+        assert_eq!(
+            parse(&["--pid=-1"]).unwrap_err(),
+            Error::InvalidPid("-1".to_owned())
+        );
+        assert_eq!(
+            parse(&["--pid=99999999"]).unwrap_err(),
+            Error::InvalidPid("99999999".to_owned())
+        );
+        assert_eq!(
+            parse(&["--pid", "10", "nginx"]).unwrap_err(),
+            Error::PidWithNames
+        );
+    }
+
+    #[test]
+    fn test_tiny_signal_forms() {
+        assert_eq!(
+            parse(&["-sHUP", "x"]).unwrap().signal.as_deref(),
+            Some("HUP")
+        );
+        assert_eq!(
+            parse(&["--signal=INT", "x"]).unwrap().signal.as_deref(),
+            Some("INT")
+        );
+        assert_eq!(
+            parse(&["--signal", "USR1", "x"]).unwrap().signal.as_deref(),
+            Some("USR1")
+        );
+        assert_eq!(parse(&["x", "-s"]).unwrap_err(), Error::MissingValue("-s"));
+        assert_eq!(
+            parse(&["--reason", "INC-1234", "x"])
+                .unwrap()
+                .reason
+                .as_deref(),
+            Some("INC-1234")
+        );
+    }
+
+    #[test]
+    fn test_tiny_errors() {
+        assert_eq!(parse(&[]).unwrap_err(), Error::MissingProcessName);
+        assert!(parse(&["-l"]).unwrap().list);
+        assert_eq!(
+            parse(&["-x", "a"]).unwrap_err(),
+            Error::UnknownOption("-x".to_owned())
+        );
+        assert_eq!(parse(&["--renice=-20", "a"]).unwrap().renice, Some(-20));
+        assert_eq!(
+            parse(&["--renice", "1000", "a"]).unwrap_err().to_string(),
+            "invalid value '1000' for '--renice <N>': 1000 is not in -20..=19"
+        );
+        assert!(matches!(
+            parse(&["--renice", "low", "a"]).unwrap_err(),
+            Error::InvalidValue { .. }
+        ));
+        assert_eq!(
+            parse(&["--quiet=yes", "a"]).unwrap_err(),
+            Error::UnexpectedValue("--quiet".to_owned())
+        );
+        assert_eq!(parse(&["-h"]).unwrap_err(), Error::Help);
+    }
+
+    #[test]
+    fn test_tiny_env_defaults() {
+        let var = |name: &str| match name {
+            "FAULX_SIGNAL" => Some("KILL".to_owned()),
+            "FAULX_VERBOSE" => Some("1".to_owned()),
+            "FAULX_WAIT" => Some("off".to_owned()),
+            _ => None,
+        };
+        let mut args = parse(&["x"]).unwrap();
+        apply_env(&mut args, var);
+        assert_eq!(args.signal.as_deref(), Some("KILL"));
+        assert!(args.verbose && !args.wait && !args.quiet);
+
+        let mut args = parse(&["-s", "HUP", "x"]).unwrap();
+        apply_env(&mut args, var);
+        assert_eq!(args.signal.as_deref(), Some("HUP"));
+    }
+
+    #[test]
+    fn test_tiny_pgrep_and_pkill() {
+        let pgrep = try_parse_pgrep_from(["pgrep", "-lx", "nginx"]);
+        assert_eq!(pgrep.unwrap_err(), Error::UnknownOption("-lx".to_owned()));
+
+        let pgrep = try_parse_pgrep_from(["pgrep", "-l", "-x", "nginx"]).unwrap();
+        assert!(pgrep.list_name && pgrep.pattern.exact);
+        assert_eq!(pgrep.pattern.pattern, "nginx");
+
+        let pkill = try_parse_pkill_from(["pkill", "--signal=9", "-e", "sleep"]).unwrap();
+        assert_eq!(pkill.signal.as_deref(), Some("9"));
+        assert!(pkill.echo);
+        assert_eq!(
+            try_parse_pkill_from(["pkill", "-l", "x"]).unwrap_err(),
+            Error::UnknownOption("-l".to_owned())
+        );
+        assert_eq!(
+            try_parse_pgrep_from(["pgrep"]).unwrap_err(),
+            Error::MissingPattern
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Whether output is colored, decided in one place for every formatter.
+//!
+//! `--color` comes first. Under `auto`, the default, `NO_COLOR` turns
+//! colors off and `CLICOLOR_FORCE` on, as <https://no-color.org> and
+//! <https://bixense.com/clicolors> have it; otherwise a stream is colored
+//! only when it is a terminal, other than a dumb one. Without the `color`
+//! feature nothing is.
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fmt,
+    io::{self, IsTerminal},
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+/// `--color`: when to color output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ColorChoice {
+    /// When the output is a terminal, unless the environment says otherwise
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Whether to color a stream that is a terminal or not, as `terminal`
+    /// tells, reading the environment through `var`.
+    pub fn enabled(self, terminal: bool, var: impl Fn(&str) -> Option<OsString>) -> bool {
+        let set = |name| var(name).is_some_and(|value| !value.is_empty());
+        let is = |name, value: &str| var(name).is_some_and(|v| v == *value);
+        cfg!(feature = "color")
+            && match self {
+                Self::Always => true,
+                Self::Never => false,
+                Self::Auto if set("NO_COLOR") => false,
+                Self::Auto if set("CLICOLOR_FORCE") && !is("CLICOLOR_FORCE", "0") => true,
+                Self::Auto => terminal && !is("CLICOLOR", "0") && !is("TERM", "dumb"),
+            }
+    }
+
+    /// The choice `--color` makes in `args`, which clap needs before it
+    /// parses them to color its own help and errors.
+    #[must_use]
+    pub fn requested(args: &[OsString]) -> Self {
+        let mut choice = None;
+        let mut args = args.iter().map(OsString::as_os_str);
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                break;
+            }
+            let value = if arg == "--color" {
+                args.next()
+            } else {
+                arg.to_str()
+                    .and_then(|arg| arg.strip_prefix("--color="))
+                    .map(OsStr::new)
+            };
+            if let Some(value) = value {
+                choice = Self::named(value);
+            }
+        }
+        choice
+            .or_else(|| Self::named(&env::var_os("FAULX_COLOR")?))
+            .unwrap_or_default()
+    }
+
+    fn named(value: &OsStr) -> Option<Self> {
+        match value.to_str()? {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+}
+
+/// What a piece of output is, which decides its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// A process name.
+    Name,
+    /// What was done to a process.
+    Success,
+    /// What couldn't be.
+    Failure,
+    /// What was left alone.
+    Skipped,
+}
+
+impl Style {
+    const fn code(self) -> &'static str {
+        match self {
+            Self::Name => "1",
+            Self::Success => "32",
+            Self::Failure => "31",
+            Self::Skipped => "33",
+        }
+    }
+}
+
+/// Paints text for one stream, or leaves it plain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    #[must_use]
+    pub const fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    #[must_use]
+    pub const fn paint<T: fmt::Display>(self, style: Style, text: T) -> Painted<T> {
+        Painted {
+            style: if self.enabled { Some(style) } else { None },
+            text,
+        }
+    }
+}
+
+/// Text to write, with the escape sequences of its style if any.
+#[derive(Debug, Clone, Copy)]
+pub struct Painted<T> {
+    style: Option<Style>,
+    text: T,
+}
+
+impl<T: fmt::Display> fmt::Display for Painted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.style {
+            Some(style) => write!(f, "\x1b[{}m{}\x1b[0m", style.code(), self.text),
+            None => self.text.fmt(f),
+        }
+    }
+}
+
+/// The palettes of stdout and stderr, only one of which may be a terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Palettes {
+    pub out: Palette,
+    pub err: Palette,
+}
+
+impl Palettes {
+    /// The palettes `choice` gives this process's stdout and stderr.
+    #[must_use]
+    pub fn new(choice: ColorChoice) -> Self {
+        let palette = |terminal| Palette::new(choice.enabled(terminal, |name| env::var_os(name)));
+        Self {
+            out: palette(io::stdout().is_terminal()),
+            err: palette(io::stderr().is_terminal()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+        let vars: Vec<(String, OsString)> = vars
+            .iter()
+            .map(|&(name, value)| (name.to_owned(), value.into()))
+            .collect();
+        move |name| {
+            vars.iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, value)| value.clone())
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "color")]
+    fn test_enabled() {
+        let auto = ColorChoice::Auto;
+        assert!(auto.enabled(true, vars(&[])));
+        assert!(!auto.enabled(false, vars(&[])));
+        assert!(!auto.enabled(true, vars(&[("NO_COLOR", "1")])));
+        // An empty NO_COLOR is as good as unset.
+        assert!(auto.enabled(true, vars(&[("NO_COLOR", "")])));
+        assert!(auto.enabled(false, vars(&[("CLICOLOR_FORCE", "1")])));
+        assert!(!auto.enabled(false, vars(&[("CLICOLOR_FORCE", "0")])));
+        assert!(!auto.enabled(true, vars(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")])));
+        assert!(!auto.enabled(true, vars(&[("CLICOLOR", "0")])));
+        assert!(!auto.enabled(true, vars(&[("TERM", "dumb")])));
+
+        assert!(ColorChoice::Always.enabled(false, vars(&[("NO_COLOR", "1")])));
+        assert!(!ColorChoice::Never.enabled(true, vars(&[("CLICOLOR_FORCE", "1")])));
+    }
+
+    #[test]
+    #[cfg(not(feature = "color"))]
+    fn test_enabled() {
+        assert!(!ColorChoice::Always.enabled(true, vars(&[])));
+    }
+
+    #[test]
+    fn test_requested() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            ColorChoice::requested(&args(&["faulx", "--color", "never", "sleep"])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            ColorChoice::requested(&args(&["faulx", "--color=never", "--color=always"])),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            ColorChoice::requested(&args(&["faulx", "--", "--color=never"])),
+            ColorChoice::Auto
+        );
+    }
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(
+            Palette::new(true)
+                .paint(Style::Failure, "Failed")
+                .to_string(),
+            "\x1b[31mFailed\x1b[0m"
+        );
+        assert_eq!(
+            Palette::new(false)
+                .paint(Style::Failure, "Failed")
+                .to_string(),
+            "Failed"
+        );
+    }
+}
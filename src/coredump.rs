@@ -0,0 +1,427 @@
+//! `--core`: signals processes with SIGQUIT or SIGABRT so that they dump
+//! core, then finds the dumps where `core_pattern` sends them, in a file
+//! named after the process or through systemd-coredump.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::{actions::Action, pid::Pid, processes::ProcessInfo, signals::Signal};
+
+/// Where systemd-coredump stores dumps.
+pub const SYSTEMD_COREDUMP_DIR: &str = "/var/lib/systemd/coredump";
+
+/// How long a dump may take to show up once its process is gone, as a
+/// pipe handler compresses and stores it.
+pub const GRACE: Duration = Duration::from_secs(5);
+
+/// How often dumps are looked for meanwhile.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The signal `--core` sends, both of which dump core by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum CoreSignal {
+    /// SIGQUIT, which programs rarely catch.
+    #[default]
+    Quit,
+    /// SIGABRT, for those that catch SIGQUIT, as the JVM does.
+    Abrt,
+}
+
+impl CoreSignal {
+    #[must_use]
+    pub const fn signal(self) -> Signal {
+        match self {
+            Self::Quit => Signal::QUIT,
+            Self::Abrt => Signal::ABRT,
+        }
+    }
+}
+
+/// What a process asked to dump core is known by, read before it dies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dumping {
+    pub pid: Pid,
+    pub comm: Vec<u8>,
+    uid: Option<u32>,
+    /// Where relative patterns put the dump.
+    cwd: Option<PathBuf>,
+    /// The soft `RLIMIT_CORE`, unless unlimited.
+    limit: Option<u64>,
+    /// When it was signalled, so that older dumps aren't taken for its.
+    since: SystemTime,
+}
+
+impl Dumping {
+    #[must_use]
+    pub fn of(process: &ProcessInfo) -> Self {
+        let limit = fs::read_to_string(process.path("limits"))
+            .ok()
+            .and_then(|limits| {
+                limits
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Max core file size"))?
+                    .split_whitespace()
+                    .next()?
+                    .parse()
+                    .ok()
+            });
+        Self {
+            pid: process.pid,
+            comm: process.comm.to_vec(),
+            uid: process.uid(),
+            cwd: process.link("cwd").ok(),
+            limit,
+            // File times come from a coarser clock, and may be a little
+            // behind.
+            since: SystemTime::now() - Duration::from_millis(100),
+        }
+    }
+}
+
+/// Applies `inner`, remembering the processes it succeeded on, so that
+/// their dumps can be looked for once they are gone.
+pub struct CoreWatch<A> {
+    inner: A,
+    dumping: Arc<Mutex<Vec<Dumping>>>,
+}
+
+impl<A: Action> CoreWatch<A> {
+    #[must_use]
+    pub const fn new(inner: A, dumping: Arc<Mutex<Vec<Dumping>>>) -> Self {
+        Self { inner, dumping }
+    }
+}
+
+impl<A: Action> Action for CoreWatch<A> {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        let dumping = Dumping::of(process);
+        self.inner.apply(process)?;
+        self.dumping
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(dumping);
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+}
+
+/// Where the kernel sends core dumps, from `/proc/sys/kernel/core_pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorePattern {
+    /// A file name with `%` specifiers, relative to the working directory
+    /// of the process unless absolute. With `core_uses_pid`, the PID is
+    /// appended if the pattern lacks `%p`.
+    File { pattern: String, uses_pid: bool },
+    /// A program the dump is piped to, with its arguments.
+    Pipe(String),
+}
+
+/// Where the dump of a process went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Found {
+    File(PathBuf),
+    /// Nothing matching the pattern in that directory.
+    Missing(PathBuf),
+    /// Piped to a program faulx doesn't know where it keeps dumps.
+    Handler(String),
+    /// The process's `RLIMIT_CORE` is 0.
+    Disabled,
+}
+
+impl CorePattern {
+    /// Reads the pattern of the system under `root`, the `/proc` directory.
+    pub fn read(root: &Path) -> io::Result<Self> {
+        let pattern = fs::read_to_string(root.join("sys/kernel/core_pattern"))?;
+        let pattern = pattern.trim_end_matches('\n');
+        if let Some(program) = pattern.strip_prefix('|') {
+            return Ok(Self::Pipe(program.trim_start().to_owned()));
+        }
+        // As the kernel reads an empty pattern.
+        let pattern = if pattern.is_empty() { "core" } else { pattern };
+        let uses_pid = fs::read_to_string(root.join("sys/kernel/core_uses_pid"))
+            .is_ok_and(|value| value.trim() != "0");
+        Ok(Self::File {
+            pattern: pattern.to_owned(),
+            uses_pid,
+        })
+    }
+
+    /// Looks for the dump of `dumping`, in `coredump_dir` if it was piped to
+    /// systemd-coredump.
+    #[must_use]
+    pub fn find(&self, dumping: &Dumping, coredump_dir: &Path) -> Found {
+        if dumping.limit == Some(0) {
+            return Found::Disabled;
+        }
+        match self {
+            Self::Pipe(program) if program.contains("systemd-coredump") => {
+                find_in(coredump_dir, dumping, |name| {
+                    stored_by_systemd(name, dumping)
+                })
+            }
+            Self::Pipe(program) => Found::Handler(program.clone()),
+            Self::File { pattern, uses_pid } => {
+                let mut parts = expand(pattern, dumping);
+                if *uses_pid && !pattern.contains("%p") {
+                    parts.push(Part::Literal(format!(".{}", dumping.pid)));
+                }
+                let (dir, name) = split_dir(parts);
+                let dir = match (dir, &dumping.cwd) {
+                    (Some(dir), _) if dir.is_absolute() => dir,
+                    (Some(dir), Some(cwd)) => cwd.join(dir),
+                    (None, Some(cwd)) => cwd.clone(),
+                    (dir, None) => return Found::Missing(dir.unwrap_or_default()),
+                };
+                find_in(&dir, dumping, |file| matches(&name, file))
+            }
+        }
+    }
+}
+
+/// The first file in `dir` with a `matching` name written since `dumping`
+/// was signalled.
+fn find_in(dir: &Path, dumping: &Dumping, mut matching: impl FnMut(&str) -> bool) -> Found {
+    let found = fs::read_dir(dir).ok().and_then(|entries| {
+        entries.filter_map(Result::ok).find(|entry| {
+            entry.file_name().to_str().is_some_and(&mut matching)
+                && entry
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .is_ok_and(|modified| modified >= dumping.since)
+        })
+    });
+    found.map_or_else(
+        || Found::Missing(dir.to_path_buf()),
+        |entry| Found::File(entry.path()),
+    )
+}
+
+/// Whether systemd-coredump named a file for `dumping`, as in
+/// `core.nginx.33.<boot ID>.1234.1700000000000000.zst`.
+fn stored_by_systemd(name: &str, dumping: &Dumping) -> bool {
+    let Some(rest) = name.strip_prefix("core.") else {
+        return false;
+    };
+    let fields: Vec<&str> = rest.split('.').collect();
+    let pid = dumping.pid.to_string();
+    let uid = dumping.uid.map(|uid| uid.to_string());
+    // The command name may itself have dots: find the boot ID followed by
+    // the PID instead of counting fields.
+    fields.windows(3).any(|window| {
+        let [uid_field, boot_id, pid_field] = window else {
+            return false;
+        };
+        boot_id.len() == 32
+            && boot_id.bytes().all(|b| b.is_ascii_hexdigit())
+            && *pid_field == pid
+            && uid.as_deref().is_none_or(|uid| uid == *uid_field)
+    })
+}
+
+/// A piece of an expanded pattern: known text, or a specifier faulx can't
+/// know the value of, such as `%t`, which matches anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Any,
+}
+
+/// Expands the specifiers of `pattern` that `dumping` tells, as the kernel
+/// does in `core(5)`.
+fn expand(pattern: &str, dumping: &Dumping) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        let value = match chars.next() {
+            Some('%') => Some("%".to_owned()),
+            // The main thread, in this and the initial PID namespace alike
+            // from where faulx sees it.
+            Some('p' | 'P' | 'i' | 'I') => Some(dumping.pid.to_string()),
+            Some('u') => dumping.uid.map(|uid| uid.to_string()),
+            // The kernel replaces slashes, which can't be in file names.
+            Some('e') => Some(String::from_utf8_lossy(&dumping.comm).replace('/', "!")),
+            // The last `%` is dropped.
+            None => Some(String::new()),
+            Some(_) => None,
+        };
+        if let Some(value) = value {
+            literal.push_str(&value);
+        } else {
+            parts.push(Part::Literal(std::mem::take(&mut literal)));
+            parts.push(Part::Any);
+        }
+    }
+    parts.push(Part::Literal(literal));
+    parts
+}
+
+/// Splits an expanded pattern into its directory, if it has one, and the
+/// parts of the file name. Only the directory of a pattern whose
+/// specifiers are all in the file name is known.
+fn split_dir(mut parts: Vec<Part>) -> (Option<PathBuf>, Vec<Part>) {
+    let in_name = parts[1..]
+        .iter()
+        .all(|part| !matches!(part, Part::Literal(text) if text.contains('/')));
+    let mut dir = None;
+    if let Some(Part::Literal(first)) = parts.first_mut()
+        && let Some((head, tail)) = first.rsplit_once('/')
+        && in_name
+    {
+        dir = Some(PathBuf::from(if head.is_empty() { "/" } else { head }));
+        *first = tail.to_owned();
+    }
+    (dir, parts)
+}
+
+/// Whether `name` matches `parts`, wildcards taking as few characters as
+/// needed.
+fn matches(parts: &[Part], name: &str) -> bool {
+    match parts.split_first() {
+        None => name.is_empty(),
+        Some((Part::Literal(text), rest)) => name
+            .strip_prefix(text.as_str())
+            .is_some_and(|name| matches(rest, name)),
+        Some((Part::Any, rest)) => name
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([name.len()])
+            .any(|i| matches(rest, &name[i..])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, unique_test_dir};
+
+    fn dumping(cwd: &Path, limit: Option<u64>) -> Dumping {
+        Dumping {
+            pid: pid(1234),
+            comm: b"nginx".to_vec(),
+            uid: Some(33),
+            cwd: Some(cwd.to_path_buf()),
+            limit,
+            since: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_read() {
+        let tmp = unique_test_dir();
+        fs::create_dir_all(tmp.join("sys/kernel")).unwrap();
+        fs::write(
+            tmp.join("sys/kernel/core_pattern"),
+            "|/usr/lib/systemd/systemd-coredump %P %u %g %s %t %c %h\n",
+        )
+        .unwrap();
+        assert_eq!(
+            CorePattern::read(&tmp).unwrap(),
+            CorePattern::Pipe("/usr/lib/systemd/systemd-coredump %P %u %g %s %t %c %h".to_owned())
+        );
+        fs::write(tmp.join("sys/kernel/core_pattern"), "core\n").unwrap();
+        fs::write(tmp.join("sys/kernel/core_uses_pid"), "1\n").unwrap();
+        assert_eq!(
+            CorePattern::read(&tmp).unwrap(),
+            CorePattern::File {
+                pattern: "core".to_owned(),
+                uses_pid: true
+            }
+        );
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_find_file() {
+        let tmp = unique_test_dir();
+        fs::create_dir_all(tmp.join("crash")).unwrap();
+        let pattern = |pattern: String| CorePattern::File {
+            pattern,
+            uses_pid: false,
+        };
+        let absolute = pattern(format!("{}/crash/core.%e.%p.%t", tmp.display()));
+        assert_eq!(
+            absolute.find(&dumping(&tmp, None), &tmp),
+            Found::Missing(tmp.join("crash"))
+        );
+        fs::write(tmp.join("crash/core.nginx.1234.1700000000"), "").unwrap();
+        assert_eq!(
+            absolute.find(&dumping(&tmp, None), &tmp),
+            Found::File(tmp.join("crash/core.nginx.1234.1700000000"))
+        );
+        assert_eq!(
+            absolute.find(&dumping(&tmp, Some(0)), &tmp),
+            Found::Disabled
+        );
+        let stale = Dumping {
+            since: SystemTime::now() + Duration::from_secs(3600),
+            ..dumping(&tmp, None)
+        };
+        assert_eq!(
+            absolute.find(&stale, &tmp),
+            Found::Missing(tmp.join("crash"))
+        );
+
+        fs::write(tmp.join("core.1234"), "").unwrap();
+        let relative = CorePattern::File {
+            pattern: "core".to_owned(),
+            uses_pid: true,
+        };
+        assert_eq!(
+            relative.find(&dumping(&tmp, None), &tmp),
+            Found::File(tmp.join("core.1234"))
+        );
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_find_systemd() {
+        let tmp = unique_test_dir();
+        fs::create_dir_all(&tmp).unwrap();
+        let boot_id = "0123456789abcdef0123456789abcdef";
+        fs::write(tmp.join(format!("core.nginx.33.{boot_id}.999.1.zst")), "").unwrap();
+        let pipe = CorePattern::Pipe("/usr/lib/systemd/systemd-coredump %P".to_owned());
+        assert_eq!(
+            pipe.find(&dumping(&tmp, None), &tmp),
+            Found::Missing(tmp.clone())
+        );
+        let name = format!("core.my.app.33.{boot_id}.1234.1700000000000000.zst");
+        fs::write(tmp.join(&name), "").unwrap();
+        assert_eq!(
+            pipe.find(&dumping(&tmp, None), &tmp),
+            Found::File(tmp.join(name))
+        );
+        let apport = CorePattern::Pipe("/usr/share/apport/apport -p%p".to_owned());
+        assert_eq!(
+            apport.find(&dumping(&tmp, None), &tmp),
+            Found::Handler("/usr/share/apport/apport -p%p".to_owned())
+        );
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_matches() {
+        let parts = expand("core.%e.%p.%t", &dumping(Path::new("/"), None));
+        assert!(matches(&parts, "core.nginx.1234.1700000000"));
+        assert!(!matches(&parts, "core.nginx.12345.1700000000"));
+        assert!(matches(
+            &expand("%E%%", &dumping(Path::new("/"), None)),
+            "!usr!bin%"
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+//! `--daemon`: a kill broker on the system bus, with the `dbus` feature.
+//!
+//! Running as root, it lets unprivileged callers signal their own processes
+//! by name through `org.faulx.Killer.Kill`, once polkit authorizes the
+//! `org.faulx.kill` action for them. The bus and polkit configuration it
+//! needs is in `dist/`.
+
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+};
+
+use zbus::{
+    Connection, blocking::connection, fdo, interface, message::Header, proxy, zvariant::Value,
+};
+
+use crate::{
+    actions::Kill,
+    filters::{NameFilter, UserFilter},
+    pid::Pid,
+    pipeline::Pipeline,
+    processes::{ProcessInfo, Scanner},
+    ratelimit::{RateLimit, TokenBucket},
+    signals::parse_signal,
+};
+
+/// Well-known name the daemon owns on the system bus.
+pub const BUS_NAME: &str = "org.faulx.Killer";
+/// Path of the `org.faulx.Killer` object.
+pub const OBJECT_PATH: &str = "/org/faulx/Killer";
+/// polkit action a caller must be authorized for.
+pub const KILL_ACTION: &str = "org.faulx.kill";
+
+/// Lets polkit ask the user to authenticate if its policy requires it.
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+#[proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: &HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// The exported object.
+#[derive(Debug)]
+struct Killer {
+    root: Arc<Path>,
+    /// Bounds accepted `Kill` calls, from every caller together.
+    rate_limit: Option<Mutex<TokenBucket>>,
+}
+
+impl Killer {
+    /// Takes a token for one call, failing once the rate limit is exceeded.
+    fn admit(&self) -> fdo::Result<()> {
+        let Some(bucket) = &self.rate_limit else {
+            return Ok(());
+        };
+        let mut bucket = bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        if bucket.try_take() {
+            return Ok(());
+        }
+        let refused = bucket.refused();
+        drop(bucket);
+        // Log the first refusal of every power of two, not each one.
+        if refused.is_power_of_two() {
+            eprintln!("Rate limit exceeded: {refused} kill requests refused so far");
+        }
+        Err(fdo::Error::LimitsExceeded("rate limit exceeded".to_owned()))
+    }
+}
+
+#[interface(name = "org.faulx.Killer")]
+impl Killer {
+    /// Sends `signal` to the caller's processes named `name`, returning the
+    /// PIDs signalled.
+    async fn kill(
+        &self,
+        name: &str,
+        signal: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] connection: &Connection,
+    ) -> fdo::Result<Vec<i32>> {
+        let sender = header
+            .sender()
+            .ok_or_else(|| fdo::Error::AccessDenied("unknown caller".to_owned()))?;
+        self.admit()?;
+        let uid = fdo::DBusProxy::new(connection)
+            .await?
+            .get_connection_unix_user(sender.clone().into())
+            .await?;
+
+        let subject = (
+            "system-bus-name",
+            HashMap::from([("name", Value::from(sender.as_str()))]),
+        );
+        let (authorized, _, _) = AuthorityProxy::new(connection)
+            .await?
+            .check_authorization(
+                &subject,
+                KILL_ACTION,
+                &HashMap::new(),
+                ALLOW_USER_INTERACTION,
+                "",
+            )
+            .await?;
+        if !authorized {
+            return Err(fdo::Error::AccessDenied(format!(
+                "not authorized for {KILL_ACTION}"
+            )));
+        }
+
+        kill_own(&self.root, name, signal, uid)
+    }
+
+    /// How many `Kill` calls the rate limit refused.
+    #[zbus(property)]
+    fn refused(&self) -> u64 {
+        self.rate_limit.as_ref().map_or(0, |bucket| {
+            bucket
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .refused()
+        })
+    }
+}
+
+/// Signals the processes named `name` that belong to `uid`, never the
+/// daemon itself.
+fn kill_own(root: &Path, name: &str, signal: &str, uid: u32) -> fdo::Result<Vec<i32>> {
+    let signal = parse_signal(signal)
+        .ok_or_else(|| fdo::Error::InvalidArgs(format!("{signal}: unknown signal")))?;
+    let own_pid = Pid::this();
+    let pipeline = Pipeline::new(Scanner::new(root))
+        .filter(NameFilter::new(name))
+        .filter(UserFilter::new(vec![uid]))
+        .filter(move |p: &ProcessInfo| p.pid != own_pid);
+    let outcomes = pipeline
+        .run(&mut Kill::new(signal))
+        .map_err(|e| fdo::Error::IOError(e.to_string()))?;
+    if outcomes.is_empty() {
+        return Err(fdo::Error::Failed(format!("{name}: no process found")));
+    }
+    let signalled: Vec<i32> = outcomes
+        .iter()
+        .filter(|o| o.result.is_ok())
+        .map(|o| o.process.pid.as_raw())
+        .collect();
+    match outcomes.into_iter().find_map(|o| o.result.err()) {
+        Some(e) if signalled.is_empty() => Err(fdo::Error::Failed(e.to_string())),
+        _ => Ok(signalled),
+    }
+}
+
+/// Serves `org.faulx.Killer` on the system bus until the process is killed,
+/// accepting at most `rate_limit` `Kill` calls if set.
+pub fn serve(root: &Path, rate_limit: Option<RateLimit>) -> zbus::Result<()> {
+    let killer = Killer {
+        root: Arc::from(root),
+        rate_limit: rate_limit.map(|limit| Mutex::new(TokenBucket::new(limit))),
+    };
+    let _connection = connection::Builder::system()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, killer)?
+        .build()?;
+    loop {
+        thread::park();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process::Command};
+
+    #[test]
+    fn test_kill_own_only() {
+        // A copy of sleep under a name nothing else runs as.
+        let name = format!("fxbus{}", std::process::id());
+        let exe = std::env::temp_dir().join(&name);
+        fs::copy("/bin/sleep", &exe).unwrap();
+        let mut child = Command::new(&exe).arg("30").spawn().unwrap();
+        let root = Path::new("/proc");
+        let uid = crate::sys::uid();
+
+        assert!(matches!(
+            kill_own(root, &name, "KILL", uid.wrapping_add(1)),
+            Err(fdo::Error::Failed(_))
+        ));
+        assert!(matches!(
+            kill_own(root, &name, "NOPE", uid),
+            Err(fdo::Error::InvalidArgs(_))
+        ));
+        let pid = i32::try_from(child.id()).unwrap();
+        assert_eq!(kill_own(root, &name, "KILL", uid).unwrap(), vec![pid]);
+
+        child.wait().unwrap();
+        fs::remove_file(&exe).unwrap();
+    }
+
+    #[test]
+    fn test_admit_rate_limit() {
+        let killer = Killer {
+            root: Arc::from(Path::new("/proc")),
+            rate_limit: Some(Mutex::new(TokenBucket::new("2/h".parse().unwrap()))),
+        };
+        assert!(killer.admit().is_ok());
+        assert!(killer.admit().is_ok());
+        assert!(matches!(killer.admit(), Err(fdo::Error::LimitsExceeded(_))));
+        assert_eq!(killer.refused(), 1);
+    }
+}
@@ -0,0 +1,392 @@
+//! `faulx doctor`: checks what in the environment could keep faulx from
+//! seeing or signalling processes, and says what to do about it.
+//!
+//! Most "no process found" surprises come from one of these, such as an
+//! unmounted `/proc` or one mounted with `hidepid`.
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{
+    pid::{PID_MAX_LIMIT, pid_max},
+    processes::is_mounted,
+    sys,
+    wsl::is_wsl,
+};
+
+/// Where `faulx doctor` looks for cgroups, under `/sys`.
+pub const SYS: &str = "/sys";
+
+/// `CAP_KILL`, the capability to signal processes of any user.
+const CAP_KILL: u32 = 5;
+
+/// How much a finding stands in the way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Ok,
+    /// Some processes or features are out of reach.
+    Warning,
+    /// Nothing will work as expected until it is fixed.
+    Problem,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Ok => "ok",
+            Self::Warning => "warning",
+            Self::Problem => "problem",
+        })
+    }
+}
+
+/// The result of one check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// What was checked, as in `proc` or `cgroup`.
+    pub check: &'static str,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(severity: Severity, check: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            check,
+            message: message.into(),
+        }
+    }
+}
+
+/// Runs every check against the process table at `proc` and the sysfs at
+/// `sys`.
+#[must_use]
+pub fn diagnose(proc: &Path, sys: &Path) -> Vec<Finding> {
+    if !is_mounted(proc) {
+        return vec![Finding::new(
+            Severity::Problem,
+            "proc",
+            format!(
+                "{0} is not mounted, so no process can be matched by name; mount it with \
+                 `mount -t proc proc {0}`, or give PIDs with --pid",
+                proc.display()
+            ),
+        )];
+    }
+    let mut findings = vec![Finding::new(
+        Severity::Ok,
+        "proc",
+        format!("{} is mounted", proc.display()),
+    )];
+    findings.extend(mount_options(proc));
+    findings.extend(signal_permission(proc));
+    findings.extend(pid_range(proc));
+    findings.push(pidfd());
+    findings.push(cgroups(sys));
+    findings.extend(namespaces(proc));
+    if is_wsl(proc) {
+        findings.push(Finding::new(
+            Severity::Ok,
+            "wsl",
+            "Windows programs show up as relay processes, which signals don't reach; \
+             --windows-interop ends them with taskkill.exe",
+        ));
+    }
+    findings
+}
+
+/// Writes one finding per line, as `warning  check: message`.
+pub fn write_findings(out: &mut dyn Write, findings: &[Finding]) -> io::Result<()> {
+    for finding in findings {
+        writeln!(
+            out,
+            "{:<9}{}: {}",
+            finding.severity, finding.check, finding.message
+        )?;
+    }
+    Ok(())
+}
+
+/// `hidepid`, which hides the processes of other users from everyone but
+/// root and the group given with `gid`.
+fn mount_options(proc: &Path) -> Option<Finding> {
+    let mounts = fs::read_to_string(proc.join("mounts")).ok()?;
+    let options = mounts
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            (fields.len() > 3 && fields[2] == "proc" && Path::new(fields[1]) == proc)
+                .then(|| fields[3].to_owned())
+        })
+        .next_back()?;
+    let option = |name: &str| {
+        options
+            .split(',')
+            .find_map(|option| option.strip_prefix(name)?.strip_prefix('='))
+    };
+    let hidepid = option("hidepid").filter(|&value| !matches!(value, "0" | "off"));
+    let Some(hidepid) = hidepid else {
+        return Some(Finding::new(
+            Severity::Ok,
+            "mount",
+            "every process is visible",
+        ));
+    };
+    let exempt = option("gid").and_then(|gid| {
+        let name = sys::group_name(gid.parse().ok()?).ok().flatten()?;
+        Some(format!(", except to root and members of group {name}"))
+    });
+    Some(Finding::new(
+        Severity::Warning,
+        "mount",
+        format!(
+            "{} is mounted with hidepid={hidepid}: processes of other users are hidden{}, \
+             so they are never matched",
+            proc.display(),
+            exempt.unwrap_or_default()
+        ),
+    ))
+}
+
+/// Whether processes of other users can be signalled, which takes
+/// `CAP_KILL`.
+fn signal_permission(proc: &Path) -> Option<Finding> {
+    let status = fs::read_to_string(proc.join("self/status")).ok()?;
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .and_then(|value| value.split_whitespace().next())
+    };
+    let caps = u64::from_str_radix(field("CapEff")?, 16).ok()?;
+    Some(if caps & (1 << CAP_KILL) != 0 {
+        Finding::new(
+            Severity::Ok,
+            "signal",
+            "processes of every user can be signalled (CAP_KILL)",
+        )
+    } else {
+        Finding::new(
+            Severity::Warning,
+            "signal",
+            format!(
+                "only processes of uid {} can be signalled; run as root, with sudo or with \
+                 --sudo for those of other users",
+                field("Uid")?
+            ),
+        )
+    })
+}
+
+/// The highest PID in use, from `pid_max`.
+fn pid_range(proc: &Path) -> Option<Finding> {
+    let max = pid_max(proc)?;
+    Some(if (2..=PID_MAX_LIMIT).contains(&max) {
+        Finding::new(
+            Severity::Ok,
+            "pid",
+            format!("PIDs go up to {}, of at most {PID_MAX_LIMIT}", max - 1),
+        )
+    } else {
+        Finding::new(
+            Severity::Warning,
+            "pid",
+            format!(
+                "pid_max is {max}, outside of 2 to {PID_MAX_LIMIT}; PIDs from the latter on are skipped"
+            ),
+        )
+    })
+}
+
+/// Whether the kernel hands out pidfds, which need Linux 5.3.
+fn pidfd() -> Finding {
+    // SAFETY: pidfd_open takes no pointers, and the descriptor it returns
+    // is closed right away.
+    let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, libc::getpid(), 0) };
+    if fd < 0 {
+        return Finding::new(
+            Severity::Warning,
+            "pidfd",
+            format!(
+                "pidfd_open failed ({}): the kernel is older than 5.3, or a seccomp filter \
+                 blocks it; reused PIDs are told apart by start time instead",
+                io::Error::last_os_error()
+            ),
+        );
+    }
+    #[allow(clippy::cast_possible_truncation)] // file descriptors fit in a c_int
+    // SAFETY: fd was just opened, and nothing else owns it.
+    unsafe {
+        libc::close(fd as libc::c_int);
+    }
+    Finding::new(Severity::Ok, "pidfd", "pidfds are supported")
+}
+
+/// The cgroup version, which decides what `--move-to-cgroup` takes.
+fn cgroups(sys: &Path) -> Finding {
+    let root = sys.join("fs/cgroup");
+    if root.join("cgroup.controllers").exists() {
+        return Finding::new(
+            Severity::Ok,
+            "cgroup",
+            format!(
+                "cgroup v2: --move-to-cgroup takes a path under {}",
+                root.display()
+            ),
+        );
+    }
+    let hierarchies = fs::read_dir(&root).map_or(0, Iterator::count);
+    if hierarchies == 0 {
+        return Finding::new(
+            Severity::Warning,
+            "cgroup",
+            format!(
+                "no cgroup filesystem at {}, so --move-to-cgroup can't work",
+                root.display()
+            ),
+        );
+    }
+    Finding::new(
+        Severity::Ok,
+        "cgroup",
+        "cgroup v1: --move-to-cgroup takes a path starting with its controller, as in \
+         cpu/NAME",
+    )
+}
+
+/// Whether `proc` shows the PID namespace faulx runs in, and whether users
+/// are remapped.
+fn namespaces(proc: &Path) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Ok(own) = fs::read_link(proc.join("self/ns/pid")) {
+        findings.push(match fs::read_link(proc.join("1/ns/pid")) {
+            Ok(init) if init == own => Finding::new(
+                Severity::Ok,
+                "namespace",
+                format!("{} shows the PID namespace faulx runs in", proc.display()),
+            ),
+            Ok(_) => Finding::new(
+                Severity::Problem,
+                "namespace",
+                format!(
+                    "{} belongs to another PID namespace, so the PIDs read there are not \
+                     those signals go to; mount a proc filesystem of this namespace, or \
+                     use --ns-of from the host",
+                    proc.display()
+                ),
+            ),
+            Err(e) => Finding::new(
+                Severity::Warning,
+                "namespace",
+                format!("can't read the PID namespace of PID 1 ({e}), which takes CAP_SYS_PTRACE"),
+            ),
+        });
+    }
+    let remapped = fs::read_to_string(proc.join("self/uid_map"))
+        .is_ok_and(|map| map.split_whitespace().collect::<Vec<_>>() != ["0", "0", "4294967295"]);
+    if remapped {
+        findings.push(Finding::new(
+            Severity::Warning,
+            "namespace",
+            "in a user namespace: processes of users not mapped into it show up as owned \
+             by the overflow uid, and can't be signalled",
+        ));
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, setup_fake_proc, unique_test_dir};
+    use std::os::unix::fs::symlink;
+
+    fn severity(findings: &[Finding], check: &str) -> Option<Severity> {
+        findings
+            .iter()
+            .find(|finding| finding.check == check)
+            .map(|finding| finding.severity)
+    }
+
+    #[test]
+    fn test_unmounted() {
+        let tmp = unique_test_dir();
+        let findings = diagnose(&tmp, &tmp);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Problem);
+        assert!(findings[0].message.contains("mount -t proc"));
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("1", "init"), ("self", "faulx")]);
+        let proc = tmp.display();
+        fs::write(
+            tmp.join("mounts"),
+            format!("sysfs /sys sysfs rw 0 0\nproc {proc} proc rw,nosuid,hidepid=invisible 0 0\n"),
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("self/status"),
+            "Name:\tfaulx\nUid:\t1000\t1000\t1000\t1000\nCapEff:\t0000000000000000\n",
+        )
+        .unwrap();
+        for pid in ["1", "self"] {
+            fs::create_dir_all(tmp.join(pid).join("ns")).unwrap();
+        }
+        symlink("pid:[4026531836]", tmp.join("self/ns/pid")).unwrap();
+        symlink("pid:[4026532001]", tmp.join("1/ns/pid")).unwrap();
+        fs::write(
+            tmp.join("self/uid_map"),
+            "         0          0 4294967295\n",
+        )
+        .unwrap();
+        fs::create_dir_all(tmp.join("sys/kernel")).unwrap();
+        fs::write(tmp.join("sys/kernel/pid_max"), "4194304\n").unwrap();
+        fs::create_dir_all(tmp.join("sys/fs/cgroup")).unwrap();
+        fs::write(tmp.join("sys/fs/cgroup/cgroup.controllers"), "cpu io\n").unwrap();
+
+        let findings = diagnose(&tmp, &tmp.join("sys"));
+        assert_eq!(severity(&findings, "proc"), Some(Severity::Ok));
+        assert_eq!(severity(&findings, "mount"), Some(Severity::Warning));
+        assert_eq!(severity(&findings, "signal"), Some(Severity::Warning));
+        assert_eq!(severity(&findings, "pid"), Some(Severity::Ok));
+        assert_eq!(severity(&findings, "cgroup"), Some(Severity::Ok));
+        assert_eq!(severity(&findings, "namespace"), Some(Severity::Problem));
+        assert!(severity(&findings, "pidfd").is_some());
+        assert_eq!(
+            findings.iter().filter(|f| f.check == "namespace").count(),
+            1
+        );
+
+        let mut out = Vec::new();
+        write_findings(&mut out, &findings[..1]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("ok       proc: {proc} is mounted\n")
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+
+    #[test]
+    fn test_signal_permission() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("self", "faulx")]);
+        fs::write(
+            tmp.join("self/status"),
+            "Name:\tfaulx\nUid:\t0\t0\t0\t0\nCapEff:\t000001ffffffffff\n",
+        )
+        .unwrap();
+        assert_eq!(
+            signal_permission(&tmp).map(|finding| finding.severity),
+            Some(Severity::Ok)
+        );
+        cleanup_fake_proc(&tmp);
+    }
+}
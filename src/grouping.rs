@@ -0,0 +1,190 @@
+//! `--group-by`: the `--verbose` or `--echo` lines of the processes acted
+//! upon, gathered under their parent, session or systemd unit, so that
+//! pools of hundreds of workers read as a few groups.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    sync::Arc,
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::{
+    pid::Pid,
+    processes::{ProcessInfo, systemd_cgroup},
+};
+
+/// What `--group-by` gathers processes under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum GroupBy {
+    /// Parent process.
+    Parent,
+    /// Session, named after its leader.
+    Session,
+    /// systemd service or scope, read from the process's cgroup.
+    Unit,
+}
+
+impl GroupBy {
+    /// The heading of the group of `process`, as `parent 1200 (nginx)`,
+    /// `session 800 (sshd)` or `unit nginx.service`.
+    #[must_use]
+    pub fn label(self, root: &Path, process: &ProcessInfo) -> String {
+        let leader = |kind: &str, pid: i32| {
+            let comm = Pid::new(pid)
+                .map(|pid| ProcessInfo::by_pid(Arc::from(root), pid).comm)
+                .unwrap_or_default();
+            if comm.is_empty() {
+                format!("{kind} {pid}")
+            } else {
+                format!("{kind} {pid} ({})", comm.escaped())
+            }
+        };
+        match (self, process.stat()) {
+            (Self::Parent, Some(stat)) => leader("parent", stat.ppid),
+            (Self::Session, Some(stat)) => leader("session", stat.session),
+            (Self::Parent | Self::Session, None) => "unknown".to_owned(),
+            (Self::Unit, _) => fs::read_to_string(process.path("cgroup"))
+                .ok()
+                .and_then(|cgroup| unit(&cgroup).map(|unit| format!("unit {unit}")))
+                .unwrap_or_else(|| "no unit".to_owned()),
+        }
+    }
+}
+
+/// The systemd unit in the contents of a `cgroup` file: the innermost
+/// service or scope, or the innermost slice outside of those.
+fn unit(cgroup: &str) -> Option<&str> {
+    let path = systemd_cgroup(cgroup)?;
+    let of_type = |kinds: &'static [&str]| {
+        path.rsplit('/').find(|component| {
+            component
+                .rsplit_once('.')
+                .is_some_and(|(_, kind)| kinds.contains(&kind))
+        })
+    };
+    of_type(&["service", "scope"]).or_else(|| of_type(&["slice"]))
+}
+
+/// Output lines gathered per group, in the order groups are first seen.
+#[derive(Debug)]
+pub struct Groups {
+    by: GroupBy,
+    entries: Vec<(String, Vec<u8>)>,
+    index: HashMap<String, usize>,
+}
+
+impl Groups {
+    #[must_use]
+    pub fn new(by: GroupBy) -> Self {
+        Self {
+            by,
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+
+    /// Where the lines about `process` go.
+    pub fn buffer(&mut self, root: &Path, process: &ProcessInfo) -> &mut Vec<u8> {
+        let label = self.by.label(root, process);
+        let index = *self.index.entry(label.clone()).or_insert_with(|| {
+            self.entries.push((label, Vec::new()));
+            self.entries.len() - 1
+        });
+        &mut self.entries[index].1
+    }
+
+    /// Writes each group with a nonempty output, under its heading and with
+    /// how many lines it has, indented.
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<()> {
+        for (label, output) in &self.entries {
+            let lines: Vec<_> = output.split_inclusive(|&b| b == b'\n').collect();
+            if lines.is_empty() {
+                continue;
+            }
+            let plural = if lines.len() == 1 { "" } else { "es" };
+            writeln!(out, "{label}: {} process{plural}", lines.len())?;
+            for line in lines {
+                out.write_all(b"  ")?;
+                out.write_all(line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat,
+    };
+
+    #[test]
+    fn test_unit() {
+        assert_eq!(
+            unit("0::/system.slice/nginx.service\n"),
+            Some("nginx.service")
+        );
+        assert_eq!(
+            unit(
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-firefox-12.scope\n"
+            ),
+            Some("app-firefox-12.scope")
+        );
+        assert_eq!(
+            unit("0::/user.slice/user-1000.slice\n"),
+            Some("user-1000.slice")
+        );
+        assert_eq!(
+            unit("12:memory:/x\n1:name=systemd:/system.slice/cron.service\n"),
+            Some("cron.service")
+        );
+        assert_eq!(unit("0::/\n"), None);
+    }
+
+    #[test]
+    fn test_groups() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx"), ("12", "nginx")]);
+        write_fake_stat(&tmp, "10", "nginx", 1, 10);
+        write_fake_stat(&tmp, "11", "nginx", 10, 10);
+        write_fake_stat(&tmp, "12", "nginx", 10, 10);
+        fs::write(tmp.join("11/cgroup"), "0::/system.slice/nginx.service\n").unwrap();
+        let process = |pid| ProcessInfo::by_pid(Arc::from(tmp.as_path()), pid);
+
+        assert_eq!(
+            GroupBy::Parent.label(&tmp, &process(pid(11))),
+            "parent 10 (nginx)"
+        );
+        assert_eq!(GroupBy::Parent.label(&tmp, &process(pid(10))), "parent 1");
+        assert_eq!(
+            GroupBy::Unit.label(&tmp, &process(pid(11))),
+            "unit nginx.service"
+        );
+        assert_eq!(GroupBy::Unit.label(&tmp, &process(pid(12))), "no unit");
+
+        let mut groups = Groups::new(GroupBy::Parent);
+        for raw in [11, 10, 12] {
+            writeln!(
+                groups.buffer(&tmp, &process(pid(raw))),
+                "Killed nginx({raw})"
+            )
+            .unwrap();
+        }
+        let mut out = Vec::new();
+        groups.write_to(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "parent 10 (nginx): 2 processes\n  Killed nginx(11)\n  Killed nginx(12)\n\
+             parent 1: 1 process\n  Killed nginx(10)\n"
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+}
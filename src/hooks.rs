@@ -0,0 +1,102 @@
+//! `--pre-hook` and `--post-hook`: shell commands run before the matched
+//! processes are acted upon and once that is done, to notify or clean up.
+//!
+//! A hook gets the processes on stdin, a PID and a name per line as
+//! `--stdin-pids` reads them, and their PIDs in `FAULX_PIDS`, separated by
+//! spaces. `FAULX_HOOK` tells `pre` from `post`.
+
+use std::{
+    ffi::OsStr,
+    io::{self, Write},
+    process::{Command, ExitStatus, Stdio},
+};
+
+use crate::{limits::OriginalNofileLimit, processes::ProcessInfo};
+
+/// When a hook runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Before any of the processes is acted upon; they are all spared if
+    /// it fails.
+    Pre,
+    /// Once the action, and the wait if any, are done, with the processes
+    /// the action succeeded on.
+    Post,
+}
+
+impl Hook {
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Pre => "pre",
+            Self::Post => "post",
+        }
+    }
+}
+
+/// Runs `command` with `sh -c` as the `hook` for `processes`, returning how
+/// it exited.
+pub fn run<'a>(
+    command: &OsStr,
+    hook: Hook,
+    processes: impl IntoIterator<Item = &'a ProcessInfo>,
+) -> io::Result<ExitStatus> {
+    let mut pids = Vec::new();
+    let mut list = Vec::new();
+    for process in processes {
+        pids.push(process.pid.to_string());
+        list.extend_from_slice(format!("{} ", process.pid).as_bytes());
+        list.extend_from_slice(&process.comm);
+        list.push(b'\n');
+    }
+    let mut child = Command::new("sh")
+        .original_nofile_limit()
+        .arg("-c")
+        .arg(command)
+        .env("FAULX_HOOK", hook.name())
+        .env("FAULX_PIDS", pids.join(" "))
+        .stdin(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that doesn't read its stdin can leave before it's written.
+        match stdin.write_all(&list) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, sync::Arc};
+
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir};
+
+    #[test]
+    fn test_run() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "php-fpm")]);
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        let processes = [
+            ProcessInfo::by_pid(Arc::clone(&root), pid(10)),
+            ProcessInfo::by_pid(Arc::clone(&root), pid(11)),
+        ];
+        let saved = tmp.join("saved");
+        let command = format!(
+            "{{ echo \"$FAULX_HOOK: $FAULX_PIDS\"; cat; }} > '{}'",
+            saved.display()
+        );
+
+        let status = run(OsStr::new(&command), Hook::Post, &processes).unwrap();
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(&saved).unwrap(),
+            "post: 10 11\n10 nginx\n11 php-fpm\n"
+        );
+        let status = run(OsStr::new("exit 3"), Hook::Pre, &processes).unwrap();
+        assert_eq!(status.code(), Some(3));
+        cleanup_fake_proc(&tmp);
+    }
+}
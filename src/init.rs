@@ -0,0 +1,120 @@
+//! `--init`: runs a command as a container's PID 1.
+//!
+//! The signals faulx receives are forwarded to the command, or to the
+//! processes of some names, and every orphan left to faulx is reaped until
+//! the command exits.
+
+use std::{ffi::OsString, io, os::unix::process::CommandExt, path::Path, process::Command};
+
+use crate::{
+    exit::wait_status_code,
+    filters::NameFilter,
+    killer::{Killer, SystemKiller},
+    limits::OriginalNofileLimit,
+    pid::Pid,
+    pipeline::Pipeline,
+    processes::{PROC, Scanner},
+    signals::Signal,
+    sys::{self, SignalSet, WaitStatus},
+};
+
+/// The signals passed on, those a container runtime or a user sends to
+/// stop, reload or resize what runs inside.
+const FORWARDED: [libc::c_int; 8] = [
+    libc::SIGHUP,
+    libc::SIGINT,
+    libc::SIGQUIT,
+    libc::SIGTERM,
+    libc::SIGUSR1,
+    libc::SIGUSR2,
+    libc::SIGWINCH,
+    libc::SIGALRM,
+];
+
+/// Runs `command`, forwarding signals to it, or to the processes named
+/// `forward_to` if any, and returns with its exit status.
+///
+/// Must be called before any other thread is started, so that all of them
+/// leave the signals to `sigwait`.
+pub fn run(command: &[OsString], forward_to: &[String]) -> io::Result<u8> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command to run"))?;
+    let sigchld = Signal::from_raw(libc::SIGCHLD);
+    let caught: Vec<_> = FORWARDED
+        .into_iter()
+        .filter_map(Signal::from_raw)
+        .chain(sigchld)
+        .collect();
+    let signals = SignalSet::new(&caught);
+    signals.block()?;
+    // Orphans are then reparented to faulx even when it is not PID 1, as
+    // under a runtime that runs its own init.
+    sys::set_child_subreaper()?;
+
+    let mut command = Command::new(program);
+    command.args(args).original_nofile_limit();
+    // SAFETY: only unblocks signals in the child, which is async-signal-safe.
+    unsafe {
+        command.pre_exec(move || signals.unblock());
+    }
+    let child = command.spawn()?;
+    let main = i32::try_from(child.id())
+        .ok()
+        .and_then(Pid::new)
+        .ok_or_else(|| io::Error::other("the command has no PID"))?;
+    loop {
+        match signals.wait()? {
+            signal if Some(signal) == sigchld => {
+                if let Some(code) = reap(main)? {
+                    return Ok(code);
+                }
+            }
+            signal => forward(main, forward_to, signal),
+        }
+    }
+}
+
+/// Reaps every child that has exited, returning the status of `main` if
+/// it is one of them.
+fn reap(main: Pid) -> io::Result<Option<u8>> {
+    let mut code = None;
+    loop {
+        match sys::waitpid(None, true) {
+            Ok(WaitStatus::StillAlive) => return Ok(code),
+            Err(e) if e.raw_os_error() == Some(libc::ECHILD) => return Ok(code),
+            Ok(status) if status.pid() == Some(main) => code = wait_status_code(status),
+            Ok(_) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Sends `signal` to `main`, or to the processes named `forward_to`
+/// instead. Processes gone in the meantime are not an error.
+fn forward(main: Pid, forward_to: &[String], signal: Signal) {
+    if forward_to.is_empty() {
+        let _ = SystemKiller.kill(main, signal);
+        return;
+    }
+    let own_pid = Pid::this();
+    for name in forward_to {
+        let pipeline = Pipeline::new(Scanner::new(Path::new(PROC))).filter(NameFilter::new(name));
+        for process in pipeline.select().unwrap_or_default() {
+            if process.pid != own_pid {
+                let _ = SystemKiller.kill(process.pid, signal);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_without_command() {
+        let err = run(&[], &[]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
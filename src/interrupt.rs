@@ -0,0 +1,74 @@
+//! SIGINT and SIGTERM received by faulx itself, caught instead of ending
+//! it, so that a long run can tell what it did before stopping.
+
+use std::sync::{
+    Mutex, PoisonError,
+    atomic::{AtomicI32, Ordering},
+};
+
+use crate::{
+    signals::Signal,
+    sys::{self, Disposition},
+};
+
+const CAUGHT: [Signal; 2] = [Signal::INT, Signal::TERM];
+
+/// The last signal caught, or 0.
+static RECEIVED: AtomicI32 = AtomicI32::new(0);
+
+/// How many `Interrupts` are alive, and the actions they replaced.
+static INSTALLED: Mutex<(usize, Vec<(Signal, Disposition)>)> = Mutex::new((0, Vec::new()));
+
+extern "C" fn record(signal: libc::c_int) {
+    RECEIVED.store(signal, Ordering::Relaxed);
+}
+
+/// Catches SIGINT and SIGTERM for as long as one lives, after which what
+/// was there before is restored.
+#[derive(Debug)]
+pub struct Interrupts(());
+
+impl Interrupts {
+    /// Starts catching, unless another one already is; a signal caught
+    /// before is then forgotten. Signals that cannot be caught are left as
+    /// they were.
+    #[must_use]
+    pub fn catch() -> Self {
+        let mut installed = INSTALLED.lock().unwrap_or_else(PoisonError::into_inner);
+        if installed.0 == 0 {
+            RECEIVED.store(0, Ordering::Relaxed);
+            installed.1 = CAUGHT
+                .into_iter()
+                // SAFETY: the handler only stores to an atomic, which is
+                // async-signal-safe.
+                .filter_map(|signal| {
+                    unsafe { sys::catch(signal, record) }
+                        .ok()
+                        .map(|old| (signal, old))
+                })
+                .collect();
+        }
+        installed.0 += 1;
+        drop(installed);
+        Self(())
+    }
+
+    /// The signal caught since `catch`, if any.
+    #[must_use]
+    pub fn received(&self) -> Option<Signal> {
+        Signal::from_raw(RECEIVED.load(Ordering::Relaxed))
+    }
+}
+
+impl Drop for Interrupts {
+    fn drop(&mut self) {
+        let mut installed = INSTALLED.lock().unwrap_or_else(PoisonError::into_inner);
+        installed.0 -= 1;
+        if installed.0 == 0 {
+            for (signal, old) in installed.1.drain(..) {
+                let _ = sys::restore(signal, &old);
+            }
+        }
+        drop(installed);
+    }
+}
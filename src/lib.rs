@@ -1,4 +1,78 @@
+//! The `faulx` command line, built on faulx-core, which it re-exports.
+//!
+//! The command line and what only the binary needs are built with the `cli`
+//! feature, through `clap`, or with `tiny-cli`; without either, as with
+//! `default-features = false`, the crate is faulx-core alone. Libraries
+//! should depend on faulx-core itself, whose API doesn't follow the releases
+//! of the command line.
+
+#[cfg(all(any(feature = "clap", feature = "tiny-cli"), feature = "json"))]
+pub mod actionlog;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod app;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod archive;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod capture;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
 pub mod cli;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod color;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod coredump;
+#[cfg(all(any(feature = "clap", feature = "tiny-cli"), feature = "dbus"))]
+pub mod daemon;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod doctor;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod grouping;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod hooks;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod init;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod interrupt;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod listing;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
 pub mod macros;
-pub mod processes;
-pub mod signals;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod metrics;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod namespace;
+#[cfg(all(any(feature = "clap", feature = "tiny-cli"), feature = "notify"))]
+pub mod notify;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod policy;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod preset;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod profile;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod remote;
+#[cfg(all(any(feature = "clap", feature = "tiny-cli"), feature = "json"))]
+pub mod results;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod selftest;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod session;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod sudo;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod tree;
+#[cfg(all(any(feature = "clap", feature = "tiny-cli"), feature = "tui"))]
+pub mod tui;
+#[cfg(all(any(feature = "clap", feature = "tiny-cli"), feature = "window"))]
+pub mod window;
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub mod wsl;
+
+#[cfg(any(feature = "clap", feature = "tiny-cli"))]
+pub use app::{Env, Invocation, Report, run};
+#[cfg(test)]
+use faulx_core::testing;
+pub use faulx_core::{
+    Policy, Signal, Termination, actions, clock, comm, exit, expr, filters, kill_with_policy,
+    killer, limits, permission, pid, pipeline, processes, query, ratelimit, signals, snapshot,
+    sockets, sys, terminate, time, units, wait,
+};
@@ -0,0 +1,250 @@
+//! `faulx list`: every running process with its user, read by the same
+//! scanner as the rest, for when `ps` is not installed, as in scratch
+//! containers.
+
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    io::{self, Write},
+};
+
+#[cfg(feature = "clap")]
+use clap::ValueEnum;
+
+use crate::{pid::Pid, processes::Scanner, sys};
+
+/// What `faulx list` sorts processes by. Ties are broken by PID, so the
+/// listing is the same from one run to the next.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(ValueEnum))]
+pub enum ListSort {
+    #[default]
+    Pid,
+    /// Process name.
+    Name,
+    /// User name, or UID for users without one.
+    User,
+    /// Oldest process first.
+    Start,
+}
+
+/// A line of `faulx list`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub pid: Pid,
+    pub user: String,
+    pub comm: String,
+    /// Time the process started after system boot, in clock ticks.
+    pub starttime: Option<u64>,
+}
+
+/// How to order the rows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sorting {
+    pub by: ListSort,
+    /// Compare the numbers within names by value, so `worker2` comes before
+    /// `worker10`.
+    pub version: bool,
+    pub reverse: bool,
+}
+
+/// Reads every process, with its user name looked up once per UID.
+pub fn rows(scanner: &Scanner) -> io::Result<Vec<Row>> {
+    let mut users = HashMap::new();
+    Ok(scanner
+        .scan(|_| true)?
+        .into_iter()
+        .map(|process| {
+            let user = process.uid().map_or_else(
+                || "?".to_owned(),
+                |uid| users.entry(uid).or_insert_with(|| user_name(uid)).clone(),
+            );
+            Row {
+                pid: process.pid,
+                user,
+                comm: process.comm.escaped().to_string(),
+                starttime: process.stat().map(|stat| stat.starttime),
+            }
+        })
+        .collect())
+}
+
+fn user_name(uid: u32) -> String {
+    match sys::user_name(uid) {
+        Ok(Some(name)) => name,
+        _ => uid.to_string(),
+    }
+}
+
+/// Sorts `rows` as `sorting` says. Reversing flips the key but not the PID
+/// tie-break.
+pub fn sort(rows: &mut [Row], sorting: Sorting) {
+    let text = |a: &str, b: &str| {
+        if sorting.version {
+            version_cmp(a, b)
+        } else {
+            a.cmp(b)
+        }
+    };
+    rows.sort_by(|a, b| {
+        let key = match sorting.by {
+            ListSort::Pid => Ordering::Equal,
+            ListSort::Name => text(&a.comm, &b.comm),
+            ListSort::User => text(&a.user, &b.user),
+            ListSort::Start => a.starttime.cmp(&b.starttime),
+        };
+        let key = if sorting.reverse { key.reverse() } else { key };
+        let pid = a.pid.cmp(&b.pid);
+        let pid = if sorting.reverse && sorting.by == ListSort::Pid {
+            pid.reverse()
+        } else {
+            pid
+        };
+        key.then(pid)
+    });
+}
+
+/// Compares like `sort -V`: runs of digits by their value, the rest byte by
+/// byte.
+#[must_use]
+pub fn version_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, rest_a) = split_digits(a);
+                let (y, rest_b) = split_digits(b);
+                let (x, y) = (trim_zeros(x), trim_zeros(y));
+                let order = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a, b) = (rest_a, rest_b);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(y);
+                }
+                (a, b) = (&a[1..], &b[1..]);
+            }
+        }
+    }
+}
+
+fn split_digits(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let end = bytes
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(bytes.len());
+    bytes.split_at(end)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let start = digits
+        .iter()
+        .position(|&b| b != b'0')
+        .unwrap_or(digits.len());
+    &digits[start..]
+}
+
+/// Writes `rows` as a table with a header, like `ps -e -o pid,user,comm`.
+pub fn write_rows(out: &mut dyn Write, rows: &[Row]) -> io::Result<()> {
+    writeln!(out, "{:>7} {:<8} COMMAND", "PID", "USER")?;
+    for row in rows {
+        writeln!(out, "{:>7} {:<8} {}", row.pid, row.user, row.comm)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::testing::{
+        cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir, write_fake_stat_fields,
+    };
+
+    fn row(raw: i32, user: &str, comm: &str) -> Row {
+        Row {
+            pid: pid(raw),
+            user: user.to_owned(),
+            comm: comm.to_owned(),
+            starttime: None,
+        }
+    }
+
+    fn pids(rows: &[Row]) -> Vec<i32> {
+        rows.iter().map(|row| row.pid.as_raw()).collect()
+    }
+
+    #[test]
+    fn test_version_cmp() {
+        assert_eq!(version_cmp("worker2", "worker10"), Ordering::Less);
+        assert_eq!(version_cmp("worker10", "worker10"), Ordering::Equal);
+        assert_eq!(version_cmp("worker010", "worker9"), Ordering::Greater);
+        assert_eq!(version_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(version_cmp("b1", "a2"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut rows = vec![
+            row(3, "root", "worker10"),
+            row(1, "www", "worker2"),
+            row(2, "root", "worker2"),
+        ];
+        let by = |by, version, reverse| Sorting {
+            by,
+            version,
+            reverse,
+        };
+
+        sort(&mut rows, by(ListSort::Name, false, false));
+        assert_eq!(pids(&rows), [3, 1, 2]);
+        sort(&mut rows, by(ListSort::Name, true, false));
+        assert_eq!(pids(&rows), [1, 2, 3]);
+        sort(&mut rows, by(ListSort::Name, true, true));
+        assert_eq!(pids(&rows), [3, 1, 2]);
+        sort(&mut rows, by(ListSort::User, false, false));
+        assert_eq!(pids(&rows), [2, 3, 1]);
+        sort(&mut rows, by(ListSort::Pid, false, true));
+        assert_eq!(pids(&rows), [3, 2, 1]);
+    }
+
+    #[test]
+    fn test_rows() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("20", "sshd"), ("10", "init")]);
+        write_fake_stat_fields(&tmp, "20", "sshd", 'S', 10, 20, 500);
+        fs::write(tmp.join("20/status"), "Name:\tsshd\nUid:\t0\t0\t0\t0\n").unwrap();
+
+        let mut rows = rows(&Scanner::new(&tmp)).unwrap();
+        sort(
+            &mut rows,
+            Sorting {
+                by: ListSort::Start,
+                ..Sorting::default()
+            },
+        );
+        assert_eq!(pids(&rows), [10, 20]);
+        assert_eq!(rows[0].user, "?");
+        assert_eq!(rows[1].user, user_name(0));
+        assert_eq!(rows[1].starttime, Some(500));
+
+        let mut out = Vec::new();
+        write_rows(&mut out, &rows).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "    PID USER     COMMAND\n     10 ?        init\n     20 {:<8} sshd\n",
+                user_name(0)
+            )
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -1,69 +1,196 @@
-use std::{process, sync::atomic::Ordering};
-
-use clap::Parser;
-use nix::{
-    sys::signal::{Signal, kill},
-    unistd::Pid,
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
 };
 
 use faulx::{
-    cli::{FaulxArgs, MAX_NAMES},
-    macros::QUIET,
-    processes::list_pids,
-    qprintln,
-    signals::{list_signals, parse_signal},
+    Env, Invocation,
+    cli::{Personality, env_name, expand_signal_shorthand},
+    clock::SystemClock,
+    exit::ExitCode,
+    init,
+    killer::SystemKiller,
+    limits::raise_nofile_limit,
+    namespace::{self, Entered},
+    policy::{POLICY_PATH, SitePolicy},
+    preset::{self, Presets},
+    processes::PROC,
+    session, sudo, sys,
 };
 
-fn main() {
-    let args = FaulxArgs::parse();
+#[cfg(not(any(feature = "clap", feature = "tiny-cli")))]
+compile_error!("the faulx binary needs either the `clap` or the `tiny-cli` feature");
+
+#[cfg(feature = "tiny-cli")]
+mod parse {
+    pub use faulx::cli::tiny::{
+        parse_from as faulx, parse_pgrep_from as pgrep, parse_pkill_from as pkill,
+    };
+}
+
+#[cfg(all(feature = "clap", not(feature = "tiny-cli")))]
+mod parse {
+    use std::ffi::OsString;
+
+    use clap::{CommandFactory, FromArgMatches};
+    use faulx::cli::{FaulxArgs, PgrepArgs, PkillArgs, with_env};
 
-    QUIET.store(args.quiet, Ordering::Relaxed);
+    /// Parses `args` like `Parser::parse_from`, with `FAULX_*` environment
+    /// variables as defaults.
+    fn parse<P: CommandFactory + FromArgMatches>(args: Vec<OsString>) -> P {
+        let cmd = with_env(P::command());
+        // Help and errors follow --color like the rest of the output.
+        #[cfg(feature = "color")]
+        let cmd = cmd.color(match faulx::color::ColorChoice::requested(&args) {
+            faulx::color::ColorChoice::Auto => clap::ColorChoice::Auto,
+            faulx::color::ColorChoice::Always => clap::ColorChoice::Always,
+            faulx::color::ColorChoice::Never => clap::ColorChoice::Never,
+        });
+        let mut matches = cmd.get_matches_from(args);
+        P::from_arg_matches_mut(&mut matches).unwrap_or_else(|e| e.exit())
+    }
 
-    if args.list {
-        println!("{}", list_signals());
-        return;
+    pub fn faulx(args: Vec<OsString>) -> FaulxArgs {
+        parse(args)
     }
 
-    if args.process_names.len() > MAX_NAMES {
-        qprintln!(
-            "{}: Maximum number of names is {} and you gave {}",
-            env!("CARGO_PKG_NAME"),
-            MAX_NAMES,
-            args.process_names.len(),
-        );
-        process::exit(1);
+    pub fn pgrep(args: Vec<OsString>) -> PgrepArgs {
+        parse(args)
     }
 
-    let sig = args.signal.as_deref().map_or(Signal::SIGTERM, |name| {
-        parse_signal(name).map_or_else(
-            || {
-                qprintln!("{name}: unknown signal");
-                process::exit(1);
-            },
-            |s| s,
-        )
-    });
+    pub fn pkill(args: Vec<OsString>) -> PkillArgs {
+        parse(args)
+    }
+}
 
-    for process_name in &args.process_names {
-        let pids = match list_pids(process_name, args.process_group) {
-            Ok(pids) => pids,
+fn main() -> process::ExitCode {
+    // Best effort: scans still work within a low limit, only slower.
+    let _ = raise_nofile_limit();
+    let mut invocation = match parse(env::args_os().collect()) {
+        Ok(invocation) => invocation,
+        Err(code) => return code.into(),
+    };
+    if session::on_desktop() {
+        invocation.scope_to_session();
+    }
+    // The host's policy, not that of a container entered with --ns-of.
+    let policy = match SitePolicy::load(Path::new(POLICY_PATH)) {
+        Ok(policy) => policy,
+        Err(e) => {
+            eprintln!("{}: {POLICY_PATH}: {e}", env!("CARGO_CRATE_NAME"));
+            return ExitCode::Internal.into();
+        }
+    };
+    // While no other thread has started yet.
+    if let Some(pid) = invocation.ns_of() {
+        match namespace::enter(Path::new(PROC), pid) {
+            Ok(Entered::Inside) => {}
+            Ok(Entered::Outside(code)) => return code.into(),
+            Err(e) => {
+                eprintln!(
+                    "{}: cannot enter the namespaces of {pid}: {e}",
+                    env!("CARGO_CRATE_NAME")
+                );
+                return ExitCode::Internal.into();
+            }
+        }
+    }
+    if let Some((command, forward_to)) = invocation.init() {
+        return match init::run(command, forward_to) {
+            Ok(code) => code.into(),
             Err(e) => {
-                qprintln!("Error: {e}");
-                continue;
+                eprintln!("{}: --init: {e}", env!("CARGO_CRATE_NAME"));
+                ExitCode::Internal.into()
             }
         };
+    }
+    let mut stdout = io::stdout().lock();
+    let mut env = Env {
+        root: Path::new(PROC),
+        out: &mut stdout,
+        killer: Arc::new(SystemKiller),
+        clock: Arc::new(SystemClock),
+        policy,
+    };
+    let sudo = invocation.sudo().map(<[OsString]>::to_vec);
+    let result = faulx::run(invocation, &mut env);
+    if let (Some(command_line), Ok(report)) = (&sudo, &result)
+        && sudo::all_denied(&report.outcomes)
+        && let Some(code) = offer_sudo(command_line, report.outcomes.len())
+    {
+        return code.into();
+    }
+    match result {
+        Ok(report) => report.code,
+        Err(code) => code,
+    }
+    .into()
+}
 
-        if pids.is_empty() {
-            qprintln!("{process_name}: no process found");
-            process::exit(1);
+/// `--sudo`: asks whether to run `command_line` again under sudo for the
+/// `count` processes permission was denied for, and does it if so. Returns
+/// only if not, unless starting sudo failed.
+fn offer_sudo(command_line: &[OsString], count: usize) -> Option<ExitCode> {
+    let _ = io::stdout().flush();
+    let stdin = io::stdin();
+    if sys::euid() == 0 || !stdin.is_terminal() {
+        return None;
+    }
+    let confirmed = sudo::confirm(count, &mut stdin.lock(), &mut io::stderr());
+    match confirmed {
+        Ok(true) => {
+            let e = sudo::reexec(&sudo::without_flag(command_line));
+            eprintln!("{}: sudo: {e}", env!("CARGO_CRATE_NAME"));
+            Some(ExitCode::Internal)
         }
+        Ok(false) | Err(_) => None,
+    }
+}
 
-        for pid in pids {
-            if let Err(err) = kill(Pid::from_raw(pid), sig) {
-                qprintln!("Failed to send signal to {pid}: {err}");
-            } else if args.verbose {
-                println!("Killed {process_name}({pid}) with signal {}", sig as i32);
+/// Parses `argv` for the tool `argv[0]` names. Invalid command lines,
+/// `--help` and `--version` exit from here, except for a bad `--preset`.
+fn parse(argv: Vec<OsString>) -> Result<Invocation, ExitCode> {
+    Ok(
+        match argv.first().map(|argv0| Personality::from_argv0(argv0)) {
+            Some(Personality::Pgrep) => Invocation::Pgrep(parse::pgrep(argv)),
+            Some(Personality::Pkill) => {
+                Invocation::Pkill(parse::pkill(expand_signal_shorthand(argv)))
             }
-        }
-    }
+            Some(Personality::Killall) | None => {
+                let paths = || {
+                    let site = PathBuf::from(preset::SITE_PATH);
+                    preset::user_path()
+                        .into_iter()
+                        .chain([site])
+                        .collect::<Vec<_>>()
+                };
+                let mut argv = argv;
+                // Like the defaults of other options, overridden by the whole
+                // command line.
+                if let Some(name) = env::var_os(env_name("preset")).filter(|name| !name.is_empty())
+                {
+                    let mut arg = OsString::from("--preset=");
+                    arg.push(name);
+                    argv.insert(1, arg);
+                }
+                // Below the FAULX_* variables set, as clap takes those empty
+                // as unset.
+                let is_set = |name: &str| env::var_os(name).is_some_and(|value| !value.is_empty());
+                let load = || Presets::load(&paths()).map(|presets| presets.under_env(is_set));
+                let argv = preset::expand(argv, load).map_err(|e| {
+                    eprintln!("{}: --preset: {e}", env!("CARGO_CRATE_NAME"));
+                    ExitCode::Usage
+                })?;
+                let command_line = expand_signal_shorthand(argv);
+                Invocation::Killall {
+                    args: Box::new(parse::faulx(command_line.clone())),
+                    command_line,
+                }
+            }
+        },
+    )
 }
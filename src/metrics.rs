@@ -0,0 +1,108 @@
+//! Counters of what a run did, written in the Prometheus text format for the
+//! node exporter's textfile collector.
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::pipeline::Outcome;
+
+/// Running totals, to be rewritten after every round so that a collector
+/// reading the file always sees a complete set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Processes the filters matched.
+    pub matched: u64,
+    /// Processes the action succeeded on.
+    pub succeeded: u64,
+    /// Processes the action failed on.
+    pub failed: u64,
+    /// Zombies matched but left alone, counted apart from the others.
+    pub zombies_skipped: u64,
+}
+
+impl Metrics {
+    pub const fn record(&mut self, outcome: &Outcome) {
+        self.matched += 1;
+        if outcome.result.is_ok() {
+            self.succeeded += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+
+    /// Replaces `path` with the current totals. The file is written next to
+    /// it under a hidden name first, which the collector ignores, so it is
+    /// never read half-written.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+        let mut tmp_name = std::ffi::OsString::from(".");
+        tmp_name.push(name);
+        let tmp = path.with_file_name(tmp_name);
+        let mut file = fs::File::create(&tmp)?;
+        write!(file, "{self}")?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, help, value) in [
+            ("matched", "Processes matched.", self.matched),
+            (
+                "succeeded",
+                "Processes the action succeeded on.",
+                self.succeeded,
+            ),
+            ("failed", "Processes the action failed on.", self.failed),
+            (
+                "zombies_skipped",
+                "Zombie processes matched and left alone.",
+                self.zombies_skipped,
+            ),
+        ] {
+            writeln!(f, "# HELP faulx_{name}_total {help}")?;
+            writeln!(f, "# TYPE faulx_{name}_total counter")?;
+            writeln!(f, "faulx_{name}_total {value}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{processes::ProcessInfo, testing::pid};
+    use std::sync::Arc;
+
+    fn outcome(result: io::Result<()>) -> Outcome {
+        Outcome {
+            process: ProcessInfo::new(Arc::from(Path::new("/nonexistent")), pid(1), Vec::new()),
+            result,
+        }
+    }
+
+    #[test]
+    fn test_metrics_textfile() {
+        let mut metrics = Metrics::default();
+        metrics.record(&outcome(Ok(())));
+        metrics.record(&outcome(Err(io::Error::from_raw_os_error(libc::EPERM))));
+        metrics.record(&outcome(Ok(())));
+
+        let dir = std::env::temp_dir().join(format!("faulx-metrics-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("faulx.prom");
+        metrics.write_to(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("# TYPE faulx_matched_total counter\nfaulx_matched_total 3\n"));
+        assert!(text.contains("faulx_succeeded_total 2\n"));
+        assert!(text.contains("faulx_failed_total 1\n"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,65 @@
+//! `--ns-of PID`: runs in the PID and mount namespaces of another process,
+//! such as a container's init, so that names and PIDs are matched and
+//! signalled as the container sees them.
+
+use std::{fs::File, io, path::Path};
+
+use crate::{
+    exit::wait_status_code,
+    pid::Pid,
+    sys::{self, Fork, Namespace},
+};
+
+/// Which side of the fork `enter` returned on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entered {
+    /// In the process inside the namespaces, which carries on with the run.
+    Inside,
+    /// In the process left outside, with the exit status of the one inside.
+    Outside(u8),
+}
+
+/// Moves into the PID and mount namespaces of `pid`, as listed under
+/// `root`.
+///
+/// A new PID namespace only applies to children, so this forks: the child
+/// carries on inside, where `/proc` is the container's, and the parent
+/// waits for it.
+///
+/// Must be called before any other thread is started, as the kernel does
+/// not move a multithreaded process to another mount namespace. Needs
+/// `CAP_SYS_ADMIN`.
+pub fn enter(root: &Path, pid: Pid) -> io::Result<Entered> {
+    let dir = root.join(pid.to_string()).join("ns");
+    let pid_ns = File::open(dir.join("pid"))?;
+    let mnt_ns = File::open(dir.join("mnt"))?;
+    sys::setns(&pid_ns, Namespace::Pid)?;
+    sys::setns(&mnt_ns, Namespace::Mount)?;
+    // SAFETY: the process is single-threaded, as required of callers, so
+    // the child cannot inherit a lock held by another thread.
+    match unsafe { sys::fork() }? {
+        Fork::Child => Ok(Entered::Inside),
+        Fork::Parent(child) => loop {
+            if let Some(code) = wait_status_code(sys::waitpid(Some(child), false)?) {
+                return Ok(Entered::Outside(code));
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir};
+
+    #[test]
+    fn test_enter_without_namespaces() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "init")]);
+
+        let err = enter(&tmp, pid(10)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,178 @@
+//! `--notify`: a desktop notification of how a run went, with the `notify`
+//! feature, for a long `--wait` started in a terminal and forgotten.
+//!
+//! It goes to `org.freedesktop.Notifications` on the session bus, which
+//! libnotify uses and every notification daemon serves.
+
+use std::{collections::HashMap, fmt::Write as _};
+
+use zbus::{blocking::Connection, proxy, zvariant::Value};
+
+use crate::{app::Report, exit::ExitCode};
+
+/// Lets the notification daemon decide how long it is shown.
+const DEFAULT_EXPIRY: i32 = -1;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: &HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// The title and text of the notification of a run on `targets`, the names
+/// or PIDs it was given, that ended with `report`.
+#[must_use]
+pub fn message(targets: &str, report: &Report) -> (String, String) {
+    let title = format!("{} {targets}", env!("CARGO_CRATE_NAME"));
+    let failed = report
+        .outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_err())
+        .count();
+    let succeeded = report.outcomes.len() - failed;
+    let mut body = match (succeeded, failed) {
+        (0, 0) => "No process found".to_owned(),
+        (succeeded, 0) => format!("Acted on {succeeded} {}", processes(succeeded)),
+        (succeeded, failed) => format!(
+            "Acted on {succeeded} of {} processes, {failed} failed",
+            succeeded + failed
+        ),
+    };
+    let exited = report
+        .waited
+        .iter()
+        .filter(|(_, waited)| waited.exited.is_some())
+        .count();
+    let escalated = report
+        .waited
+        .iter()
+        .filter(|(_, waited)| waited.expired)
+        .count();
+    if !report.waited.is_empty() {
+        let _ = write!(body, ", {exited} exited");
+    }
+    if escalated > 0 {
+        let _ = write!(body, ", {escalated} killed after the timeout");
+    }
+    if report.respawns > 0 {
+        let _ = write!(body, ", {} respawned", report.respawns);
+    }
+    if report.code == ExitCode::Interrupted {
+        body.push_str(", interrupted");
+    }
+    (title, body)
+}
+
+const fn processes(n: usize) -> &'static str {
+    if n == 1 { "process" } else { "processes" }
+}
+
+/// Shows the notification `message` gives for `report`.
+pub fn send(targets: &str, report: &Report) -> zbus::Result<()> {
+    let (title, body) = message(targets, report);
+    let connection = Connection::session()?;
+    NotificationsProxyBlocking::new(&connection)?.notify(
+        env!("CARGO_CRATE_NAME"),
+        0,
+        "",
+        &title,
+        &body,
+        &[],
+        &HashMap::new(),
+        DEFAULT_EXPIRY,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, path::Path, sync::Arc, time::Instant};
+
+    use super::*;
+    use crate::{
+        pipeline::Outcome,
+        processes::{ProcessIdent, ProcessInfo},
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir},
+        wait::Waited,
+    };
+
+    #[test]
+    fn test_message() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx")]);
+        let root: Arc<Path> = Arc::from(tmp.as_path());
+        let outcome = |raw, result| Outcome {
+            process: ProcessInfo::by_pid(Arc::clone(&root), pid(raw)),
+            result,
+        };
+        let waited = |raw, exited: bool, expired| {
+            let now = Instant::now();
+            let ident = ProcessIdent {
+                pid: pid(raw),
+                starttime: 1,
+            };
+            let exited = exited.then_some(now);
+            (
+                now,
+                Waited {
+                    ident,
+                    exited,
+                    expired,
+                },
+            )
+        };
+
+        let mut report = Report {
+            outcomes: Vec::new(),
+            code: ExitCode::NoneMatched,
+            waited: Vec::new(),
+            respawns: 0,
+        };
+        assert_eq!(
+            message("nginx", &report),
+            ("faulx nginx".to_owned(), "No process found".to_owned())
+        );
+        report.outcomes = vec![outcome(10, Ok(()))];
+        assert_eq!(message("nginx", &report).1, "Acted on 1 process");
+        report
+            .outcomes
+            .push(outcome(11, Err(io::Error::from_raw_os_error(libc::EPERM))));
+        assert_eq!(
+            message("nginx", &report).1,
+            "Acted on 1 of 2 processes, 1 failed"
+        );
+        report.outcomes[1].result = Ok(());
+        report.waited = vec![waited(10, true, false), waited(11, true, true)];
+        assert_eq!(
+            message("nginx", &report).1,
+            "Acted on 2 processes, 2 exited, 1 killed after the timeout"
+        );
+        report.respawns = 1;
+        assert_eq!(
+            message("nginx", &report).1,
+            "Acted on 2 processes, 2 exited, 1 killed after the timeout, 1 respawned"
+        );
+        report.respawns = 0;
+        report.code = ExitCode::Interrupted;
+        report.waited.pop();
+        assert_eq!(
+            message("nginx", &report).1,
+            "Acted on 2 processes, 1 exited, interrupted"
+        );
+        cleanup_fake_proc(&tmp);
+    }
+}
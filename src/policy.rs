@@ -0,0 +1,160 @@
+//! The administrator's policy for faulx, read from `/etc/faulx/policy`:
+//! `key = value` lines, with `#` comments, e.g.
+//!
+//! ```text
+//! # Regulated hosts: say why before signalling someone else's process.
+//! require_reason = other-users
+//! ```
+
+use std::{fmt, fs, io, path::Path, str::FromStr};
+
+use crate::processes::ProcessInfo;
+
+/// Where `main` reads the policy from, before entering any namespace.
+pub const POLICY_PATH: &str = "/etc/faulx/policy";
+
+/// When `--reason` must be given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RequireReason {
+    #[default]
+    Never,
+    /// To act on processes owned by another user than the one running
+    /// faulx, or whose owner can't be read.
+    OtherUsers,
+    /// To act on any process.
+    Always,
+}
+
+impl FromStr for RequireReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Self::Never),
+            "other-users" => Ok(Self::OtherUsers),
+            "always" => Ok(Self::Always),
+            _ => Err(format!(
+                "invalid require_reason {s:?}, expected never, other-users or always"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RequireReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Never => "never",
+            Self::OtherUsers => "other-users",
+            Self::Always => "always",
+        })
+    }
+}
+
+/// What the policy file says, everything allowed if there is none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SitePolicy {
+    pub require_reason: RequireReason,
+}
+
+impl SitePolicy {
+    /// Reads the policy at `path`, the default one if there is no file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Parses the contents of a policy file. Unknown keys are errors, so
+    /// that a misspelt rule is not silently ignored.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut policy = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split_once('#').map_or(line, |(rule, _)| rule).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected key = value", n + 1))?;
+            match key.trim() {
+                "require_reason" => {
+                    policy.require_reason = value
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("line {}: {e}", n + 1))?;
+                }
+                key => return Err(format!("line {}: unknown key {key:?}", n + 1)),
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Whether acting on `processes` as `uid` needs a `--reason`.
+    #[must_use]
+    pub fn needs_reason(&self, uid: u32, processes: &[ProcessInfo]) -> bool {
+        match self.require_reason {
+            RequireReason::Never => false,
+            RequireReason::OtherUsers => processes.iter().any(|process| process.uid() != Some(uid)),
+            RequireReason::Always => !processes.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(SitePolicy::parse(""), Ok(SitePolicy::default()));
+        assert_eq!(
+            SitePolicy::parse("# audited\nrequire_reason = other-users # see ticket\n")
+                .map(|policy| policy.require_reason),
+            Ok(RequireReason::OtherUsers)
+        );
+        assert_eq!(
+            SitePolicy::parse("\nrequire_reasons = always\n"),
+            Err("line 2: unknown key \"require_reasons\"".to_owned())
+        );
+        assert!(SitePolicy::parse("require_reason = sometimes").is_err());
+        assert!(SitePolicy::parse("require_reason").is_err());
+        assert_eq!(
+            SitePolicy::load(Path::new("/nonexistent/policy")).unwrap(),
+            SitePolicy::default()
+        );
+    }
+
+    #[test]
+    fn test_needs_reason() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx")]);
+        fs::write(
+            tmp.join("10/status"),
+            "Name:\tnginx\nUid:\t1000\t1000\t1000\t1000\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.join("11/status"),
+            "Name:\tnginx\nUid:\t33\t33\t33\t33\n",
+        )
+        .unwrap();
+        let process = |pid| ProcessInfo::by_pid(Arc::from(tmp.as_path()), pid);
+        let own = [process(pid(10))];
+        let both = [process(pid(10)), process(pid(11))];
+        let policy = |require_reason| SitePolicy { require_reason };
+
+        assert!(!policy(RequireReason::Never).needs_reason(1000, &both));
+        assert!(!policy(RequireReason::OtherUsers).needs_reason(1000, &own));
+        assert!(policy(RequireReason::OtherUsers).needs_reason(1000, &both));
+        assert!(policy(RequireReason::Always).needs_reason(1000, &own));
+        assert!(!policy(RequireReason::Always).needs_reason(1000, &[]));
+
+        cleanup_fake_proc(&tmp);
+    }
+}
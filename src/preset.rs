@@ -0,0 +1,289 @@
+//! `--preset NAME`: options saved under a name, so that a routine operation
+//! is run the same way every time instead of retyped, e.g.
+//!
+//! ```text
+//! # Let the workers finish their jobs, then make sure they're gone.
+//! [drain-workers]
+//! names = worker
+//! user = www-data
+//! signal = TERM
+//! wait = true
+//! ```
+//!
+//! Keys are long option names, as in the `FAULX_*` variables, and `true`
+//! stands for a flag. `names` lists process names, separated by spaces.
+//! Repeat a key for options taking several values.
+//!
+//! Presets are the lowest of defaults: the command line overrides them, and
+//! so does any `FAULX_*` variable that is set.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::cli::env_name;
+
+/// The presets of the whole host, shadowed by the user's own.
+pub const SITE_PATH: &str = "/etc/faulx/presets";
+
+/// `$XDG_CONFIG_HOME/faulx/presets`, or `~/.config/faulx/presets`.
+#[must_use]
+pub fn user_path() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("faulx/presets"))
+}
+
+/// Presets by name, each with the arguments it stands for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Presets(Vec<(String, Vec<OsString>)>);
+
+impl Presets {
+    /// Reads the presets at `paths`, skipping files that don't exist. A
+    /// preset is taken from the first file defining it.
+    pub fn load(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut presets = Self::default();
+        for path in paths {
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let parsed = Self::parse(&text).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {e}", path.display()),
+                )
+            })?;
+            for (name, args) in parsed.0 {
+                if presets.get(&name).is_none() {
+                    presets.0.push((name, args));
+                }
+            }
+        }
+        Ok(presets)
+    }
+
+    /// Parses the contents of a presets file.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut presets: Vec<(String, Vec<OsString>)> = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                let name = name.trim();
+                if presets.iter().any(|(known, _)| known == name) {
+                    return Err(format!("line {}: preset {name} defined twice", n + 1));
+                }
+                presets.push((name.to_owned(), Vec::new()));
+                continue;
+            }
+            let Some((_, args)) = presets.last_mut() else {
+                return Err(format!("line {}: expected [name]", n + 1));
+            };
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected key = value", n + 1))?;
+            let (key, value) = (key.trim().replace('_', "-"), value.trim());
+            if key.is_empty() || key.starts_with('-') {
+                return Err(format!("line {}: invalid key {key:?}", n + 1));
+            }
+            match (key.as_str(), value) {
+                ("preset", _) => {
+                    return Err(format!("line {}: presets can't include presets", n + 1));
+                }
+                ("names", names) => args.extend(names.split_whitespace().map(OsString::from)),
+                (_, "true") => args.push(format!("--{key}").into()),
+                (_, "false") => {}
+                (_, value) => args.push(format!("--{key}={value}").into()),
+            }
+        }
+        Ok(Self(presets))
+    }
+
+    /// Drops the options of every preset whose `FAULX_*` variable
+    /// `is_set`, so that the environment takes precedence over them.
+    #[must_use]
+    pub fn under_env(mut self, is_set: impl Fn(&str) -> bool) -> Self {
+        for (_, args) in &mut self.0 {
+            args.retain(|arg| {
+                let option = arg.to_str().and_then(|arg| arg.strip_prefix("--"));
+                option.is_none_or(|option| {
+                    let key = option.split_once('=').map_or(option, |(key, _)| key);
+                    !is_set(&env_name(key))
+                })
+            });
+        }
+        self
+    }
+
+    /// The arguments saved under `name`.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&[OsString]> {
+        self.0
+            .iter()
+            .find(|(known, _)| known == name)
+            .map(|(_, args)| args.as_slice())
+    }
+}
+
+/// Replaces `--preset NAME` in `args` with the arguments saved under
+/// `NAME`, before `--`. Options given after it override the preset's.
+/// `load` is only called if there is a preset to expand.
+pub fn expand(
+    args: Vec<OsString>,
+    load: impl FnOnce() -> io::Result<Presets>,
+) -> Result<Vec<OsString>, String> {
+    let mut load = Some(load);
+    let mut presets = Presets::default();
+    let mut expanded = Vec::with_capacity(args.len());
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            expanded.push(arg);
+            expanded.extend(args);
+            break;
+        }
+        let name = match arg.to_str() {
+            Some("--preset") => args
+                .next()
+                .ok_or("a value is required for --preset")?
+                .into_string()
+                .map_err(|name| format!("no preset {}", name.display()))?,
+            Some(arg) if arg.starts_with("--preset=") => arg["--preset=".len()..].to_owned(),
+            _ => {
+                expanded.push(arg);
+                continue;
+            }
+        };
+        if let Some(load) = load.take() {
+            presets = load().map_err(|e| e.to_string())?;
+        }
+        let saved = presets
+            .get(&name)
+            .ok_or_else(|| format!("no preset {name}"))?;
+        expanded.extend_from_slice(saved);
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    const PRESETS: &str = "\
+# Routine.
+[drain-workers]
+names = worker janitor
+user = www-data
+signal = TERM
+wait = true
+include_zombies = false
+
+[stray]
+where = comm~^tmp && age>1d
+";
+
+    #[test]
+    fn test_parse() {
+        let presets = Presets::parse(PRESETS).unwrap();
+        assert_eq!(
+            presets.get("drain-workers").unwrap(),
+            args(&[
+                "worker",
+                "janitor",
+                "--user=www-data",
+                "--signal=TERM",
+                "--wait"
+            ])
+        );
+        assert_eq!(
+            presets.get("stray").unwrap(),
+            args(&["--where=comm~^tmp && age>1d"])
+        );
+        assert_eq!(presets.get("drain"), None);
+
+        assert_eq!(
+            Presets::parse("signal = KILL"),
+            Err("line 1: expected [name]".to_owned())
+        );
+        assert!(Presets::parse("[a]\n[a]").is_err());
+        assert!(Presets::parse("[a]\npreset = b").is_err());
+        assert!(Presets::parse("[a]\nwait").is_err());
+    }
+
+    #[test]
+    fn test_under_env() {
+        let presets = Presets::parse(PRESETS)
+            .unwrap()
+            .under_env(|name| ["FAULX_SIGNAL", "FAULX_WAIT"].contains(&name));
+        assert_eq!(
+            presets.get("drain-workers").unwrap(),
+            args(&["worker", "janitor", "--user=www-data"])
+        );
+        assert_eq!(
+            presets.get("stray").unwrap(),
+            args(&["--where=comm~^tmp && age>1d"])
+        );
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = env::temp_dir().join(format!("faulx-presets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (user, site) = (dir.join("user"), dir.join("site"));
+        fs::write(&user, "[stray]\nnames = tmp\n").unwrap();
+        fs::write(&site, PRESETS).unwrap();
+
+        let presets = Presets::load(&[user, dir.join("missing"), site]).unwrap();
+        assert_eq!(presets.get("stray").unwrap(), args(&["tmp"]));
+        assert!(presets.get("drain-workers").is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand() {
+        let presets = || Presets::parse(PRESETS).map_err(io::Error::other);
+        assert_eq!(
+            expand(
+                args(&["faulx", "--preset", "drain-workers", "-s", "KILL"]),
+                presets
+            ),
+            Ok(args(&[
+                "faulx",
+                "worker",
+                "janitor",
+                "--user=www-data",
+                "--signal=TERM",
+                "--wait",
+                "-s",
+                "KILL"
+            ]))
+        );
+        assert_eq!(
+            expand(args(&["faulx", "-v", "--preset=stray"]), presets),
+            Ok(args(&["faulx", "-v", "--where=comm~^tmp && age>1d"]))
+        );
+        assert_eq!(
+            expand(args(&["faulx", "--preset=nope"]), presets),
+            Err("no preset nope".to_owned())
+        );
+        let unread = || -> io::Result<Presets> { panic!("presets read for nothing") };
+        assert_eq!(
+            expand(args(&["faulx", "--", "--preset=stray"]), unread),
+            Ok(args(&["faulx", "--", "--preset=stray"]))
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! `--profile`: how long each phase of a run took and how many processes
+//! each filter let through, printed at the end like the shell's `time`, to
+//! help tune patterns and features such as `rayon`.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{filters::Filter, pipeline::Pipeline, processes::ProcessInfo};
+
+/// The phases of a run, in the order they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Reading `/proc`, filters included.
+    Scan,
+    /// Applying the action to each process selected.
+    Act,
+    /// `--wait`.
+    Wait,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Scan => "scan",
+            Self::Act => "act",
+            Self::Wait => "wait",
+        })
+    }
+}
+
+/// Counts shared between a filter and the profile it reports to.
+#[derive(Debug, Default)]
+struct FilterStats {
+    checked: AtomicU64,
+    hits: AtomicU64,
+    nanos: AtomicU64,
+}
+
+/// A filter timed and counted for `--profile`.
+#[derive(Debug)]
+pub struct Profiled<F> {
+    inner: F,
+    stats: Arc<FilterStats>,
+}
+
+impl<F: Filter> Filter for Profiled<F> {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        let start = Instant::now();
+        let hit = self.inner.matches(process);
+        let nanos = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.stats.nanos.fetch_add(nanos, Ordering::Relaxed);
+        self.stats.checked.fetch_add(1, Ordering::Relaxed);
+        if hit {
+            self.stats.hits.fetch_add(1, Ordering::Relaxed);
+        }
+        hit
+    }
+}
+
+/// Phase timings and filter counts, gathered over a run.
+#[derive(Debug, Default)]
+pub struct Profile {
+    phases: RefCell<Vec<(Phase, Duration)>>,
+    filters: RefCell<Vec<(String, Arc<FilterStats>)>>,
+}
+
+impl Profile {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `f`, adding the time it took to `phase`.
+    pub fn time<R>(&self, phase: Phase, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.add(phase, start.elapsed());
+        result
+    }
+
+    /// Adds `elapsed` to `phase`.
+    pub fn add(&self, phase: Phase, elapsed: Duration) {
+        let mut phases = self.phases.borrow_mut();
+        match phases.iter_mut().find(|(known, _)| *known == phase) {
+            Some((_, total)) => *total += elapsed,
+            None => phases.push((phase, elapsed)),
+        }
+    }
+
+    /// Wraps `filter` to be counted under `label`, together with the other
+    /// filters of the same label.
+    pub fn wrap<F: Filter>(&self, label: &str, filter: F) -> Profiled<F> {
+        let mut filters = self.filters.borrow_mut();
+        let known = filters.iter().find(|(known, _)| known == label);
+        let stats = known.map_or_else(Arc::default, |(_, stats)| Arc::clone(stats));
+        if known.is_none() {
+            filters.push((label.to_owned(), Arc::clone(&stats)));
+        }
+        Profiled {
+            inner: filter,
+            stats,
+        }
+    }
+
+    /// Writes the phases, then each filter with how many processes it
+    /// checked, let through and spent time on, summed over threads.
+    pub fn write_to(&self, out: &mut dyn Write) -> io::Result<()> {
+        let phases = self.phases.borrow();
+        let total: Duration = phases.iter().map(|(_, elapsed)| *elapsed).sum();
+        for (phase, elapsed) in phases.iter() {
+            writeln!(out, "{:<8}{}", phase.to_string(), Millis(*elapsed))?;
+        }
+        writeln!(out, "{:<8}{}", "total", Millis(total))?;
+        #[cfg(feature = "rayon")]
+        {
+            let threads = rayon::current_num_threads();
+            let plural = if threads == 1 { "" } else { "s" };
+            writeln!(out, "scanned on {threads} thread{plural}")?;
+        }
+        for (label, stats) in self.filters.borrow().iter() {
+            writeln!(
+                out,
+                "filter {label}: {} checked, {} matched, {}",
+                stats.checked.load(Ordering::Relaxed),
+                stats.hits.load(Ordering::Relaxed),
+                Millis(Duration::from_nanos(stats.nanos.load(Ordering::Relaxed)))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `f`, timed as `phase` if profiling.
+pub fn timed<R>(profile: Option<&Profile>, phase: Phase, f: impl FnOnce() -> R) -> R {
+    match profile {
+        Some(profile) => profile.time(phase, f),
+        None => f(),
+    }
+}
+
+/// Adds `filter` to `pipeline`, counted under `label` if profiling.
+#[must_use]
+pub fn add_filter(
+    pipeline: Pipeline,
+    profile: Option<&Profile>,
+    label: &str,
+    filter: impl Filter + 'static,
+) -> Pipeline {
+    match profile {
+        Some(profile) => pipeline.filter(profile.wrap(label, filter)),
+        None => pipeline.filter(filter),
+    }
+}
+
+/// Shows a duration in milliseconds, as in `12.345ms`.
+struct Millis(Duration);
+
+impl fmt::Display for Millis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.3}ms", self.0.as_secs_f64() * 1000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        filters::NameFilter,
+        processes::Scanner,
+        testing::{cleanup_fake_proc, setup_fake_proc, unique_test_dir},
+    };
+
+    #[test]
+    fn test_profile() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "sshd"), ("12", "nginx")]);
+
+        let profile = Profile::new();
+        for _ in 0..2 {
+            let pipeline = Pipeline::new(Scanner::new(&tmp));
+            let pipeline = add_filter(
+                pipeline,
+                Some(&profile),
+                "name nginx",
+                NameFilter::new("nginx"),
+            );
+            let matched = profile.time(Phase::Scan, || pipeline.matches().unwrap());
+            assert_eq!(matched.len(), 2);
+        }
+        profile.time(Phase::Act, || {});
+
+        let mut out = Vec::new();
+        profile.write_to(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert!(lines[0].starts_with("scan    "));
+        assert!(lines[1].starts_with("act     "));
+        assert!(lines[2].starts_with("total   "));
+        assert!(
+            lines
+                .last()
+                .unwrap()
+                .starts_with("filter name nginx: 6 checked, 4 matched, ")
+        );
+
+        cleanup_fake_proc(&tmp);
+    }
+}
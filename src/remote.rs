@@ -0,0 +1,215 @@
+//! `--host`: runs the same command on other machines over SSH, all hosts at
+//! once, and collects what each printed.
+
+use std::{
+    ffi::OsString,
+    fmt::Write,
+    io,
+    process::{Command, Output, Stdio},
+    thread,
+};
+
+use crate::limits::OriginalNofileLimit;
+
+/// Program run on the remote hosts unless told otherwise.
+pub const DEFAULT_PROGRAM: &str = "faulx";
+
+/// How to reach the hosts and what to run there.
+#[derive(Debug, Clone)]
+pub struct Remote {
+    ssh: OsString,
+    program: String,
+}
+
+/// What one host printed and how its command exited.
+#[derive(Debug)]
+pub struct HostResult {
+    pub host: String,
+    pub output: io::Result<Output>,
+}
+
+impl HostResult {
+    /// Whether ssh ran and the remote command exited with status 0.
+    #[must_use]
+    pub fn success(&self) -> bool {
+        self.output.as_ref().is_ok_and(|o| o.status.success())
+    }
+}
+
+impl Default for Remote {
+    fn default() -> Self {
+        Self {
+            ssh: OsString::from("ssh"),
+            program: DEFAULT_PROGRAM.to_owned(),
+        }
+    }
+}
+
+impl Remote {
+    /// Uses `ssh` instead of the `ssh` found in `PATH`.
+    #[must_use]
+    pub fn ssh(mut self, ssh: impl Into<OsString>) -> Self {
+        self.ssh = ssh.into();
+        self
+    }
+
+    /// Runs `program` on the hosts instead of `faulx`.
+    #[must_use]
+    pub fn program(mut self, program: impl Into<String>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// The ssh invocation running the program with `args` on `host`. ssh
+    /// joins the command into a shell line, so every argument is quoted.
+    fn command(&self, host: &str, args: &[String]) -> Command {
+        let line = std::iter::once(self.program.as_str())
+            .chain(args.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut command = Command::new(&self.ssh);
+        command
+            .original_nofile_limit()
+            .args(["-o", "BatchMode=yes", "--", host])
+            .arg(line)
+            .stdin(Stdio::null());
+        command
+    }
+
+    /// Runs the program with `args` on every host in parallel, returning the
+    /// results in the order of `hosts`.
+    #[must_use]
+    pub fn run(&self, hosts: &[String], args: &[String]) -> Vec<HostResult> {
+        thread::scope(|scope| {
+            let running: Vec<_> = hosts
+                .iter()
+                .map(|host| scope.spawn(|| self.command(host, args).output()))
+                .collect();
+            hosts
+                .iter()
+                .zip(running)
+                .map(|(host, running)| HostResult {
+                    host: host.clone(),
+                    output: running
+                        .join()
+                        .unwrap_or_else(|_| Err(io::Error::other("ssh thread panicked"))),
+                })
+                .collect()
+        })
+    }
+}
+
+/// Quotes `arg` for a POSIX shell, leaving plain words alone.
+#[must_use]
+pub fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_./=:,+@%".contains(&b));
+    if plain {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Options that only make sense locally, with the value they take.
+const LOCAL_OPTIONS: [&str; 2] = ["--host", "--remote-program"];
+
+/// The arguments to pass on to the hosts: `args` without the program name
+/// and without the local options, so that `--host` does not recurse.
+#[must_use]
+pub fn forwarded_args(args: &[OsString]) -> Vec<String> {
+    let mut forwarded = Vec::new();
+    let mut args = args.iter().skip(1).map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            forwarded.push(arg.into_owned());
+            forwarded.extend(args.map(std::borrow::Cow::into_owned));
+            break;
+        }
+        let local = LOCAL_OPTIONS.iter().find(|&&option| {
+            arg.strip_prefix(option)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('='))
+        });
+        match local {
+            Some(option) if arg == *option => {
+                args.next();
+            }
+            Some(_) => {}
+            None => forwarded.push(arg.into_owned()),
+        }
+    }
+    forwarded
+}
+
+/// Splits `output` into lines prefixed with `host: `, for interleaving the
+/// output of several hosts.
+#[must_use]
+pub fn prefix_lines(host: &str, output: &[u8]) -> String {
+    let mut prefixed = String::new();
+    for line in String::from_utf8_lossy(output).lines() {
+        let _ = writeln!(prefixed, "{host}: {line}");
+    }
+    prefixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("nginx"), "nginx");
+        assert_eq!(shell_quote("--signal=HUP"), "--signal=HUP");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$(reboot)"), "'$(reboot)'");
+    }
+
+    #[test]
+    fn test_forwarded_args_drop_host() {
+        let args: Vec<OsString> = [
+            "faulx",
+            "--host",
+            "a,b",
+            "-s",
+            "HUP",
+            "--host=c",
+            "--remote-program=fx",
+            "nginx",
+        ]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+        assert_eq!(forwarded_args(&args), ["-s", "HUP", "nginx"]);
+
+        let args: Vec<OsString> = ["faulx", "--", "--host"]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        assert_eq!(forwarded_args(&args), ["--", "--host"]);
+    }
+
+    #[test]
+    fn test_run_collects_per_host() {
+        // `echo` stands in for ssh, printing the command it would run.
+        let remote = Remote::default().ssh("echo");
+        let results = remote.run(
+            &["h1".to_owned(), "h2".to_owned()],
+            &["-s".to_owned(), "it's".to_owned()],
+        );
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(HostResult::success));
+        let output = &results[1].output.as_ref().unwrap().stdout;
+        assert_eq!(
+            prefix_lines(&results[1].host, output),
+            "h2: -o BatchMode=yes -- h2 faulx -s 'it'\\''s'\n"
+        );
+
+        let results = Remote::default().ssh("false").run(&["h1".to_owned()], &[]);
+        assert!(!results[0].success());
+    }
+}
@@ -0,0 +1,126 @@
+//! `--output json` for runs acting on processes: what became of each, so
+//! that orchestrators can tell services that shut down gracefully from
+//! those that had to be killed.
+
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::{pipeline::Outcome, wait::Waited};
+
+/// What became of one process.
+#[derive(Debug, Serialize)]
+struct ProcessResult<'a> {
+    pid: i32,
+    /// The name, with what isn't UTF-8 in it replaced.
+    comm: String,
+    /// The name as it is, byte for byte.
+    comm_bytes: &'a [u8],
+    /// `ok`, or the error the action failed with.
+    result: String,
+    /// Whether it exited before the wait stopped, unless it wasn't waited
+    /// for.
+    exited: Option<bool>,
+    /// How long it took to exit after it was signalled, to within the
+    /// interval the wait checks at.
+    wait_ms: Option<u64>,
+    /// Whether it outlasted `--timeout` and was sent SIGKILL.
+    escalated: bool,
+}
+
+/// The results of `outcomes` as a JSON array, with how the wait went for
+/// those `waited` for, given when each of them was signalled.
+pub fn to_json(outcomes: &[Outcome], waited: &[(Instant, Waited)]) -> serde_json::Result<String> {
+    let results: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| {
+            let pid = outcome.process.pid;
+            let wait = waited.iter().find(|(_, waited)| waited.ident.pid == pid);
+            ProcessResult {
+                pid: pid.as_raw(),
+                comm: String::from_utf8_lossy(&outcome.process.comm).into_owned(),
+                comm_bytes: &outcome.process.comm,
+                result: outcome
+                    .result
+                    .as_ref()
+                    .map_or_else(ToString::to_string, |()| "ok".to_owned()),
+                exited: wait.map(|(_, waited)| waited.exited.is_some()),
+                wait_ms: wait.and_then(|(at, waited)| {
+                    let took = waited.exited?.saturating_duration_since(*at);
+                    u64::try_from(took.as_millis()).ok()
+                }),
+                escalated: wait.is_some_and(|(_, waited)| waited.expired),
+            }
+        })
+        .collect();
+    serde_json::to_string(&results)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io, sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::{
+        processes::{ProcessIdent, ProcessInfo},
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir},
+    };
+
+    #[test]
+    fn test_to_json() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "nginx"), ("11", "nginx")]);
+        fs::create_dir_all(tmp.join("12")).unwrap();
+        fs::write(tmp.join("12/comm"), b"ng\xffx\n").unwrap();
+        let root: Arc<std::path::Path> = Arc::from(tmp.as_path());
+        let outcome = |raw, result| Outcome {
+            process: ProcessInfo::by_pid(Arc::clone(&root), pid(raw)),
+            result,
+        };
+        let outcomes = [
+            outcome(10, Ok(())),
+            outcome(11, Ok(())),
+            outcome(12, Err(io::Error::from_raw_os_error(libc::EPERM))),
+        ];
+        let ident = |raw| ProcessIdent {
+            pid: pid(raw),
+            starttime: 1,
+        };
+        let start = Instant::now();
+        let waited = [
+            (
+                start,
+                Waited {
+                    ident: ident(10),
+                    exited: Some(start + Duration::from_millis(1500)),
+                    expired: false,
+                },
+            ),
+            (
+                start,
+                Waited {
+                    ident: ident(11),
+                    exited: Some(start + Duration::from_secs(6)),
+                    expired: true,
+                },
+            ),
+        ];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&to_json(&outcomes, &waited).unwrap()).unwrap();
+        let nginx = b"nginx";
+        assert_eq!(
+            json,
+            serde_json::json!([
+                {"pid": 10, "comm": "nginx", "comm_bytes": nginx, "result": "ok",
+                 "exited": true, "wait_ms": 1500, "escalated": false},
+                {"pid": 11, "comm": "nginx", "comm_bytes": nginx, "result": "ok",
+                 "exited": true, "wait_ms": 6000, "escalated": true},
+                {"pid": 12, "comm": "ng\u{fffd}x", "comm_bytes": [110, 103, 255, 120],
+                 "result": "Operation not permitted (os error 1)",
+                 "exited": null, "wait_ms": null, "escalated": false},
+            ])
+        );
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,123 @@
+//! `faulx --self-test`: runs faulx against children of its own.
+//!
+//! Packagers can check with it on their architecture, without the test
+//! suite, that faulx matches, signals and waits for processes as it should.
+
+use std::{
+    ffi::CString,
+    fs, io,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    pid::Pid,
+    signals::{Signal, send_signal},
+    sys::{self, Fork, SignalSet, WaitStatus},
+};
+
+/// How long the child ignoring SIGTERM gets before it is sent SIGKILL.
+pub const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long children get to rename themselves once forked.
+const RENAME_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A child doing nothing under the name it gave itself, until it is
+/// killed. SIGKILL ends it when dropped, if nothing did before.
+#[derive(Debug)]
+pub struct Dummy {
+    pub pid: Pid,
+}
+
+impl Dummy {
+    /// Forks a child naming itself `name`, which ignores SIGTERM if
+    /// `stubborn`.
+    pub fn spawn(name: &str, stubborn: bool) -> io::Result<Self> {
+        let name = CString::new(name).map_err(io::Error::other)?;
+        // SAFETY: the child only makes async-signal-safe calls.
+        match unsafe { sys::fork() }? {
+            Fork::Parent(pid) => Ok(Self { pid }),
+            Fork::Child => idle(&name, stubborn),
+        }
+    }
+
+    /// Whether the child calls itself `name`, waiting a while for it to
+    /// have renamed itself.
+    #[must_use]
+    pub fn renamed(&self, root: &Path, name: &str) -> bool {
+        let started = Instant::now();
+        let path = root.join(self.pid.to_string()).join("comm");
+        loop {
+            if fs::read_to_string(&path).is_ok_and(|comm| comm.trim_end_matches('\n') == name) {
+                return true;
+            }
+            if started.elapsed() >= RENAME_TIMEOUT {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Whether the child is still running. A zombie is reaped, which keeps
+    /// its PID from being reused until then.
+    pub fn running(&self) -> io::Result<bool> {
+        match sys::waitpid(Some(self.pid), true) {
+            Ok(WaitStatus::StillAlive | WaitStatus::Changed(_)) => Ok(true),
+            Ok(_) => Ok(false),
+            // Already reaped, by the wait of the run.
+            Err(e) if e.raw_os_error() == Some(libc::ECHILD) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for Dummy {
+    fn drop(&mut self) {
+        // Unless it is still there unreaped, its PID may be another
+        // process's by now.
+        if self.running().unwrap_or(false) {
+            let _ = send_signal(self.pid, Signal::KILL);
+            let _ = sys::waitpid(Some(self.pid), false);
+        }
+    }
+}
+
+/// What a `Dummy` runs in the child, until it is killed.
+fn idle(name: &CString, stubborn: bool) -> ! {
+    // Leaves no child behind if the self-test dies first.
+    let _ = sys::set_parent_death_signal(Signal::KILL);
+    let _ = SignalSet::new(&[Signal::TERM]).unblock();
+    if stubborn {
+        // SAFETY: the handler does nothing at all.
+        let _ = unsafe { sys::catch(Signal::TERM, ignore) };
+    }
+    let _ = sys::set_name(name);
+    loop {
+        thread::sleep(Duration::from_secs(3600));
+    }
+}
+
+const extern "C" fn ignore(_: libc::c_int) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processes::PROC;
+
+    #[test]
+    fn test_dummy() {
+        let name = format!("fxdm{}", std::process::id() % 100_000);
+        let dummy = Dummy::spawn(&name, true).unwrap();
+        assert!(dummy.renamed(Path::new(PROC), &name));
+        send_signal(dummy.pid, Signal::TERM).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(dummy.running().unwrap());
+        send_signal(dummy.pid, Signal::KILL).unwrap();
+        assert_eq!(
+            sys::waitpid(Some(dummy.pid), false).unwrap(),
+            WaitStatus::Signaled(dummy.pid, libc::SIGKILL)
+        );
+        assert!(!dummy.running().unwrap());
+    }
+}
@@ -0,0 +1,169 @@
+//! `--session-scope`: limits matches to the login session faulx runs in.
+//!
+//! Sessions are found as logind lays them out in the cgroup tree, so that
+//! `faulx firefox` on a machine several people are logged into leaves the
+//! others' alone. A session's processes sit under `session-<id>.scope`,
+//! those its user's service manager starts, as desktops do for
+//! applications, under `user@<uid>.service`. The latter is shared by all
+//! sessions of the user.
+
+use std::{env, fs, path::Path};
+
+use crate::{
+    filters::Filter,
+    processes::{ProcessInfo, systemd_cgroup},
+    sys,
+};
+
+/// A logind session, and the user it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub id: String,
+    pub uid: u32,
+}
+
+impl Session {
+    /// The session of this process: `XDG_SESSION_ID` if set, the scope of
+    /// its own cgroup under `root` otherwise.
+    #[must_use]
+    pub fn current(root: &Path) -> Option<Self> {
+        if let Some(id) = env::var("XDG_SESSION_ID").ok().filter(|id| !id.is_empty()) {
+            return Some(Self {
+                id,
+                uid: sys::uid(),
+            });
+        }
+        let cgroup = fs::read_to_string(root.join("self/cgroup")).ok()?;
+        Self::of_cgroup(systemd_cgroup(&cgroup)?)
+    }
+
+    /// The session whose scope `path` is in, as in
+    /// `/user.slice/user-1000.slice/session-3.scope`.
+    fn of_cgroup(path: &str) -> Option<Self> {
+        let mut uid = None;
+        let mut id = None;
+        for component in path.split('/') {
+            if let Some(raw) = strip_affixes(component, "user-", ".slice") {
+                uid = raw.parse().ok();
+            } else if let Some(raw) = strip_affixes(component, "session-", ".scope") {
+                id = Some(raw.to_owned());
+            }
+        }
+        Some(Self { id: id?, uid: uid? })
+    }
+
+    /// Whether the cgroup `path` belongs to this session or to its user's
+    /// service manager.
+    fn holds(&self, path: &str) -> bool {
+        path.split('/').any(|component| {
+            strip_affixes(component, "session-", ".scope") == Some(&self.id)
+                || strip_affixes(component, "user@", ".service")
+                    .is_some_and(|uid| uid.parse() == Ok(self.uid))
+        })
+    }
+}
+
+fn strip_affixes<'a>(component: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    component.strip_prefix(prefix)?.strip_suffix(suffix)
+}
+
+/// Whether faulx runs in a graphical login session, where
+/// `--session-scope` is the default.
+#[must_use]
+pub fn on_desktop() -> bool {
+    matches!(
+        env::var("XDG_SESSION_TYPE").as_deref(),
+        Ok("x11" | "wayland")
+    ) && env::var_os("XDG_SESSION_ID").is_some_and(|id| !id.is_empty())
+}
+
+/// Matches processes of a session, or of its user's service manager.
+/// Processes whose cgroup can't be read, such as kernel threads, don't
+/// match.
+#[derive(Debug, Clone)]
+pub struct SessionFilter {
+    session: Session,
+}
+
+impl SessionFilter {
+    #[must_use]
+    pub const fn new(session: Session) -> Self {
+        Self { session }
+    }
+}
+
+impl Filter for SessionFilter {
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        fs::read_to_string(process.path("cgroup"))
+            .ok()
+            .is_some_and(|cgroup| {
+                systemd_cgroup(&cgroup).is_some_and(|path| self.session.holds(path))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir};
+
+    #[test]
+    fn test_of_cgroup() {
+        assert_eq!(
+            Session::of_cgroup("/user.slice/user-1000.slice/session-3.scope"),
+            Some(Session {
+                id: "3".to_owned(),
+                uid: 1000
+            })
+        );
+        assert_eq!(
+            Session::of_cgroup("/user.slice/user-1000.slice/user@1000.service/app.slice"),
+            None
+        );
+        assert_eq!(Session::of_cgroup("/system.slice/sshd.service"), None);
+    }
+
+    #[test]
+    fn test_session_filter() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(
+            &tmp,
+            &[
+                ("10", "firefox"),
+                ("11", "firefox"),
+                ("12", "firefox"),
+                ("13", "firefox"),
+                ("14", "kthreadd"),
+            ],
+        );
+        let cgroups = [
+            ("10", "0::/user.slice/user-1000.slice/session-3.scope\n"),
+            (
+                "11",
+                "0::/user.slice/user-1000.slice/user@1000.service/app.slice/app-firefox-1.scope\n",
+            ),
+            ("12", "0::/user.slice/user-1001.slice/session-4.scope\n"),
+            (
+                "13",
+                "0::/user.slice/user-1001.slice/user@1001.service/app.slice/app-firefox-2.scope\n",
+            ),
+        ];
+        for (raw, cgroup) in cgroups {
+            fs::write(tmp.join(raw).join("cgroup"), cgroup).unwrap();
+        }
+        let filter = SessionFilter::new(Session {
+            id: "3".to_owned(),
+            uid: 1000,
+        });
+        let matches =
+            |raw| filter.matches(&ProcessInfo::by_pid(Arc::from(tmp.as_path()), pid(raw)));
+        assert!(matches(10));
+        assert!(matches(11));
+        assert!(!matches(12));
+        assert!(!matches(13));
+        assert!(!matches(14));
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,117 @@
+//! `--sudo`: when permission was denied for every process matched, offers
+//! to run the same command line again under sudo, for the common case of
+//! having forgotten it.
+
+use std::{
+    env,
+    ffi::OsString,
+    io::{self, BufRead, Write},
+    os::unix::process::CommandExt,
+    process::Command,
+};
+
+use crate::{limits::OriginalNofileLimit, pipeline::Outcome};
+
+/// Whether there were processes and the action failed on each of them for
+/// lack of permission, in which case sudo would help.
+#[must_use]
+pub fn all_denied(outcomes: &[Outcome]) -> bool {
+    !outcomes.is_empty()
+        && outcomes.iter().all(|outcome| {
+            outcome
+                .result
+                .as_ref()
+                .is_err_and(|e| e.kind() == io::ErrorKind::PermissionDenied)
+        })
+}
+
+/// `command_line` without the program name and `--sudo`, so that the run
+/// under sudo is the same one.
+#[must_use]
+pub fn without_flag(command_line: &[OsString]) -> Vec<OsString> {
+    let mut positional_only = false;
+    command_line
+        .iter()
+        .skip(1)
+        .filter(|&arg| {
+            positional_only |= arg == "--";
+            positional_only || arg != "--sudo"
+        })
+        .cloned()
+        .collect()
+}
+
+/// Asks on `prompt` whether to go on with sudo for `count` processes, and
+/// reads the answer from `input`; anything but yes is a no.
+pub fn confirm(count: usize, input: &mut dyn BufRead, prompt: &mut dyn Write) -> io::Result<bool> {
+    let plural = if count == 1 { "" } else { "es" };
+    write!(
+        prompt,
+        "Permission denied for all {count} process{plural}. Run again with sudo? [y/N] "
+    )?;
+    prompt.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes" | "Yes"))
+}
+
+/// Replaces this process with `sudo -- <this binary> <args>`, returning
+/// only if that fails.
+#[must_use]
+pub fn reexec(args: &[OsString]) -> io::Error {
+    let program = match env::current_exe() {
+        Ok(program) => program,
+        Err(e) => return e,
+    };
+    Command::new("sudo")
+        .original_nofile_limit()
+        .arg("--")
+        .arg(program)
+        .args(args)
+        .exec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{processes::ProcessInfo, testing::pid};
+    use std::{path::Path, sync::Arc};
+
+    #[test]
+    fn test_all_denied() {
+        let outcome = |errno: Option<i32>| Outcome {
+            process: ProcessInfo::new(Arc::from(Path::new("/nonexistent")), pid(1), Vec::new()),
+            result: errno.map_or(Ok(()), |errno| Err(io::Error::from_raw_os_error(errno))),
+        };
+        assert!(!all_denied(&[]));
+        assert!(all_denied(&[
+            outcome(Some(libc::EPERM)),
+            outcome(Some(libc::EPERM))
+        ]));
+        assert!(!all_denied(&[outcome(Some(libc::EPERM)), outcome(None)]));
+        assert!(!all_denied(&[outcome(Some(libc::ESRCH))]));
+    }
+
+    #[test]
+    fn test_without_flag() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            without_flag(&args(&[
+                "faulx", "--sudo", "-s", "HUP", "nginx", "--", "--sudo"
+            ])),
+            args(&["-s", "HUP", "nginx", "--", "--sudo"])
+        );
+    }
+
+    #[test]
+    fn test_confirm() {
+        let mut prompt = Vec::new();
+        assert!(confirm(2, &mut &b"y\n"[..], &mut prompt).unwrap());
+        assert_eq!(
+            prompt,
+            b"Permission denied for all 2 processes. Run again with sudo? [y/N] "
+        );
+        assert!(!confirm(1, &mut &b"\n"[..], &mut Vec::new()).unwrap());
+        assert!(!confirm(1, &mut &b""[..], &mut Vec::new()).unwrap());
+    }
+}
@@ -0,0 +1,116 @@
+//! `faulx tree`: the matched processes and everything below them, drawn
+//! like `pstree`, to see what a kill of the whole tree would reach.
+
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use crate::{pid::Pid, processes::ProcessInfo, snapshot::ProcSnapshot};
+
+/// The processes of `matched` that have no matched ancestor, which are
+/// drawn with the others below them. In ascending PID order.
+#[must_use]
+pub fn roots<'a>(snapshot: &ProcSnapshot, matched: &[&'a ProcessInfo]) -> Vec<&'a ProcessInfo> {
+    let pids: HashSet<_> = matched.iter().map(|process| process.pid).collect();
+    let mut roots: Vec<_> = matched
+        .iter()
+        .copied()
+        .filter(|process| {
+            !snapshot
+                .ancestors(process.pid)
+                .iter()
+                .any(|ancestor| pids.contains(&ancestor.pid))
+        })
+        .collect();
+    roots.sort_unstable_by_key(|process| process.pid);
+    roots
+}
+
+/// Draws each of `roots` and its descendants in `snapshot`, as
+/// `name(pid)`, children in ascending PID order.
+pub fn write_tree(
+    out: &mut dyn Write,
+    snapshot: &ProcSnapshot,
+    roots: &[&ProcessInfo],
+) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    for root in roots {
+        seen.insert(root.pid);
+        writeln!(out, "{}", label(root))?;
+        write_children(out, snapshot, root.pid, &mut String::new(), &mut seen)?;
+    }
+    Ok(())
+}
+
+fn write_children(
+    out: &mut dyn Write,
+    snapshot: &ProcSnapshot,
+    pid: Pid,
+    prefix: &mut String,
+    seen: &mut HashSet<Pid>,
+) -> io::Result<()> {
+    // Marked before descending, so that a loop left by reused PIDs draws
+    // every process once.
+    let children: Vec<_> = snapshot
+        .children(pid)
+        .filter(|child| seen.insert(child.pid))
+        .collect();
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let branch = if last { "└─ " } else { "├─ " };
+        writeln!(out, "{prefix}{branch}{}", label(child))?;
+        let len = prefix.len();
+        prefix.push_str(if last { "   " } else { "│  " });
+        write_children(out, snapshot, child.pid, prefix, seen)?;
+        prefix.truncate(len);
+    }
+    Ok(())
+}
+
+fn label(process: &ProcessInfo) -> String {
+    format!("{}({})", process.comm.escaped(), process.pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        processes::Scanner,
+        testing::{cleanup_fake_proc, setup_fake_proc, unique_test_dir, write_fake_stat},
+    };
+
+    #[test]
+    fn test_write_tree() {
+        let tmp = unique_test_dir();
+        let entries = [
+            ("1", "init", 0),
+            ("10", "nginx", 1),
+            ("11", "nginx", 10),
+            ("12", "nginx", 10),
+            ("13", "sh", 11),
+            ("20", "cron", 1),
+            ("30", "nginx", 1),
+        ];
+        setup_fake_proc(&tmp, &entries.map(|(pid, comm, _)| (pid, comm)));
+        for (pid, comm, parent) in entries {
+            write_fake_stat(&tmp, pid, comm, parent, 1);
+        }
+        let snapshot = ProcSnapshot::take(&Scanner::new(&tmp)).unwrap();
+        cleanup_fake_proc(&tmp);
+
+        let matched: Vec<_> = snapshot.by_comm(b"nginx").collect();
+        let roots = roots(&snapshot, &matched);
+        assert_eq!(
+            roots.iter().map(|process| process.pid).collect::<Vec<_>>(),
+            [10, 30]
+        );
+
+        let mut out = Vec::new();
+        write_tree(&mut out, &snapshot, &roots).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "nginx(10)\n├─ nginx(11)\n│  └─ sh(13)\n└─ nginx(12)\nnginx(30)\n"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! Interactive picker listing matched processes so that the ones to act
+//! upon can be chosen one by one, with the `tui` feature.
+
+use std::{io, path::Path};
+
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Row, Table, TableState},
+};
+
+use crate::{
+    permission::{self, PermissionStatus},
+    pid::Pid,
+    processes::{ProcessInfo, page_size},
+    signals::probe_signal,
+    sys,
+    time::{clock_ticks, uptime},
+};
+
+/// What is shown for one process.
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    pid: Pid,
+    comm: String,
+    user: String,
+    /// Average CPU usage over the lifetime of the process, like `ps`.
+    cpu: f64,
+    rss_kib: u64,
+    /// Whether it can be signalled; rows of those that can't are greyed
+    /// out and can't be selected.
+    status: PermissionStatus,
+}
+
+impl Entry {
+    fn new(process: &ProcessInfo, uptime: Option<f64>) -> Self {
+        let user = process.uid().map_or_else(
+            || "?".to_owned(),
+            |uid| match sys::user_name(uid) {
+                Ok(Some(name)) => name,
+                _ => uid.to_string(),
+            },
+        );
+        let stat = process.stat();
+        Self {
+            pid: process.pid,
+            comm: process.comm.escaped().to_string(),
+            user,
+            cpu: stat.zip(uptime).map_or(0.0, |(stat, uptime)| {
+                cpu_percent(
+                    stat.utime + stat.stime,
+                    stat.starttime,
+                    uptime,
+                    clock_ticks(),
+                )
+            }),
+            rss_kib: stat.map_or(0, |stat| stat.rss * page_size() / 1024),
+            status: permission::status(&probe_signal(process.pid), process),
+        }
+    }
+}
+
+#[allow(clippy::cast_precision_loss)] // tick counts fit easily in an f64
+fn cpu_percent(cpu_ticks: u64, starttime: u64, uptime: f64, ticks_per_sec: u64) -> f64 {
+    let ticks_per_sec = ticks_per_sec as f64;
+    let elapsed = starttime as f64 / ticks_per_sec;
+    let elapsed = uptime - elapsed;
+    if elapsed <= 0.0 {
+        return 0.0;
+    }
+    100.0 * cpu_ticks as f64 / ticks_per_sec / elapsed
+}
+
+/// Selection state, separate from drawing so key handling can be tested.
+#[derive(Debug)]
+struct Picker {
+    entries: Vec<Entry>,
+    selected: Vec<bool>,
+    table: TableState,
+}
+
+/// Result of a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Continue,
+    Confirm,
+    Cancel,
+}
+
+impl Picker {
+    fn new(entries: Vec<Entry>) -> Self {
+        let selected = vec![false; entries.len()];
+        let table = TableState::default().with_selected(Some(0));
+        Self {
+            entries,
+            selected,
+            table,
+        }
+    }
+
+    fn selectable(&self, index: usize) -> bool {
+        self.entries
+            .get(index)
+            .is_some_and(|entry| entry.status == PermissionStatus::Allowed)
+    }
+
+    fn handle(&mut self, key: KeyCode) -> Step {
+        let cursor = self.table.selected().unwrap_or(0);
+        match key {
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.table
+                    .select(Some((cursor + 1).min(self.entries.len().saturating_sub(1))));
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.table.select(Some(cursor.saturating_sub(1))),
+            KeyCode::Char(' ') => {
+                if self.selectable(cursor)
+                    && let Some(selected) = self.selected.get_mut(cursor)
+                {
+                    *selected = !*selected;
+                }
+            }
+            KeyCode::Char('a') => {
+                let all = (0..self.entries.len())
+                    .filter(|&i| self.selectable(i))
+                    .all(|i| self.selected[i]);
+                for i in 0..self.entries.len() {
+                    self.selected[i] = !all && self.selectable(i);
+                }
+            }
+            KeyCode::Enter => return Step::Confirm,
+            KeyCode::Esc | KeyCode::Char('q') => return Step::Cancel,
+            _ => {}
+        }
+        Step::Continue
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let rows = self
+            .entries
+            .iter()
+            .zip(&self.selected)
+            .map(|(entry, &selected)| {
+                let row = Row::new([
+                    if selected { "[x]" } else { "[ ]" }.to_owned(),
+                    entry.pid.to_string(),
+                    entry.comm.clone(),
+                    entry.user.clone(),
+                    format!("{:.1}", entry.cpu),
+                    format!("{} KiB", entry.rss_kib),
+                ]);
+                if entry.status == PermissionStatus::Allowed {
+                    row
+                } else {
+                    row.style(Style::new().add_modifier(Modifier::DIM))
+                }
+            });
+        let widths = [
+            Constraint::Length(3),
+            Constraint::Length(8),
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(6),
+            Constraint::Min(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(["", "PID", "COMMAND", "USER", "%CPU", "RSS"])
+                    .style(Style::new().add_modifier(Modifier::BOLD)),
+            )
+            .block(Block::bordered().title("faulx"))
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let [main, help] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        frame.render_stateful_widget(table, main, &mut self.table);
+        frame.render_widget(
+            Line::from("space: select  a: all  enter: confirm  q: cancel"),
+            help,
+        );
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<Step> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match self.handle(key.code) {
+                    Step::Continue => {}
+                    step => return Ok(step),
+                }
+            }
+        }
+    }
+}
+
+/// Shows `processes` and returns the ones the user selected, or none if
+/// they cancelled.
+pub fn pick(root: &Path, processes: Vec<ProcessInfo>) -> io::Result<Vec<ProcessInfo>> {
+    let uptime = uptime(root);
+    let entries = processes
+        .iter()
+        .map(|process| Entry::new(process, uptime))
+        .collect();
+    let mut picker = Picker::new(entries);
+
+    let mut terminal = ratatui::try_init()?;
+    let step = picker.run(&mut terminal);
+    ratatui::try_restore()?;
+
+    Ok(match step? {
+        Step::Confirm => processes
+            .into_iter()
+            .zip(picker.selected)
+            .filter_map(|(process, selected)| selected.then_some(process))
+            .collect(),
+        Step::Continue | Step::Cancel => Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pid;
+
+    fn entry(raw: i32) -> Entry {
+        Entry {
+            pid: pid(raw),
+            comm: "sleep".to_owned(),
+            user: "root".to_owned(),
+            cpu: 0.0,
+            rss_kib: 0,
+            status: PermissionStatus::Allowed,
+        }
+    }
+
+    #[test]
+    fn test_picker_keys() {
+        let mut picker = Picker::new(vec![entry(1), entry(2), entry(3)]);
+        assert_eq!(picker.handle(KeyCode::Char(' ')), Step::Continue);
+        picker.handle(KeyCode::Down);
+        picker.handle(KeyCode::Down);
+        picker.handle(KeyCode::Down);
+        picker.handle(KeyCode::Char(' '));
+        assert_eq!(picker.selected, vec![true, false, true]);
+        picker.handle(KeyCode::Char('a'));
+        assert_eq!(picker.selected, vec![true; 3]);
+        picker.handle(KeyCode::Char('a'));
+        assert_eq!(picker.selected, vec![false; 3]);
+        assert_eq!(picker.handle(KeyCode::Enter), Step::Confirm);
+        assert_eq!(picker.handle(KeyCode::Esc), Step::Cancel);
+    }
+
+    #[test]
+    fn test_picker_skips_untouchable() {
+        let mut entries = vec![entry(1), entry(2), entry(3)];
+        entries[1].status = PermissionStatus::NoPermission;
+        let mut picker = Picker::new(entries);
+        picker.handle(KeyCode::Down);
+        picker.handle(KeyCode::Char(' '));
+        assert_eq!(picker.selected, vec![false; 3]);
+        picker.handle(KeyCode::Char('a'));
+        assert_eq!(picker.selected, vec![true, false, true]);
+        picker.handle(KeyCode::Char('a'));
+        assert_eq!(picker.selected, vec![false; 3]);
+    }
+
+    #[test]
+    fn test_cpu_percent() {
+        // Started 10s after boot, ran 5s of CPU time over the 10s since.
+        assert!((cpu_percent(500, 1000, 20.0, 100) - 50.0).abs() < f64::EPSILON);
+        assert!(cpu_percent(500, 2000, 20.0, 100).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,222 @@
+//! `--window`: the user clicks a window, as with xkill, and faulx acts on
+//! the process owning it instead of only cutting its X connection.
+//!
+//! The owner is the `_NET_WM_PID` its client sets, trusted only when
+//! `WM_CLIENT_MACHINE` names this host: a remote client's PID is that of
+//! another machine. Native Wayland windows can't be picked, those of
+//! Xwayland clients can.
+
+use std::io;
+
+use x11rb::{
+    CURRENT_TIME, NONE,
+    connection::Connection,
+    protocol::{
+        Event,
+        xproto::{AtomEnum, ConnectionExt, EventMask, GrabMode, GrabStatus, Window},
+    },
+};
+
+use crate::pid::Pid;
+
+/// `XC_crosshair` in the standard cursor font, followed by its mask.
+const CROSSHAIR: u16 = 34;
+
+/// The window clicked, as its client describes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Clicked {
+    /// `_NET_WM_PID`.
+    pub pid: Option<u32>,
+    /// `WM_CLIENT_MACHINE`, the host the client runs on.
+    pub machine: Option<String>,
+    /// `_NET_WM_NAME`, or `WM_NAME`.
+    pub title: Option<String>,
+}
+
+impl Clicked {
+    /// The process owning the window, on the machine named `hostname`.
+    pub fn owner(&self, hostname: &str) -> Result<Pid, String> {
+        let pid = self
+            .pid
+            .ok_or("the window does not name its process (_NET_WM_PID)")?;
+        match self.machine.as_deref() {
+            Some(machine) if machine == hostname => {}
+            Some(machine) => return Err(format!("the window comes from {machine}")),
+            None => return Err("the window does not name its host (WM_CLIENT_MACHINE)".to_owned()),
+        }
+        i32::try_from(pid)
+            .ok()
+            .and_then(Pid::new)
+            .ok_or_else(|| format!("the window names {pid}, which is not a PID"))
+    }
+}
+
+/// Turns the pointer into a crosshair until the user clicks a window, and
+/// describes it. Clicking with another button than the first cancels.
+pub fn pick() -> io::Result<Option<Clicked>> {
+    let (conn, screen) = x11rb::connect(None).map_err(io::Error::other)?;
+    let root = conn.setup().roots[screen].root;
+
+    let font = conn.generate_id().map_err(io::Error::other)?;
+    conn.open_font(font, b"cursor").map_err(io::Error::other)?;
+    let cursor = conn.generate_id().map_err(io::Error::other)?;
+    conn.create_glyph_cursor(
+        cursor,
+        font,
+        font,
+        CROSSHAIR,
+        CROSSHAIR + 1,
+        0,
+        0,
+        0,
+        0xffff,
+        0xffff,
+        0xffff,
+    )
+    .map_err(io::Error::other)?;
+    conn.close_font(font).map_err(io::Error::other)?;
+
+    let grab = conn
+        .grab_pointer(
+            false,
+            root,
+            EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE,
+            GrabMode::ASYNC,
+            GrabMode::ASYNC,
+            root,
+            cursor,
+            CURRENT_TIME,
+        )
+        .map_err(io::Error::other)?
+        .reply()
+        .map_err(io::Error::other)?;
+    if grab.status != GrabStatus::SUCCESS {
+        return Err(io::Error::other("cannot grab the pointer"));
+    }
+    // The window under the press, once its button is released, so that the
+    // release doesn't reach whatever is below.
+    let mut pressed = None;
+    let clicked = loop {
+        match conn.wait_for_event().map_err(io::Error::other)? {
+            Event::ButtonPress(press) if pressed.is_none() => {
+                pressed = Some((press.detail, press.child));
+            }
+            Event::ButtonRelease(release) => {
+                if let Some((button, child)) = pressed
+                    && button == release.detail
+                {
+                    break (button == 1).then_some(child);
+                }
+            }
+            _ => {}
+        }
+    };
+    conn.ungrab_pointer(CURRENT_TIME)
+        .map_err(io::Error::other)?;
+    conn.free_cursor(cursor).map_err(io::Error::other)?;
+    conn.flush().map_err(io::Error::other)?;
+
+    match clicked {
+        None => Ok(None),
+        // The desktop itself.
+        Some(NONE) => Ok(Some(Clicked::default())),
+        Some(frame) => {
+            let client = client_window(&conn, frame)?.unwrap_or(frame);
+            describe(&conn, client).map(Some)
+        }
+    }
+}
+
+/// The window of the client under `window`, which the window manager may
+/// have wrapped in frames: the first with `WM_STATE`, as xkill and xprop
+/// look for.
+fn client_window(conn: &impl Connection, window: Window) -> io::Result<Option<Window>> {
+    let wm_state = atom(conn, b"WM_STATE")?;
+    let mut pending = vec![window];
+    while let Some(window) = pending.pop() {
+        let state = conn
+            .get_property(false, window, wm_state, AtomEnum::ANY, 0, 0)
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?;
+        if state.type_ != NONE {
+            return Ok(Some(window));
+        }
+        let tree = conn
+            .query_tree(window)
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?;
+        pending.extend(tree.children.into_iter().rev());
+    }
+    Ok(None)
+}
+
+fn describe(conn: &impl Connection, window: Window) -> io::Result<Clicked> {
+    let property = |atom: u32| -> io::Result<Vec<u8>> {
+        Ok(conn
+            .get_property(false, window, atom, AtomEnum::ANY, 0, 1024)
+            .map_err(io::Error::other)?
+            .reply()
+            .map_err(io::Error::other)?
+            .value)
+    };
+    let text = |bytes: Vec<u8>| {
+        // Lists of strings are separated, and may end, with NULs.
+        let first = bytes.split(|&b| b == 0).next().unwrap_or_default();
+        (!first.is_empty()).then(|| String::from_utf8_lossy(first).into_owned())
+    };
+    let pid = property(atom(conn, b"_NET_WM_PID")?)?;
+    let title = text(property(atom(conn, b"_NET_WM_NAME")?)?).map_or_else(
+        || property(AtomEnum::WM_NAME.into()).map(text),
+        |title| Ok(Some(title)),
+    )?;
+    Ok(Clicked {
+        pid: pid
+            .get(..4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_ne_bytes),
+        machine: text(property(AtomEnum::WM_CLIENT_MACHINE.into())?),
+        title,
+    })
+}
+
+fn atom(conn: &impl Connection, name: &[u8]) -> io::Result<u32> {
+    Ok(conn
+        .intern_atom(false, name)
+        .map_err(io::Error::other)?
+        .reply()
+        .map_err(io::Error::other)?
+        .atom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pid;
+
+    #[test]
+    fn test_owner() {
+        let clicked = Clicked {
+            pid: Some(1234),
+            machine: Some("desk".to_owned()),
+            title: Some("Firefox".to_owned()),
+        };
+        assert_eq!(clicked.owner("desk"), Ok(pid(1234)));
+        assert_eq!(
+            clicked.owner("laptop"),
+            Err("the window comes from desk".to_owned())
+        );
+        let without_machine = Clicked {
+            machine: None,
+            ..clicked.clone()
+        };
+        assert!(without_machine.owner("desk").is_err());
+        assert!(Clicked::default().owner("desk").is_err());
+        let bad_pid = Clicked {
+            pid: Some(u32::MAX),
+            ..clicked
+        };
+        assert!(bad_pid.owner("desk").is_err());
+    }
+}
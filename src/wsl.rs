@@ -0,0 +1,167 @@
+//! Windows programs under WSL, which signals do not reach.
+//!
+//! Those started from Linux show up as relay processes run by `/init`,
+//! named after their `.exe`. They are told apart here, and either refused
+//! with a clear message or ended with `taskkill.exe` through interop.
+
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs, io,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use crate::{actions::Action, limits::OriginalNofileLimit, processes::ProcessInfo};
+
+/// Whether the kernel under `root` is WSL's, which names itself in its
+/// release, as in `5.15.167.4-microsoft-standard-WSL2`.
+#[must_use]
+pub fn is_wsl(root: &Path) -> bool {
+    fs::read_to_string(root.join("sys/kernel/osrelease"))
+        .is_ok_and(|release| release.to_ascii_lowercase().contains("microsoft"))
+}
+
+/// The `.exe` a Windows interop relay runs, as `notepad.exe`, if `process`
+/// is one: its first argument names an `.exe`, and its executable is
+/// `/init`, when that can be read.
+#[must_use]
+pub fn windows_image(process: &ProcessInfo) -> Option<String> {
+    let argv0 = process.cmdline().ok()?.into_iter().next()?;
+    let argv0 = String::from_utf8_lossy(&argv0);
+    let image = argv0.rsplit(['/', '\\']).next()?;
+    if !image.to_ascii_lowercase().ends_with(".exe") {
+        return None;
+    }
+    match process.link("exe") {
+        Ok(exe) if exe != Path::new("/init") => None,
+        _ => Some(image.to_owned()),
+    }
+}
+
+/// Applies `inner` to Linux processes, but not to Windows interop relays.
+///
+/// For those, fails with a clear message or, when routing, ends their
+/// program with `taskkill.exe /IM`, once per image. Outside of WSL, only
+/// applies `inner`.
+pub struct Interop<A> {
+    inner: A,
+    wsl: bool,
+    route: bool,
+    taskkill: OsString,
+    ended: HashSet<String>,
+}
+
+impl<A: Action> Interop<A> {
+    /// Wraps `inner` for the processes under `root`, routing Windows ones
+    /// to `taskkill.exe` if `route` is set.
+    #[must_use]
+    pub fn new(root: &Path, inner: A, route: bool) -> Self {
+        Self {
+            inner,
+            wsl: is_wsl(root),
+            route,
+            taskkill: OsString::from("taskkill.exe"),
+            ended: HashSet::new(),
+        }
+    }
+
+    fn end(&mut self, image: String) -> io::Result<()> {
+        if self.ended.contains(&image) {
+            return Ok(());
+        }
+        let status = Command::new(&self.taskkill)
+            .original_nofile_limit()
+            .args(["/IM", &image, "/F"])
+            .stdout(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("taskkill.exe failed for {image}")));
+        }
+        self.ended.insert(image);
+        Ok(())
+    }
+}
+
+impl<A: Action> Action for Interop<A> {
+    fn apply(&mut self, process: &ProcessInfo) -> io::Result<()> {
+        if !self.wsl {
+            return self.inner.apply(process);
+        }
+        match windows_image(process) {
+            None => self.inner.apply(process),
+            Some(image) if self.route => self.end(image),
+            Some(image) => Err(io::Error::other(format!(
+                "{image} is a Windows process; --windows-interop ends it with taskkill.exe"
+            ))),
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.inner.describe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::symlink, sync::Arc};
+
+    use super::*;
+    use crate::{
+        actions::Count,
+        testing::{cleanup_fake_proc, pid, setup_fake_proc, unique_test_dir},
+    };
+
+    fn process(root: &Path, raw: i32) -> ProcessInfo {
+        ProcessInfo::by_pid(Arc::from(root), pid(raw))
+    }
+
+    #[test]
+    fn test_interop() {
+        let tmp = unique_test_dir();
+        setup_fake_proc(&tmp, &[("10", "notepad.exe"), ("11", "bash")]);
+        fs::write(
+            tmp.join("10/cmdline"),
+            b"/mnt/c/Windows/notepad.exe\0x.txt\0",
+        )
+        .unwrap();
+        symlink("/init", tmp.join("10/exe")).unwrap();
+        fs::write(tmp.join("11/cmdline"), b"bash\0").unwrap();
+        assert!(!is_wsl(&tmp));
+        let windows = process(&tmp, 10);
+        let mut linux_only = Interop::new(&tmp, Count::default(), false);
+        assert!(linux_only.apply(&windows).is_ok());
+
+        fs::create_dir_all(tmp.join("sys/kernel")).unwrap();
+        fs::write(
+            tmp.join("sys/kernel/osrelease"),
+            "5.15.167.4-microsoft-standard-WSL2\n",
+        )
+        .unwrap();
+        assert!(is_wsl(&tmp));
+
+        let linux = process(&tmp, 11);
+        assert_eq!(windows_image(&windows).as_deref(), Some("notepad.exe"));
+        assert_eq!(windows_image(&linux), None);
+
+        let mut refuse = Interop::new(&tmp, Count::default(), false);
+        assert!(refuse.apply(&linux).is_ok());
+        let err = refuse.apply(&windows).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("notepad.exe is a Windows process")
+        );
+        assert_eq!(refuse.inner.count, 1);
+
+        let mut route = Interop::new(&tmp, Count::default(), true);
+        route.taskkill = OsString::from("true");
+        assert!(route.apply(&windows).is_ok());
+        assert!(route.ended.contains("notepad.exe"));
+        route.taskkill = OsString::from("false");
+        // Already ended, so not run again.
+        assert!(route.apply(&windows).is_ok());
+        assert_eq!(route.inner.count, 0);
+
+        cleanup_fake_proc(&tmp);
+    }
+}
@@ -0,0 +1,106 @@
+//! Locks in the command line surface: the help texts, and the wording of
+//! errors where killall's or procps' is kept. After an intended change,
+//! review the new snapshots with `cargo insta review`.
+#![cfg(all(feature = "clap", not(feature = "tiny-cli")))]
+
+use std::{os::unix::process::CommandExt, process::Command};
+
+use insta::assert_snapshot;
+
+/// Runs the binary as `name` with `args` and a clean environment, so that
+/// no `FAULX_*` variable changes the defaults, and shows what it printed.
+fn run(name: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_faulx"))
+        .arg0(name)
+        .args(args)
+        .env_clear()
+        .output()
+        .unwrap();
+    format!(
+        "status: {:?}\n--- stdout\n{}--- stderr\n{}",
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    )
+}
+
+// What killall's help shows depends on the optional features, so it is
+// only checked with the default set.
+#[cfg(all(feature = "json", not(any(feature = "tui", feature = "dbus"))))]
+#[test]
+fn test_killall_help() {
+    assert_snapshot!(run("faulx", &["--help"]));
+}
+
+#[test]
+fn test_renice_help() {
+    assert_snapshot!(run("faulx", &["renice", "--help"]));
+}
+
+#[test]
+fn test_list_help() {
+    assert_snapshot!(run("faulx", &["list", "--help"]));
+}
+
+#[test]
+fn test_tree_help() {
+    assert_snapshot!(run("faulx", &["tree", "--help"]));
+}
+
+#[test]
+fn test_waitfor_help() {
+    assert_snapshot!(run("faulx", &["waitfor", "--help"]));
+}
+
+#[test]
+fn test_restart_help() {
+    assert_snapshot!(run("faulx", &["restart", "--help"]));
+}
+
+#[test]
+fn test_pgrep_help() {
+    assert_snapshot!(run("pgrep", &["--help"]));
+}
+
+#[test]
+fn test_pkill_help() {
+    assert_snapshot!(run("pkill", &["--help"]));
+}
+
+#[test]
+fn test_unknown_option() {
+    assert_snapshot!(run("faulx", &["--no-such-option", "nginx"]));
+}
+
+#[test]
+fn test_missing_process_name() {
+    assert_snapshot!(run("faulx", &[]));
+}
+
+#[test]
+fn test_unknown_signal() {
+    assert_snapshot!(run("faulx", &["-s", "TERN", "faulx-test-no-such-process"]));
+}
+
+#[test]
+fn test_no_process_found() {
+    assert_snapshot!(run("faulx", &["faulx-test-no-such-process"]));
+}
+
+#[cfg(target_pointer_width = "64")]
+#[test]
+fn test_too_many_names() {
+    let names = vec!["faulx-test-no-such-process"; 65];
+    assert_snapshot!(run("faulx", &names));
+}
+
+#[test]
+fn test_pkill_missing_pattern() {
+    assert_snapshot!(run("pkill", &["-HUP"]));
+}
+
+#[cfg(feature = "regex")]
+#[test]
+fn test_pgrep_invalid_pattern() {
+    assert_snapshot!(run("pgrep", &["("]));
+}
@@ -0,0 +1,64 @@
+//! Colors in the output of the binary, whose stdout is a pipe here: plain
+//! unless `--color` or `CLICOLOR_FORCE` asks for colors, and `NO_COLOR`
+//! wins over the latter. Each case kills a copy of `sleep` of its own.
+#![cfg(all(feature = "color", not(feature = "tiny-cli")))]
+
+use std::{
+    env, fs,
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+const KILLED: &str = "\x1b[32mKilled\x1b[0m";
+
+/// What `faulx --verbose` prints on stdout when killing a fixture, with
+/// `vars` set.
+fn run(case: &str, args: &[&str], vars: &[(&str, &str)]) -> String {
+    let dir = env::temp_dir().join(format!("faulx-color-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let name = format!("fxcol{:05}{case}", std::process::id() % 100_000);
+    let binary: PathBuf = dir.join(&name);
+    fs::copy("/bin/sleep", &binary).unwrap();
+    let mut fixture = Command::new(&binary).arg("60").spawn().unwrap();
+    // Let the fixture exec, so that its name is the copy's.
+    thread::sleep(Duration::from_millis(50));
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_faulx"));
+    command.arg0("faulx").arg("--verbose").args(args).arg(&name);
+    for var in ["NO_COLOR", "CLICOLOR", "CLICOLOR_FORCE", "FAULX_COLOR"] {
+        command.env_remove(var);
+    }
+    let output = command
+        .envs(vars.iter().copied())
+        .stderr(Stdio::null())
+        .output()
+        .unwrap();
+    fixture.wait().unwrap();
+    fs::remove_file(&binary).unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_plain_without_tty() {
+    let out = run("a", &[], &[]);
+    assert!(out.starts_with("Killed fxcol"), "{out:?}");
+}
+
+#[test]
+fn test_clicolor_force() {
+    let out = run("b", &[], &[("CLICOLOR_FORCE", "1")]);
+    assert!(out.starts_with(KILLED), "{out:?}");
+    let out = run("c", &[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]);
+    assert!(out.starts_with("Killed fxcol"), "{out:?}");
+}
+
+#[test]
+fn test_color_option() {
+    let out = run("d", &["--color=always"], &[("NO_COLOR", "1")]);
+    assert!(out.starts_with(KILLED), "{out:?}");
+    let out = run("e", &["--color=never"], &[("CLICOLOR_FORCE", "1")]);
+    assert!(out.starts_with("Killed fxcol"), "{out:?}");
+}
@@ -0,0 +1,458 @@
+//! Compares the binary with the tools it stands in for, psmisc's killall
+//! and procps' pgrep and pkill, when they are installed. Both run the same
+//! cases against fixture processes of their own, and must exit alike,
+//! print alike and leave the fixture alike, except for the exit statuses
+//! listed in `DIVERGENCES` and the output of the cases in `OWN_OUTPUT`,
+//! such as `-l`. A listed difference that went away fails too, so that
+//! the lists stay true.
+//!
+//! The fixtures are copies of `sleep` under names no other process has,
+//! and every case targets those names only. Some cases use options only
+//! the full command line has, so the suite does not run with `tiny-cli`.
+#![cfg(not(feature = "tiny-cli"))]
+
+use std::{
+    env, fs,
+    os::unix::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{Child, Command},
+    sync::atomic::{AtomicU32, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Killall,
+    Pgrep,
+    Pkill,
+}
+
+impl Tool {
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Killall => "killall",
+            Self::Pgrep => "pgrep",
+            Self::Pkill => "pkill",
+        }
+    }
+}
+
+/// A command line to run under both tools. In `args`, `NAME` stands for
+/// the fixture's name, `PARTIAL` for part of it and `PATH` for the file it
+/// runs.
+struct Case {
+    id: &'static str,
+    tool: Tool,
+    args: &'static [&'static str],
+    /// Whether a fixture is running while the tool runs.
+    fixture: bool,
+}
+
+const CASES: &[Case] = &[
+    Case {
+        id: "killall",
+        tool: Tool::Killall,
+        args: &["NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-missing",
+        tool: Tool::Killall,
+        args: &["NAME"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-partial",
+        tool: Tool::Killall,
+        args: &["PARTIAL"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-signal",
+        tool: Tool::Killall,
+        args: &["-s", "KILL", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-signal-0",
+        tool: Tool::Killall,
+        args: &["-s", "0", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-unknown-signal",
+        tool: Tool::Killall,
+        args: &["-s", "BOGUS", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-path",
+        tool: Tool::Killall,
+        args: &["PATH"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-path-missing",
+        tool: Tool::Killall,
+        args: &["PATH"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-list",
+        tool: Tool::Killall,
+        args: &["-l"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-list-name",
+        tool: Tool::Killall,
+        args: &["-l", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-list-quiet",
+        tool: Tool::Killall,
+        args: &["-lq"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-list-unknown-signal",
+        tool: Tool::Killall,
+        args: &["-l", "-s", "BOGUS", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-list-unknown-option",
+        tool: Tool::Killall,
+        args: &["-l", "--bogus"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-version",
+        tool: Tool::Killall,
+        args: &["-V"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-version-name",
+        tool: Tool::Killall,
+        args: &["-V", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "killall-quiet-missing",
+        tool: Tool::Killall,
+        args: &["-q", "NAME"],
+        fixture: false,
+    },
+    Case {
+        id: "killall-quiet-without-name",
+        tool: Tool::Killall,
+        args: &["-q"],
+        fixture: false,
+    },
+    Case {
+        id: "pgrep",
+        tool: Tool::Pgrep,
+        args: &["NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "pgrep-missing",
+        tool: Tool::Pgrep,
+        args: &["NAME"],
+        fixture: false,
+    },
+    Case {
+        id: "pgrep-partial",
+        tool: Tool::Pgrep,
+        args: &["PARTIAL"],
+        fixture: true,
+    },
+    Case {
+        id: "pgrep-exact-partial",
+        tool: Tool::Pgrep,
+        args: &["-x", "PARTIAL"],
+        fixture: true,
+    },
+    Case {
+        id: "pgrep-list-name",
+        tool: Tool::Pgrep,
+        args: &["-l", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "pgrep-count",
+        tool: Tool::Pgrep,
+        args: &["-c", "NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "pgrep-count-missing",
+        tool: Tool::Pgrep,
+        args: &["-c", "NAME"],
+        fixture: false,
+    },
+    Case {
+        id: "pkill",
+        tool: Tool::Pkill,
+        args: &["NAME"],
+        fixture: true,
+    },
+    Case {
+        id: "pkill-missing",
+        tool: Tool::Pkill,
+        args: &["NAME"],
+        fixture: false,
+    },
+    Case {
+        id: "pkill-exact-partial",
+        tool: Tool::Pkill,
+        args: &["-x", "PARTIAL"],
+        fixture: true,
+    },
+    Case {
+        id: "pkill-unknown-signal",
+        tool: Tool::Pkill,
+        args: &["--signal", "BOGUS", "NAME"],
+        fixture: true,
+    },
+];
+
+/// A case where the exit statuses differ on purpose, or until fixed.
+struct Divergence {
+    id: &'static str,
+    reference: i32,
+    faulx: i32,
+    why: &'static str,
+}
+
+const DIVERGENCES: &[Divergence] = &[
+    Divergence {
+        id: "killall-signal-0",
+        reference: 0,
+        faulx: 2,
+        why: "signal 0 is not accepted by name or number",
+    },
+    Divergence {
+        id: "killall-list-unknown-option",
+        reference: 0,
+        faulx: 2,
+        why: "the whole command line is checked before -l lists",
+    },
+    Divergence {
+        id: "killall-quiet-without-name",
+        reference: 1,
+        faulx: 2,
+        why: "usage errors exit 2 under every name, as in procps",
+    },
+    Divergence {
+        id: "killall-unknown-signal",
+        reference: 1,
+        faulx: 2,
+        why: "usage errors exit 2 under every name, as in procps",
+    },
+    Divergence {
+        id: "pkill-unknown-signal",
+        reference: 1,
+        faulx: 2,
+        why: "an unknown signal is a usage error, not a failed kill",
+    },
+];
+
+/// A case whose output is faulx's own, compared for the exit status and
+/// the fixture only.
+struct OwnOutput {
+    id: &'static str,
+    why: &'static str,
+}
+
+const OWN_OUTPUT: &[OwnOutput] = &[
+    OwnOutput {
+        id: "killall-list",
+        why: "signals are listed on one line, with the real-time ones",
+    },
+    OwnOutput {
+        id: "killall-list-name",
+        why: "signals are listed on one line, with the real-time ones",
+    },
+    OwnOutput {
+        id: "killall-list-quiet",
+        why: "signals are listed on one line, with the real-time ones",
+    },
+    OwnOutput {
+        id: "killall-list-unknown-signal",
+        why: "signals are listed on one line, with the real-time ones",
+    },
+    OwnOutput {
+        id: "killall-list-unknown-option",
+        why: "nothing is listed after a usage error",
+    },
+    OwnOutput {
+        id: "killall-version",
+        why: "the version goes to stdout, and is faulx's",
+    },
+    OwnOutput {
+        id: "killall-version-name",
+        why: "the version goes to stdout, and is faulx's",
+    },
+];
+
+/// What running a case left behind.
+#[derive(Debug, PartialEq, Eq)]
+struct Outcome {
+    status: Option<i32>,
+    /// The output, with the fixture's PID replaced by `PID`.
+    stdout: String,
+    fixture_alive: Option<bool>,
+}
+
+/// Finds the reference `tool` on `PATH`, unless it is this binary again.
+fn reference(tool: Tool) -> Option<PathBuf> {
+    let own = fs::canonicalize(env!("CARGO_BIN_EXE_faulx")).ok()?;
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(tool.name()))
+        .find(|path| fs::canonicalize(path).is_ok_and(|real| real != own))
+}
+
+/// Where the fixtures of `tool`'s cases are copied to.
+fn fixture_dir(tool: Tool) -> PathBuf {
+    env::temp_dir().join(format!(
+        "faulx-compat-{}-{}",
+        std::process::id(),
+        tool.name()
+    ))
+}
+
+/// A copy of `sleep` under a name of its own, so that nothing else matches.
+fn fixture_binary(tool: Tool) -> PathBuf {
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    let dir = fixture_dir(tool);
+    fs::create_dir_all(&dir).unwrap();
+    let name = format!(
+        "fxc{:05}{:02}",
+        std::process::id() % 100_000,
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    );
+    let path = dir.join(name);
+    fs::copy("/bin/sleep", &path).unwrap();
+    path
+}
+
+/// Whether `child` is still running a moment after the tool returned, which
+/// is how long a signal it sent takes to land.
+fn still_alive(child: &mut Child) -> bool {
+    let deadline = Instant::now() + Duration::from_millis(300);
+    while Instant::now() < deadline {
+        if child.try_wait().unwrap().is_some() {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    true
+}
+
+fn run_case(program: &Path, case: &Case, fixture: &Path) -> Outcome {
+    let name = fixture.file_name().unwrap().to_str().unwrap();
+    let partial = &name[1..];
+    let mut child = case
+        .fixture
+        .then(|| Command::new(fixture).arg("30").spawn().unwrap());
+    // Let the fixture exec, so that its name is the copy's.
+    thread::sleep(Duration::from_millis(50));
+    let output = Command::new(program)
+        .arg0(case.tool.name())
+        .args(case.args.iter().map(|arg| match *arg {
+            "PATH" => fixture.to_str().unwrap().to_owned(),
+            arg => arg.replace("NAME", name).replace("PARTIAL", partial),
+        }))
+        .env_clear()
+        .env("LC_ALL", "C")
+        .output()
+        .unwrap();
+    let fixture_alive = child.as_mut().map(|child| {
+        let alive = still_alive(child);
+        let _ = child.kill();
+        let _ = child.wait();
+        alive
+    });
+    let mut stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if let Some(child) = &child {
+        stdout = stdout.replace(&child.id().to_string(), "PID");
+    }
+    Outcome {
+        status: output.status.code(),
+        stdout,
+        fixture_alive,
+    }
+}
+
+fn compare(tool: Tool) {
+    let Some(program) = reference(tool) else {
+        eprintln!("{} is not installed, skipping", tool.name());
+        return;
+    };
+    for case in CASES.iter().filter(|case| case.tool == tool) {
+        let fixture = fixture_binary(tool);
+        let expected = run_case(&program, case, &fixture);
+        let actual = run_case(Path::new(env!("CARGO_BIN_EXE_faulx")), case, &fixture);
+        let _ = fs::remove_file(&fixture);
+        let divergence = DIVERGENCES.iter().find(|known| known.id == case.id);
+        match divergence {
+            // The exit statuses differ as listed.
+            Some(known) => assert_eq!(
+                (expected.status, actual.status),
+                (Some(known.reference), Some(known.faulx)),
+                "{}: no longer diverges as listed ({})",
+                case.id,
+                known.why
+            ),
+            None => assert_eq!(expected.status, actual.status, "{}", case.id),
+        }
+        assert_eq!(expected.fixture_alive, actual.fixture_alive, "{}", case.id);
+        if let Some(own) = OWN_OUTPUT.iter().find(|own| own.id == case.id) {
+            assert_ne!(
+                expected.stdout, actual.stdout,
+                "{}: prints alike now ({})",
+                case.id, own.why
+            );
+        } else {
+            assert_eq!(expected.stdout, actual.stdout, "{}", case.id);
+        }
+    }
+    let _ = fs::remove_dir(fixture_dir(tool));
+}
+
+#[test]
+fn test_divergences_name_cases() {
+    for known in DIVERGENCES {
+        assert!(
+            CASES.iter().any(|case| case.id == known.id),
+            "{} is not a case",
+            known.id
+        );
+    }
+    for own in OWN_OUTPUT {
+        assert!(
+            CASES.iter().any(|case| case.id == own.id),
+            "{} is not a case",
+            own.id
+        );
+    }
+}
+
+#[test]
+fn test_killall() {
+    compare(Tool::Killall);
+}
+
+#[test]
+fn test_pgrep() {
+    compare(Tool::Pgrep);
+}
+
+#[test]
+fn test_pkill() {
+    compare(Tool::Pkill);
+}
@@ -0,0 +1,115 @@
+//! Exit statuses of the binary under each of its names, per the procps
+//! contract: 0 matched, 1 none matched, 2 usage error, 3 internal error,
+//! and faulx's 130 when interrupted.
+
+use std::{
+    fs, io,
+    os::unix::process::{CommandExt, ExitStatusExt},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use nix::{
+    sys::signal::{Signal, kill},
+    unistd::Pid,
+};
+
+/// The binary, running as `name`.
+fn faulx(name: &str) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_faulx"));
+    command
+        .arg0(name)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+fn status(command: &mut Command) -> Option<i32> {
+    command.status().unwrap().code()
+}
+
+/// A pattern only the test binary itself matches, as its name is the name
+/// of this file and a hash. It is both a regex and a plain substring.
+const OWN_NAME: &str = "exit_codes-";
+
+/// A name no process has.
+const NO_SUCH_NAME: &str = "faulx-test-no-such-process";
+
+#[test]
+fn test_matched() {
+    assert_eq!(status(faulx("pgrep").arg(OWN_NAME)), Some(0));
+    assert_eq!(status(faulx("faulx").arg("--list")), Some(0));
+}
+
+#[test]
+fn test_none_matched() {
+    assert_eq!(status(faulx("pgrep").arg(NO_SUCH_NAME)), Some(1));
+    assert_eq!(status(faulx("pkill").arg(NO_SUCH_NAME)), Some(1));
+    assert_eq!(status(faulx("faulx").arg(NO_SUCH_NAME)), Some(1));
+}
+
+#[test]
+fn test_usage_error() {
+    assert_eq!(status(faulx("faulx").arg("--no-such-option")), Some(2));
+    assert_eq!(
+        status(faulx("faulx").args(["-s", "NOPE", NO_SUCH_NAME])),
+        Some(2)
+    );
+    assert_eq!(
+        status(faulx("pkill").args(["--signal=NOPE", NO_SUCH_NAME])),
+        Some(2)
+    );
+    assert_eq!(status(&mut faulx("pgrep")), Some(2));
+    #[cfg(feature = "regex")]
+    assert_eq!(status(faulx("pgrep").arg("(")), Some(2));
+}
+
+#[test]
+fn test_internal_error() {
+    // Writing the PIDs to a pipe nobody reads from fails.
+    let (reader, writer) = io::pipe().unwrap();
+    drop(reader);
+    assert_eq!(status(faulx("pgrep").arg(OWN_NAME).stdout(writer)), Some(3));
+}
+
+/// Waits until `check` holds for the `/proc` status of `pid`.
+fn wait_for_status(pid: u32, check: impl Fn(&str) -> bool) {
+    while !fs::read_to_string(format!("/proc/{pid}/status")).is_ok_and(|status| check(&status)) {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Whether the `/proc` status line `field`, a signal mask, has `signal`.
+fn has_signal(status: &str, field: &str, signal: Signal) -> bool {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .is_some_and(|mask| mask & (1 << (signal as i32 - 1)) != 0)
+}
+
+#[test]
+fn test_interrupted() {
+    // Ignores SIGTERM, so that only the SIGINT passed on ends it.
+    let mut target = Command::new("sh")
+        .args(["-c", "trap '' TERM; exec sleep 30"])
+        .spawn()
+        .unwrap();
+    wait_for_status(target.id(), |status| {
+        status.starts_with("Name:\tsleep") && has_signal(status, "SigIgn:", Signal::SIGTERM)
+    });
+
+    let mut waiting = faulx("faulx")
+        .args(["-w", "--pid", &target.id().to_string()])
+        .spawn()
+        .unwrap();
+    wait_for_status(waiting.id(), |status| {
+        has_signal(status, "SigCgt:", Signal::SIGINT)
+    });
+    let pid = Pid::from_raw(i32::try_from(waiting.id()).unwrap());
+    kill(pid, Signal::SIGINT).unwrap();
+
+    assert_eq!(waiting.wait().unwrap().code(), Some(130));
+    assert_eq!(target.wait().unwrap().signal(), Some(Signal::SIGINT as i32));
+}
@@ -0,0 +1,34 @@
+//! `faulx restart` runs its command only once the process it replaces has
+//! exited, here a copy of `sleep` of its own.
+#![cfg(not(feature = "tiny-cli"))]
+
+use std::{env, fs, process::Command, thread, time::Duration};
+
+#[test]
+fn test_restart() {
+    let dir = env::temp_dir().join(format!("faulx-restart-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let name = format!("fxrst{:05}", std::process::id() % 100_000);
+    let binary = dir.join(&name);
+    fs::copy("/bin/sleep", &binary).unwrap();
+    let mut fixture = Command::new(&binary).arg("60").spawn().unwrap();
+    // Let the fixture exec, so that its name is the copy's.
+    thread::sleep(Duration::from_millis(50));
+
+    // Only this test reaps the fixture, so once dead it is a zombie until
+    // then, which the command tells from a running process by its state.
+    let output = Command::new(env!("CARGO_BIN_EXE_faulx"))
+        .args(["restart", "-x", &name, "--", "sh", "-c"])
+        .arg(format!(
+            "grep -q '^State:.Z' /proc/{}/status && echo restarted",
+            fixture.id()
+        ))
+        .output()
+        .unwrap();
+    let status = fixture.wait().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "restarted\n");
+    assert!(output.status.success());
+    assert!(!status.success());
+}
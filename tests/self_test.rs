@@ -0,0 +1,24 @@
+//! `faulx --self-test` passes on the machine the tests run on.
+#![cfg(not(feature = "tiny-cli"))]
+
+use std::process::Command;
+
+#[test]
+fn test_self_test() {
+    let output = Command::new(env!("CARGO_BIN_EXE_faulx"))
+        .arg("--self-test")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{stdout}");
+    let checks: Vec<_> = stdout
+        .lines()
+        .filter_map(|line| line.strip_prefix("ok").map(str::trim_start))
+        .filter_map(|line| line.split(':').next())
+        .collect();
+    assert_eq!(
+        checks,
+        ["fork", "rename", "match", "signal", "wait"],
+        "{stdout}"
+    );
+}